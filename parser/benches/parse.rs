@@ -0,0 +1,56 @@
+//! Benchmarks of finding the commands in comments, which happens for every comment the bot sees.
+//!
+//! Run them with `cargo bench` from the `parser` directory.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use parser::command::Input;
+
+/// The sizes of the comments, in paragraphs (of about 190 bytes each).
+const SIZES: &[usize] = &[1, 10, 100];
+
+/// A comment like those on issues, with inline code, code blocks and links, and a command halfway
+/// through if `mention` is set.
+fn comment(paragraphs: usize, mention: bool) -> String {
+    let mut comment = String::new();
+    for i in 0..paragraphs {
+        comment.push_str(
+            "This fails to compile with `cargo build`, see the error below:\n\n\
+             ```\nerror[E0308]: mismatched types\n  --> src/main.rs:2:5\n```\n\n\
+             I tried *a few* things, like [this](https://example.com).\n\n",
+        );
+        if mention && i == paragraphs / 2 {
+            comment.push_str("@rustbot modify labels: +C-bug, -I-nominated.\n\n");
+        }
+    }
+    comment
+}
+
+fn commands(comment: &str) -> usize {
+    let mut input = Input::new(comment, &["rustbot"]);
+    input.commands().count()
+}
+
+fn without_mention(c: &mut Criterion) {
+    c.bench_function_over_inputs(
+        "without mention",
+        |b, &&paragraphs| {
+            let comment = comment(paragraphs, false);
+            b.iter(|| commands(&comment))
+        },
+        SIZES,
+    );
+}
+
+fn with_command(c: &mut Criterion) {
+    c.bench_function_over_inputs(
+        "with command",
+        |b, &&paragraphs| {
+            let comment = comment(paragraphs, true);
+            b.iter(|| commands(&comment))
+        },
+        SIZES,
+    );
+}
+
+criterion_group!(benches, without_mention, with_command);
+criterion_main!(benches);
@@ -0,0 +1,40 @@
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+
+use parser::command::{Command, Input};
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(data) = std::str::from_utf8(data) {
+        let mut input = Input::new(data, &["bot"]);
+        for command in input.commands() {
+            // Rendering errors slices the input, so exercise that as well.
+            match command {
+                Command::Relabel(Err(err))
+                | Command::Assign(Err(err))
+                | Command::Close(Err(err))
+                | Command::Ping(Err(err))
+                | Command::Triage(Err(err))
+                | Command::Blocked(Err(err))
+                | Command::Note(Err(err))
+                | Command::Second(Err(err))
+                | Command::Milestone(Err(err))
+                | Command::Rename(Err(err))
+                | Command::Poll(Err(err))
+                | Command::Help(Err(err))
+                | Command::Transfer(Err(err))
+                | Command::Hello(Err(err))
+                | Command::Minimize(Err(err))
+                | Command::Resolve(Err(err))
+                | Command::Log(Err(err))
+                | Command::Lock(Err(err))
+                | Command::Subscribe(Err(err))
+                | Command::Status(Err(err)) => {
+                    err.to_string();
+                    err.snippet();
+                }
+                _ => {}
+            }
+        }
+    }
+});
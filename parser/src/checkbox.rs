@@ -0,0 +1,132 @@
+//! Checkboxes in markdown task lists, like those of issue templates.
+//!
+//! A checkbox is a list item starting with `[ ]` (unchecked) or `[x]` (checked, also `[X]`),
+//! followed by its text:
+//!
+//! ```text
+//! - [x] This is a regression
+//! * [ ] I have searched for duplicates
+//! 1. [X] Numbered lists work too
+//! ```
+//!
+//! Checkboxes in code, or without any text, don't count.
+
+use crate::code_block::ColorCodeBlocks;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Checkbox<'a> {
+    /// The text after the box, trimmed.
+    pub text: &'a str,
+    pub checked: bool,
+}
+
+/// The checkboxes in `input`, in order.
+pub fn checkboxes(input: &str) -> Vec<Checkbox<'_>> {
+    let code = ColorCodeBlocks::new(input);
+    let mut checkboxes = Vec::new();
+    let mut line_start = 0;
+    for line in input.split('\n') {
+        let start = line_start;
+        line_start += line.len() + 1;
+        let item = match list_item(line) {
+            Some(item) => item,
+            None => continue,
+        };
+        let (checked, text) = if item.starts_with("[ ]") {
+            (false, &item[3..])
+        } else if item.starts_with("[x]") || item.starts_with("[X]") {
+            (true, &item[3..])
+        } else {
+            continue;
+        };
+        // `- [x]foo` isn't a checkbox, but a link reference.
+        if !text.is_empty() && !text.starts_with(char::is_whitespace) {
+            continue;
+        }
+        let text = text.trim();
+        let offset = start + (line.len() - item.len());
+        if text.is_empty() || code.overlaps_code(offset..offset + 3).is_some() {
+            continue;
+        }
+        checkboxes.push(Checkbox { text, checked });
+    }
+    checkboxes
+}
+
+/// The contents of `line` after its list marker (`-`, `*`, `+`, or a number followed by `.` or
+/// `)`), if it's a list item.
+fn list_item(line: &str) -> Option<&str> {
+    let line = line.trim_start();
+    let rest = if line.starts_with(|c| c == '-' || c == '*' || c == '+') {
+        &line[1..]
+    } else {
+        let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        if digits == 0 {
+            return None;
+        }
+        let rest = &line[digits..];
+        if !rest.starts_with(|c| c == '.' || c == ')') {
+            return None;
+        }
+        &rest[1..]
+    };
+    if !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    Some(rest.trim_start())
+}
+
+#[cfg(test)]
+fn checked(input: &str) -> Vec<(&str, bool)> {
+    checkboxes(input)
+        .into_iter()
+        .map(|checkbox| (checkbox.text, checkbox.checked))
+        .collect()
+}
+
+#[test]
+fn checkbox_states() {
+    assert_eq!(
+        checked(
+            "### Checks\n\n- [x] This is a regression\n- [ ] I searched for duplicates\n\
+             * [X]   Nightly only  \n"
+        ),
+        [
+            ("This is a regression", true),
+            ("I searched for duplicates", false),
+            ("Nightly only", true),
+        ]
+    );
+}
+
+#[test]
+fn checkbox_list_markers() {
+    assert_eq!(
+        checked("+ [x] plus\r\n  1. [x] nested\r\n10) [ ] paren\r\n"),
+        [("plus", true), ("nested", true), ("paren", false)]
+    );
+}
+
+#[test]
+fn checkbox_not_list_items() {
+    for input in &[
+        "[x] no marker",
+        "-[x] no space",
+        "- [x]",
+        "- [x]regression",
+        "- [y] other letter",
+        "> - [x] quoted",
+        "1 [x] no dot",
+        "- x regression",
+    ] {
+        assert!(checkboxes(input).is_empty(), "{}", input);
+    }
+}
+
+#[test]
+fn checkbox_in_code() {
+    assert_eq!(
+        checked("```\n- [x] in a fence\n```\n- [x] after\n\n    - [x] indented\n"),
+        [("after", true)]
+    );
+}
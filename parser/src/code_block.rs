@@ -1,6 +1,11 @@
 use pulldown_cmark::{Event, Parser, Tag};
 use std::ops::Range;
 
+/// The regions of a markdown document which are code, and so should never be interpreted as
+/// commands.
+///
+/// This covers inline code spans as well as all forms of code blocks: those fenced with backticks
+/// or tildes, and those indented by four spaces.
 #[derive(Debug)]
 pub struct ColorCodeBlocks {
     code: Vec<Range<usize>>,
@@ -168,3 +173,36 @@ fn cbs_8() {
         [Code::Yes("`one`"), Code::No(" not "), Code::Yes("`two`")]
     );
 }
+
+#[test]
+fn cbs_tilde_fence() {
+    assert_eq!(
+        bodies(
+            "
+~~~
+testing
+~~~
+
+nope
+"
+        ),
+        [
+            Code::No("\n"),
+            Code::Yes("~~~\ntesting\n~~~\n"),
+            Code::No("\nnope\n")
+        ],
+    );
+}
+
+#[test]
+fn cbs_tilde_fence_unclosed() {
+    assert_eq!(
+        bodies(
+            "
+~~~rust
+testing unclosed
+"
+        ),
+        [Code::No("\n"), Code::Yes("~~~rust\ntesting unclosed\n"),],
+    );
+}
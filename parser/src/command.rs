@@ -1,117 +1,507 @@
 use crate::code_block::ColorCodeBlocks;
 use crate::error::Error;
 use crate::token::{Token, Tokenizer};
+use std::cmp::Reverse;
+use std::fmt;
+use std::ops::Range;
 
 pub mod assign;
+pub mod blocked;
+pub mod close;
+pub mod hello;
+pub mod help;
+pub mod lock;
+pub mod log;
+pub mod milestone;
+pub mod minimize;
+pub mod note;
+pub mod ping;
+pub mod poll;
 pub mod relabel;
+pub mod rename;
+pub mod resolve;
+pub mod second;
+pub mod status;
+pub mod subscribe;
+pub mod transfer;
+pub mod triage;
 
-pub fn find_commmand_start(input: &str, bot: &str) -> Option<usize> {
-    input.find(&format!("@{}", bot))
+/// Finds the earliest mention of any of the `bot` names in `input`.
+///
+/// Like on GitHub, mentions are matched case-insensitively.
+pub fn find_commmand_start(input: &str, bot: &[&str]) -> Option<usize> {
+    bot.iter()
+        .filter_map(|name| find_mention(input, name))
+        .min()
 }
 
-#[derive(Debug)]
+fn find_mention(input: &str, bot: &str) -> Option<usize> {
+    let mention = format!("@{}", bot);
+    // Matches always start at an `@`, so they are on a char boundary.
+    input
+        .as_bytes()
+        .windows(mention.len())
+        .position(|window| window.eq_ignore_ascii_case(mention.as_bytes()))
+}
+
+/// Whether the user mentioned (without the `@`) is any of the `bot` names.
+pub(crate) fn is_mention_of(user: &str, bot: &[&str]) -> bool {
+    bot.iter().any(|name| user.eq_ignore_ascii_case(name))
+}
+
+/// Whether `name` could be a GitHub username: alphanumerics and single hyphens (but not at
+/// either end), at most 39 characters long.
+pub fn is_valid_username(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 39
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        && !name.starts_with('-')
+        && !name.ends_with('-')
+        && !name.contains("--")
+}
+
+#[derive(Debug, PartialEq)]
 pub enum Command<'a> {
     Relabel(Result<relabel::RelabelCommand, Error<'a>>),
     Assign(Result<assign::AssignCommand, Error<'a>>),
+    Close(Result<close::CloseCommand, Error<'a>>),
+    Ping(Result<ping::PingCommand, Error<'a>>),
+    Triage(Result<triage::TriageCommand, Error<'a>>),
+    Blocked(Result<blocked::BlockedCommand, Error<'a>>),
+    Note(Result<note::NoteCommand, Error<'a>>),
+    Second(Result<second::SecondCommand, Error<'a>>),
+    Milestone(Result<milestone::MilestoneCommand, Error<'a>>),
+    Rename(Result<rename::RenameCommand, Error<'a>>),
+    Poll(Result<poll::PollCommand, Error<'a>>),
+    Help(Result<help::HelpCommand, Error<'a>>),
+    Transfer(Result<transfer::TransferCommand, Error<'a>>),
+    Hello(Result<hello::HelloCommand, Error<'a>>),
+    Minimize(Result<minimize::MinimizeCommand, Error<'a>>),
+    Resolve(Result<resolve::ResolveCommand, Error<'a>>),
+    Log(Result<log::LogCommand, Error<'a>>),
+    Lock(Result<lock::LockCommand, Error<'a>>),
+    Subscribe(Result<subscribe::SubscribeCommand, Error<'a>>),
+    Status(Result<status::StatusCommand, Error<'a>>),
     None,
 }
 
+/// A command, along with where in the input it is.
+#[derive(Debug, PartialEq)]
+pub struct ParsedCommand<'a> {
+    pub command: Command<'a>,
+    /// The byte range of the command in the input, from the `@` of the mention. For commands
+    /// which failed to parse, it ends with the part which couldn't be parsed; it's empty if there
+    /// was no command.
+    pub range: Range<usize>,
+}
+
 #[derive(Debug)]
 pub struct Input<'a> {
     all: &'a str,
     parsed: usize,
-    code: ColorCodeBlocks,
-    bot: &'a str,
+    /// The code in the input, found the first time there's a mention to check: most comments
+    /// don't mention the bot at all, and scanning them for code would be wasted.
+    code: Option<ColorCodeBlocks>,
+    bot: Vec<&'a str>,
 }
 
 impl<'a> Input<'a> {
-    pub fn new(input: &'a str, bot: &'a str) -> Input<'a> {
+    /// Creates an input which responds to mentions of any of the names in `bot`.
+    pub fn new(input: &'a str, bot: &[&'a str]) -> Input<'a> {
         Input {
             all: input,
             parsed: 0,
-            code: ColorCodeBlocks::new(input),
-            bot,
+            code: None,
+            bot: bot.to_vec(),
         }
     }
 
+    /// Parses the next command in the input.
+    ///
+    /// The command grammars aren't meant to overlap, but if a command parses as more than one of
+    /// them, one is picked deterministically: successful parses are preferred, then those which
+    /// consumed the most input, then the command listed first out of relabel, assign, close,
+    /// ping, triage, blocked, note, second, milestone, rename, poll, help, transfer, hello,
+    /// minimize, resolve, log, lock, subscribe and status.
     pub fn parse_command(&mut self) -> Command<'a> {
-        let start = match find_commmand_start(&self.all[self.parsed..], self.bot) {
-            Some(pos) => pos,
-            None => return Command::None,
-        };
-        self.parsed += start;
-        let mut tok = Tokenizer::new(&self.all[self.parsed..]);
-        assert_eq!(
-            tok.next_token().unwrap(),
-            Some(Token::Word(&format!("@{}", self.bot)))
-        );
-
-        let mut success = vec![];
-
-        let original_tokenizer = tok.clone();
+        self.parse_command_spanned().command
+    }
 
-        {
-            let mut tok = original_tokenizer.clone();
-            let res = relabel::RelabelCommand::parse(&mut tok);
-            match res {
-                Ok(None) => {}
-                Ok(Some(cmd)) => {
-                    success.push((tok, Command::Relabel(Ok(cmd))));
+    /// Like `parse_command`, but also gives where in the input the command is, so that it can be
+    /// quoted back.
+    pub fn parse_command_spanned(&mut self) -> ParsedCommand<'a> {
+        let tok = match self.command_start() {
+            Some(tok) => tok,
+            None => return self.no_command(),
+        };
+        match parse_candidates(&tok).into_iter().next() {
+            Some((mut tok, c)) => {
+                let start = self.parsed;
+                let end = match c.error() {
+                    Some(err) => err.span().end,
+                    None => tok.position(),
+                };
+                // if we errored out while parsing the command do not move the input forwards
+                if c.is_ok() {
+                    self.parsed = tok.position();
                 }
-                Err(err) => {
-                    success.push((tok, Command::Relabel(Err(err))));
+                ParsedCommand {
+                    command: c,
+                    range: start..(start + self.all[start..end].trim_end().len()),
                 }
             }
+            None => self.no_command(),
         }
+    }
 
-        {
-            let mut tok = original_tokenizer.clone();
-            let res = assign::AssignCommand::parse(&mut tok);
-            match res {
-                Ok(None) => {}
-                Ok(Some(cmd)) => {
-                    success.push((tok, Command::Assign(Ok(cmd))));
-                }
-                Err(err) => {
-                    success.push((tok, Command::Assign(Err(err))));
-                }
+    /// Like `parse_command`, but gives every successful parse of the next command, in order of
+    /// precedence, so that overlapping grammars can be noticed. The input moves on just as it
+    /// does for `parse_command`.
+    pub fn parse_command_all(&mut self) -> Vec<Command<'a>> {
+        let tok = match self.command_start() {
+            Some(tok) => tok,
+            None => return Vec::new(),
+        };
+        let mut candidates = parse_candidates(&tok);
+        if let Some((tok, c)) = candidates.first_mut() {
+            if c.is_ok() {
+                self.parsed = tok.position();
             }
         }
+        candidates
+            .into_iter()
+            .map(|(_, c)| c)
+            .filter(|c| c.is_ok())
+            .collect()
+    }
 
-        if success.len() > 1 {
-            panic!(
-                "succeeded parsing {:?} to multiple commands: {:?}",
-                &self.all[self.parsed..],
-                success
-            );
+    /// Moves to the next mention of the bot, giving a tokenizer positioned at whatever follows
+    /// it, if that could be a command.
+    fn command_start(&mut self) -> Option<Tokenizer<'a>> {
+        self.parsed += find_commmand_start(&self.all[self.parsed..], &self.bot)?;
+        let mut tok = Tokenizer::new_at(self.all, self.parsed);
+        match tok.next_token() {
+            Ok(Some(Token::Mention(mention))) if is_mention_of(mention, &self.bot) => {}
+            // Something like `@bot-ty` or `@bot"`, which merely starts with a mention.
+            _ => return None,
         }
-
-        if self
-            .code
-            .overlaps_code((self.parsed)..(self.parsed + tok.position()))
+        if tok.peek_token().is_err() {
+            // Whatever follows the mention can't be tokenized (e.g., it's an unterminated
+            // string), so it's not a command.
+            return None;
+        }
+        skip_filler(&mut tok);
+        let all = self.all;
+        let code = self.code.get_or_insert_with(|| ColorCodeBlocks::new(all));
+        if code
+            .overlaps_code((self.parsed)..(tok.position()))
             .is_some()
         {
-            return Command::None;
+            return None;
         }
+        Some(tok)
+    }
 
-        match success.pop() {
-            Some((mut tok, c)) => {
-                // if we errored out while parsing the command do not move the input forwards
-                if c.is_ok() {
-                    self.parsed += tok.position();
+    fn no_command(&self) -> ParsedCommand<'a> {
+        ParsedCommand {
+            command: Command::None,
+            range: self.parsed..self.parsed,
+        }
+    }
+
+    /// Iterates over all of the commands in the input, in order.
+    ///
+    /// A command which fails to parse is still yielded, but it does not stop iteration: we skip
+    /// past its mention so that commands later in the input are found as well.
+    pub fn commands<'b>(&'b mut self) -> impl Iterator<Item = Command<'a>> + 'b {
+        std::iter::from_fn(move || self.next_command())
+    }
+
+    fn next_command(&mut self) -> Option<Command<'a>> {
+        loop {
+            let start = self.parsed + find_commmand_start(&self.all[self.parsed..], &self.bot)?;
+            let command = self.parse_command();
+            if self.parsed == start {
+                // Nothing was consumed (either the command failed to parse or there was no
+                // command after the mention), so step over the mention's `@` to make progress.
+                self.parsed += 1;
+            }
+            if !command.is_none() {
+                return Some(command);
+            }
+        }
+    }
+}
+
+/// Skips the filler people put between a mention and the command when phrasing it politely: a
+/// comma or colon right after the mention, then any of `please`, `could you` and `can you`. So
+/// `@bot, could you please claim` is the same as `@bot claim`.
+///
+/// This is deliberately limited to those, so that ordinary sentences mentioning the bot aren't
+/// taken for commands.
+fn skip_filler(tok: &mut Tokenizer<'_>) {
+    let mut toks = tok.clone();
+    match toks.next_token() {
+        Ok(Some(Token::Comma)) | Ok(Some(Token::Colon)) => *tok = toks,
+        _ => {}
+    }
+    let is_word = |toks: &mut Tokenizer<'_>, expected: &[&str]| match toks.next_token() {
+        Ok(Some(Token::Word(word))) => expected.iter().any(|e| word.eq_ignore_ascii_case(e)),
+        _ => false,
+    };
+    loop {
+        let mut please = tok.clone();
+        if is_word(&mut please, &["please"]) {
+            *tok = please;
+            continue;
+        }
+        let mut you = tok.clone();
+        if is_word(&mut you, &["could", "can"]) && is_word(&mut you, &["you"]) {
+            *tok = you;
+            continue;
+        }
+        return;
+    }
+}
+
+/// Parses every command addressed to `bot` in `input`, in order.
+///
+/// Commands which fail to parse are included (as errors), so that they can be reported.
+pub fn parse_all<'a>(input: &'a str, bot: &'a str) -> Vec<Command<'a>> {
+    let mut input = Input::new(input, &[bot]);
+    input.commands().collect()
+}
+
+/// Parses the commands addressed to `bot` in `new` which weren't already in `old`, for when a
+/// comment has been edited from `old` to `new`: the commands in `old` have already been run.
+///
+/// Commands are compared regardless of where they are in the text, so a command given twice in
+/// `new` but once in `old` is returned once. Commands which fail to parse are always returned, as
+/// they were never run.
+pub fn parse_added<'a>(old: &str, new: &'a str, bot: &[&'a str]) -> Vec<Command<'a>> {
+    let mut old = Input::new(old, bot).commands().collect::<Vec<_>>();
+    let mut new = Input::new(new, bot);
+    new.commands()
+        .filter(|command| {
+            if command.is_err() {
+                return true;
+            }
+            match old.iter().position(|c| c == command) {
+                Some(i) => {
+                    old.remove(i);
+                    false
                 }
-                c
+                None => true,
             }
-            None => Command::None,
+        })
+        .collect()
+}
+
+/// Parses `tok` as each of the commands, giving those it's recognized as, in order of precedence
+/// (see `Input::parse_command`).
+fn parse_candidates<'a>(tok: &Tokenizer<'a>) -> Vec<(Tokenizer<'a>, Command<'a>)> {
+    let mut success = vec![];
+    success.extend(parse_single_command(
+        relabel::RelabelCommand::parse,
+        Command::Relabel,
+        tok,
+    ));
+    success.extend(parse_single_command(
+        assign::AssignCommand::parse,
+        Command::Assign,
+        tok,
+    ));
+    success.extend(parse_single_command(
+        close::CloseCommand::parse,
+        Command::Close,
+        tok,
+    ));
+    success.extend(parse_single_command(
+        ping::PingCommand::parse,
+        Command::Ping,
+        tok,
+    ));
+    success.extend(parse_single_command(
+        triage::TriageCommand::parse,
+        Command::Triage,
+        tok,
+    ));
+    success.extend(parse_single_command(
+        blocked::BlockedCommand::parse,
+        Command::Blocked,
+        tok,
+    ));
+    success.extend(parse_single_command(
+        note::NoteCommand::parse,
+        Command::Note,
+        tok,
+    ));
+    success.extend(parse_single_command(
+        second::SecondCommand::parse,
+        Command::Second,
+        tok,
+    ));
+    success.extend(parse_single_command(
+        milestone::MilestoneCommand::parse,
+        Command::Milestone,
+        tok,
+    ));
+    success.extend(parse_single_command(
+        rename::RenameCommand::parse,
+        Command::Rename,
+        tok,
+    ));
+    success.extend(parse_single_command(
+        poll::PollCommand::parse,
+        Command::Poll,
+        tok,
+    ));
+    success.extend(parse_single_command(
+        help::HelpCommand::parse,
+        Command::Help,
+        tok,
+    ));
+    success.extend(parse_single_command(
+        transfer::TransferCommand::parse,
+        Command::Transfer,
+        tok,
+    ));
+    success.extend(parse_single_command(
+        |input| parse_keyword(input, "hello", hello::HelloCommand),
+        Command::Hello,
+        tok,
+    ));
+    success.extend(parse_single_command(
+        |input| parse_keyword(input, "minimize", minimize::MinimizeCommand),
+        Command::Minimize,
+        tok,
+    ));
+    success.extend(parse_single_command(
+        |input| parse_keyword(input, "resolve", resolve::ResolveCommand),
+        Command::Resolve,
+        tok,
+    ));
+    success.extend(parse_single_command(
+        |input| parse_keyword(input, "log", log::LogCommand),
+        Command::Log,
+        tok,
+    ));
+    success.extend(parse_single_command(
+        lock::LockCommand::parse,
+        Command::Lock,
+        tok,
+    ));
+    success.extend(parse_single_command(
+        subscribe::SubscribeCommand::parse,
+        Command::Subscribe,
+        tok,
+    ));
+    success.extend(parse_single_command(
+        |input| parse_keyword(input, "status", status::StatusCommand),
+        Command::Status,
+        tok,
+    ));
+    by_precedence(success)
+}
+
+/// Sorts the parses of a command, which are in the order the commands were tried in, by
+/// precedence.
+fn by_precedence<'a>(
+    candidates: Vec<(Tokenizer<'a>, Command<'a>)>,
+) -> Vec<(Tokenizer<'a>, Command<'a>)> {
+    let mut candidates = candidates
+        .into_iter()
+        .map(|(mut tok, c)| ((Reverse(c.is_ok()), Reverse(tok.position())), tok, c))
+        .collect::<Vec<_>>();
+    // The sort is stable, so ties stay in the order the commands were tried in.
+    candidates.sort_by_key(|(key, _, _)| *key);
+    candidates.into_iter().map(|(_, tok, c)| (tok, c)).collect()
+}
+
+/// Why a command which is just a keyword couldn't be parsed.
+#[derive(PartialEq, Eq, Debug)]
+pub enum KeywordError {
+    ExpectedEnd,
+}
+
+impl std::error::Error for KeywordError {}
+
+impl fmt::Display for KeywordError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            KeywordError::ExpectedEnd => write!(f, "expected end of command"),
         }
     }
 }
 
+/// Parses a command which is just `keyword`, without any arguments (like `@bot hello`), as
+/// `command`.
+///
+/// The grammar is as follows:
+///
+/// ```text
+/// Command: `@bot <keyword>`.
+/// ```
+fn parse_keyword<'a, T>(
+    input: &mut Tokenizer<'a>,
+    keyword: &str,
+    command: T,
+) -> Result<Option<T>, Error<'a>> {
+    let mut toks = input.clone();
+    match toks.peek_token()? {
+        Some(Token::Word(word)) if word == keyword => {
+            toks.next_token()?;
+        }
+        _ => return Ok(None),
+    }
+    if toks.eat_terminator()? {
+        *input = toks;
+        Ok(Some(command))
+    } else {
+        Err(toks.error(KeywordError::ExpectedEnd))
+    }
+}
+
+fn parse_single_command<'a, T, F, M>(
+    parse: F,
+    mapper: M,
+    tokenizer: &Tokenizer<'a>,
+) -> Option<(Tokenizer<'a>, Command<'a>)>
+where
+    F: FnOnce(&mut Tokenizer<'a>) -> Result<Option<T>, Error<'a>>,
+    M: FnOnce(Result<T, Error<'a>>) -> Command<'a>,
+{
+    let mut tok = tokenizer.clone();
+    match parse(&mut tok) {
+        Ok(None) => None,
+        Ok(Some(command)) => Some((tok, mapper(Ok(command)))),
+        Err(err) => Some((tok, mapper(Err(err)))),
+    }
+}
+
 impl<'a> Command<'a> {
     pub fn is_ok(&self) -> bool {
         match self {
             Command::Relabel(r) => r.is_ok(),
             Command::Assign(r) => r.is_ok(),
+            Command::Close(r) => r.is_ok(),
+            Command::Ping(r) => r.is_ok(),
+            Command::Triage(r) => r.is_ok(),
+            Command::Blocked(r) => r.is_ok(),
+            Command::Note(r) => r.is_ok(),
+            Command::Second(r) => r.is_ok(),
+            Command::Milestone(r) => r.is_ok(),
+            Command::Rename(r) => r.is_ok(),
+            Command::Poll(r) => r.is_ok(),
+            Command::Help(r) => r.is_ok(),
+            Command::Transfer(r) => r.is_ok(),
+            Command::Hello(r) => r.is_ok(),
+            Command::Minimize(r) => r.is_ok(),
+            Command::Resolve(r) => r.is_ok(),
+            Command::Log(r) => r.is_ok(),
+            Command::Lock(r) => r.is_ok(),
+            Command::Subscribe(r) => r.is_ok(),
+            Command::Status(r) => r.is_ok(),
             Command::None => true,
         }
     }
@@ -120,6 +510,65 @@ impl<'a> Command<'a> {
         !self.is_ok()
     }
 
+    /// Why the command failed to parse, if it did.
+    pub fn error(&self) -> Option<&Error<'a>> {
+        match self {
+            Command::Relabel(Err(err)) => Some(err),
+            Command::Assign(Err(err)) => Some(err),
+            Command::Close(Err(err)) => Some(err),
+            Command::Ping(Err(err)) => Some(err),
+            Command::Triage(Err(err)) => Some(err),
+            Command::Blocked(Err(err)) => Some(err),
+            Command::Note(Err(err)) => Some(err),
+            Command::Second(Err(err)) => Some(err),
+            Command::Milestone(Err(err)) => Some(err),
+            Command::Rename(Err(err)) => Some(err),
+            Command::Poll(Err(err)) => Some(err),
+            Command::Help(Err(err)) => Some(err),
+            Command::Transfer(Err(err)) => Some(err),
+            Command::Hello(Err(err)) => Some(err),
+            Command::Minimize(Err(err)) => Some(err),
+            Command::Resolve(Err(err)) => Some(err),
+            Command::Log(Err(err)) => Some(err),
+            Command::Lock(Err(err)) => Some(err),
+            Command::Subscribe(Err(err)) => Some(err),
+            Command::Status(Err(err)) => Some(err),
+            _ => None,
+        }
+    }
+
+    /// The forms the command takes, without the mention of the bot, for telling people how to
+    /// use it (see `help::COMMANDS`).
+    pub fn syntax(&self) -> &'static [&'static str] {
+        let name = match self {
+            Command::Relabel(_) => "relabel",
+            Command::Assign(_) => "assign",
+            Command::Close(_) => "close",
+            Command::Ping(_) => "ping",
+            Command::Triage(_) => "triage",
+            Command::Blocked(_) => "blocked",
+            Command::Note(_) => "note",
+            Command::Second(_) => "second",
+            Command::Milestone(_) => "milestone",
+            Command::Rename(_) => "rename",
+            Command::Poll(_) => "poll",
+            Command::Help(_) => "help",
+            Command::Transfer(_) => "transfer",
+            Command::Hello(_) => "hello",
+            Command::Minimize(_) => "minimize",
+            Command::Resolve(_) => "resolve",
+            Command::Log(_) => "log",
+            Command::Lock(_) => "lock",
+            Command::Subscribe(_) => "subscribe",
+            Command::Status(_) => "status",
+            Command::None => return &[],
+        };
+        help::COMMANDS
+            .iter()
+            .find(|command| command.name == name)
+            .map_or(&[], |command| command.syntax)
+    }
+
     pub fn is_none(&self) -> bool {
         match self {
             Command::None => true,
@@ -132,14 +581,14 @@ impl<'a> Command<'a> {
 fn errors_outside_command_are_fine() {
     let input =
         "haha\" unterminated quotes @bot modify labels: +bug. Terminating after the command";
-    let mut input = Input::new(input, "bot");
+    let mut input = Input::new(input, &["bot"]);
     assert!(input.parse_command().is_ok());
 }
 
 #[test]
 fn code_1() {
     let input = "`@bot modify labels: +bug.`";
-    let mut input = Input::new(input, "bot");
+    let mut input = Input::new(input, &["bot"]);
     assert!(input.parse_command().is_none());
 }
 
@@ -148,24 +597,432 @@ fn code_2() {
     let input = "```
     @bot modify labels: +bug.
     ```";
-    let mut input = Input::new(input, "bot");
+    let mut input = Input::new(input, &["bot"]);
     assert!(input.parse_command().is_none());
 }
 
+#[test]
+fn code_only_found_for_mentions() {
+    let mut input = Input::new("Some `code`, and no command.", &["bot"]);
+    assert!(input.parse_command().is_none());
+    assert!(input.code.is_none());
+}
+
+#[test]
+fn code_before_command() {
+    let input = "Looks like `foo()` panics. @bot modify labels: +`I-ICE`.";
+    let mut input = Input::new(input, &["bot"]);
+    assert_eq!(
+        input.parse_command(),
+        Command::Relabel(Ok(relabel::RelabelCommand {
+            deltas: vec![relabel::LabelDelta::Add("I-ICE".into())],
+            preview: false,
+        }))
+    );
+}
+
+#[test]
+fn code_tilde_fence() {
+    let input = "~~~
+@bot modify labels: +bug.
+~~~";
+    let mut input = Input::new(input, &["bot"]);
+    assert!(input.parse_command().is_none());
+}
+
+#[test]
+fn code_indented() {
+    let input = "Not a command:
+
+    @bot modify labels: +bug.
+";
+    let mut input = Input::new(input, &["bot"]);
+    assert!(input.parse_command().is_none());
+}
+
+#[test]
+fn error_span() {
+    let input = "Some text.\nNow, @bot assign @-bob- please";
+    let mut input = Input::new(input, &["bot"]);
+    let err = match input.parse_command() {
+        Command::Assign(Err(err)) => err,
+        c => panic!("unexpected {:?}", c),
+    };
+    assert_eq!(&err.input[err.span()], "@-bob-");
+    assert_eq!(
+        err.snippet(),
+        "```text\nNow, @bot assign @-bob- please\n                 ^^^^^^\n```"
+    );
+}
+
 #[test]
 fn move_input_along() {
     let input = "@bot modify labels: +bug. Afterwards, delete the world.";
-    let mut input = Input::new(input, "bot");
+    let mut input = Input::new(input, &["bot"]);
     let parsed = input.parse_command();
     assert!(parsed.is_ok());
     assert_eq!(&input.all[input.parsed..], " Afterwards, delete the world.");
 }
 
+#[test]
+fn move_input_along_semicolon() {
+    let input = "@bot claim; I'll get to it this week.";
+    let mut input = Input::new(input, &["bot"]);
+    assert!(input.parse_command().is_ok());
+    assert_eq!(&input.all[input.parsed..], " I'll get to it this week.");
+}
+
+#[test]
+fn move_input_along_comma() {
+    let input = "@bot claim, if nobody minds.";
+    let mut input = Input::new(input, &["bot"]);
+    assert!(input.parse_command().is_ok());
+    assert_eq!(&input.all[input.parsed..], " if nobody minds.");
+}
+
+#[test]
+fn move_input_along_relabel_semicolon() {
+    let input = "@bot modify labels: +bug, -feature; thanks!";
+    let mut input = Input::new(input, &["bot"]);
+    assert!(match input.parse_command() {
+        Command::Relabel(Ok(command)) => command.deltas.len() == 2,
+        _ => false,
+    });
+    assert_eq!(&input.all[input.parsed..], " thanks!");
+}
+
+#[test]
+fn move_input_along_triage_comma() {
+    let input = "@bot prioritize P-high, as discussed.";
+    let mut input = Input::new(input, &["bot"]);
+    assert!(input.parse_command().is_ok());
+    assert_eq!(&input.all[input.parsed..], " as discussed.");
+}
+
 #[test]
 fn move_input_along_1() {
     let input = "@bot modify labels\": +bug. Afterwards, delete the world.";
-    let mut input = Input::new(input, "bot");
+    let mut input = Input::new(input, &["bot"]);
     assert!(input.parse_command().is_err());
     // don't move input along if parsing the command fails
     assert_eq!(input.parsed, 0);
 }
+
+#[test]
+fn multiple_commands() {
+    let input = "@bot claim. Also, @bot modify labels: +bug.";
+    let mut input = Input::new(input, &["bot"]);
+    let commands = input.commands().collect::<Vec<_>>();
+    assert_eq!(commands.len(), 2);
+    assert!(match commands[0] {
+        Command::Assign(Ok(_)) => true,
+        _ => false,
+    });
+    assert!(match commands[1] {
+        Command::Relabel(Ok(_)) => true,
+        _ => false,
+    });
+}
+
+#[test]
+fn multiple_commands_after_error() {
+    let input = "@bot modify labels\": +bug. @bot claim. @bot nothing to see here.";
+    let mut input = Input::new(input, &["bot"]);
+    let commands = input.commands().collect::<Vec<_>>();
+    assert_eq!(commands.len(), 2);
+    assert!(commands[0].is_err());
+    assert!(match commands[1] {
+        Command::Assign(Ok(_)) => true,
+        _ => false,
+    });
+}
+
+#[test]
+fn mention_case_insensitive() {
+    let input = "@BoT modify labels: +bug.";
+    let mut input = Input::new(input, &["bot"]);
+    assert!(match input.parse_command() {
+        Command::Relabel(Ok(_)) => true,
+        _ => false,
+    });
+}
+
+#[test]
+fn spanned_ok() {
+    let input = "Thanks! @bot modify labels: +bug.\nAnd more.";
+    let mut input = Input::new(input, &["bot"]);
+    let parsed = input.parse_command_spanned();
+    assert!(parsed.command.is_ok() && !parsed.command.is_none());
+    assert_eq!(&input.all[parsed.range], "@bot modify labels: +bug.");
+}
+
+#[test]
+fn spanned_end_of_line() {
+    let input = "@bot claim\n@bot release-assignment";
+    let mut input = Input::new(input, &["bot"]);
+    let first = input.parse_command_spanned();
+    let second = input.parse_command_spanned();
+    assert_eq!(&input.all[first.range], "@bot claim");
+    assert_eq!(&input.all[second.range], "@bot release-assignment");
+}
+
+#[test]
+fn spanned_error() {
+    let input = "Now, @bot assign @-bob- please";
+    let mut input = Input::new(input, &["bot"]);
+    let parsed = input.parse_command_spanned();
+    assert!(parsed.command.is_err());
+    assert_eq!(&input.all[parsed.range], "@bot assign @-bob-");
+}
+
+#[test]
+fn spanned_none() {
+    let mut input = Input::new("No commands here.", &["bot"]);
+    let parsed = input.parse_command_spanned();
+    assert!(parsed.command.is_none());
+    assert_eq!(parsed.range, 0..0);
+}
+
+#[test]
+fn assign_mention() {
+    let mut input = Input::new("@rustbot assign @octocat", &["rustbot"]);
+    assert_eq!(
+        input.parse_command(),
+        Command::Assign(Ok(assign::AssignCommand::User {
+            username: "octocat".to_owned()
+        }))
+    );
+}
+
+#[test]
+fn filler_comma() {
+    let mut input = Input::new("@rustbot, claim", &["rustbot"]);
+    assert_eq!(
+        input.parse_command(),
+        Command::Assign(Ok(assign::AssignCommand::Own))
+    );
+}
+
+#[test]
+fn filler_please() {
+    let mut input = Input::new("@rustbot please claim", &["rustbot"]);
+    assert_eq!(
+        input.parse_command(),
+        Command::Assign(Ok(assign::AssignCommand::Own))
+    );
+}
+
+#[test]
+fn filler_could_you() {
+    let mut input = Input::new("Hey @rustbot, could you please claim.", &["rustbot"]);
+    assert_eq!(
+        input.parse_command(),
+        Command::Assign(Ok(assign::AssignCommand::Own))
+    );
+}
+
+#[test]
+fn filler_not_a_command() {
+    let mut input = Input::new("@rustbot please, could we discuss this", &["rustbot"]);
+    assert!(input.parse_command().is_none());
+}
+
+#[test]
+fn mention_aliases() {
+    let input = "@other claim. @bot modify labels: +bug.";
+    let mut input = Input::new(input, &["bot", "other"]);
+    let commands = input.commands().collect::<Vec<_>>();
+    assert_eq!(commands.len(), 2);
+    assert!(match commands[0] {
+        Command::Assign(Ok(_)) => true,
+        _ => false,
+    });
+    assert!(match commands[1] {
+        Command::Relabel(Ok(_)) => true,
+        _ => false,
+    });
+}
+
+#[test]
+fn unterminated_quoted_label() {
+    let input = "@bot modify labels: +\"good first issue. Afterwards, delete the world.";
+    let mut input = Input::new(input, &["bot"]);
+    assert!(input.parse_command().is_err());
+    assert_eq!(input.parsed, 0);
+}
+
+#[test]
+fn parse_all_in_order() {
+    let commands = parse_all("@bot modify labels: +bug. Then @bot claim.", "bot");
+    assert_eq!(commands.len(), 2);
+    assert!(match commands[0] {
+        Command::Relabel(Ok(_)) => true,
+        _ => false,
+    });
+    assert!(match commands[1] {
+        Command::Assign(Ok(_)) => true,
+        _ => false,
+    });
+}
+
+#[test]
+fn parse_all_no_commands() {
+    assert!(parse_all("Nothing to see here, @bot.", "bot").is_empty());
+}
+
+#[test]
+fn parse_added_fixed_typo() {
+    let old = "@bot claim.\n@bot modify lables: +bug.";
+    let new = "@bot claim.\n@bot modify labels: +bug.";
+    assert!(match &parse_added(old, new, &["bot"])[..] {
+        [Command::Relabel(Ok(_))] => true,
+        _ => false,
+    });
+}
+
+#[test]
+fn parse_added_unchanged() {
+    let old = "@bot claim. @bot modify labels: +bug.";
+    let new = "Edit: @bot modify labels: +bug. and @bot claim.";
+    assert!(parse_added(old, new, &["bot"]).is_empty());
+}
+
+#[test]
+fn parse_added_repeated() {
+    let old = "@bot modify labels: +bug.";
+    let new = "@bot modify labels: +bug. @bot modify labels: +bug.";
+    assert_eq!(parse_added(old, new, &["bot"]).len(), 1);
+}
+
+#[test]
+fn parse_added_errors_kept() {
+    let old = "@bot prioritize.";
+    let new = "@bot prioritize. Oops.";
+    assert!(match &parse_added(old, new, &["bot"])[..] {
+        [Command::Triage(Err(_))] => true,
+        _ => false,
+    });
+}
+
+#[test]
+fn note_mentioning_bot() {
+    let input = "@bot note then @bot claim, maybe.\n@bot close";
+    let commands = parse_all(input, "bot");
+    assert!(match &commands[..] {
+        [Command::Note(Ok(note)), Command::Close(Ok(_))] => note.text == "then @bot claim, maybe.",
+        _ => false,
+    });
+}
+
+// Inputs which used to make the parser panic, found by fuzzing (see `fuzz/`).
+#[test]
+fn fuzz_regressions() {
+    for input in &[
+        "@botty",
+        "@BOT+to-",
+        ":@bot@bot",
+        "@bot \"",
+        "+@bot  \"",
+        "@bot\t+modify\"@é日本.",
+        "😀@BOT ping",
+        "日本.:claim @botty@bot日本modify@bottyprioritize😀",
+    ] {
+        for command in parse_all(input, "bot") {
+            match command {
+                Command::Relabel(Err(err))
+                | Command::Assign(Err(err))
+                | Command::Close(Err(err))
+                | Command::Ping(Err(err))
+                | Command::Triage(Err(err))
+                | Command::Blocked(Err(err))
+                | Command::Note(Err(err))
+                | Command::Second(Err(err))
+                | Command::Milestone(Err(err))
+                | Command::Rename(Err(err))
+                | Command::Poll(Err(err))
+                | Command::Help(Err(err))
+                | Command::Transfer(Err(err))
+                | Command::Hello(Err(err))
+                | Command::Minimize(Err(err))
+                | Command::Resolve(Err(err))
+                | Command::Log(Err(err))
+                | Command::Lock(Err(err))
+                | Command::Subscribe(Err(err))
+                | Command::Status(Err(err)) => {
+                    err.to_string();
+                    err.snippet();
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[test]
+fn parse_all_candidates() {
+    let mut input = Input::new("@bot claim. @bot modify labels: +T-compiler", &["bot"]);
+    assert_eq!(
+        input.parse_command_all(),
+        vec![Command::Assign(Ok(assign::AssignCommand::Own))]
+    );
+    assert_eq!(input.parse_command_all().len(), 1);
+    assert_eq!(input.parse_command_all(), vec![]);
+}
+
+#[cfg(test)]
+fn commands<'a>(candidates: Vec<(Tokenizer<'a>, Command<'a>)>) -> Vec<Command<'a>> {
+    by_precedence(candidates)
+        .into_iter()
+        .map(|(_, c)| c)
+        .collect()
+}
+
+#[test]
+fn precedence() {
+    let input = "claim and more";
+    let close = || Command::Close(Ok(close::CloseCommand::Close));
+    let claim = || Command::Assign(Ok(assign::AssignCommand::Own));
+    let at = |pos| Tokenizer::new_at(input, pos);
+    let failed = || Command::Close(Err(at(14).error(close::ParseError::ExpectedEnd)));
+    // Longer parses win.
+    assert_eq!(
+        commands(vec![(at(5), close()), (at(9), claim())]),
+        vec![claim(), close()]
+    );
+    // Then the command tried first.
+    assert_eq!(
+        commands(vec![(at(5), close()), (at(5), claim())]),
+        vec![close(), claim()]
+    );
+    // And successful parses always beat failed ones.
+    assert_eq!(
+        commands(vec![(at(14), failed()), (at(5), claim())]),
+        vec![claim(), failed()]
+    );
+}
+
+#[cfg(test)]
+fn keyword<'a>(input: &'a str, keyword: &str) -> Result<Option<()>, Error<'a>> {
+    parse_keyword(&mut Tokenizer::new(input), keyword, ())
+}
+
+#[test]
+fn keyword_commands() {
+    assert_eq!(keyword("hello.", "hello"), Ok(Some(())));
+    assert_eq!(keyword("hello", "hello"), Ok(Some(())));
+    assert_eq!(keyword("hi", "hello"), Ok(None));
+    assert_eq!(keyword("logs", "log"), Ok(None));
+}
+
+#[test]
+fn keyword_trailing() {
+    use std::error::Error;
+    assert_eq!(
+        keyword("status report", "status")
+            .unwrap_err()
+            .source()
+            .unwrap()
+            .downcast_ref(),
+        Some(&KeywordError::ExpectedEnd),
+    );
+}
@@ -1,22 +1,41 @@
 //! The assignment command parser.
 //!
-//! This can parse arbitrary input, giving the user to be assigned.
+//! This can parse arbitrary input, giving the user (or users) to be assigned.
 //!
 //! The grammar is as follows:
 //!
 //! ```text
-//! Command: `@bot claim`, `@bot release-assignment`, or `@bot assign @user`.
+//! Command: `@bot claim`, `@bot release-assignment`, `@bot assign @user`,
+//!          `@bot assign @user @user ...`, `@bot assign`, `@bot unassign @user`, or
+//!          `@bot unassign`.
 //! ```
+//!
+//! Users may be given with or without the `@`, or quoted; when assigning several users, all but
+//! the first need the `@`, so that the command doesn't run on into the words after it.
+//!
+//! A bare `assign` asks for the next reviewer from the repository's review rotation, and a bare
+//! `unassign` is the same as `release-assignment`.
 
+use crate::command::is_valid_username;
 use crate::error::Error;
-use crate::token::{Token, Tokenizer};
+use crate::token::{Argument, Token, Tokenizer};
 use std::fmt;
 
 #[derive(PartialEq, Eq, Debug)]
 pub enum AssignCommand {
     Own,
     Release,
-    User { username: String },
+    User {
+        username: String,
+    },
+    /// Assigning several users at once; there are always at least two.
+    Users {
+        usernames: Vec<String>,
+    },
+    Rotation,
+    Unassign {
+        username: String,
+    },
 }
 
 #[derive(PartialEq, Eq, Debug)]
@@ -24,6 +43,7 @@ pub enum ParseError {
     ExpectedEnd,
     MentionUser,
     NoUser,
+    InvalidUser,
 }
 
 impl std::error::Error for ParseError {}
@@ -34,6 +54,7 @@ impl fmt::Display for ParseError {
             ParseError::MentionUser => write!(f, "user should start with @"),
             ParseError::ExpectedEnd => write!(f, "expected end of command"),
             ParseError::NoUser => write!(f, "specify user to assign to"),
+            ParseError::InvalidUser => write!(f, "not a valid GitHub username"),
         }
     }
 }
@@ -43,8 +64,7 @@ impl AssignCommand {
         let mut toks = input.clone();
         if let Some(Token::Word("claim")) = toks.peek_token()? {
             toks.next_token()?;
-            if let Some(Token::Dot) | Some(Token::EndOfLine) = toks.peek_token()? {
-                toks.next_token()?;
+            if toks.eat_terminator()? {
                 *input = toks;
                 return Ok(Some(AssignCommand::Own));
             } else {
@@ -52,21 +72,44 @@ impl AssignCommand {
             }
         } else if let Some(Token::Word("assign")) = toks.peek_token()? {
             toks.next_token()?;
-            if let Some(Token::Word(user)) = toks.next_token()? {
-                if user.starts_with("@") && user.len() != 1 {
-                    Ok(Some(AssignCommand::User {
-                        username: user[1..].to_owned(),
-                    }))
-                } else {
-                    return Err(toks.error(ParseError::MentionUser));
+            if toks.eat_terminator()? {
+                *input = toks;
+                return Ok(Some(AssignCommand::Rotation));
+            }
+            let mut usernames = vec![parse_user(&mut toks)?];
+            loop {
+                match toks.peek_token()? {
+                    Some(Token::Mention(_)) => {}
+                    // Something like `@-bob-`, which isn't a valid mention.
+                    Some(Token::Word(word)) if word.starts_with('@') => {}
+                    _ => break,
                 }
+                usernames.push(parse_user(&mut toks)?);
+            }
+            *input = toks;
+            if usernames.len() == 1 {
+                Ok(Some(AssignCommand::User {
+                    username: usernames.remove(0),
+                }))
+            } else {
+                Ok(Some(AssignCommand::Users { usernames }))
+            }
+        } else if let Some(Token::Word("unassign")) = toks.peek_token()? {
+            toks.next_token()?;
+            if toks.eat_terminator()? {
+                *input = toks;
+                return Ok(Some(AssignCommand::Release));
+            }
+            let username = parse_user(&mut toks)?;
+            if toks.eat_terminator()? {
+                *input = toks;
+                Ok(Some(AssignCommand::Unassign { username }))
             } else {
-                return Err(toks.error(ParseError::NoUser));
+                Err(toks.error(ParseError::ExpectedEnd))
             }
         } else if let Some(Token::Word("release-assignment")) = toks.peek_token()? {
             toks.next_token()?;
-            if let Some(Token::Dot) | Some(Token::EndOfLine) = toks.peek_token()? {
-                toks.next_token()?;
+            if toks.eat_terminator()? {
                 *input = toks;
                 return Ok(Some(AssignCommand::Release));
             } else {
@@ -78,6 +121,23 @@ impl AssignCommand {
     }
 }
 
+/// Parses a username, with or without the `@` of a mention.
+fn parse_user<'a>(toks: &mut Tokenizer<'a>) -> Result<String, Error<'a>> {
+    if let Some(Token::Mention(user)) = toks.peek_token()? {
+        toks.next_token()?;
+        return Ok(user.to_owned());
+    }
+    let mut at = toks.clone();
+    match toks.next_quoted_or_word()?.map(Argument::text) {
+        Some(user) if is_valid_username(user) => Ok(user.to_owned()),
+        Some(user) if user.starts_with('@') && user.len() != 1 => {
+            Err(at.error(ParseError::InvalidUser))
+        }
+        Some(_) => Err(at.error(ParseError::MentionUser)),
+        None => Err(at.error(ParseError::NoUser)),
+    }
+}
+
 #[cfg(test)]
 fn parse<'a>(input: &'a str) -> Result<Option<AssignCommand>, Error<'a>> {
     let mut toks = Tokenizer::new(input);
@@ -116,3 +176,111 @@ fn test_4() {
         Some(&ParseError::MentionUser),
     );
 }
+
+#[test]
+fn test_rotation() {
+    assert_eq!(parse("assign"), Ok(Some(AssignCommand::Rotation)));
+    assert_eq!(parse("assign."), Ok(Some(AssignCommand::Rotation)));
+}
+
+#[test]
+fn test_unassign() {
+    assert_eq!(
+        parse("unassign @user."),
+        Ok(Some(AssignCommand::Unassign {
+            username: "user".to_owned()
+        })),
+    );
+    assert_eq!(parse("unassign"), Ok(Some(AssignCommand::Release)));
+}
+
+#[test]
+fn test_unassign_bare_user() {
+    assert_eq!(
+        parse("unassign user"),
+        Ok(Some(AssignCommand::Unassign {
+            username: "user".to_owned()
+        })),
+    );
+}
+
+#[test]
+fn test_unassign_not_mention() {
+    use std::error::Error;
+    assert_eq!(
+        parse("unassign 日本")
+            .unwrap_err()
+            .source()
+            .unwrap()
+            .downcast_ref(),
+        Some(&ParseError::MentionUser),
+    );
+}
+
+#[test]
+fn test_quoted_user() {
+    assert_eq!(
+        parse(r#"assign "octocat""#),
+        Ok(Some(AssignCommand::User {
+            username: "octocat".to_owned()
+        })),
+    );
+}
+
+#[test]
+fn test_bare_user() {
+    assert_eq!(
+        parse("assign octocat"),
+        Ok(Some(AssignCommand::User {
+            username: "octocat".to_owned()
+        })),
+    );
+}
+
+#[test]
+fn test_invalid_user() {
+    use std::error::Error;
+    assert_eq!(
+        parse("assign @-bad-")
+            .unwrap_err()
+            .source()
+            .unwrap()
+            .downcast_ref(),
+        Some(&ParseError::InvalidUser),
+    );
+}
+
+#[test]
+fn test_several_users() {
+    assert_eq!(
+        parse("assign @alice @bob-2 @carol please"),
+        Ok(Some(AssignCommand::Users {
+            usernames: vec!["alice".to_owned(), "bob-2".to_owned(), "carol".to_owned()]
+        })),
+    );
+    assert_eq!(
+        parse("assign alice @bob"),
+        Ok(Some(AssignCommand::Users {
+            usernames: vec!["alice".to_owned(), "bob".to_owned()]
+        })),
+    );
+    assert_eq!(
+        parse("assign @alice bob"),
+        Ok(Some(AssignCommand::User {
+            username: "alice".to_owned()
+        })),
+    );
+}
+
+#[test]
+fn test_several_users_invalid() {
+    use std::error::Error;
+    assert_eq!(
+        parse("assign @alice @-bad- @bob")
+            .unwrap_err()
+            .source()
+            .unwrap()
+            .downcast_ref(),
+        Some(&ParseError::InvalidUser),
+    );
+}
@@ -0,0 +1,132 @@
+//! The blocked command parser.
+//!
+//! This can parse arbitrary input, giving the issue this one is blocked on (if any).
+//!
+//! The grammar is as follows:
+//!
+//! ```text
+//! Command: `@bot blocked on #<issue>` or `@bot unblocked`.
+//!
+//! `on` may be omitted.
+//!
+//! <issue>:
+//!  - a positive integer
+//! ```
+
+use crate::error::Error;
+use crate::token::{Token, Tokenizer};
+use std::fmt;
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum BlockedCommand {
+    Blocked { issue: u64 },
+    Unblocked,
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum ParseError {
+    ExpectedIssue,
+    InvalidIssue,
+    ExpectedEnd,
+}
+
+impl std::error::Error for ParseError {}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::ExpectedIssue => write!(f, "expected the blocking issue, like #1234"),
+            ParseError::InvalidIssue => write!(f, "issue numbers must be positive integers"),
+            ParseError::ExpectedEnd => write!(f, "expected end of command"),
+        }
+    }
+}
+
+impl BlockedCommand {
+    pub fn parse<'a>(input: &mut Tokenizer<'a>) -> Result<Option<Self>, Error<'a>> {
+        let mut toks = input.clone();
+        let command = match toks.peek_token()? {
+            Some(Token::Word("blocked")) => {
+                toks.next_token()?;
+                if let Some(Token::Word("on")) = toks.peek_token()? {
+                    toks.next_token()?;
+                }
+                let issue = match toks.peek_token()? {
+                    Some(Token::Word(issue)) if issue.starts_with('#') => issue,
+                    _ => return Err(toks.error(ParseError::ExpectedIssue)),
+                };
+                let issue = match issue[1..].parse::<u64>() {
+                    Ok(issue) if issue > 0 => issue,
+                    _ => return Err(toks.error(ParseError::InvalidIssue)),
+                };
+                toks.next_token()?;
+                BlockedCommand::Blocked { issue }
+            }
+            Some(Token::Word("unblocked")) => {
+                toks.next_token()?;
+                BlockedCommand::Unblocked
+            }
+            _ => return Ok(None),
+        };
+        if toks.eat_terminator()? {
+            *input = toks;
+            Ok(Some(command))
+        } else {
+            Err(toks.error(ParseError::ExpectedEnd))
+        }
+    }
+}
+
+#[cfg(test)]
+fn parse<'a>(input: &'a str) -> Result<Option<BlockedCommand>, Error<'a>> {
+    let mut toks = Tokenizer::new(input);
+    Ok(BlockedCommand::parse(&mut toks)?)
+}
+
+#[test]
+fn test_blocked() {
+    assert_eq!(
+        parse("blocked on #1234."),
+        Ok(Some(BlockedCommand::Blocked { issue: 1234 }))
+    );
+    assert_eq!(
+        parse("blocked #5"),
+        Ok(Some(BlockedCommand::Blocked { issue: 5 }))
+    );
+}
+
+#[test]
+fn test_unblocked() {
+    assert_eq!(parse("unblocked"), Ok(Some(BlockedCommand::Unblocked)));
+}
+
+#[test]
+fn test_no_issue() {
+    use std::error::Error;
+    assert_eq!(
+        parse("blocked on the PR")
+            .unwrap_err()
+            .source()
+            .unwrap()
+            .downcast_ref(),
+        Some(&ParseError::ExpectedIssue),
+    );
+}
+
+#[test]
+fn test_invalid_issue() {
+    use std::error::Error;
+    for input in &[
+        "blocked on #0",
+        "blocked on #-3",
+        "blocked on #12a",
+        "blocked on #",
+    ] {
+        assert_eq!(
+            parse(input).unwrap_err().source().unwrap().downcast_ref(),
+            Some(&ParseError::InvalidIssue),
+            "{}",
+            input
+        );
+    }
+}
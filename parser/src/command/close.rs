@@ -0,0 +1,87 @@
+//! The close command parser.
+//!
+//! This can parse arbitrary input, giving the state the issue should be moved to.
+//!
+//! The grammar is as follows:
+//!
+//! ```text
+//! Command: `@bot close` or `@bot reopen`.
+//! ```
+
+use crate::error::Error;
+use crate::token::{Token, Tokenizer};
+use std::fmt;
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum CloseCommand {
+    Close,
+    Reopen,
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum ParseError {
+    ExpectedEnd,
+}
+
+impl std::error::Error for ParseError {}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::ExpectedEnd => write!(f, "expected end of command"),
+        }
+    }
+}
+
+impl CloseCommand {
+    pub fn parse<'a>(input: &mut Tokenizer<'a>) -> Result<Option<Self>, Error<'a>> {
+        let mut toks = input.clone();
+        let command = match toks.peek_token()? {
+            Some(Token::Word("close")) => CloseCommand::Close,
+            Some(Token::Word("reopen")) => CloseCommand::Reopen,
+            _ => return Ok(None),
+        };
+        toks.next_token()?;
+        if toks.eat_terminator()? {
+            *input = toks;
+            Ok(Some(command))
+        } else {
+            Err(toks.error(ParseError::ExpectedEnd))
+        }
+    }
+}
+
+#[cfg(test)]
+fn parse<'a>(input: &'a str) -> Result<Option<CloseCommand>, Error<'a>> {
+    let mut toks = Tokenizer::new(input);
+    Ok(CloseCommand::parse(&mut toks)?)
+}
+
+#[test]
+fn test_close() {
+    assert_eq!(parse("close."), Ok(Some(CloseCommand::Close)));
+    assert_eq!(parse("close"), Ok(Some(CloseCommand::Close)));
+}
+
+#[test]
+fn test_reopen() {
+    assert_eq!(parse("reopen"), Ok(Some(CloseCommand::Reopen)));
+}
+
+#[test]
+fn test_trailing() {
+    use std::error::Error;
+    assert_eq!(
+        parse("close this please")
+            .unwrap_err()
+            .source()
+            .unwrap()
+            .downcast_ref(),
+        Some(&ParseError::ExpectedEnd),
+    );
+}
+
+#[test]
+fn test_other() {
+    assert_eq!(parse("closed issues are good"), Ok(None));
+}
@@ -0,0 +1,5 @@
+//! The hello command (`@bot hello`), which has no arguments; it's there to check that the bot is
+//! listening. It's parsed by `parse_keyword`.
+
+#[derive(PartialEq, Eq, Debug)]
+pub struct HelloCommand;
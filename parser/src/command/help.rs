@@ -0,0 +1,257 @@
+//! The help command parser.
+//!
+//! This can parse arbitrary input, giving the command (if any) to describe.
+//!
+//! The grammar is as follows:
+//!
+//! ```text
+//! Command: `@bot help` or `@bot help <command>`.
+//!
+//! <command>:
+//!  - the name of a command, or the first word of one of its forms (e.g., `assign` or `claim`)
+//! ```
+//!
+//! This module also lists how each command is used, which is what the help consists of.
+
+use crate::error::Error;
+use crate::token::{Token, Tokenizer};
+use std::fmt;
+
+#[derive(PartialEq, Eq, Debug)]
+pub struct HelpCommand {
+    pub command: Option<String>,
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum ParseError {
+    ExpectedCommand,
+    ExpectedEnd,
+}
+
+impl std::error::Error for ParseError {}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::ExpectedCommand => write!(f, "expected the name of a command"),
+            ParseError::ExpectedEnd => write!(f, "expected end of command"),
+        }
+    }
+}
+
+impl HelpCommand {
+    pub fn parse<'a>(input: &mut Tokenizer<'a>) -> Result<Option<Self>, Error<'a>> {
+        let mut toks = input.clone();
+        if let Some(Token::Word("help")) = toks.peek_token()? {
+            toks.next_token()?;
+        } else {
+            return Ok(None);
+        }
+        if toks.eat_terminator()? {
+            *input = toks;
+            return Ok(Some(HelpCommand { command: None }));
+        }
+        let command = match toks.peek_token()? {
+            Some(Token::Word(command)) => command,
+            _ => return Err(toks.error(ParseError::ExpectedCommand)),
+        };
+        toks.next_token()?;
+        if !toks.eat_terminator()? {
+            return Err(toks.error(ParseError::ExpectedEnd));
+        }
+        *input = toks;
+        Ok(Some(HelpCommand {
+            command: Some(command.to_owned()),
+        }))
+    }
+}
+
+/// How one of the commands is used.
+#[derive(Debug)]
+pub struct CommandHelp {
+    /// The name of the command, which is also the name of the feature handling it.
+    pub name: &'static str,
+    /// The forms the command takes, without the mention of the bot.
+    pub syntax: &'static [&'static str],
+    pub description: &'static str,
+}
+
+impl CommandHelp {
+    /// Whether `word` refers to this command, by its name or the first word of one of its forms.
+    pub fn is_called(&self, word: &str) -> bool {
+        self.name.eq_ignore_ascii_case(word)
+            || self.syntax.iter().any(|form| {
+                form.split(|c: char| c.is_whitespace() || c == ':')
+                    .next()
+                    .map_or(false, |first| first.eq_ignore_ascii_case(word))
+            })
+    }
+}
+
+/// Every command, in the order the help lists them.
+pub static COMMANDS: &[CommandHelp] = &[
+    CommandHelp {
+        name: "assign",
+        syntax: &[
+            "claim",
+            "release-assignment",
+            "assign @<user>",
+            "assign @<user> @<user> ...",
+            "unassign @<user>",
+        ],
+        description: "assign someone to the issue, or release the assignment",
+    },
+    CommandHelp {
+        name: "blocked",
+        syntax: &["blocked on #<issue>", "unblocked"],
+        description: "mark the issue as blocked on another one",
+    },
+    CommandHelp {
+        name: "close",
+        syntax: &["close", "reopen"],
+        description: "close or reopen the issue",
+    },
+    CommandHelp {
+        name: "hello",
+        syntax: &["hello"],
+        description: "check that the bot is working here, and see how it's set up",
+    },
+    CommandHelp {
+        name: "help",
+        syntax: &["help", "help <command>"],
+        description: "list the commands, or show how one is used",
+    },
+    CommandHelp {
+        name: "lock",
+        syntax: &["lock", "lock as <reason>", "unlock"],
+        description: "lock or unlock the conversation, optionally saying why",
+    },
+    CommandHelp {
+        name: "log",
+        syntax: &["log"],
+        description: "list the commands run on the issue, and who ran them",
+    },
+    CommandHelp {
+        name: "milestone",
+        syntax: &["milestone <milestone>", "milestone none"],
+        description: "set the issue's milestone",
+    },
+    CommandHelp {
+        name: "minimize",
+        syntax: &["minimize"],
+        description: "ask for a minimal example reproducing the issue",
+    },
+    CommandHelp {
+        name: "note",
+        syntax: &["note <text>"],
+        description: "add a note to the summary in the issue's top comment",
+    },
+    CommandHelp {
+        name: "ping",
+        syntax: &["ping <group>"],
+        description: "notify one of the configured groups about the issue",
+    },
+    CommandHelp {
+        name: "poll",
+        syntax: &["poll \"<question>\" <option> <option>...", "poll close"],
+        description: "open a poll on the issue, or close it and post the results",
+    },
+    CommandHelp {
+        name: "relabel",
+        syntax: &[
+            "modify labels: +<label> -<label>",
+            "label +<label> -<label>",
+            "label +<label> -<label> dry",
+        ],
+        description: "add or remove labels; with `dry` (or `preview`), only say what would change",
+    },
+    CommandHelp {
+        name: "rename",
+        syntax: &["rename <title>"],
+        description: "change the issue's title",
+    },
+    CommandHelp {
+        name: "resolve",
+        syntax: &["resolve"],
+        description: "mark the review thread this is a reply in as resolved",
+    },
+    CommandHelp {
+        name: "second",
+        syntax: &["second"],
+        description: "second the proposal in the issue",
+    },
+    CommandHelp {
+        name: "status",
+        syntax: &["status"],
+        description: "show who's assigned to the issue and its priority, as the bot sees them",
+    },
+    CommandHelp {
+        name: "subscribe",
+        syntax: &[
+            "subscribe",
+            "subscribe @<user>",
+            "unsubscribe",
+            "unsubscribe @<user>",
+        ],
+        description: "get notified when the issue is closed, reopened or relabeled",
+    },
+    CommandHelp {
+        name: "transfer",
+        syntax: &["transfer <owner>/<repo>"],
+        description: "move the issue to another repository",
+    },
+    CommandHelp {
+        name: "triage",
+        syntax: &[
+            "prioritize <priority>",
+            "prioritize <priority> status <status>",
+            "prioritize none",
+        ],
+        description: "set (or clear) the issue's priority, and optionally its status",
+    },
+];
+
+/// The command called `name` (see `CommandHelp::is_called`), if there is one.
+pub fn find(name: &str) -> Option<&'static CommandHelp> {
+    COMMANDS.iter().find(|command| command.is_called(name))
+}
+
+#[cfg(test)]
+fn parse<'a>(input: &'a str) -> Result<Option<HelpCommand>, Error<'a>> {
+    let mut toks = Tokenizer::new(input);
+    Ok(HelpCommand::parse(&mut toks)?)
+}
+
+#[test]
+fn test_help() {
+    assert_eq!(parse("help."), Ok(Some(HelpCommand { command: None })));
+    assert_eq!(
+        parse("help assign"),
+        Ok(Some(HelpCommand {
+            command: Some("assign".to_owned())
+        }))
+    );
+}
+
+#[test]
+fn test_trailing() {
+    use std::error::Error;
+    assert_eq!(
+        parse("help assign claim")
+            .unwrap_err()
+            .source()
+            .unwrap()
+            .downcast_ref(),
+        Some(&ParseError::ExpectedEnd),
+    );
+}
+
+#[test]
+fn test_find() {
+    assert_eq!(find("assign").unwrap().name, "assign");
+    assert_eq!(find("Claim").unwrap().name, "assign");
+    assert_eq!(find("modify").unwrap().name, "relabel");
+    assert_eq!(find("label").unwrap().name, "relabel");
+    assert_eq!(find("prioritize").unwrap().name, "triage");
+    assert!(find("frobnicate").is_none());
+}
@@ -0,0 +1,182 @@
+//! The lock command parser.
+//!
+//! This can parse arbitrary input, giving whether to lock the issue's conversation (and why) or
+//! unlock it.
+//!
+//! The grammar is as follows:
+//!
+//! ```text
+//! Command: `@bot lock`, `@bot lock as <reason>` or `@bot unlock`.
+//!
+//! <reason>:
+//!  - `off-topic`, `too heated`, `resolved` or `spam`, possibly quoted
+//! ```
+
+use crate::error::Error;
+use crate::token::{Argument, Token, Tokenizer};
+use std::fmt;
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum LockCommand {
+    Lock(Option<LockReason>),
+    Unlock,
+}
+
+/// Why a conversation is locked; these are the reasons GitHub allows.
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub enum LockReason {
+    OffTopic,
+    TooHeated,
+    Resolved,
+    Spam,
+}
+
+impl LockReason {
+    /// How GitHub names the reason.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LockReason::OffTopic => "off-topic",
+            LockReason::TooHeated => "too heated",
+            LockReason::Resolved => "resolved",
+            LockReason::Spam => "spam",
+        }
+    }
+}
+
+impl fmt::Display for LockReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum ParseError {
+    InvalidReason,
+    ExpectedEnd,
+}
+
+impl std::error::Error for ParseError {}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::InvalidReason => write!(
+                f,
+                "expected the reason for locking: `off-topic`, `too heated`, `resolved` or `spam`"
+            ),
+            ParseError::ExpectedEnd => write!(f, "expected end of command"),
+        }
+    }
+}
+
+impl LockCommand {
+    pub fn parse<'a>(input: &mut Tokenizer<'a>) -> Result<Option<Self>, Error<'a>> {
+        let mut toks = input.clone();
+        let command = match toks.peek_token()? {
+            Some(Token::Word("lock")) => {
+                toks.next_token()?;
+                if let Some(Token::Word("as")) = toks.peek_token()? {
+                    toks.next_token()?;
+                    LockCommand::Lock(Some(parse_reason(&mut toks)?))
+                } else {
+                    LockCommand::Lock(None)
+                }
+            }
+            Some(Token::Word("unlock")) => {
+                toks.next_token()?;
+                LockCommand::Unlock
+            }
+            _ => return Ok(None),
+        };
+        if toks.eat_terminator()? {
+            *input = toks;
+            Ok(Some(command))
+        } else {
+            Err(toks.error(ParseError::ExpectedEnd))
+        }
+    }
+}
+
+fn parse_reason<'a>(toks: &mut Tokenizer<'a>) -> Result<LockReason, Error<'a>> {
+    let mut next = toks.clone();
+    let reason = match next.next_quoted_or_word()? {
+        Some(Argument::Word("off-topic")) | Some(Argument::Quoted("off-topic")) => {
+            LockReason::OffTopic
+        }
+        Some(Argument::Word("too")) => match next.next_token()? {
+            Some(Token::Word("heated")) => LockReason::TooHeated,
+            _ => return Err(toks.error(ParseError::InvalidReason)),
+        },
+        Some(Argument::Quoted("too heated")) => LockReason::TooHeated,
+        Some(Argument::Word("resolved")) | Some(Argument::Quoted("resolved")) => {
+            LockReason::Resolved
+        }
+        Some(Argument::Word("spam")) | Some(Argument::Quoted("spam")) => LockReason::Spam,
+        _ => return Err(toks.error(ParseError::InvalidReason)),
+    };
+    *toks = next;
+    Ok(reason)
+}
+
+#[cfg(test)]
+fn parse<'a>(input: &'a str) -> Result<Option<LockCommand>, Error<'a>> {
+    let mut toks = Tokenizer::new(input);
+    Ok(LockCommand::parse(&mut toks)?)
+}
+
+#[test]
+fn test_lock() {
+    assert_eq!(parse("lock."), Ok(Some(LockCommand::Lock(None))));
+    assert_eq!(
+        parse("lock as off-topic"),
+        Ok(Some(LockCommand::Lock(Some(LockReason::OffTopic))))
+    );
+    assert_eq!(
+        parse("lock as too heated."),
+        Ok(Some(LockCommand::Lock(Some(LockReason::TooHeated))))
+    );
+    assert_eq!(
+        parse(r#"lock as "too heated""#),
+        Ok(Some(LockCommand::Lock(Some(LockReason::TooHeated))))
+    );
+    assert_eq!(
+        parse("lock as spam"),
+        Ok(Some(LockCommand::Lock(Some(LockReason::Spam))))
+    );
+}
+
+#[test]
+fn test_unlock() {
+    assert_eq!(parse("unlock"), Ok(Some(LockCommand::Unlock)));
+}
+
+#[test]
+fn test_invalid_reason() {
+    use std::error::Error;
+    for input in &["lock as rude", "lock as too hot", "lock as"] {
+        assert_eq!(
+            parse(input).unwrap_err().source().unwrap().downcast_ref(),
+            Some(&ParseError::InvalidReason),
+            "{}",
+            input
+        );
+    }
+}
+
+#[test]
+fn test_trailing() {
+    use std::error::Error;
+    assert_eq!(
+        parse("lock this thread")
+            .unwrap_err()
+            .source()
+            .unwrap()
+            .downcast_ref(),
+        Some(&ParseError::ExpectedEnd),
+    );
+}
+
+#[test]
+fn test_other() {
+    assert_eq!(parse("locked"), Ok(None));
+}
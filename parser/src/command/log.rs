@@ -0,0 +1,5 @@
+//! The log command (`@bot log`), which has no arguments; it asks for the commands the bot has run
+//! on the issue. It's parsed by `parse_keyword`.
+
+#[derive(PartialEq, Eq, Debug)]
+pub struct LogCommand;
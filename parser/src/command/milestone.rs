@@ -0,0 +1,132 @@
+//! The milestone command parser.
+//!
+//! This can parse arbitrary input, giving the milestone the issue should be in (if any).
+//!
+//! The grammar is as follows:
+//!
+//! ```text
+//! Command: `@bot milestone <milestone>` or `@bot milestone none`.
+//!
+//! <milestone>:
+//!  - a milestone title, either quoted (`"Rust 2018"`) or a single word (`1.40.0`)
+//! ```
+//!
+//! Unquoted titles are taken verbatim up to the next whitespace, so may contain dots.
+
+use crate::error::Error;
+use crate::token::{Token, Tokenizer};
+use std::fmt;
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum MilestoneCommand {
+    Set { title: String },
+    Clear,
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum ParseError {
+    ExpectedMilestone,
+    ExpectedEnd,
+}
+
+impl std::error::Error for ParseError {}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::ExpectedMilestone => write!(f, "expected milestone (or `none`)"),
+            ParseError::ExpectedEnd => write!(f, "expected end of command"),
+        }
+    }
+}
+
+impl MilestoneCommand {
+    pub fn parse<'a>(input: &mut Tokenizer<'a>) -> Result<Option<Self>, Error<'a>> {
+        let mut toks = input.clone();
+        if let Some(Token::Word("milestone")) = toks.peek_token()? {
+            toks.next_token()?;
+        } else {
+            return Ok(None);
+        }
+        let command = if let Some(Token::Word(_)) = toks.peek_token()? {
+            // Unquoted titles are taken verbatim, as they usually have dots in them.
+            match toks.verbatim_word() {
+                Some("none") => MilestoneCommand::Clear,
+                Some(title) => MilestoneCommand::Set {
+                    title: title.to_owned(),
+                },
+                None => return Err(toks.error(ParseError::ExpectedMilestone)),
+            }
+        } else {
+            match toks.next_quoted_or_word()? {
+                Some(title) => MilestoneCommand::Set {
+                    title: title.text().to_owned(),
+                },
+                None => return Err(toks.error(ParseError::ExpectedMilestone)),
+            }
+        };
+        if toks.eat_terminator()? {
+            *input = toks;
+            Ok(Some(command))
+        } else {
+            Err(toks.error(ParseError::ExpectedEnd))
+        }
+    }
+}
+
+#[cfg(test)]
+fn parse<'a>(input: &'a str) -> Result<Option<MilestoneCommand>, Error<'a>> {
+    let mut toks = Tokenizer::new(input);
+    Ok(MilestoneCommand::parse(&mut toks)?)
+}
+
+#[test]
+fn test_bare() {
+    assert_eq!(
+        parse("milestone 1.40.0."),
+        Ok(Some(MilestoneCommand::Set {
+            title: "1.40.0".to_owned()
+        })),
+    );
+}
+
+#[test]
+fn test_quoted() {
+    assert_eq!(
+        parse("milestone \"Rust 2018\""),
+        Ok(Some(MilestoneCommand::Set {
+            title: "Rust 2018".to_owned()
+        })),
+    );
+}
+
+#[test]
+fn test_none() {
+    assert_eq!(parse("milestone none"), Ok(Some(MilestoneCommand::Clear)));
+}
+
+#[test]
+fn test_no_milestone() {
+    use std::error::Error;
+    assert_eq!(
+        parse("milestone.")
+            .unwrap_err()
+            .source()
+            .unwrap()
+            .downcast_ref(),
+        Some(&ParseError::ExpectedMilestone),
+    );
+}
+
+#[test]
+fn test_trailing() {
+    use std::error::Error;
+    assert_eq!(
+        parse("milestone Rust 2018")
+            .unwrap_err()
+            .source()
+            .unwrap()
+            .downcast_ref(),
+        Some(&ParseError::ExpectedEnd),
+    );
+}
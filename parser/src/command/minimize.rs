@@ -0,0 +1,5 @@
+//! The minimize command (`@bot minimize`), which has no arguments; it asks for a minimal example
+//! reproducing the issue. It's parsed by `parse_keyword`.
+
+#[derive(PartialEq, Eq, Debug)]
+pub struct MinimizeCommand;
@@ -0,0 +1,99 @@
+//! The note command parser.
+//!
+//! This can parse arbitrary input, giving the text of the note.
+//!
+//! The grammar is as follows:
+//!
+//! ```text
+//! Command: `@bot note <text>`.
+//!
+//! <text>:
+//!  - the rest of the line, verbatim
+//! ```
+//!
+//! The text isn't tokenized, so it may contain anything (including quotes, or mentions of the
+//! bot, which aren't commands).
+
+use crate::error::Error;
+use crate::token::{Token, Tokenizer};
+use std::fmt;
+
+#[derive(PartialEq, Eq, Debug)]
+pub struct NoteCommand {
+    pub text: String,
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum ParseError {
+    EmptyNote,
+}
+
+impl std::error::Error for ParseError {}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::EmptyNote => write!(f, "expected the text of the note"),
+        }
+    }
+}
+
+impl NoteCommand {
+    pub fn parse<'a>(input: &mut Tokenizer<'a>) -> Result<Option<Self>, Error<'a>> {
+        let mut toks = input.clone();
+        if let Some(Token::Word("note")) = toks.peek_token()? {
+            toks.next_token()?;
+        } else {
+            return Ok(None);
+        }
+        let text = toks.rest_of_line();
+        if text.is_empty() {
+            return Err(toks.error(ParseError::EmptyNote));
+        }
+        // The end of the line.
+        toks.next_token()?;
+        *input = toks;
+        Ok(Some(NoteCommand {
+            text: text.to_owned(),
+        }))
+    }
+}
+
+#[cfg(test)]
+fn parse<'a>(input: &'a str) -> Result<Option<NoteCommand>, Error<'a>> {
+    let mut toks = Tokenizer::new(input);
+    Ok(NoteCommand::parse(&mut toks)?)
+}
+
+#[test]
+fn test_note() {
+    assert_eq!(
+        parse("note needs a \"crater\" run, see #123.\nThanks!"),
+        Ok(Some(NoteCommand {
+            text: "needs a \"crater\" run, see #123.".to_owned()
+        }))
+    );
+}
+
+#[test]
+fn test_note_at_end() {
+    assert_eq!(
+        parse("note   regressed in 1.36  "),
+        Ok(Some(NoteCommand {
+            text: "regressed in 1.36".to_owned()
+        }))
+    );
+}
+
+#[test]
+fn test_empty_note() {
+    use std::error::Error;
+    assert_eq!(
+        parse("note \nmore")
+            .unwrap_err()
+            .source()
+            .unwrap()
+            .downcast_ref(),
+        Some(&ParseError::EmptyNote),
+    );
+}
@@ -0,0 +1,103 @@
+//! The ping command parser.
+//!
+//! This can parse arbitrary input, giving the group to be pinged.
+//!
+//! The grammar is as follows:
+//!
+//! ```text
+//! Command: `@bot ping <group>`.
+//!
+//! <group>:
+//!  - a name made up of alphanumerics and hyphens
+//! ```
+
+use crate::error::Error;
+use crate::token::{Token, Tokenizer};
+use std::fmt;
+
+#[derive(PartialEq, Eq, Debug)]
+pub struct PingCommand {
+    pub group: String,
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum ParseError {
+    ExpectedGroup,
+    InvalidGroup,
+}
+
+impl std::error::Error for ParseError {}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::ExpectedGroup => write!(f, "expected group to ping"),
+            ParseError::InvalidGroup => {
+                write!(f, "group names may only contain alphanumerics and hyphens")
+            }
+        }
+    }
+}
+
+impl PingCommand {
+    pub fn parse<'a>(input: &mut Tokenizer<'a>) -> Result<Option<Self>, Error<'a>> {
+        let mut toks = input.clone();
+        if let Some(Token::Word("ping")) = toks.peek_token()? {
+            toks.next_token()?;
+        } else {
+            return Ok(None);
+        }
+        let group = match toks.peek_token()? {
+            Some(Token::Word(group)) => group,
+            Some(Token::Mention(_)) => return Err(toks.error(ParseError::InvalidGroup)),
+            _ => return Err(toks.error(ParseError::ExpectedGroup)),
+        };
+        if !group.chars().all(|c| c.is_alphanumeric() || c == '-') {
+            return Err(toks.error(ParseError::InvalidGroup));
+        }
+        toks.next_token()?;
+        toks.eat_terminator()?;
+        *input = toks;
+        Ok(Some(PingCommand {
+            group: group.to_owned(),
+        }))
+    }
+}
+
+#[cfg(test)]
+fn parse<'a>(input: &'a str) -> Result<Option<PingCommand>, Error<'a>> {
+    let mut toks = Tokenizer::new(input);
+    Ok(PingCommand::parse(&mut toks)?)
+}
+
+#[test]
+fn test_group() {
+    assert_eq!(
+        parse("ping icebreakers-cleanup-crew2."),
+        Ok(Some(PingCommand {
+            group: "icebreakers-cleanup-crew2".to_owned()
+        })),
+    );
+}
+
+#[test]
+fn test_no_group() {
+    use std::error::Error;
+    assert_eq!(
+        parse("ping.").unwrap_err().source().unwrap().downcast_ref(),
+        Some(&ParseError::ExpectedGroup),
+    );
+}
+
+#[test]
+fn test_invalid_group() {
+    use std::error::Error;
+    assert_eq!(
+        parse("ping @group")
+            .unwrap_err()
+            .source()
+            .unwrap()
+            .downcast_ref(),
+        Some(&ParseError::InvalidGroup),
+    );
+}
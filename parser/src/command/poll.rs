@@ -0,0 +1,176 @@
+//! The poll command parser.
+//!
+//! This can parse arbitrary input, giving the poll to open, or that the open poll should be
+//! closed.
+//!
+//! The grammar is as follows:
+//!
+//! ```text
+//! Command: `@bot poll <question> <option> <option>...` or `@bot poll close`.
+//!
+//! <question>:
+//!  - a quoted string
+//!
+//! <option>:
+//!  - a word
+//!  - a quoted string
+//! ```
+//!
+//! There must be at least two options, separated by whitespace, and no option may be given twice.
+
+use crate::error::Error;
+use crate::token::{Token, Tokenizer};
+use std::fmt;
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum PollCommand {
+    Open {
+        question: String,
+        options: Vec<String>,
+    },
+    Close,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum ParseError {
+    ExpectedQuestion,
+    ExpectedOption,
+    DuplicateOption(String),
+    TooFewOptions,
+    ExpectedEnd,
+}
+
+impl std::error::Error for ParseError {}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::ExpectedQuestion => write!(f, "expected the question, in quotes"),
+            ParseError::ExpectedOption => write!(f, "options must be words or quoted strings"),
+            ParseError::DuplicateOption(option) => {
+                write!(f, "the option `{}` is given twice", option)
+            }
+            ParseError::TooFewOptions => write!(f, "a poll needs at least two options"),
+            ParseError::ExpectedEnd => write!(f, "expected end of command"),
+        }
+    }
+}
+
+impl PollCommand {
+    pub fn parse<'a>(input: &mut Tokenizer<'a>) -> Result<Option<Self>, Error<'a>> {
+        let mut toks = input.clone();
+        if let Some(Token::Word("poll")) = toks.peek_token()? {
+            toks.next_token()?;
+        } else {
+            return Ok(None);
+        }
+        let question = match toks.peek_token()? {
+            Some(Token::Word("close")) => {
+                toks.next_token()?;
+                return if toks.eat_terminator()? {
+                    *input = toks;
+                    Ok(Some(PollCommand::Close))
+                } else {
+                    Err(toks.error(ParseError::ExpectedEnd))
+                };
+            }
+            Some(Token::Quote(question)) if !question.trim().is_empty() => question,
+            _ => return Err(toks.error(ParseError::ExpectedQuestion)),
+        };
+        toks.next_token()?;
+
+        let mut options: Vec<String> = Vec::new();
+        while !toks.eat_terminator()? {
+            let option = match toks.peek_token()? {
+                Some(Token::Word(option)) => option,
+                Some(Token::Quote(option)) if !option.trim().is_empty() => option.trim(),
+                _ => return Err(toks.error(ParseError::ExpectedOption)),
+            };
+            if options.iter().any(|o| o.eq_ignore_ascii_case(option)) {
+                return Err(toks.error(ParseError::DuplicateOption(option.to_owned())));
+            }
+            toks.next_token()?;
+            options.push(option.to_owned());
+        }
+        if options.len() < 2 {
+            return Err(toks.error(ParseError::TooFewOptions));
+        }
+        *input = toks;
+        Ok(Some(PollCommand::Open {
+            question: question.trim().to_owned(),
+            options,
+        }))
+    }
+}
+
+#[cfg(test)]
+fn parse<'a>(input: &'a str) -> Result<Option<PollCommand>, Error<'a>> {
+    let mut toks = Tokenizer::new(input);
+    Ok(PollCommand::parse(&mut toks)?)
+}
+
+#[cfg(test)]
+fn parse_error(input: &str) -> ParseError {
+    use std::error::Error;
+    let err = parse(input).unwrap_err();
+    err.source()
+        .unwrap()
+        .downcast_ref::<ParseError>()
+        .unwrap()
+        .clone()
+}
+
+#[test]
+fn test_open() {
+    assert_eq!(
+        parse("poll \"Ship it in 1.40?\" yes no \"wait for 1.41\"."),
+        Ok(Some(PollCommand::Open {
+            question: "Ship it in 1.40?".to_owned(),
+            options: vec![
+                "yes".to_owned(),
+                "no".to_owned(),
+                "wait for 1.41".to_owned()
+            ],
+        }))
+    );
+}
+
+#[test]
+fn test_close() {
+    assert_eq!(parse("poll close"), Ok(Some(PollCommand::Close)));
+    assert_eq!(parse_error("poll close now"), ParseError::ExpectedEnd);
+}
+
+#[test]
+fn test_question() {
+    assert_eq!(
+        parse_error("poll ship it? yes no"),
+        ParseError::ExpectedQuestion
+    );
+    assert_eq!(
+        parse_error("poll \" \" yes no"),
+        ParseError::ExpectedQuestion
+    );
+    assert_eq!(parse_error("poll"), ParseError::ExpectedQuestion);
+}
+
+#[test]
+fn test_options() {
+    assert_eq!(parse_error("poll \"Ship it?\""), ParseError::TooFewOptions);
+    assert_eq!(
+        parse_error("poll \"Ship it?\" yes"),
+        ParseError::TooFewOptions
+    );
+    assert_eq!(
+        parse_error("poll \"Ship it?\" yes \"\" no"),
+        ParseError::ExpectedOption
+    );
+    assert_eq!(
+        parse_error("poll \"Ship it?\" yes! no"),
+        ParseError::ExpectedOption
+    );
+    assert_eq!(
+        parse_error("poll \"Ship it?\" yes no Yes"),
+        ParseError::DuplicateOption("Yes".to_owned())
+    );
+}
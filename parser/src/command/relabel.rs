@@ -1,17 +1,26 @@
 //! The labels command parser.
 //!
-//! This can parse arbitrary input, giving the list of labels added/removed.
+//! This can parse arbitrary input, giving the list of labels added/removed, and whether to only
+//! preview the change.
 //!
 //! The grammar is as follows:
 //!
 //! ```text
-//! Command: `@bot modify labels:? to? <label-list>.`
+//! Command:
+//!  - `@bot modify labels:? to? <label-list> <preview>?.`
+//!  - `@bot labels:? <label-list> <preview>?.`
+//!
+//! The command ends with a `.`, a `;`, or the end of the line (but not a `,`, which separates
+//! deltas).
+//!
+//! `label` may be used in place of `labels` in either form.
 //!
 //! <label-list>:
 //!  - <label-delta>
-//!  - <label-delta> and <label-list>
-//!  - <label-delta>, <label-list>
-//!  - <label-delta>, and <label-list>
+//!  - <label-delta> <separator> <label-list>
+//!
+//! <separator>:
+//!  - any number of `,`s (including none), optionally followed by `and`
 //!
 //! <label-delta>:
 //!  - +<label>
@@ -20,17 +29,34 @@
 //!  is with the previous two variants of this (i.e., ++label and -+label).
 //!  - <label>
 //!
-//! <label>: \S+
+//! <label>:
+//!  - \S+
+//!  - "<text>", for labels containing whitespace (e.g., +"good first issue")
+//!
+//! <preview>:
+//!  - `dry` or `preview`, right before the end of the command
 //! ```
+//!
+//! So `+bug +enhancement`, `+bug, +enhancement` and `+bug,, and +enhancement` are all the same,
+//! and a separator may also come right before the end of the command.
+//!
+//! Deltas are applied in order; a label may not be both added and removed by the same command.
+//!
+//! A trailing bare `dry` or `preview` asks what the command would change, without changing it;
+//! anywhere else, or as `+dry` or `"dry"`, it's a label like any other.
 
 use crate::error::Error;
-use crate::token::{Token, Tokenizer};
+use crate::token::{Argument, Token, Tokenizer};
 #[cfg(test)]
 use std::error::Error as _;
 use std::fmt;
 
-#[derive(Debug)]
-pub struct RelabelCommand(pub Vec<LabelDelta>);
+#[derive(Debug, PartialEq, Eq)]
+pub struct RelabelCommand {
+    pub deltas: Vec<LabelDelta>,
+    /// Whether to only say what would change, instead of changing it.
+    pub preview: bool,
+}
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum LabelDelta {
@@ -47,6 +73,7 @@ pub enum ParseError {
     ExpectedLabelDelta,
     MisleadingTo,
     NoSeparator,
+    AddAndRemove(Label),
 }
 
 impl std::error::Error for ParseError {}
@@ -58,6 +85,9 @@ impl fmt::Display for ParseError {
             ParseError::ExpectedLabelDelta => write!(f, "a label delta"),
             ParseError::MisleadingTo => write!(f, "forbidden to, use +to"),
             ParseError::NoSeparator => write!(f, "must have : or to as label starter"),
+            ParseError::AddAndRemove(label) => {
+                write!(f, "label {} is both added and removed", label.as_str())
+            }
         }
     }
 }
@@ -72,6 +102,12 @@ impl Label {
     }
 }
 
+impl From<&str> for Label {
+    fn from(name: &str) -> Label {
+        Label(name.to_owned())
+    }
+}
+
 impl std::ops::Deref for Label {
     type Target = String;
     fn deref(&self) -> &String {
@@ -81,27 +117,34 @@ impl std::ops::Deref for Label {
 
 impl LabelDelta {
     fn parse<'a>(input: &mut Tokenizer<'a>) -> Result<LabelDelta, Error<'a>> {
-        let delta = match input.peek_token()? {
-            Some(Token::Word(delta)) => {
-                input.next_token()?;
-                delta
+        let delta = match input.next_quoted_or_word()? {
+            Some(Argument::Word(delta)) => delta,
+            Some(Argument::Quoted(label)) => {
+                return Ok(LabelDelta::Add(
+                    Label::parse(label).map_err(|e| input.error(e))?,
+                ));
             }
-            _ => {
+            None => {
                 return Err(input.error(ParseError::ExpectedLabelDelta));
             }
         };
-        if delta.starts_with('+') {
-            Ok(LabelDelta::Add(
-                Label::parse(&delta[1..]).map_err(|e| input.error(e))?,
-            ))
+        let (add, mut label) = if delta.starts_with('+') {
+            (true, &delta[1..])
         } else if delta.starts_with('-') {
-            Ok(LabelDelta::Remove(
-                Label::parse(&delta[1..]).map_err(|e| input.error(e))?,
-            ))
+            (false, &delta[1..])
+        } else {
+            (true, delta)
+        };
+        if label.is_empty() && (input.at_quote() || input.at_code()) {
+            if let Some(quoted) = input.next_quoted_or_word()? {
+                label = quoted.text();
+            }
+        }
+        let label = Label::parse(label).map_err(|e| input.error(e))?;
+        if add {
+            Ok(LabelDelta::Add(label))
         } else {
-            Ok(LabelDelta::Add(
-                Label::parse(delta).map_err(|e| input.error(e))?,
-            ))
+            Ok(LabelDelta::Remove(label))
         }
     }
 
@@ -127,52 +170,142 @@ fn delta_empty() {
 impl RelabelCommand {
     pub fn parse<'a>(input: &mut Tokenizer<'a>) -> Result<Option<Self>, Error<'a>> {
         let mut toks = input.clone();
-        if let Some(Token::Word("modify")) = toks.next_token()? {
-            // continue
-        } else {
-            return Ok(None);
-        }
-        if let Some(Token::Word("labels")) = toks.next_token()? {
-            // continue
-        } else {
-            return Ok(None);
-        }
-        if let Some(Token::Colon) = toks.peek_token()? {
-            toks.next_token()?;
-        } else if let Some(Token::Word("to")) = toks.peek_token()? {
-            toks.next_token()?;
-        } else {
-            return Err(toks.error(ParseError::NoSeparator));
-        }
-        if let Some(Token::Word("to")) = toks.peek_token()? {
-            return Err(toks.error(ParseError::MisleadingTo));
+        match toks.next_token()? {
+            Some(Token::Word("modify")) => {
+                if let Some(Token::Word("labels")) | Some(Token::Word("label")) =
+                    toks.next_token()?
+                {
+                    // continue
+                } else {
+                    return Ok(None);
+                }
+                if let Some(Token::Colon) = toks.peek_token()? {
+                    toks.next_token()?;
+                } else if let Some(Token::Word("to")) = toks.peek_token()? {
+                    toks.next_token()?;
+                } else {
+                    return Err(toks.error(ParseError::NoSeparator));
+                }
+                if let Some(Token::Word("to")) = toks.peek_token()? {
+                    return Err(toks.error(ParseError::MisleadingTo));
+                }
+            }
+            Some(Token::Word("labels")) | Some(Token::Word("label")) => {
+                if let Some(Token::Colon) = toks.peek_token()? {
+                    toks.next_token()?;
+                }
+            }
+            _ => return Ok(None),
         }
         // start parsing deltas
         let mut deltas = Vec::new();
         loop {
-            deltas.push(LabelDelta::parse(&mut toks)?);
+            if !deltas.is_empty() && at_preview(&toks)? {
+                toks.next_token()?;
+                toks.next_token()?;
+                *input = toks;
+                return Ok(Some(RelabelCommand {
+                    deltas,
+                    preview: true,
+                }));
+            }
+            let mut before = toks.clone();
+            let delta = LabelDelta::parse(&mut toks)?;
+            if deltas
+                .iter()
+                .any(|d: &LabelDelta| d.label() == delta.label() && *d != delta)
+            {
+                return Err(before.error(ParseError::AddAndRemove(delta.label().clone())));
+            }
+            deltas.push(delta);
 
             // optional `, and` separator
-            if let Some(Token::Comma) = toks.peek_token()? {
+            while let Some(Token::Comma) = toks.peek_token()? {
                 toks.next_token()?;
             }
             if let Some(Token::Word("and")) = toks.peek_token()? {
                 toks.next_token()?;
             }
 
-            if let Some(Token::Dot) | Some(Token::EndOfLine) = toks.peek_token()? {
+            // Unlike other commands, a comma doesn't end this one, as it separates the deltas.
+            if let Some(Token::Dot) | Some(Token::Semi) | Some(Token::EndOfLine) =
+                toks.peek_token()?
+            {
                 toks.next_token()?;
                 *input = toks;
-                return Ok(Some(RelabelCommand(deltas)));
+                return Ok(Some(RelabelCommand {
+                    deltas,
+                    preview: false,
+                }));
             }
         }
     }
 }
 
+/// Whether `toks` is at a `<preview>` keyword, followed by the end of the command.
+fn at_preview<'a>(toks: &Tokenizer<'a>) -> Result<bool, Error<'a>> {
+    let mut toks = toks.clone();
+    if let Some(Token::Word("dry")) | Some(Token::Word("preview")) = toks.next_token()? {
+        if let Some(Token::Dot) | Some(Token::Semi) | Some(Token::EndOfLine) = toks.next_token()? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
 #[cfg(test)]
 fn parse<'a>(input: &'a str) -> Result<Option<Vec<LabelDelta>>, Error<'a>> {
     let mut toks = Tokenizer::new(input);
-    Ok(RelabelCommand::parse(&mut toks)?.map(|c| c.0))
+    Ok(RelabelCommand::parse(&mut toks)?.map(|c| c.deltas))
+}
+
+#[cfg(test)]
+fn parse_preview<'a>(input: &'a str) -> Result<Option<bool>, Error<'a>> {
+    let mut toks = Tokenizer::new(input);
+    Ok(RelabelCommand::parse(&mut toks)?.map(|c| c.preview))
+}
+
+#[test]
+fn parse_dry() {
+    for input in &[
+        "label +bug +enhancement dry",
+        "label +bug, +enhancement, preview.",
+        "modify labels: +bug and +enhancement dry; thanks",
+    ] {
+        assert_eq!(parse(input), Ok(Some(bug_and_enhancement())), "{}", input);
+        assert_eq!(parse_preview(input), Ok(Some(true)), "{}", input);
+    }
+    assert_eq!(parse_preview("label +bug +enhancement"), Ok(Some(false)));
+}
+
+#[test]
+fn parse_dry_as_label() {
+    assert_eq!(
+        parse("label +bug +dry"),
+        Ok(Some(vec![
+            LabelDelta::Add(Label("bug".into())),
+            LabelDelta::Add(Label("dry".into())),
+        ]))
+    );
+    assert_eq!(
+        parse(r#"label +bug "preview""#),
+        Ok(Some(vec![
+            LabelDelta::Add(Label("bug".into())),
+            LabelDelta::Add(Label("preview".into())),
+        ]))
+    );
+    assert_eq!(
+        parse("label dry +bug"),
+        Ok(Some(vec![
+            LabelDelta::Add(Label("dry".into())),
+            LabelDelta::Add(Label("bug".into())),
+        ]))
+    );
+    assert_eq!(
+        parse("label dry"),
+        Ok(Some(vec![LabelDelta::Add(Label("dry".into()))]))
+    );
+    assert_eq!(parse_preview("label +bug dry run"), Ok(Some(false)));
 }
 
 #[test]
@@ -226,3 +359,181 @@ fn parse_no_dot() {
         ]))
     );
 }
+
+#[test]
+fn parse_in_order() {
+    assert_eq!(
+        parse("modify labels: +A, -B, C"),
+        Ok(Some(vec![
+            LabelDelta::Add(Label("A".into())),
+            LabelDelta::Remove(Label("B".into())),
+            LabelDelta::Add(Label("C".into())),
+        ]))
+    );
+}
+
+#[cfg(test)]
+fn bug_and_enhancement() -> Vec<LabelDelta> {
+    vec![
+        LabelDelta::Add(Label("bug".into())),
+        LabelDelta::Add(Label("enhancement".into())),
+    ]
+}
+
+#[test]
+fn parse_space_separated() {
+    assert_eq!(
+        parse("label +bug +enhancement"),
+        Ok(Some(bug_and_enhancement()))
+    );
+}
+
+#[test]
+fn parse_comma_separated() {
+    assert_eq!(
+        parse("label +bug,+enhancement"),
+        Ok(Some(bug_and_enhancement()))
+    );
+    assert_eq!(
+        parse("label +bug, +enhancement."),
+        Ok(Some(bug_and_enhancement()))
+    );
+}
+
+#[test]
+fn parse_mixed_separators() {
+    for input in &[
+        "label +bug,, +enhancement",
+        "label +bug , and +enhancement",
+        "label +bug and +enhancement",
+        "label +bug,,and +enhancement",
+    ] {
+        assert_eq!(parse(input), Ok(Some(bug_and_enhancement())), "{}", input);
+    }
+}
+
+#[test]
+fn parse_trailing_separator() {
+    for input in &[
+        "label +bug +enhancement,",
+        "label +bug +enhancement,,.",
+        "label +bug, +enhancement,\nthanks",
+    ] {
+        assert_eq!(parse(input), Ok(Some(bug_and_enhancement())), "{}", input);
+    }
+}
+
+#[test]
+fn parse_add_and_remove() {
+    let err = parse("modify labels: +A, -B, -A").unwrap_err();
+    assert_eq!(
+        err.source().unwrap().downcast_ref(),
+        Some(&ParseError::AddAndRemove(Label("A".into())))
+    );
+    assert_eq!(&err.input[err.span()], "-A");
+}
+
+#[test]
+fn parse_repeated() {
+    assert_eq!(
+        parse("modify labels: +A, A"),
+        Ok(Some(vec![
+            LabelDelta::Add(Label("A".into())),
+            LabelDelta::Add(Label("A".into())),
+        ]))
+    );
+}
+
+#[test]
+fn parse_modify_label_singular() {
+    assert_eq!(
+        parse("modify label: +bug"),
+        Ok(Some(vec![LabelDelta::Add(Label("bug".into()))]))
+    );
+}
+
+#[test]
+fn parse_label_shorthand() {
+    assert_eq!(
+        parse("label +bug -T-lang."),
+        Ok(Some(vec![
+            LabelDelta::Add(Label("bug".into())),
+            LabelDelta::Remove(Label("T-lang".into())),
+        ]))
+    );
+}
+
+#[test]
+fn parse_labels_shorthand() {
+    assert_eq!(
+        parse("labels +bug"),
+        Ok(Some(vec![LabelDelta::Add(Label("bug".into()))]))
+    );
+}
+
+#[test]
+fn parse_label_shorthand_colon() {
+    assert_eq!(
+        parse("label: +bug"),
+        Ok(Some(vec![LabelDelta::Add(Label("bug".into()))]))
+    );
+}
+
+#[test]
+fn parse_quoted_add() {
+    assert_eq!(
+        parse(r#"modify labels: +"good first issue", +E-easy."#),
+        Ok(Some(vec![
+            LabelDelta::Add(Label("good first issue".into())),
+            LabelDelta::Add(Label("E-easy".into())),
+        ]))
+    );
+}
+
+#[test]
+fn parse_quoted_remove() {
+    assert_eq!(
+        parse(r#"label -"help wanted" "needs triage""#),
+        Ok(Some(vec![
+            LabelDelta::Remove(Label("help wanted".into())),
+            LabelDelta::Add(Label("needs triage".into())),
+        ]))
+    );
+}
+
+#[test]
+fn parse_inline_code() {
+    assert_eq!(
+        parse("modify labels: +`bug`, -`S-blocked` `E-easy`."),
+        Ok(Some(vec![
+            LabelDelta::Add(Label("bug".into())),
+            LabelDelta::Remove(Label("S-blocked".into())),
+            LabelDelta::Add(Label("E-easy".into())),
+        ]))
+    );
+}
+
+#[test]
+fn parse_quoted_empty() {
+    assert_eq!(
+        parse(r#"label +"""#)
+            .unwrap_err()
+            .source()
+            .unwrap()
+            .downcast_ref(),
+        Some(&ParseError::EmptyLabel)
+    );
+}
+
+#[test]
+fn parse_quoted_unterminated() {
+    use crate::token::ErrorKind;
+    assert_eq!(
+        parse(r#"label +"good first issue"#)
+            .unwrap_err()
+            .source()
+            .unwrap()
+            .downcast_ref(),
+        Some(&ErrorKind::UnterminatedString)
+    );
+}
@@ -0,0 +1,98 @@
+//! The rename command parser.
+//!
+//! This can parse arbitrary input, giving the new title of the issue.
+//!
+//! The grammar is as follows:
+//!
+//! ```text
+//! Command: `@bot rename <title>`.
+//!
+//! <title>:
+//!  - the rest of the line, verbatim
+//! ```
+//!
+//! Like a note, the title isn't tokenized, so it may contain anything.
+
+use crate::error::Error;
+use crate::token::{Token, Tokenizer};
+use std::fmt;
+
+#[derive(PartialEq, Eq, Debug)]
+pub struct RenameCommand {
+    pub title: String,
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum ParseError {
+    EmptyTitle,
+}
+
+impl std::error::Error for ParseError {}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::EmptyTitle => write!(f, "expected the new title"),
+        }
+    }
+}
+
+impl RenameCommand {
+    pub fn parse<'a>(input: &mut Tokenizer<'a>) -> Result<Option<Self>, Error<'a>> {
+        let mut toks = input.clone();
+        if let Some(Token::Word("rename")) = toks.peek_token()? {
+            toks.next_token()?;
+        } else {
+            return Ok(None);
+        }
+        let title = toks.rest_of_line();
+        if title.is_empty() {
+            return Err(toks.error(ParseError::EmptyTitle));
+        }
+        // The end of the line.
+        toks.next_token()?;
+        *input = toks;
+        Ok(Some(RenameCommand {
+            title: title.to_owned(),
+        }))
+    }
+}
+
+#[cfg(test)]
+fn parse<'a>(input: &'a str) -> Result<Option<RenameCommand>, Error<'a>> {
+    let mut toks = Tokenizer::new(input);
+    Ok(RenameCommand::parse(&mut toks)?)
+}
+
+#[test]
+fn test_rename() {
+    assert_eq!(
+        parse("rename ICE: \"unreachable\" in `rustc_typeck`\nThanks!"),
+        Ok(Some(RenameCommand {
+            title: "ICE: \"unreachable\" in `rustc_typeck`".to_owned()
+        }))
+    );
+}
+
+#[test]
+fn test_rename_trims() {
+    assert_eq!(
+        parse("rename   Tracking issue for `#[track_caller]`  "),
+        Ok(Some(RenameCommand {
+            title: "Tracking issue for `#[track_caller]`".to_owned()
+        }))
+    );
+}
+
+#[test]
+fn test_empty_title() {
+    use std::error::Error;
+    assert_eq!(
+        parse("rename  \nmore")
+            .unwrap_err()
+            .source()
+            .unwrap()
+            .downcast_ref(),
+        Some(&ParseError::EmptyTitle),
+    );
+}
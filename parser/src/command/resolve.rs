@@ -0,0 +1,5 @@
+//! The resolve command (`@bot resolve`), which has no arguments; it resolves the review thread it
+//! is a reply in. It's parsed by `parse_keyword`.
+
+#[derive(PartialEq, Eq, Debug)]
+pub struct ResolveCommand;
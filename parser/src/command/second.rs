@@ -0,0 +1,73 @@
+//! The second command parser.
+//!
+//! This can parse arbitrary input, giving whether the proposal was seconded.
+//!
+//! The grammar is as follows:
+//!
+//! ```text
+//! Command: `@bot second`.
+//! ```
+
+use crate::error::Error;
+use crate::token::{Token, Tokenizer};
+use std::fmt;
+
+#[derive(PartialEq, Eq, Debug)]
+pub struct SecondCommand;
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum ParseError {
+    ExpectedEnd,
+}
+
+impl std::error::Error for ParseError {}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::ExpectedEnd => write!(f, "expected end of command"),
+        }
+    }
+}
+
+impl SecondCommand {
+    pub fn parse<'a>(input: &mut Tokenizer<'a>) -> Result<Option<Self>, Error<'a>> {
+        let mut toks = input.clone();
+        if let Some(Token::Word("second")) = toks.peek_token()? {
+            toks.next_token()?;
+        } else {
+            return Ok(None);
+        }
+        if toks.eat_terminator()? {
+            *input = toks;
+            Ok(Some(SecondCommand))
+        } else {
+            Err(toks.error(ParseError::ExpectedEnd))
+        }
+    }
+}
+
+#[cfg(test)]
+fn parse<'a>(input: &'a str) -> Result<Option<SecondCommand>, Error<'a>> {
+    let mut toks = Tokenizer::new(input);
+    Ok(SecondCommand::parse(&mut toks)?)
+}
+
+#[test]
+fn test_second() {
+    assert_eq!(parse("second."), Ok(Some(SecondCommand)));
+    assert_eq!(parse("second"), Ok(Some(SecondCommand)));
+}
+
+#[test]
+fn test_second_trailing() {
+    use std::error::Error;
+    assert_eq!(
+        parse("second this")
+            .unwrap_err()
+            .source()
+            .unwrap()
+            .downcast_ref(),
+        Some(&ParseError::ExpectedEnd),
+    );
+}
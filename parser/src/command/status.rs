@@ -0,0 +1,5 @@
+//! The status command (`@bot status`), which has no arguments; it asks what the bot is tracking
+//! about the issue. It's parsed by `parse_keyword`.
+
+#[derive(PartialEq, Eq, Debug)]
+pub struct StatusCommand;
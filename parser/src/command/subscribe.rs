@@ -0,0 +1,165 @@
+//! The subscribe command parser.
+//!
+//! This can parse arbitrary input, giving whether to add someone to the issue's list of
+//! subscribers or remove them from it, and who (if it's not whoever asked).
+//!
+//! The grammar is as follows:
+//!
+//! ```text
+//! Command: `@bot subscribe`, `@bot subscribe @user`, `@bot unsubscribe` or
+//!          `@bot unsubscribe @user`.
+//! ```
+
+use crate::command::is_valid_username;
+use crate::error::Error;
+use crate::token::{Argument, Token, Tokenizer};
+use std::fmt;
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum SubscribeCommand {
+    /// Subscribes `username`, or whoever asked if there's none.
+    Subscribe {
+        username: Option<String>,
+    },
+    Unsubscribe {
+        username: Option<String>,
+    },
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum ParseError {
+    ExpectedEnd,
+    MentionUser,
+    InvalidUser,
+}
+
+impl std::error::Error for ParseError {}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::ExpectedEnd => write!(f, "expected end of command"),
+            ParseError::MentionUser => write!(f, "user should start with @"),
+            ParseError::InvalidUser => write!(f, "not a valid GitHub username"),
+        }
+    }
+}
+
+impl SubscribeCommand {
+    pub fn parse<'a>(input: &mut Tokenizer<'a>) -> Result<Option<Self>, Error<'a>> {
+        let mut toks = input.clone();
+        let subscribe = match toks.peek_token()? {
+            Some(Token::Word("subscribe")) => true,
+            Some(Token::Word("unsubscribe")) => false,
+            _ => return Ok(None),
+        };
+        toks.next_token()?;
+        let username = if toks.eat_terminator()? {
+            None
+        } else {
+            let username = parse_user(&mut toks)?;
+            if !toks.eat_terminator()? {
+                return Err(toks.error(ParseError::ExpectedEnd));
+            }
+            Some(username)
+        };
+        *input = toks;
+        if subscribe {
+            Ok(Some(SubscribeCommand::Subscribe { username }))
+        } else {
+            Ok(Some(SubscribeCommand::Unsubscribe { username }))
+        }
+    }
+}
+
+/// Parses a username, which needs the `@` of a mention so that `subscribe me` isn't taken for
+/// subscribing @me.
+fn parse_user<'a>(toks: &mut Tokenizer<'a>) -> Result<String, Error<'a>> {
+    if let Some(Token::Mention(user)) = toks.peek_token()? {
+        toks.next_token()?;
+        return Ok(user.to_owned());
+    }
+    let mut at = toks.clone();
+    match toks.next_quoted_or_word()?.map(Argument::text) {
+        Some(user) if user.starts_with('@') && is_valid_username(&user[1..]) => {
+            Ok(user[1..].to_owned())
+        }
+        Some(user) if user.starts_with('@') && user.len() != 1 => {
+            Err(at.error(ParseError::InvalidUser))
+        }
+        _ => Err(at.error(ParseError::MentionUser)),
+    }
+}
+
+#[cfg(test)]
+fn parse<'a>(input: &'a str) -> Result<Option<SubscribeCommand>, Error<'a>> {
+    let mut toks = Tokenizer::new(input);
+    Ok(SubscribeCommand::parse(&mut toks)?)
+}
+
+#[test]
+fn test_subscribe() {
+    assert_eq!(
+        parse("subscribe."),
+        Ok(Some(SubscribeCommand::Subscribe { username: None }))
+    );
+    assert_eq!(
+        parse("subscribe @octocat"),
+        Ok(Some(SubscribeCommand::Subscribe {
+            username: Some("octocat".to_owned())
+        }))
+    );
+}
+
+#[test]
+fn test_unsubscribe() {
+    assert_eq!(
+        parse("unsubscribe"),
+        Ok(Some(SubscribeCommand::Unsubscribe { username: None }))
+    );
+    assert_eq!(
+        parse("unsubscribe @octocat."),
+        Ok(Some(SubscribeCommand::Unsubscribe {
+            username: Some("octocat".to_owned())
+        }))
+    );
+}
+
+#[test]
+fn test_not_mentioned() {
+    use std::error::Error;
+    assert_eq!(
+        parse("subscribe me")
+            .unwrap_err()
+            .source()
+            .unwrap()
+            .downcast_ref(),
+        Some(&ParseError::MentionUser),
+    );
+    assert_eq!(
+        parse("subscribe @-bob-")
+            .unwrap_err()
+            .source()
+            .unwrap()
+            .downcast_ref(),
+        Some(&ParseError::InvalidUser),
+    );
+}
+
+#[test]
+fn test_trailing() {
+    use std::error::Error;
+    assert_eq!(
+        parse("subscribe @octocat please")
+            .unwrap_err()
+            .source()
+            .unwrap()
+            .downcast_ref(),
+        Some(&ParseError::ExpectedEnd),
+    );
+}
+
+#[test]
+fn test_other() {
+    assert_eq!(parse("subscribed"), Ok(None));
+}
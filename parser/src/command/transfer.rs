@@ -0,0 +1,140 @@
+//! The transfer command parser.
+//!
+//! This can parse arbitrary input, giving the repository the issue should be moved to.
+//!
+//! The grammar is as follows:
+//!
+//! ```text
+//! Command: `@bot transfer <repo>`.
+//!
+//! <repo>:
+//!  - the full name of a repository, `owner/name` (e.g., `rust-lang/cargo`)
+//! ```
+
+use crate::command::is_valid_username;
+use crate::error::Error;
+use crate::token::{Token, Tokenizer};
+use std::fmt;
+
+#[derive(PartialEq, Eq, Debug)]
+pub struct TransferCommand {
+    pub repo: String,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum ParseError {
+    ExpectedRepo,
+    InvalidRepo,
+    ExpectedEnd,
+}
+
+impl std::error::Error for ParseError {}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::ExpectedRepo => write!(f, "expected the repository to transfer to"),
+            ParseError::InvalidRepo => write!(f, "repositories must be given as `owner/name`"),
+            ParseError::ExpectedEnd => write!(f, "expected end of command"),
+        }
+    }
+}
+
+/// Whether `repo` could be the full name of a GitHub repository: `owner/name`, where the owner is
+/// a valid username, and the name is made up of alphanumerics, `-`, `_` and `.`.
+pub fn is_valid_repo(repo: &str) -> bool {
+    let mut parts = repo.splitn(2, '/');
+    let owner = parts.next().unwrap_or("");
+    let name = parts.next().unwrap_or("");
+    is_valid_username(owner)
+        && !name.is_empty()
+        && name.len() <= 100
+        && name != "."
+        && name != ".."
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+}
+
+impl TransferCommand {
+    pub fn parse<'a>(input: &mut Tokenizer<'a>) -> Result<Option<Self>, Error<'a>> {
+        let mut toks = input.clone();
+        if let Some(Token::Word("transfer")) = toks.peek_token()? {
+            toks.next_token()?;
+        } else {
+            return Ok(None);
+        }
+        // Repository names may have dots in them.
+        let repo = match toks.peek_token()? {
+            Some(Token::Word(_)) => {
+                let mut at = toks.clone();
+                match toks.verbatim_word() {
+                    Some(repo) if is_valid_repo(repo) => repo,
+                    _ => return Err(at.error(ParseError::InvalidRepo)),
+                }
+            }
+            _ => return Err(toks.error(ParseError::ExpectedRepo)),
+        };
+        if !toks.eat_terminator()? {
+            return Err(toks.error(ParseError::ExpectedEnd));
+        }
+        *input = toks;
+        Ok(Some(TransferCommand {
+            repo: repo.to_owned(),
+        }))
+    }
+}
+
+#[cfg(test)]
+fn parse<'a>(input: &'a str) -> Result<Option<TransferCommand>, Error<'a>> {
+    let mut toks = Tokenizer::new(input);
+    Ok(TransferCommand::parse(&mut toks)?)
+}
+
+#[cfg(test)]
+fn parse_error(input: &str) -> ParseError {
+    use std::error::Error;
+    let err = parse(input).unwrap_err();
+    err.source()
+        .unwrap()
+        .downcast_ref::<ParseError>()
+        .unwrap()
+        .clone()
+}
+
+#[test]
+fn test_transfer() {
+    assert_eq!(
+        parse("transfer rust-lang/rust.vim."),
+        Ok(Some(TransferCommand {
+            repo: "rust-lang/rust.vim".to_owned()
+        }))
+    );
+    assert_eq!(
+        parse("transfer rust-lang/cargo"),
+        Ok(Some(TransferCommand {
+            repo: "rust-lang/cargo".to_owned()
+        }))
+    );
+}
+
+#[test]
+fn test_invalid_repo() {
+    assert_eq!(parse_error("transfer cargo"), ParseError::InvalidRepo);
+    assert_eq!(parse_error("transfer rust-lang/"), ParseError::InvalidRepo);
+    assert_eq!(parse_error("transfer -rust/cargo"), ParseError::InvalidRepo);
+    assert_eq!(
+        parse_error("transfer rust-lang/car/go"),
+        ParseError::InvalidRepo
+    );
+    assert_eq!(parse_error("transfer ."), ParseError::ExpectedRepo);
+    assert_eq!(parse_error("transfer"), ParseError::ExpectedRepo);
+}
+
+#[test]
+fn test_trailing() {
+    assert_eq!(
+        parse_error("transfer rust-lang/cargo please"),
+        ParseError::ExpectedEnd
+    );
+}
@@ -0,0 +1,198 @@
+//! The triage command parser.
+//!
+//! This can parse arbitrary input, giving the priority the issue should be given (or that its
+//! priority should be cleared), and optionally its status.
+//!
+//! The grammar is as follows:
+//!
+//! ```text
+//! Command: `@bot prioritize <priority>`, `@bot triage <priority>` or `@bot priority <priority>`,
+//!          optionally followed by `status <status>`.
+//!
+//! <priority>:
+//!  - the name of a priority label (e.g., `P-high`)
+//!  - `none` or `clear`, to remove the priority the issue has
+//!
+//! <status>:
+//!  - the name of a status label (e.g., `S-needs-decision`)
+//! ```
+//!
+//! Which priorities and statuses are valid is up to the repository's configuration, so any label
+//! name is accepted here. Like other arguments, labels with spaces in them can be quoted; a
+//! quoted `"none"` is taken to be a label, though repositories can't have such a priority.
+
+use crate::error::Error;
+use crate::token::{Argument, Token, Tokenizer};
+use std::fmt;
+
+#[derive(PartialEq, Eq, Debug)]
+pub struct TriageCommand {
+    /// The priority to set, or `None` to clear the priority.
+    pub priority: Option<String>,
+    pub status: Option<String>,
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum ParseError {
+    ExpectedPriority,
+    ExpectedStatus,
+    ExpectedEnd,
+}
+
+impl std::error::Error for ParseError {}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::ExpectedPriority => write!(f, "expected priority"),
+            ParseError::ExpectedStatus => write!(f, "expected status"),
+            ParseError::ExpectedEnd => write!(f, "expected end of command"),
+        }
+    }
+}
+
+impl TriageCommand {
+    pub fn parse<'a>(input: &mut Tokenizer<'a>) -> Result<Option<Self>, Error<'a>> {
+        let mut toks = input.clone();
+        match toks.peek_token()? {
+            Some(Token::Word("prioritize"))
+            | Some(Token::Word("triage"))
+            | Some(Token::Word("priority")) => {
+                toks.next_token()?;
+            }
+            _ => return Ok(None),
+        }
+        let priority = match toks.next_quoted_or_word()? {
+            Some(Argument::Word("none")) | Some(Argument::Word("clear")) => None,
+            Some(priority) => Some(priority.text().to_owned()),
+            None => return Err(toks.error(ParseError::ExpectedPriority)),
+        };
+        let mut status = None;
+        if let Some(Token::Word("status")) = toks.peek_token()? {
+            toks.next_token()?;
+            match toks.next_quoted_or_word()? {
+                Some(word) => status = Some(word.text().to_owned()),
+                None => return Err(toks.error(ParseError::ExpectedStatus)),
+            }
+        }
+        if !toks.eat_terminator()? {
+            return Err(toks.error(ParseError::ExpectedEnd));
+        }
+        *input = toks;
+        Ok(Some(TriageCommand { priority, status }))
+    }
+}
+
+#[cfg(test)]
+fn parse<'a>(input: &'a str) -> Result<Option<TriageCommand>, Error<'a>> {
+    let mut toks = Tokenizer::new(input);
+    Ok(TriageCommand::parse(&mut toks)?)
+}
+
+#[test]
+fn test_prioritize() {
+    assert_eq!(
+        parse("prioritize P-high."),
+        Ok(Some(TriageCommand {
+            priority: Some("P-high".to_owned()),
+            status: None,
+        })),
+    );
+}
+
+#[test]
+fn test_triage() {
+    assert_eq!(
+        parse("triage P-low"),
+        Ok(Some(TriageCommand {
+            priority: Some("P-low".to_owned()),
+            status: None,
+        })),
+    );
+}
+
+#[test]
+fn test_with_status() {
+    assert_eq!(
+        parse("prioritize P-high status S-needs-decision."),
+        Ok(Some(TriageCommand {
+            priority: Some("P-high".to_owned()),
+            status: Some("S-needs-decision".to_owned()),
+        })),
+    );
+}
+
+#[test]
+fn test_quoted() {
+    assert_eq!(
+        parse(r#"prioritize "P high" status "needs decision""#),
+        Ok(Some(TriageCommand {
+            priority: Some("P high".to_owned()),
+            status: Some("needs decision".to_owned()),
+        })),
+    );
+}
+
+#[test]
+fn test_clear() {
+    assert_eq!(
+        parse("prioritize none."),
+        Ok(Some(TriageCommand {
+            priority: None,
+            status: None,
+        })),
+    );
+    assert_eq!(
+        parse("priority clear status S-blocked"),
+        Ok(Some(TriageCommand {
+            priority: None,
+            status: Some("S-blocked".to_owned()),
+        })),
+    );
+    assert_eq!(
+        parse(r#"triage "none""#),
+        Ok(Some(TriageCommand {
+            priority: Some("none".to_owned()),
+            status: None,
+        })),
+    );
+}
+
+#[test]
+fn test_no_status() {
+    use std::error::Error;
+    assert_eq!(
+        parse("triage P-high status.")
+            .unwrap_err()
+            .source()
+            .unwrap()
+            .downcast_ref(),
+        Some(&ParseError::ExpectedStatus),
+    );
+}
+
+#[test]
+fn test_no_priority() {
+    use std::error::Error;
+    assert_eq!(
+        parse("prioritize.")
+            .unwrap_err()
+            .source()
+            .unwrap()
+            .downcast_ref(),
+        Some(&ParseError::ExpectedPriority),
+    );
+}
+
+#[test]
+fn test_trailing() {
+    use std::error::Error;
+    assert_eq!(
+        parse("prioritize P-high P-low")
+            .unwrap_err()
+            .source()
+            .unwrap()
+            .downcast_ref(),
+        Some(&ParseError::ExpectedEnd),
+    );
+}
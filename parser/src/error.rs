@@ -1,5 +1,6 @@
 use std::error;
 use std::fmt;
+use std::ops::Range;
 
 #[derive(Debug)]
 pub struct Error<'a> {
@@ -24,16 +25,55 @@ impl<'a> Error<'a> {
     pub fn position(&self) -> usize {
         self.position
     }
+
+    /// The byte range of the input which this error refers to: the word at the error's
+    /// position, or an empty range if there's no such word (e.g., at the end of the input).
+    pub fn span(&self) -> Range<usize> {
+        let rest = &self.input[self.position..];
+        let start = self.position + (rest.len() - rest.trim_start_matches(is_blank).len());
+        let rest = &self.input[start..];
+        let len = rest.find(char::is_whitespace).unwrap_or_else(|| rest.len());
+        start..(start + len)
+    }
+
+    /// Renders the line of input containing the error as a fenced code block, with the region
+    /// returned by `span` underlined.
+    pub fn snippet(&self) -> String {
+        let span = self.span();
+        let line_start = self.input[..span.start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = self.input[span.start..]
+            .find('\n')
+            .map_or(self.input.len(), |i| span.start + i);
+        let column = self.input[line_start..span.start].chars().count();
+        let width = std::cmp::max(1, self.input[span].chars().count());
+        format!(
+            "```text\n{}\n{}{}\n```",
+            &self.input[line_start..line_end],
+            " ".repeat(column),
+            "^".repeat(width)
+        )
+    }
+}
+
+fn is_blank(c: char) -> bool {
+    c != '\n' && c.is_whitespace()
 }
 
 impl<'a> fmt::Display for Error<'a> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let space = 10;
-        let end = std::cmp::min(self.input.len(), self.position + space);
+        let mut start = self.position.saturating_sub(space);
+        while !self.input.is_char_boundary(start) {
+            start -= 1;
+        }
+        let mut end = std::cmp::min(self.input.len(), self.position + space);
+        while !self.input.is_char_boundary(end) {
+            end += 1;
+        }
         write!(
             f,
             "...{}|error: {} at >|{}...",
-            &self.input[self.position.saturating_sub(space)..self.position],
+            &self.input[start..self.position],
             self.source,
             &self.input[self.position..end],
         )
@@ -1,4 +1,8 @@
+pub mod checkbox;
 mod code_block;
 pub mod command;
 pub mod error;
+pub mod shortcut;
 mod token;
+
+pub use command::parse_all;
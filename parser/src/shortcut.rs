@@ -0,0 +1,148 @@
+//! Shortcuts: single words which stand for one or more commands.
+//!
+//! A repository may, for example, define `ready` as a shortcut for
+//! `modify labels: -S-waiting-on-author, +S-waiting-on-review`, so that `@bot ready` is
+//! equivalent to `@bot modify labels: -S-waiting-on-author, +S-waiting-on-review`.
+//!
+//! Shortcuts are expanded one level only: a shortcut whose commands themselves name a shortcut
+//! is not expanded further, so that shortcuts can never recurse.
+
+use crate::code_block::ColorCodeBlocks;
+use crate::command::{find_commmand_start, is_mention_of};
+use crate::token::{Token, Tokenizer};
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+/// Replaces each use of a shortcut in `input` (a mention of one of the `bot` names, followed by
+/// just a shortcut name) with its commands, each on its own line after the same mention.
+///
+/// Shortcuts inside code are left alone, as they are not commands.
+pub fn expand_shortcuts<'a>(
+    input: &'a str,
+    bot: &[&str],
+    shortcuts: &HashMap<String, Vec<String>>,
+) -> Cow<'a, str> {
+    if shortcuts.is_empty() {
+        return Cow::Borrowed(input);
+    }
+    let code = ColorCodeBlocks::new(input);
+    let mut expanded = String::new();
+    // Everything in `input` before `copied` is already in `expanded`.
+    let mut copied = 0;
+    let mut pos = 0;
+    while let Some(start) = find_commmand_start(&input[pos..], bot) {
+        let start = pos + start;
+        pos = start + 1;
+        let mut tok = Tokenizer::new_at(input, start);
+        let mention = match tok.next_token() {
+            Ok(Some(Token::Mention(mention))) if is_mention_of(mention, bot) => {
+                &input[start..tok.position()]
+            }
+            _ => continue,
+        };
+        let commands = match tok.next_token() {
+            Ok(Some(Token::Word(word))) => match shortcuts.get(word) {
+                Some(commands) => commands,
+                None => continue,
+            },
+            _ => continue,
+        };
+        match tok.peek_token() {
+            // Leave the line break to what follows, where it may still matter.
+            Ok(Some(Token::EndOfLine)) | Ok(None) => {}
+            _ => match tok.eat_terminator() {
+                Ok(true) => {}
+                _ => continue,
+            },
+        }
+        let end = tok.position();
+        if code.overlaps_code(start..end).is_some() {
+            continue;
+        }
+
+        expanded.push_str(&input[copied..start]);
+        for command in commands {
+            // The newline ends the command, so that it doesn't run into whatever followed the
+            // shortcut.
+            expanded.push_str(mention);
+            expanded.push(' ');
+            expanded.push_str(command);
+            expanded.push('\n');
+        }
+        copied = end;
+        pos = end;
+    }
+    if copied == 0 {
+        Cow::Borrowed(input)
+    } else {
+        expanded.push_str(&input[copied..]);
+        Cow::Owned(expanded)
+    }
+}
+
+#[cfg(test)]
+fn shortcuts() -> HashMap<String, Vec<String>> {
+    let mut shortcuts = HashMap::new();
+    shortcuts.insert(
+        "ready".to_owned(),
+        vec!["modify labels: -S-waiting-on-author, +S-waiting-on-review".to_owned()],
+    );
+    shortcuts.insert(
+        "done".to_owned(),
+        vec!["release-assignment".to_owned(), "ready".to_owned()],
+    );
+    shortcuts
+}
+
+#[test]
+fn expand_single() {
+    assert_eq!(
+        expand_shortcuts("Fixed! @bot ready.", &["bot"], &shortcuts()),
+        "Fixed! @bot modify labels: -S-waiting-on-author, +S-waiting-on-review\n"
+    );
+}
+
+#[test]
+fn expand_multiple_commands_one_level() {
+    assert_eq!(
+        expand_shortcuts("@Bot done\nThanks!", &["bot"], &shortcuts()),
+        "@Bot release-assignment\n@Bot ready\n\nThanks!"
+    );
+}
+
+#[test]
+fn expand_not_shortcut() {
+    let input = "@bot ready to go. @bot claim.";
+    assert!(match expand_shortcuts(input, &["bot"], &shortcuts()) {
+        Cow::Borrowed(s) => s == input,
+        Cow::Owned(_) => false,
+    });
+}
+
+#[test]
+fn expand_followed_by_text() {
+    let expanded = expand_shortcuts("@bot ready. Thanks!", &["bot"], &shortcuts());
+    let mut input = crate::command::Input::new(&expanded, &["bot"]);
+    let commands = input.commands().collect::<Vec<_>>();
+    assert!(match &commands[..] {
+        [crate::command::Command::Relabel(Ok(command))] => command.deltas.len() == 2,
+        _ => false,
+    });
+}
+
+#[test]
+fn expand_in_code() {
+    let input = "`@bot ready`";
+    assert_eq!(expand_shortcuts(input, &["bot"], &shortcuts()), input);
+}
+
+#[test]
+fn expand_then_parse() {
+    use crate::command::{Command, Input};
+    let expanded = expand_shortcuts("@bot ready", &["bot"], &shortcuts());
+    let mut input = Input::new(&expanded, &["bot"]);
+    assert!(match input.parse_command() {
+        Command::Relabel(Ok(_)) => true,
+        _ => false,
+    });
+}
@@ -1,3 +1,4 @@
+use crate::command::is_valid_username;
 use crate::error::Error;
 use std::fmt;
 use std::iter::Peekable;
@@ -14,6 +15,8 @@ pub enum Token<'a> {
     EndOfLine,
     Quote(&'a str),
     Word(&'a str),
+    /// A mention of a user, like `@octocat`, without the `@`.
+    Mention(&'a str),
 }
 
 impl fmt::Display for Token<'_> {
@@ -28,6 +31,24 @@ impl fmt::Display for Token<'_> {
             Token::EndOfLine => Ok(()),
             Token::Quote(body) => write!(f, r#""{}""#, body),
             Token::Word(word) => write!(f, "{}", word),
+            Token::Mention(user) => write!(f, "@{}", user),
+        }
+    }
+}
+
+/// An argument of a command, as read by `Tokenizer::next_quoted_or_word`.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Argument<'a> {
+    Word(&'a str),
+    /// A string, which can have spaces and punctuation in it; the quotes aren't included.
+    Quoted(&'a str),
+}
+
+impl<'a> Argument<'a> {
+    /// The argument, without quotes.
+    pub fn text(self) -> &'a str {
+        match self {
+            Argument::Word(text) | Argument::Quoted(text) => text,
         }
     }
 }
@@ -81,6 +102,17 @@ impl<'a> Tokenizer<'a> {
         }
     }
 
+    /// Creates a tokenizer over `input` which starts at byte offset `start`.
+    ///
+    /// Positions, including those of errors, are still relative to the start of `input`.
+    pub fn new_at(input: &'a str, start: usize) -> Tokenizer<'a> {
+        let mut tok = Tokenizer::new(input);
+        while tok.cur().map_or(false, |(pos, _)| pos < start) {
+            tok.advance();
+        }
+        tok
+    }
+
     pub fn error<T: 'static + std::error::Error>(&mut self, source: T) -> Error<'a> {
         Error {
             input: self.input,
@@ -159,6 +191,79 @@ impl<'a> Tokenizer<'a> {
         Ok(Some(Token::Quote(body)))
     }
 
+    /// Whether the input continues with a string, with no whitespace in between.
+    pub fn at_quote(&mut self) -> bool {
+        self.cur().map_or(false, |(_, ch)| ch == '"')
+    }
+
+    /// Whether the input continues with inline code, like `` `bug` ``, with no whitespace in
+    /// between.
+    pub fn at_code(&mut self) -> bool {
+        self.cur().map_or(false, |(_, ch)| ch == '`')
+    }
+
+    /// Consumes the rest of the current line verbatim, rather than as tokens, and returns it
+    /// without surrounding whitespace. The line break itself is left as the next token.
+    pub fn rest_of_line(&mut self) -> &'a str {
+        self.consume_whitespace();
+        let start = self.cur_pos();
+        while self.cur().map_or(false, |(_, ch)| ch != '\n') {
+            self.advance();
+        }
+        self.str_from(start).trim_end()
+    }
+
+    /// Consumes the text up to the next whitespace verbatim, rather than as tokens, for names like
+    /// `1.40.0` which would otherwise be split at their dots. Punctuation at the end of the text
+    /// is left as the next token, and so is a quote, which can't be part of the text.
+    pub fn verbatim_word(&mut self) -> Option<&'a str> {
+        self.consume_whitespace();
+        let start = self.cur_pos();
+        let rest = &self.input[start..];
+        let len = rest
+            .find(|ch: char| ch.is_whitespace() || ch == '"')
+            .unwrap_or_else(|| rest.len());
+        let word = rest[..len].trim_end_matches(|ch| ".,:;!?".contains(ch));
+        if word.is_empty() {
+            return None;
+        }
+        while self.cur_pos() < start + word.len() {
+            self.advance();
+        }
+        Some(word)
+    }
+
+    /// Consumes the next token if it's a word or a string, which is how commands take arguments
+    /// like usernames and labels, so that they can all be quoted the same way.
+    ///
+    /// If something else comes next, nothing is consumed and there's no argument. An unterminated
+    /// string is an error, and nothing is consumed then either.
+    pub fn next_quoted_or_word(&mut self) -> Result<Option<Argument<'a>>, Error<'a>> {
+        let mut toks = self.clone();
+        let argument = match toks.next_token()? {
+            Some(Token::Word(word)) => Argument::Word(word),
+            Some(Token::Quote(text)) => Argument::Quoted(text),
+            _ => return Ok(None),
+        };
+        *self = toks;
+        Ok(Some(argument))
+    }
+
+    /// Consumes the token ending a command, if that's what comes next: `.`, `,`, `;`, or the end
+    /// of the line (or of the input, which also counts if it was already reached).
+    ///
+    /// Afterwards, `position` is just past the terminator, where the rest of the input starts.
+    pub fn eat_terminator(&mut self) -> Result<bool, Error<'a>> {
+        match self.peek_token()? {
+            Some(Token::Dot) | Some(Token::Comma) | Some(Token::Semi) | Some(Token::EndOfLine) => {
+                self.next_token()?;
+                Ok(true)
+            }
+            None => Ok(true),
+            Some(_) => Ok(false),
+        }
+    }
+
     pub fn position(&mut self) -> usize {
         self.cur_pos()
     }
@@ -191,8 +296,19 @@ impl<'a> Tokenizer<'a> {
         while self.cur().map_or(false, |(_, ch)| {
             !(self.cur_punct().is_some() || ch.is_whitespace())
         }) {
-            if self.cur().unwrap().1 == '"' {
+            let (_, ch) = self.cur().unwrap();
+            if ch == '`' && (self.str_from(start) == "+" || self.str_from(start) == "-") {
+                // A sign directly followed by inline code, e.g. ``+`bug` ``; the code is its own
+                // token.
+                break;
+            }
+            if ch == '"' {
                 let so_far = self.str_from(start);
+                if so_far == "+" || so_far == "-" {
+                    // A sign directly followed by a string, e.g. `+"good first issue"`; the
+                    // string is its own token.
+                    break;
+                }
                 if so_far.starts_with('r') && so_far.chars().skip(1).all(|v| v == '#' || v == '"') {
                     return Err(self.error(ErrorKind::RawString));
                 } else {
@@ -201,7 +317,19 @@ impl<'a> Tokenizer<'a> {
             }
             self.advance();
         }
-        Ok(Some(Token::Word(&self.str_from(start))))
+        let mut word = self.str_from(start);
+        if word.len() > 2
+            && word.starts_with('`')
+            && word.ends_with('`')
+            && !word[1..word.len() - 1].contains('`')
+        {
+            // People sometimes write labels and the like as inline code, which is just markup.
+            word = &word[1..word.len() - 1];
+        }
+        if word.starts_with('@') && is_valid_username(&word[1..]) {
+            return Ok(Some(Token::Mention(&word[1..])));
+        }
+        Ok(Some(Token::Word(word)))
     }
 }
 
@@ -215,6 +343,61 @@ fn tokenize<'a>(input: &'a str) -> Result<Vec<Token<'a>>, Error<'a>> {
     Ok(tokens)
 }
 
+#[test]
+fn verbatim_word() {
+    let mut toks = Tokenizer::new("  1.40.0. next");
+    assert_eq!(toks.verbatim_word(), Some("1.40.0"));
+    assert_eq!(toks.next_token().unwrap(), Some(Token::Dot));
+    assert_eq!(toks.verbatim_word(), Some("next"));
+    assert_eq!(toks.verbatim_word(), None);
+    assert_eq!(toks.next_token().unwrap(), Some(Token::EndOfLine));
+}
+
+#[test]
+fn quoted_or_word() {
+    let mut toks = Tokenizer::new(r#"  T-compiler "good first issue", @octocat"#);
+    assert_eq!(
+        toks.next_quoted_or_word().unwrap(),
+        Some(Argument::Word("T-compiler"))
+    );
+    assert_eq!(
+        toks.next_quoted_or_word().unwrap(),
+        Some(Argument::Quoted("good first issue"))
+    );
+    assert_eq!(toks.next_quoted_or_word().unwrap(), None);
+    assert_eq!(toks.next_token().unwrap(), Some(Token::Comma));
+    assert_eq!(toks.next_quoted_or_word().unwrap(), None);
+    assert_eq!(toks.next_token().unwrap(), Some(Token::Mention("octocat")));
+    assert_eq!(Argument::Quoted("a b").text(), "a b");
+}
+
+#[test]
+fn quoted_or_word_unterminated() {
+    let mut toks = Tokenizer::new(r#"label "good first issue"#);
+    toks.next_token().unwrap();
+    let before = toks.position();
+    assert_eq!(
+        toks.next_quoted_or_word().unwrap_err().position_and_kind(),
+        (23, ErrorKind::UnterminatedString)
+    );
+    assert_eq!(toks.position(), before);
+}
+
+#[test]
+fn tokenize_mentions() {
+    assert_eq!(
+        tokenize("@octocat, @rust-lang/release @-bad- @").unwrap(),
+        [
+            Token::Mention("octocat"),
+            Token::Comma,
+            Token::Word("@rust-lang/release"),
+            Token::Word("@-bad-"),
+            Token::Word("@"),
+            Token::EndOfLine,
+        ]
+    );
+}
+
 #[test]
 fn tokenize_1() {
     assert_eq!(
@@ -316,6 +499,36 @@ fn tokenize_7() {
     );
 }
 
+#[test]
+fn tokenize_signed_quote() {
+    assert_eq!(
+        tokenize(r#"+"good first issue" -"help wanted""#).unwrap(),
+        [
+            Token::Word("+"),
+            Token::Quote("good first issue"),
+            Token::Word("-"),
+            Token::Quote("help wanted"),
+            Token::EndOfLine,
+        ]
+    );
+}
+
+#[test]
+fn tokenize_inline_code() {
+    assert_eq!(
+        tokenize("`bug` +`E-easy`, `` `a`b`").unwrap(),
+        [
+            Token::Word("bug"),
+            Token::Word("+"),
+            Token::Word("E-easy"),
+            Token::Comma,
+            Token::Word("``"),
+            Token::Word("`a`b`"),
+            Token::EndOfLine,
+        ]
+    );
+}
+
 #[test]
 fn tokenize_raw_string_prohibit() {
     assert_eq!(
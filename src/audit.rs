@@ -0,0 +1,276 @@
+//! An audit trail of what the bot has done, and at whose request.
+//!
+//! Every input a handler acts on (see `handlers::run_handler`) is recorded as an `AuditEvent`,
+//! including those which failed, so new handlers are audited without doing anything. The events
+//! are logged as one line of JSON each, with the `triagebot::audit` target, so they can be picked
+//! out of the rest of the log and kept for as long as needed.
+//!
+//! The issue is recorded as it was before the handler ran, since that's in the event anyway;
+//! what the handler changed would take another request to find out, so the action it was asked
+//! to take stands in for that.
+//!
+//! The commands people ask for are also kept in the state store, as the history of each issue,
+//! for `@rustbot log` to show. Only the most recent `MAX_HISTORY` of them are kept.
+
+use crate::db::{StateKey, StateStore};
+use crate::github::{Event, Issue, IssueState};
+use chrono::{DateTime, Utc};
+use failure::Error;
+
+const NAMESPACE: &str = "AUDIT";
+
+/// The target audit events are logged with.
+const TARGET: &str = "triagebot::audit";
+
+/// The most entries kept in the history of each issue.
+const MAX_HISTORY: usize = 100;
+
+#[derive(Debug, serde::Serialize)]
+pub struct AuditEvent<'a> {
+    /// Who asked for the action: the author of the comment with the command, or whoever
+    /// triggered the event.
+    pub actor: &'a str,
+    pub repo: &'a str,
+    pub issue: Option<u64>,
+    /// The `X-GitHub-Delivery` id of the event, if any.
+    pub delivery: Option<&'a str>,
+    pub handler: &'static str,
+    /// The input the handler acted on (usually a command).
+    pub action: String,
+    pub before: Option<IssueSnapshot<'a>>,
+    pub outcome: Outcome,
+}
+
+/// The state of an issue which handlers change.
+#[derive(Debug, serde::Serialize)]
+pub struct IssueSnapshot<'a> {
+    pub title: &'a str,
+    pub state: IssueState,
+    pub labels: Vec<&'a str>,
+    pub assignees: Vec<&'a str>,
+    pub milestone: Option<&'a str>,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "result", content = "error", rename_all = "lowercase")]
+pub enum Outcome {
+    Succeeded,
+    Failed(String),
+}
+
+impl<'a> AuditEvent<'a> {
+    pub fn new(
+        event: &'a Event,
+        delivery: Option<&'a str>,
+        handler: &'static str,
+        action: String,
+        result: &Result<(), Error>,
+    ) -> AuditEvent<'a> {
+        AuditEvent {
+            actor: event.actor(),
+            repo: event.repo_name(),
+            issue: event.issue().map(|issue| issue.number),
+            delivery,
+            handler,
+            action,
+            before: event.issue().map(IssueSnapshot::of),
+            outcome: match result {
+                Ok(()) => Outcome::Succeeded,
+                Err(err) => Outcome::Failed(err.to_string()),
+            },
+        }
+    }
+
+    /// Adds the event to the audit trail.
+    pub fn record(&self) {
+        match serde_json::to_string(self) {
+            Ok(line) => tracing::info!(target: TARGET, "{}", line),
+            Err(err) => log::error!("failed to serialize audit event {:?}: {:?}", self, err),
+        }
+    }
+
+    /// Adds the event to the history of its issue, if it's about one.
+    pub fn store(&self, state: &StateStore) -> Result<(), Error> {
+        let issue = match self.issue {
+            Some(issue) => issue,
+            None => return Ok(()),
+        };
+        let key = StateKey::new(self.repo, issue, NAMESPACE);
+        let mut history: Vec<HistoryEntry> = state.get(&key)?.unwrap_or_default();
+        history.push(HistoryEntry {
+            at: Utc::now(),
+            actor: self.actor.to_owned(),
+            handler: self.handler.to_owned(),
+            action: self.action.clone(),
+            outcome: self.outcome.clone(),
+        });
+        let excess = history.len().saturating_sub(MAX_HISTORY);
+        history.drain(..excess);
+        state.set(&key, &history)
+    }
+}
+
+/// A command run on an issue, as kept in its history.
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct HistoryEntry {
+    pub at: DateTime<Utc>,
+    pub actor: String,
+    pub handler: String,
+    pub action: String,
+    pub outcome: Outcome,
+}
+
+/// The commands run on issue `number` in `repo`, oldest first.
+pub fn history(state: &StateStore, repo: &str, number: u64) -> Result<Vec<HistoryEntry>, Error> {
+    Ok(state
+        .get(&StateKey::new(repo, number, NAMESPACE))?
+        .unwrap_or_default())
+}
+
+impl<'a> IssueSnapshot<'a> {
+    fn of(issue: &'a Issue) -> IssueSnapshot<'a> {
+        IssueSnapshot {
+            title: issue.title(),
+            state: issue.state,
+            labels: issue.labels().iter().map(|l| l.name.as_str()).collect(),
+            assignees: issue.assignees().iter().map(|u| u.login.as_str()).collect(),
+            milestone: issue.milestone().map(|m| m.title.as_str()),
+        }
+    }
+}
+
+#[test]
+fn audit_line() {
+    let event = AuditEvent {
+        actor: "alice",
+        repo: "rust-lang/rust",
+        issue: Some(12),
+        delivery: None,
+        handler: "relabel",
+        action: "RelabelCommand([Add(\"T-compiler\")])".to_owned(),
+        before: Some(IssueSnapshot {
+            title: "ICE",
+            state: IssueState::Open,
+            labels: vec!["C-bug"],
+            assignees: vec![],
+            milestone: None,
+        }),
+        outcome: Outcome::Failed("Label T-compiler can only be set by Rust team members".into()),
+    };
+    assert_eq!(
+        serde_json::to_value(&event).unwrap(),
+        serde_json::json!({
+            "actor": "alice",
+            "repo": "rust-lang/rust",
+            "issue": 12,
+            "delivery": null,
+            "handler": "relabel",
+            "action": "RelabelCommand([Add(\"T-compiler\")])",
+            "before": {
+                "title": "ICE",
+                "state": "open",
+                "labels": ["C-bug"],
+                "assignees": [],
+                "milestone": null,
+            },
+            "outcome": {
+                "result": "failed",
+                "error": "Label T-compiler can only be set by Rust team members",
+            },
+        })
+    );
+    assert_eq!(
+        serde_json::to_value(&Outcome::Succeeded).unwrap(),
+        serde_json::json!({"result": "succeeded"})
+    );
+}
+
+#[test]
+fn history_is_capped() {
+    let state = StateStore::in_memory();
+    for i in 0..MAX_HISTORY + 2 {
+        AuditEvent {
+            actor: "alice",
+            repo: "rust-lang/rust",
+            issue: Some(12),
+            delivery: None,
+            handler: "relabel",
+            action: i.to_string(),
+            before: None,
+            outcome: Outcome::Succeeded,
+        }
+        .store(&state)
+        .unwrap();
+    }
+    let entries = history(&state, "rust-lang/rust", 12).unwrap();
+    assert_eq!(entries.len(), MAX_HISTORY);
+    assert_eq!(entries[0].action, "2");
+    assert_eq!(
+        entries[MAX_HISTORY - 1].action,
+        (MAX_HISTORY + 1).to_string()
+    );
+    assert!(history(&state, "rust-lang/rust", 13).unwrap().is_empty());
+}
+
+/// A subscriber keeping the target and message of every event, for tests to look at.
+#[cfg(test)]
+struct Capture(std::sync::Arc<std::sync::Mutex<Vec<(String, String)>>>);
+
+#[cfg(test)]
+impl tracing::Subscriber for Capture {
+    fn enabled(&self, _: &tracing::Metadata) -> bool {
+        true
+    }
+
+    fn new_span(&self, _: &tracing::span::Attributes) -> tracing::span::Id {
+        tracing::span::Id::from_u64(1)
+    }
+
+    fn record(&self, _: &tracing::span::Id, _: &tracing::span::Record) {}
+
+    fn record_follows_from(&self, _: &tracing::span::Id, _: &tracing::span::Id) {}
+
+    fn event(&self, event: &tracing::Event) {
+        struct Message<'a>(&'a mut String);
+        impl<'a> tracing::field::Visit for Message<'a> {
+            fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+                if field.name() == "message" {
+                    *self.0 = format!("{:?}", value);
+                }
+            }
+        }
+        let mut message = String::new();
+        event.record(&mut Message(&mut message));
+        self.0
+            .lock()
+            .unwrap()
+            .push((event.metadata().target().to_owned(), message));
+    }
+
+    fn enter(&self, _: &tracing::span::Id) {}
+
+    fn exit(&self, _: &tracing::span::Id) {}
+}
+
+#[test]
+fn audit_recorded() {
+    let lines = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let event = AuditEvent {
+        actor: "alice",
+        repo: "rust-lang/rust",
+        issue: Some(12),
+        delivery: Some("72d3162e"),
+        handler: "close",
+        action: "CloseCommand".to_owned(),
+        before: None,
+        outcome: Outcome::Succeeded,
+    };
+    tracing::subscriber::with_default(Capture(lines.clone()), || event.record());
+    let lines = lines.lock().unwrap();
+    assert_eq!(lines.len(), 1);
+    assert_eq!(lines[0].0, TARGET);
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&lines[0].1).unwrap(),
+        serde_json::to_value(&event).unwrap()
+    );
+}
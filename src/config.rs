@@ -1,34 +1,911 @@
-use crate::github::GithubClient;
+use crate::github::{GithubClient, Label, PushEvent, Repository};
 use failure::Error;
-use std::collections::HashMap;
+use parser::command::is_valid_username;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
 use std::sync::{Arc, RwLock};
 use std::time::{Duration, Instant};
 
 static CONFIG_FILE_NAME: &str = "triagebot.toml";
+/// The branch whose configuration is used.
+static CONFIG_BRANCH: &str = "master";
 const REFRESH_EVERY: Duration = Duration::from_secs(2 * 60); // Every two minutes
 
 lazy_static::lazy_static! {
     static ref CONFIG_CACHE: RwLock<HashMap<String, (Arc<Config>, Instant)>> =
         RwLock::new(HashMap::new());
+    /// The repository, in each organization, whose `triagebot.toml` holds the defaults for the
+    /// organization's other repositories (see `inherit`); empty if there's none.
+    static ref DEFAULTS_REPO: String =
+        std::env::var("CONFIG_DEFAULTS_REPO").unwrap_or_else(|_| ".github".to_owned());
 }
 
 #[derive(serde::Deserialize)]
 pub(crate) struct Config {
+    pub(crate) autolabel: Option<AutolabelConfig>,
+    pub(crate) blocked: Option<BlockedConfig>,
+    pub(crate) checkbox_labels: Option<CheckboxLabelsConfig>,
     pub(crate) relabel: Option<RelabelConfig>,
+    pub(crate) rename: Option<RenameConfig>,
+    pub(crate) second: Option<SecondConfig>,
+    pub(crate) shortcut: Option<ShortcutConfig>,
+    pub(crate) transfer: Option<TransferConfig>,
     pub(crate) assign: Option<AssignConfig>,
+    pub(crate) close: Option<CloseConfig>,
+    pub(crate) exclusive_labels: Option<ExclusiveLabelsConfig>,
+    pub(crate) hello: Option<HelloConfig>,
+    pub(crate) help: Option<HelpConfig>,
+    pub(crate) history: Option<HistoryConfig>,
+    pub(crate) label_effects: Option<LabelEffectsConfig>,
+    pub(crate) lock: Option<LockConfig>,
+    pub(crate) mentions: Option<MentionsConfig>,
+    pub(crate) milestone: Option<MilestoneConfig>,
+    pub(crate) minimize: Option<MinimizeConfig>,
+    pub(crate) no_merges: Option<NoMergesConfig>,
+    pub(crate) note: Option<NoteConfig>,
+    pub(crate) parse_errors: Option<ParseErrorsConfig>,
+    pub(crate) permission_denied: Option<PermissionDeniedConfig>,
+    pub(crate) ping: Option<PingConfig>,
+    pub(crate) poll: Option<PollConfig>,
+    pub(crate) reactions: Option<ReactionsConfig>,
+    pub(crate) resolve: Option<ResolveConfig>,
+    pub(crate) review_labels: Option<ReviewLabelsConfig>,
+    pub(crate) status: Option<StatusConfig>,
+    pub(crate) subscribe: Option<SubscribeConfig>,
+    pub(crate) triage: Option<TriageConfig>,
+    pub(crate) welcome: Option<WelcomeConfig>,
+    /// The repository's own versions of the messages we post, keyed by name (see
+    /// `crate::template`).
+    #[serde(default)]
+    pub(crate) templates: HashMap<String, String>,
+    /// The other repositories which commands may act on (like `rust-lang/cargo`, for
+    /// `transfer`). When empty, commands can only act on the repository they're used in.
+    #[serde(default)]
+    pub(crate) trusted_repos: Vec<String>,
+    /// Whether to ignore commands from other bots, except those in `allowed_bots`. Our own
+    /// commands are always ignored.
+    #[serde(default)]
+    pub(crate) ignore_bots: bool,
+    #[serde(default)]
+    pub(crate) allowed_bots: Vec<String>,
+    /// The problems found in sections we've disabled because of them (see `Config::validate`).
+    #[serde(skip)]
+    invalid: Vec<ConfigError>,
+}
+
+/// A problem in one section of a repository's configuration.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct ConfigError {
+    pub(crate) section: &'static str,
+    pub(crate) message: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "`[{}]`: {}", self.section, self.message)
+    }
+}
+
+impl Config {
+    /// Checks the invariants which deserializing the configuration doesn't, returning every
+    /// problem found rather than just the first.
+    pub(crate) fn validate(&self) -> Result<(), Vec<ConfigError>> {
+        let mut errors = Vec::new();
+        let mut error = |section, message| errors.push(ConfigError { section, message });
+        if let Some(assign) = &self.assign {
+            for user in assign
+                .review_rotation
+                .iter()
+                .chain(assign.allowed_assignees.iter().flatten())
+            {
+                if !is_valid_username(user) {
+                    error(
+                        "assign",
+                        format!("`{}` is not a valid GitHub username", user),
+                    );
+                }
+            }
+            for (name, days) in &[
+                ("status-report-after-days", assign.status_report_after_days),
+                ("release-after-days", assign.release_after_days),
+            ] {
+                if *days < 1 || *days > MAX_INACTIVITY_DAYS {
+                    error(
+                        "assign",
+                        format!(
+                            "`{}` must be between 1 and {} days, not {}",
+                            name, MAX_INACTIVITY_DAYS, days
+                        ),
+                    );
+                }
+            }
+            if assign.activity_sources.is_empty() {
+                error(
+                    "assign",
+                    "`activity-sources` is empty, so every assignee would seem inactive".to_owned(),
+                );
+            }
+            if assign
+                .claimable_only_with
+                .as_ref()
+                .map_or(false, Vec::is_empty)
+            {
+                error(
+                    "assign",
+                    "`claimable-only-with` is empty, so no issue could be claimed".to_owned(),
+                );
+            }
+        }
+        if let Some(autolabel) = &self.autolabel {
+            for pattern in autolabel.paths.keys() {
+                if let Err(err) = glob::Pattern::new(pattern) {
+                    error(
+                        "autolabel",
+                        format!("`{}` is not a valid glob: {}", pattern, err),
+                    );
+                }
+            }
+        }
+        if let Some(checkbox_labels) = &self.checkbox_labels {
+            for (text, labels) in &checkbox_labels.boxes {
+                if text.trim().is_empty() {
+                    error("checkbox_labels", "a checkbox has no text".to_owned());
+                } else if labels.is_empty() {
+                    error(
+                        "checkbox_labels",
+                        format!("checkbox `{}` has no labels", text),
+                    );
+                }
+            }
+        }
+        if let Some(exclusive_labels) = &self.exclusive_labels {
+            for pattern in &exclusive_labels.groups {
+                if let Err(err) = glob::Pattern::new(pattern) {
+                    error(
+                        "exclusive_labels",
+                        format!("`{}` is not a valid glob: {}", pattern, err),
+                    );
+                }
+            }
+        }
+        if let Some(label_effects) = &self.label_effects {
+            for (label, effects) in &label_effects.labels {
+                if effects.comment.is_none()
+                    && effects.add_labels.is_empty()
+                    && effects.assign.is_none()
+                {
+                    error("label_effects", format!("label `{}` has no effects", label));
+                }
+                if let Some(user) = &effects.assign {
+                    if !is_valid_username(user) {
+                        error(
+                            "label_effects",
+                            format!("`{}` is not a valid GitHub username", user),
+                        );
+                    }
+                }
+            }
+        }
+        if let Some(mentions) = &self.mentions {
+            for (pattern, path) in &mentions.paths {
+                if let Err(err) = glob::Pattern::new(pattern) {
+                    error(
+                        "mentions",
+                        format!("`{}` is not a valid glob: {}", pattern, err),
+                    );
+                }
+                for user in &path.cc {
+                    if !is_valid_username(user) {
+                        error(
+                            "mentions",
+                            format!("`{}` is not a valid GitHub username", user),
+                        );
+                    }
+                }
+            }
+        }
+        if let Some(parse_errors) = &self.parse_errors {
+            if parse_errors.max_errors == 0 {
+                error("parse_errors", "`max-errors` must be at least 1".to_owned());
+            }
+        }
+        if let Some(permission_denied) = &self.permission_denied {
+            if !permission_denied.comment && !permission_denied.reaction {
+                error(
+                    "permission_denied",
+                    "one of `comment` and `reaction` must be set".to_owned(),
+                );
+            }
+        }
+        if let Some(ping) = &self.ping {
+            for (group, members) in &ping.groups {
+                match members {
+                    PingGroup::Members(members) if members.is_empty() => {
+                        error("ping", format!("group `{}` has no members", group));
+                    }
+                    PingGroup::Team { team } if team.trim().is_empty() => {
+                        error("ping", format!("group `{}` has an empty team name", group));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        if let Some(relabel) = &self.relabel {
+            for alias in relabel.aliases.keys() {
+                if let Err(chain) = relabel.resolve_alias(alias) {
+                    error(
+                        "relabel",
+                        format!("alias `{}` is cyclic: {}", alias, chain.join(" -> ")),
+                    );
+                }
+            }
+        }
+        if let Some(rename) = &self.rename {
+            if rename
+                .keep_prefixes
+                .iter()
+                .any(|prefix| prefix.trim().is_empty())
+            {
+                error("rename", "prefixes to keep cannot be empty".to_owned());
+            }
+        }
+        if let Some(second) = &self.second {
+            if second.quorum == 0 {
+                error("second", "the quorum must be at least 1".to_owned());
+            }
+            if !second.members.is_empty() && second.quorum > second.members.len() {
+                error(
+                    "second",
+                    format!(
+                        "the quorum ({}) is larger than the number of members ({})",
+                        second.quorum,
+                        second.members.len()
+                    ),
+                );
+            }
+            for member in &second.members {
+                if !is_valid_username(member) {
+                    error(
+                        "second",
+                        format!("`{}` is not a valid GitHub username", member),
+                    );
+                }
+            }
+        }
+        if let Some(shortcut) = &self.shortcut {
+            for (name, commands) in &shortcut.shortcuts {
+                if commands.is_empty() {
+                    error("shortcut", format!("shortcut `{}` has no commands", name));
+                }
+            }
+        }
+        for message in crate::template::validate(&self.templates) {
+            error("templates", message);
+        }
+        for repo in &self.trusted_repos {
+            if repo.parse::<Repository>().is_err() {
+                error(
+                    "trusted_repos",
+                    format!("`{}` is not a repository name, like `owner/name`", repo),
+                );
+            }
+        }
+        if let Some(triage) = &self.triage {
+            if triage.priorities.is_empty() {
+                error("triage", "no priorities are listed".to_owned());
+            }
+            let mut seen = HashSet::new();
+            for priority in &triage.priorities {
+                if !seen.insert(priority) {
+                    error("triage", format!("priority `{}` is listed twice", priority));
+                }
+                if priority.eq_ignore_ascii_case("none") || priority.eq_ignore_ascii_case("clear") {
+                    error(
+                        "triage",
+                        format!(
+                            "`{}` cannot be a priority, as `prioritize {}` clears the priority",
+                            priority,
+                            priority.to_lowercase()
+                        ),
+                    );
+                }
+            }
+            for status in &triage.statuses {
+                if !seen.insert(status) {
+                    error(
+                        "triage",
+                        format!("status `{}` is listed twice, or is also a priority", status),
+                    );
+                }
+            }
+            if let Some(waiting) = &triage.waiting {
+                if waiting.on_author == waiting.on_review {
+                    error(
+                        "triage",
+                        "the waiting-on-author and waiting-on-review labels are the same"
+                            .to_owned(),
+                    );
+                }
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Checks that the labels named by the configuration exist among the repository's `labels`.
+    pub(crate) fn validate_labels(&self, labels: &[Label]) -> Result<(), Vec<ConfigError>> {
+        let mut errors = Vec::new();
+        let exists = |name: &str| labels.iter().any(|l| l.name.eq_ignore_ascii_case(name));
+        if let Some(relabel) = &self.relabel {
+            for alias in relabel.aliases.keys() {
+                if let Ok(target) = relabel.resolve_alias(alias) {
+                    if !exists(target) {
+                        errors.push(ConfigError {
+                            section: "relabel",
+                            message: format!(
+                                "alias `{}` refers to `{}`, which is not a label in this \
+                                 repository",
+                                alias, target
+                            ),
+                        });
+                    }
+                }
+            }
+        }
+        if let Some(autolabel) = &self.autolabel {
+            for label in autolabel.paths.values().flatten() {
+                if !exists(label) {
+                    errors.push(ConfigError {
+                        section: "autolabel",
+                        message: format!("`{}` is not a label in this repository", label),
+                    });
+                }
+            }
+        }
+        if let Some(checkbox_labels) = &self.checkbox_labels {
+            for label in checkbox_labels.boxes.values().flatten() {
+                if !exists(label) {
+                    errors.push(ConfigError {
+                        section: "checkbox_labels",
+                        message: format!("`{}` is not a label in this repository", label),
+                    });
+                }
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Disables the sections which `errors` are about, remembering the errors to report them
+    /// when those sections' features are used.
+    fn disable_invalid(&mut self, errors: Vec<ConfigError>) {
+        for error in &errors {
+            match error.section {
+                "assign" => self.assign = None,
+                "autolabel" => self.autolabel = None,
+                "blocked" => self.blocked = None,
+                "checkbox_labels" => self.checkbox_labels = None,
+                "close" => self.close = None,
+                "exclusive_labels" => self.exclusive_labels = None,
+                "label_effects" => self.label_effects = None,
+                "mentions" => self.mentions = None,
+                "note" => self.note = None,
+                "parse_errors" => self.parse_errors = None,
+                "permission_denied" => self.permission_denied = None,
+                "ping" => self.ping = None,
+                "relabel" => self.relabel = None,
+                "rename" => self.rename = None,
+                "second" => self.second = None,
+                "shortcut" => self.shortcut = None,
+                "templates" => self.templates.clear(),
+                "triage" => self.triage = None,
+                "trusted_repos" => self.trusted_repos.clear(),
+                "welcome" => self.welcome = None,
+                // The error is still reported, but nothing stops the section being used.
+                section => log::error!("cannot disable unknown config section {}", section),
+            }
+        }
+        self.invalid.extend(errors);
+    }
+
+    /// Renders the message called `name`, as customized by the repository (see
+    /// `crate::template`).
+    pub(crate) fn render_template(&self, name: &str, vars: &[(&str, &str)]) -> String {
+        crate::template::render_template(&self.templates, name, vars)
+    }
+
+    /// The sections which are set, other than those for handlers, since they change how the
+    /// handlers behave rather than enabling one.
+    pub(crate) fn settings(&self) -> Vec<&'static str> {
+        let mut settings = Vec::new();
+        if self.parse_errors.is_some() {
+            settings.push("parse_errors");
+        }
+        if self.permission_denied.is_some() {
+            settings.push("permission_denied");
+        }
+        if self.reactions.is_some() {
+            settings.push("reactions");
+        }
+        if self.shortcut.is_some() {
+            settings.push("shortcut");
+        }
+        if !self.templates.is_empty() {
+            settings.push("templates");
+        }
+        if !self.trusted_repos.is_empty() {
+            settings.push("trusted_repos");
+        }
+        if self.ignore_bots {
+            settings.push("ignore_bots");
+        }
+        settings
+    }
+
+    /// Whether to ignore the commands of the bot `login`, going by `ignore_bots`.
+    ///
+    /// The bots in `allowed_bots` may leave off the `[bot]` suffix GitHub Apps have.
+    pub(crate) fn ignores_bot(&self, login: &str) -> bool {
+        let name = login.trim_end_matches("[bot]");
+        self.ignore_bots
+            && !self
+                .allowed_bots
+                .iter()
+                .any(|bot| bot.eq_ignore_ascii_case(login) || bot.eq_ignore_ascii_case(name))
+    }
+
+    /// Checks that commands used in `repo` may act on `target`: it must be `repo` itself, or one
+    /// of the trusted repositories.
+    pub(crate) fn check_trusted(
+        &self,
+        repo: &Repository,
+        target: &Repository,
+    ) -> Result<(), Error> {
+        let trusted = |name: &str| name.eq_ignore_ascii_case(target.full_name());
+        if trusted(repo.full_name()) || self.trusted_repos.iter().any(|name| trusted(name)) {
+            return Ok(());
+        }
+        failure::bail!(
+            "`{}` is not a trusted repository; commands here can only act on {}",
+            target,
+            std::iter::once(repo.full_name())
+                .chain(self.trusted_repos.iter().map(|name| name.as_str()))
+                .map(|name| format!("`{}`", name))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+
+    /// The sections which were disabled because they're invalid.
+    pub(crate) fn invalid_sections(&self) -> Vec<&'static str> {
+        let mut sections = self
+            .invalid
+            .iter()
+            .map(|error| error.section)
+            .collect::<Vec<_>>();
+        sections.sort();
+        sections.dedup();
+        sections
+    }
+
+    /// The problems which made us disable `section`, if it was disabled.
+    pub(crate) fn errors(&self, section: &str) -> Vec<&ConfigError> {
+        self.invalid
+            .iter()
+            .filter(|error| error.section == section)
+            .collect()
+    }
 }
 
+/// The longest `status-report-after-days` and `release-after-days` may be.
+const MAX_INACTIVITY_DAYS: i64 = 365;
+
 #[derive(serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub(crate) struct AssignConfig {
+    /// Days without a comment from the assignee before we ask them for a status report.
+    #[serde(default = "default_status_report_after_days")]
+    pub(crate) status_report_after_days: i64,
+    /// Days after asking for a status report, with no reply, before we release the assignment.
+    #[serde(default = "default_release_after_days")]
+    pub(crate) release_after_days: i64,
+    /// If set, only these users may be assigned (or claim issues).
+    #[serde(default)]
+    pub(crate) allowed_assignees: Option<Vec<String>>,
+    /// The reviewers which a bare `assign` on a pull request picks from, in turn.
+    #[serde(default)]
+    pub(crate) review_rotation: Vec<String>,
+    /// If set, the rotation skips reviewers assigned to this many open pull requests or more.
+    #[serde(default)]
+    pub(crate) max_open_reviews: Option<usize>,
+    /// If set, how many open issues one user may have claimed at once.
+    #[serde(default)]
+    pub(crate) max_claims: Option<u32>,
+    /// Whether `max-claims` doesn't apply to team members.
+    #[serde(default)]
+    pub(crate) max_claims_exempt_team: bool,
+    /// If set, people may only claim (or assign themselves to) issues with one of these labels.
     #[serde(default)]
-    _empty: (),
+    pub(crate) claimable_only_with: Option<Vec<String>>,
+    /// Whether `claimable-only-with` doesn't apply to team members.
+    #[serde(default)]
+    pub(crate) claimable_exempt_team: bool,
+    /// Whether assigning someone to a pull request also requests a review from them.
+    #[serde(default)]
+    pub(crate) request_review: bool,
+    /// Whether assigning several users at once assigns nobody if any of them can't be assigned,
+    /// rather than assigning the others.
+    #[serde(default)]
+    pub(crate) all_or_nothing: bool,
+    /// What counts as the assignee being active, for the status report requests.
+    #[serde(default = "default_activity_sources")]
+    pub(crate) activity_sources: Vec<ActivitySource>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum ActivitySource {
+    /// Commenting on the issue.
+    Comments,
+    /// Pushing commits to the pull request, or to a pull request referring to the issue.
+    Pushes,
+    /// Reviewing the pull request, or a pull request referring to the issue.
+    Reviews,
+}
+
+fn default_activity_sources() -> Vec<ActivitySource> {
+    vec![ActivitySource::Comments]
+}
+
+fn default_status_report_after_days() -> i64 {
+    14
+}
+
+fn default_release_after_days() -> i64 {
+    14
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct BlockedConfig {
+    /// The label marking blocked issues.
+    pub(crate) label: String,
+    /// Whether to also comment on the blocking issue, linking back to the blocked one.
+    #[serde(default)]
+    pub(crate) cross_reference: bool,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct CloseConfig {
+    /// When set, the issue author may no longer close or reopen their own issue; only team
+    /// members can.
+    #[serde(default)]
+    pub(crate) team_only: bool,
+}
+
+#[derive(serde::Deserialize)]
+pub(crate) struct HelloConfig {}
+
+#[derive(serde::Deserialize)]
+pub(crate) struct HistoryConfig {}
+
+#[derive(serde::Deserialize)]
+pub(crate) struct LockConfig {}
+
+#[derive(serde::Deserialize)]
+pub(crate) struct StatusConfig {}
+
+#[derive(serde::Deserialize)]
+pub(crate) struct ResolveConfig {}
+
+#[derive(serde::Deserialize)]
+pub(crate) struct HelpConfig {}
+
+#[derive(serde::Deserialize)]
+pub(crate) struct NoteConfig {}
+
+#[derive(serde::Deserialize)]
+pub(crate) struct MilestoneConfig {}
+
+#[derive(serde::Deserialize)]
+pub(crate) struct MinimizeConfig {
+    /// The label marking issues which need a minimal reproduction.
+    #[serde(default = "default_minimize_label")]
+    pub(crate) label: String,
+    /// Text marking a comment as having the reproduction, which removes the label.
+    #[serde(default = "default_minimize_marker")]
+    pub(crate) marker: String,
+}
+
+fn default_minimize_label() -> String {
+    "E-needs-mcve".to_owned()
+}
+
+fn default_minimize_marker() -> String {
+    "MCVE".to_owned()
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct ParseErrorsConfig {
+    /// How many of the commands in one comment which couldn't be parsed the reply explains.
+    #[serde(default = "default_max_errors")]
+    pub(crate) max_errors: usize,
+}
+
+fn default_max_errors() -> usize {
+    3
+}
+
+/// How to tell people that a command of theirs was refused, as they may not do what it asks.
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct PermissionDeniedConfig {
+    /// Whether to reply with the `permission-denied` message.
+    #[serde(default = "default_permission_denied_comment")]
+    pub(crate) comment: bool,
+    /// Whether to react to the comment with the command with 👎.
+    #[serde(default)]
+    pub(crate) reaction: bool,
+}
+
+fn default_permission_denied_comment() -> bool {
+    true
+}
+
+#[derive(serde::Deserialize)]
+pub(crate) struct PollConfig {}
+
+#[derive(serde::Deserialize)]
+pub(crate) struct ReactionsConfig {}
+
+#[derive(serde::Deserialize)]
+pub(crate) struct SubscribeConfig {
+    /// The changes to issues which subscribers are notified of.
+    #[serde(default = "default_subscribe_events")]
+    pub(crate) events: Vec<SubscribeEvent>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum SubscribeEvent {
+    /// The issue being closed or reopened.
+    State,
+    /// Labels being added or removed.
+    Labels,
+}
+
+fn default_subscribe_events() -> Vec<SubscribeEvent> {
+    vec![SubscribeEvent::State, SubscribeEvent::Labels]
+}
+
+/// The labels which track where a pull request is in review.
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct ReviewLabelsConfig {
+    /// Removed once a review approves the pull request or requests changes.
+    #[serde(default = "default_waiting_on_review")]
+    pub(crate) waiting_on_review: String,
+    /// Added when a review requests changes, and removed on approval.
+    #[serde(default = "default_waiting_on_author")]
+    pub(crate) waiting_on_author: String,
+    /// Added when a review approves the pull request, if set, and removed when one requests
+    /// changes.
+    #[serde(default)]
+    pub(crate) approved: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct RenameConfig {
+    /// Prefixes of titles (like `[beta]`) which are kept when an issue is renamed.
+    #[serde(default)]
+    pub(crate) keep_prefixes: Vec<String>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct TransferConfig {
+    /// Whether issues may only be moved to other repositories of the same owner.
+    #[serde(default)]
+    pub(crate) same_owner_only: bool,
+}
+
+#[derive(serde::Deserialize)]
+pub(crate) struct AutolabelConfig {
+    /// The labels for pull requests changing files matching each glob, keyed by the glob.
+    #[serde(flatten)]
+    pub(crate) paths: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(serde::Deserialize)]
+pub(crate) struct CheckboxLabelsConfig {
+    /// The labels for issues with each checkbox checked, keyed by the checkbox's text.
+    #[serde(flatten)]
+    pub(crate) boxes: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(serde::Deserialize)]
+pub(crate) struct ExclusiveLabelsConfig {
+    /// Globs of labels (like `P-*`), each of which an issue should have at most one label
+    /// matching.
+    pub(crate) groups: Vec<String>,
+}
+
+#[derive(serde::Deserialize)]
+pub(crate) struct LabelEffectsConfig {
+    /// What happens when each label is added, keyed by the label.
+    #[serde(flatten)]
+    pub(crate) labels: BTreeMap<String, LabelEffects>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct LabelEffects {
+    /// A comment to post, in which `{user}` stands for whoever added the label.
+    #[serde(default)]
+    pub(crate) comment: Option<String>,
+    /// Other labels to add.
+    #[serde(default)]
+    pub(crate) add_labels: Vec<String>,
+    /// Who to assign the issue to, instead of anyone currently assigned.
+    #[serde(default)]
+    pub(crate) assign: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+pub(crate) struct MentionsConfig {
+    /// Who to ping about pull requests changing files matching each glob, keyed by the glob.
+    #[serde(flatten)]
+    pub(crate) paths: BTreeMap<String, MentionsPathConfig>,
+}
+
+#[derive(serde::Deserialize)]
+pub(crate) struct MentionsPathConfig {
+    /// Shown next to the glob in the comment.
+    pub(crate) message: Option<String>,
+    /// The GitHub usernames to ping.
+    #[serde(default)]
+    pub(crate) cc: Vec<String>,
+}
+
+#[derive(serde::Deserialize)]
+pub(crate) struct NoMergesConfig {
+    /// The warning posted on pull requests with merge commits.
+    #[serde(default = "default_no_merges_message")]
+    pub(crate) message: String,
+}
+
+fn default_no_merges_message() -> String {
+    "This pull request contains merge commits, which we don't accept; please rebase your \
+     branch onto the latest master instead (for example with `git pull --rebase upstream \
+     master`), and force-push it."
+        .to_owned()
+}
+
+#[derive(serde::Deserialize)]
+pub(crate) struct PingConfig {
+    /// The members of each group which can be pinged, keyed by the group's name.
+    #[serde(flatten)]
+    pub(crate) groups: HashMap<String, PingGroup>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+pub(crate) enum PingGroup {
+    /// The GitHub usernames of the members.
+    Members(Vec<String>),
+    /// The members of a Rust team (e.g., `{ team = "infra" }`), as of when it's pinged.
+    Team { team: String },
 }
 
 #[derive(serde::Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub(crate) struct RelabelConfig {
+    /// Globs of the labels which anyone may change; only team members may change the others.
     #[serde(default)]
     pub(crate) allow_unauthenticated: Vec<String>,
+    /// Globs of labels which only team members may change, even if they're also
+    /// `allow-unauthenticated`.
+    #[serde(default)]
+    pub(crate) restricted: Vec<String>,
+    /// Shorthand label names, mapped to the label (or another alias) they expand to.
+    #[serde(default)]
+    pub(crate) aliases: HashMap<String, String>,
+}
+
+impl RelabelConfig {
+    /// Expands `label` through the aliases, following chains of aliases until a name which is
+    /// not itself an alias is reached.
+    ///
+    /// If the aliases are cyclic, returns the chain of aliases up to (and including) the first
+    /// repeated one instead.
+    pub(crate) fn resolve_alias<'a>(&'a self, label: &'a str) -> Result<&'a str, Vec<&'a str>> {
+        let mut chain = vec![label];
+        let mut current = label;
+        while let Some(target) = self.aliases.get(current) {
+            let target = target.as_str();
+            let cyclic = chain.contains(&target);
+            chain.push(target);
+            if cyclic {
+                return Err(chain);
+            }
+            current = target;
+        }
+        Ok(current)
+    }
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct SecondConfig {
+    /// How many members need to second a proposal.
+    pub(crate) quorum: usize,
+    /// The label applied once the quorum is reached.
+    #[serde(default = "default_second_label")]
+    pub(crate) label: String,
+    /// If set, the only team members who may second.
+    #[serde(default)]
+    pub(crate) members: Vec<String>,
+}
+
+fn default_second_label() -> String {
+    "finished-final-comment-period".to_owned()
+}
+
+#[derive(serde::Deserialize)]
+pub(crate) struct ShortcutConfig {
+    /// The commands each shortcut (keyed by its name) expands to; see `parser::shortcut`.
+    #[serde(flatten)]
+    pub(crate) shortcuts: HashMap<String, Vec<String>>,
+}
+
+#[derive(serde::Deserialize)]
+pub(crate) struct TriageConfig {
+    /// The priority labels, from lowest to highest priority.
+    pub(crate) priorities: Vec<String>,
+    /// The status labels which can be set along with the priority.
+    #[serde(default)]
+    pub(crate) statuses: Vec<String>,
+    /// If set, an issue waiting on its author goes back to waiting on review when they comment.
+    #[serde(default)]
+    pub(crate) waiting: Option<WaitingConfig>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct WaitingConfig {
+    #[serde(default = "default_waiting_on_author")]
+    pub(crate) on_author: String,
+    #[serde(default = "default_waiting_on_review")]
+    pub(crate) on_review: String,
+}
+
+fn default_waiting_on_author() -> String {
+    "S-waiting-on-author".to_owned()
+}
+
+fn default_waiting_on_review() -> String {
+    "S-waiting-on-review".to_owned()
+}
+
+#[derive(serde::Deserialize)]
+pub(crate) struct WelcomeConfig {
+    /// The comment to post on a new contributor's first issue or pull request, in which
+    /// `{user}` is replaced by their username.
+    pub(crate) message: String,
+}
+
+/// The repositories whose configuration we've loaded, i.e., those the bot has seen events for.
+pub(crate) fn known_repos() -> Vec<String> {
+    CONFIG_CACHE.read().unwrap().keys().cloned().collect()
 }
 
 pub(crate) fn get(gh: &GithubClient, repo: &str) -> Result<Arc<Config>, Error> {
@@ -51,18 +928,568 @@ fn get_cached_config(repo: &str) -> Option<Arc<Config>> {
 }
 
 fn get_fresh_config(gh: &GithubClient, repo: &str) -> Result<Arc<Config>, Error> {
-    let contents = gh
-        .raw_file(repo, "master", CONFIG_FILE_NAME)?
-        .ok_or_else(|| {
-            failure::err_msg(
-                "This repository is not enabled to use triagebot.\n\
-                 Add a `triagebot.toml` in the root of the master branch to enable it.",
-            )
-        })?;
-    let config = Arc::new(toml::from_slice::<Config>(&contents)?);
+    let (mut config, errors) = load(gh, repo, CONFIG_BRANCH)?;
+    if !errors.is_empty() {
+        for error in &errors {
+            log::warn!("invalid configuration for {}: {}", repo, error);
+        }
+        // As when reloading, an invalid configuration doesn't replace a previous one.
+        let previous = CONFIG_CACHE
+            .read()
+            .unwrap()
+            .get(repo)
+            .map(|(previous, _)| previous.clone());
+        if let Some(previous) = previous {
+            return Ok(insert(repo, previous));
+        }
+        config.disable_invalid(errors);
+    }
+    Ok(insert(repo, Arc::new(config)))
+}
+
+/// Reloads the configuration of `repo` after a `push` to it, if the push changed it.
+///
+/// If the new configuration is invalid, the current one remains in use, and the returned error
+/// describes the problems.
+pub(crate) fn reload_on_push(gh: &GithubClient, event: &PushEvent) -> Result<(), Error> {
+    if event.git_ref != format!("refs/heads/{}", CONFIG_BRANCH) || !event.touches(CONFIG_FILE_NAME)
+    {
+        return Ok(());
+    }
+    let repo = event.repository.full_name();
+    log::info!("reloading the configuration of {}", repo);
+    // Fetch the pushed commit's version, as the branch's may be served stale for a while.
+    let (config, errors) = load(gh, repo, &event.after)?;
+    if !errors.is_empty() {
+        failure::bail!(
+            "The new `{}` is invalid, so the previous configuration remains in use:\n\n{}",
+            CONFIG_FILE_NAME,
+            errors
+                .iter()
+                .map(|error| format!("- {}", error))
+                .collect::<Vec<_>>()
+                .join("\n")
+        );
+    }
+    insert(repo, Arc::new(config));
+    Ok(())
+}
+
+/// Replaces the cached configuration of `repo`.
+///
+/// Caches `config` (a `triagebot.toml`) as the configuration of `repo`, for tests.
+#[cfg(test)]
+pub(crate) fn assume(repo: &str, config: &str) {
+    insert(repo, Arc::new(toml::from_str(config).unwrap()));
+}
+
+/// The configuration is loaded before taking the lock, which is only held for the swap, so
+/// loading one repository's configuration never holds up handling events for others.
+fn insert(repo: &str, config: Arc<Config>) -> Arc<Config> {
     CONFIG_CACHE
         .write()
         .unwrap()
         .insert(repo.to_string(), (config.clone(), Instant::now()));
-    Ok(config)
+    config
+}
+
+/// Fetches and parses the configuration of `repo` at `rev` (a branch or commit), on top of its
+/// organization's defaults, along with any problems `Config::validate` finds in it.
+fn load(gh: &GithubClient, repo: &str, rev: &str) -> Result<(Config, Vec<ConfigError>), Error> {
+    let contents = gh.raw_file(repo, rev, CONFIG_FILE_NAME)?.ok_or_else(|| {
+        failure::err_msg(
+            "This repository is not enabled to use triagebot.\n\
+             Add a `triagebot.toml` in the root of the master branch to enable it.",
+        )
+    })?;
+    let mut config = toml::from_slice::<toml::Value>(&contents)?;
+    if let Some(defaults) = load_defaults(gh, repo)? {
+        inherit(&mut config, defaults);
+    }
+    let config = config.try_into::<Config>()?;
+    let mut errors = config.validate().err().unwrap_or_default();
+    if config.relabel.is_some() || config.checkbox_labels.is_some() {
+        match repo
+            .parse::<Repository>()
+            .and_then(|repository| repository.cached_labels(gh))
+        {
+            Ok(labels) => errors.extend(config.validate_labels(&labels).err().unwrap_or_default()),
+            Err(err) => log::warn!(
+                "failed to check the labels configured for {}: {:?}",
+                repo,
+                err
+            ),
+        }
+    }
+    Ok((config, errors))
+}
+
+/// Fetches the defaults for `repo` from its organization's `DEFAULTS_REPO`, if it has any.
+///
+/// The defaults are always those on `CONFIG_BRANCH`, so pushing to them takes effect in the
+/// other repositories as their cached configuration is refreshed.
+fn load_defaults(gh: &GithubClient, repo: &str) -> Result<Option<toml::Value>, Error> {
+    let org = match repo.split('/').next() {
+        Some(org) if !DEFAULTS_REPO.is_empty() => org,
+        _ => return Ok(None),
+    };
+    let defaults_repo = format!("{}/{}", org, *DEFAULTS_REPO);
+    if defaults_repo.eq_ignore_ascii_case(repo) {
+        return Ok(None);
+    }
+    let contents = match gh.raw_file(&defaults_repo, CONFIG_BRANCH, CONFIG_FILE_NAME)? {
+        Some(contents) => contents,
+        None => return Ok(None),
+    };
+    match toml::from_slice(&contents) {
+        Ok(defaults) => Ok(Some(defaults)),
+        Err(err) => failure::bail!(
+            "The organization's default `{}` in {} is invalid: {}",
+            CONFIG_FILE_NAME,
+            defaults_repo,
+            err
+        ),
+    }
+}
+
+/// Merges a repository's `config` over its organization's `defaults`.
+///
+/// Tables (like `[relabel]`, or its `aliases`) are merged key by key, so a repository only needs
+/// to give the settings it changes. Everything else, arrays included, replaces the default
+/// outright: a repository's `allow-unauthenticated = ["A-*"]` is the whole list, not an addition
+/// to the organization's.
+fn inherit(config: &mut toml::Value, defaults: toml::Value) {
+    let overrides = std::mem::replace(config, defaults);
+    merge(config, overrides);
+}
+
+fn merge(base: &mut toml::Value, overrides: toml::Value) {
+    match (base, overrides) {
+        (toml::Value::Table(base), toml::Value::Table(overrides)) => {
+            for (key, value) in overrides {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overrides) => *base = overrides,
+    }
+}
+
+#[cfg(test)]
+fn parse(config: &str) -> Config {
+    toml::from_str(config).unwrap()
+}
+
+#[cfg(test)]
+fn parse_inherited(defaults: &str, config: &str) -> Config {
+    let mut config = toml::from_str(config).unwrap();
+    inherit(&mut config, toml::from_str(defaults).unwrap());
+    config.try_into().unwrap()
+}
+
+#[test]
+fn inherit_relabel() {
+    let config = parse_inherited(
+        r#"
+        [relabel]
+        allow-unauthenticated = ["C-*", "E-*"]
+        restricted = ["I-nominated"]
+
+        [relabel.aliases]
+        bug = "C-bug"
+
+        [hello]
+        "#,
+        r#"
+        [relabel]
+        allow-unauthenticated = ["A-*"]
+
+        [relabel.aliases]
+        ice = "I-ICE"
+        "#,
+    );
+    assert_eq!(config.validate(), Ok(()));
+    let relabel = config.relabel.as_ref().unwrap();
+    assert_eq!(relabel.allow_unauthenticated, ["A-*"]);
+    assert_eq!(relabel.restricted, ["I-nominated"]);
+    assert_eq!(relabel.aliases["bug"], "C-bug");
+    assert_eq!(relabel.aliases["ice"], "I-ICE");
+    assert!(config.hello.is_some());
+    assert!(config.assign.is_none());
+}
+
+#[test]
+fn inherit_then_validate() {
+    let config = parse_inherited(
+        r#"
+        [relabel.aliases]
+        a = "b"
+        "#,
+        r#"
+        [relabel.aliases]
+        b = "a"
+        "#,
+    );
+    assert!(config.validate().is_err());
+}
+
+#[test]
+fn bots_ignored() {
+    assert!(!parse("").ignores_bot("bors"));
+    let config = parse(
+        r#"
+        ignore_bots = true
+        allowed_bots = ["bors", "rust-timer[bot]"]
+        "#,
+    );
+    assert!(config.ignores_bot("dependabot[bot]"));
+    assert!(!config.ignores_bot("bors"));
+    assert!(!config.ignores_bot("bors[bot]"));
+    assert!(!config.ignores_bot("rust-timer[bot]"));
+}
+
+#[test]
+fn validate_ok() {
+    let config = parse(
+        r#"
+        [assign]
+        review-rotation = ["alice", "bob-2"]
+
+        [ping]
+        infra = ["alice"]
+        release = { team = "release" }
+
+        [relabel.aliases]
+        bug = "C-bug"
+
+        [triage]
+        priorities = ["P-low", "P-high"]
+        "#,
+    );
+    assert_eq!(config.validate(), Ok(()));
+    match &config.ping.as_ref().unwrap().groups["release"] {
+        PingGroup::Team { team } => assert_eq!(team, "release"),
+        PingGroup::Members(_) => panic!("expected a team"),
+    }
+}
+
+#[test]
+fn validate_reports_every_error() {
+    let config = parse(
+        r#"
+        [assign]
+        review-rotation = ["alice", "-bob", "carol@example.com"]
+
+        [relabel.aliases]
+        a = "b"
+        b = "a"
+
+        [triage]
+        priorities = ["P-low", "P-high", "P-low"]
+        "#,
+    );
+    let mut errors = config
+        .validate()
+        .unwrap_err()
+        .iter()
+        .map(|error| error.to_string())
+        .collect::<Vec<_>>();
+    errors.sort();
+    assert_eq!(
+        errors,
+        [
+            "`[assign]`: `-bob` is not a valid GitHub username",
+            "`[assign]`: `carol@example.com` is not a valid GitHub username",
+            "`[relabel]`: alias `a` is cyclic: a -> b -> a",
+            "`[relabel]`: alias `b` is cyclic: b -> a -> b",
+            "`[triage]`: priority `P-low` is listed twice",
+        ]
+    );
+}
+
+#[test]
+fn disable_unknown_section() {
+    let mut config = parse("[close]");
+    config.disable_invalid(vec![ConfigError {
+        section: "no_such_section",
+        message: "oops".to_owned(),
+    }]);
+    assert!(config.close.is_some());
+    assert_eq!(config.errors("no_such_section").len(), 1);
+}
+
+#[test]
+fn inactivity_days_checked() {
+    let config = parse(
+        r#"
+        [assign]
+        status-report-after-days = 0
+        release-after-days = 100000000000000
+        "#,
+    );
+    let errors = config
+        .validate()
+        .unwrap_err()
+        .iter()
+        .map(|error| error.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        errors,
+        [
+            "`[assign]`: `status-report-after-days` must be between 1 and 365 days, not 0",
+            "`[assign]`: `release-after-days` must be between 1 and 365 days, not 100000000000000",
+        ]
+    );
+    assert!(parse("[assign]").validate().is_ok());
+}
+
+#[test]
+fn subscribe_events() {
+    let config = parse("[subscribe]");
+    assert_eq!(
+        config.subscribe.unwrap().events,
+        [SubscribeEvent::State, SubscribeEvent::Labels]
+    );
+    let config = parse(
+        r#"
+        [subscribe]
+        events = ["labels"]
+        "#,
+    );
+    assert_eq!(config.subscribe.unwrap().events, [SubscribeEvent::Labels]);
+}
+
+#[test]
+fn checkbox_labels_config() {
+    let config = parse(
+        r#"
+        [checkbox_labels]
+        "This is a regression" = ["regression-untriaged"]
+        "#,
+    );
+    assert_eq!(
+        config.checkbox_labels.as_ref().unwrap().boxes["This is a regression"],
+        ["regression-untriaged"]
+    );
+    assert!(config.validate().is_ok());
+    let config = parse(
+        r#"
+        [checkbox_labels]
+        "This is a regression" = []
+        " " = ["C-bug"]
+        "#,
+    );
+    assert_eq!(config.validate().unwrap_err().len(), 2);
+}
+
+#[test]
+fn permission_denied_responses() {
+    let config = parse("[permission_denied]");
+    let permission_denied = config.permission_denied.as_ref().unwrap();
+    assert!(permission_denied.comment && !permission_denied.reaction);
+    assert!(config.validate().is_ok());
+    let config = parse(
+        r#"
+        [permission_denied]
+        comment = false
+        "#,
+    );
+    assert!(config.validate().is_err());
+}
+
+#[test]
+fn claimable_labels() {
+    let config = parse(
+        r#"
+        [assign]
+        claimable-only-with = ["E-mentor", "help wanted"]
+        "#,
+    );
+    assert_eq!(
+        config.assign.as_ref().unwrap().claimable_only_with,
+        Some(vec!["E-mentor".to_owned(), "help wanted".to_owned()])
+    );
+    assert!(config.validate().is_ok());
+    let config = parse(
+        r#"
+        [assign]
+        claimable-only-with = []
+        "#,
+    );
+    assert!(config.validate().is_err());
+}
+
+#[test]
+fn activity_sources() {
+    let config = parse("[assign]");
+    assert_eq!(
+        config.assign.unwrap().activity_sources,
+        [ActivitySource::Comments]
+    );
+    let config = parse(
+        r#"
+        [assign]
+        activity-sources = ["comments", "pushes", "reviews"]
+        "#,
+    );
+    assert_eq!(
+        config.assign.as_ref().unwrap().activity_sources,
+        [
+            ActivitySource::Comments,
+            ActivitySource::Pushes,
+            ActivitySource::Reviews
+        ]
+    );
+    assert!(config.validate().is_ok());
+    let config = parse(
+        r#"
+        [assign]
+        activity-sources = []
+        "#,
+    );
+    assert_eq!(
+        config.validate(),
+        Err(vec![ConfigError {
+            section: "assign",
+            message: "`activity-sources` is empty, so every assignee would seem inactive"
+                .to_owned(),
+        }])
+    );
+}
+
+#[test]
+fn validate_alias_targets() {
+    let config = parse(
+        r#"
+        [relabel.aliases]
+        bug = "C-bug"
+        docs = "T-doc"
+        "#,
+    );
+    let labels = vec![Label {
+        name: "C-bug".to_owned(),
+    }];
+    assert_eq!(
+        config.validate_labels(&labels),
+        Err(vec![ConfigError {
+            section: "relabel",
+            message: "alias `docs` refers to `T-doc`, which is not a label in this repository"
+                .to_owned(),
+        }])
+    );
+}
+
+#[test]
+fn invalid_sections_disabled() {
+    let mut config = parse(
+        r#"
+        [close]
+
+        [triage]
+        priorities = []
+        "#,
+    );
+    let errors = config.validate().unwrap_err();
+    config.disable_invalid(errors);
+    assert!(config.close.is_some());
+    assert!(config.triage.is_none());
+    assert_eq!(config.errors("triage").len(), 1);
+    assert!(config.errors("close").is_empty());
+    assert_eq!(config.invalid_sections(), ["triage"]);
+}
+
+#[test]
+fn label_effects() {
+    let config = parse(
+        r#"
+        [label_effects."E-needs-mcve"]
+        comment = "@{user} asked for a minimal reproduction."
+        add-labels = ["S-waiting-on-author"]
+
+        [label_effects."I-nominated"]
+        assign = "-alice"
+
+        [label_effects."C-bug"]
+        "#,
+    );
+    let effects = &config.label_effects.as_ref().unwrap().labels["E-needs-mcve"];
+    assert_eq!(effects.add_labels, ["S-waiting-on-author"]);
+    assert!(effects.assign.is_none());
+    let mut errors = config
+        .validate()
+        .unwrap_err()
+        .iter()
+        .map(|error| error.to_string())
+        .collect::<Vec<_>>();
+    errors.sort();
+    assert_eq!(
+        errors,
+        [
+            "`[label_effects]`: `-alice` is not a valid GitHub username",
+            "`[label_effects]`: label `C-bug` has no effects",
+        ]
+    );
+}
+
+#[test]
+fn trusted_repos() {
+    let repo = "rust-lang/rust".parse().unwrap();
+    let check = |config: &Config, target: &str| {
+        config
+            .check_trusted(&repo, &target.parse().unwrap())
+            .map_err(|err| err.to_string())
+    };
+    let config = parse("");
+    assert!(check(&config, "Rust-Lang/rust").is_ok());
+    assert_eq!(
+        check(&config, "rust-lang/cargo"),
+        Err(
+            "`rust-lang/cargo` is not a trusted repository; commands here can only act on \
+             `rust-lang/rust`"
+                .to_owned()
+        )
+    );
+
+    let config = parse(r#"trusted_repos = ["rust-lang/cargo", "rust-lang/rls"]"#);
+    assert_eq!(config.validate(), Ok(()));
+    assert!(check(&config, "rust-lang/Cargo").is_ok());
+    assert_eq!(
+        check(&config, "evil/repo"),
+        Err(
+            "`evil/repo` is not a trusted repository; commands here can only act on \
+             `rust-lang/rust`, `rust-lang/cargo`, `rust-lang/rls`"
+                .to_owned()
+        )
+    );
+
+    let config = parse(r#"trusted_repos = ["rust-lang"]"#);
+    assert_eq!(
+        config.validate(),
+        Err(vec![ConfigError {
+            section: "trusted_repos",
+            message: "`rust-lang` is not a repository name, like `owner/name`".to_owned(),
+        }])
+    );
+}
+
+#[test]
+fn priority_named_none() {
+    let config = parse(
+        r#"
+        [triage]
+        priorities = ["P-low", "None"]
+        "#,
+    );
+    assert_eq!(
+        config.validate(),
+        Err(vec![ConfigError {
+            section: "triage",
+            message: "`None` cannot be a priority, as `prioritize none` clears the priority"
+                .to_owned(),
+        }])
+    );
 }
@@ -0,0 +1,237 @@
+//! Persistent state for handlers.
+//!
+//! Each handler stores its state for an issue under its own namespace, so the key for a piece of
+//! state is `(repository, issue number, namespace)`. Values are stored as JSON.
+//!
+//! In production the state lives in a Postgres table (set `DATABASE_URL`); without a database
+//! we fall back to keeping the state in memory, which is only suitable for local development as
+//! everything is lost on restart.
+
+use failure::{Error, ResultExt};
+use postgres::{Connection, TlsMode};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct StateKey {
+    pub repo: String,
+    pub issue: u64,
+    pub namespace: &'static str,
+}
+
+impl StateKey {
+    pub fn new(repo: &str, issue: u64, namespace: &'static str) -> StateKey {
+        StateKey {
+            repo: repo.to_owned(),
+            issue,
+            namespace,
+        }
+    }
+
+    /// A key for state about the repository as a whole, rather than any of its issues.
+    pub fn repo(repo: &str, namespace: &'static str) -> StateKey {
+        // GitHub numbers issues from 1, so this can't collide with an issue's state.
+        StateKey::new(repo, 0, namespace)
+    }
+}
+
+trait Backend: Send + Sync {
+    fn load(&self, key: &StateKey) -> Result<Option<Value>, Error>;
+    fn store(&self, key: &StateKey, value: Value) -> Result<(), Error>;
+    /// Loads the state of every issue in `repo` under `namespace`, ordered by issue number.
+    fn load_all(&self, repo: &str, namespace: &'static str) -> Result<Vec<(u64, Value)>, Error>;
+    /// Checks that the backend is reachable.
+    fn check(&self) -> Result<(), Error>;
+}
+
+#[derive(Clone)]
+pub struct StateStore {
+    backend: Arc<dyn Backend>,
+}
+
+impl StateStore {
+    /// Connects to the Postgres database at `url`, creating the state table if necessary.
+    pub fn postgres(url: &str) -> Result<StateStore, Error> {
+        let conn =
+            Connection::connect(url, TlsMode::None).context("failed to connect to the database")?;
+        conn.batch_execute(
+            "CREATE TABLE IF NOT EXISTS handler_state (
+                repo TEXT NOT NULL,
+                issue_number BIGINT NOT NULL,
+                namespace TEXT NOT NULL,
+                data JSONB NOT NULL,
+                PRIMARY KEY (repo, issue_number, namespace)
+            );",
+        )
+        .context("failed to create the handler_state table")?;
+        Ok(StateStore {
+            backend: Arc::new(Postgres(Mutex::new(conn))),
+        })
+    }
+
+    pub fn in_memory() -> StateStore {
+        StateStore {
+            backend: Arc::new(Memory(Mutex::new(HashMap::new()))),
+        }
+    }
+
+    pub fn get<T: DeserializeOwned>(&self, key: &StateKey) -> Result<Option<T>, Error> {
+        match self.backend.load(key)? {
+            Some(value) => {
+                Ok(Some(serde_json::from_value(value).with_context(|_| {
+                    format!("failed to deserialize state for {:?}", key)
+                })?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub fn set<T: Serialize>(&self, key: &StateKey, value: &T) -> Result<(), Error> {
+        self.backend.store(key, serde_json::to_value(value)?)
+    }
+
+    /// The state of every issue in `repo` which has some under `namespace`, by issue number.
+    ///
+    /// State about the repository as a whole (see `StateKey::repo`) isn't included.
+    pub fn get_all<T: DeserializeOwned>(
+        &self,
+        repo: &str,
+        namespace: &'static str,
+    ) -> Result<Vec<(u64, T)>, Error> {
+        self.backend
+            .load_all(repo, namespace)?
+            .into_iter()
+            .filter(|(issue, _)| *issue != 0)
+            .map(|(issue, value)| {
+                let value = serde_json::from_value(value).with_context(|_| {
+                    format!("failed to deserialize state for {}#{}", repo, issue)
+                })?;
+                Ok((issue, value))
+            })
+            .collect()
+    }
+
+    /// Checks that the store is reachable (e.g., that the database connection still works).
+    pub fn check(&self) -> Result<(), Error> {
+        self.backend.check()
+    }
+}
+
+struct Postgres(Mutex<Connection>);
+
+impl Backend for Postgres {
+    fn load(&self, key: &StateKey) -> Result<Option<Value>, Error> {
+        let conn = self.0.lock().unwrap();
+        let rows = conn
+            .query(
+                "SELECT data FROM handler_state \
+                 WHERE repo = $1 AND issue_number = $2 AND namespace = $3",
+                &[&key.repo, &(key.issue as i64), &key.namespace],
+            )
+            .with_context(|_| format!("failed to load state for {:?}", key))?;
+        Ok(rows.iter().next().map(|row| row.get(0)))
+    }
+
+    fn store(&self, key: &StateKey, value: Value) -> Result<(), Error> {
+        let conn = self.0.lock().unwrap();
+        conn.execute(
+            "INSERT INTO handler_state (repo, issue_number, namespace, data) \
+             VALUES ($1, $2, $3, $4) \
+             ON CONFLICT (repo, issue_number, namespace) DO UPDATE SET data = EXCLUDED.data",
+            &[&key.repo, &(key.issue as i64), &key.namespace, &value],
+        )
+        .with_context(|_| format!("failed to store state for {:?}", key))?;
+        Ok(())
+    }
+
+    fn load_all(&self, repo: &str, namespace: &'static str) -> Result<Vec<(u64, Value)>, Error> {
+        let conn = self.0.lock().unwrap();
+        let rows = conn
+            .query(
+                "SELECT issue_number, data FROM handler_state \
+                 WHERE repo = $1 AND namespace = $2 ORDER BY issue_number",
+                &[&repo, &namespace],
+            )
+            .with_context(|_| format!("failed to load {} state for {}", namespace, repo))?;
+        Ok(rows
+            .iter()
+            .map(|row| (row.get::<_, i64>(0) as u64, row.get(1)))
+            .collect())
+    }
+
+    fn check(&self) -> Result<(), Error> {
+        let conn = self.0.lock().unwrap();
+        conn.batch_execute("SELECT 1")
+            .context("failed to reach the database")?;
+        Ok(())
+    }
+}
+
+struct Memory(Mutex<HashMap<StateKey, Value>>);
+
+impl Backend for Memory {
+    fn load(&self, key: &StateKey) -> Result<Option<Value>, Error> {
+        Ok(self.0.lock().unwrap().get(key).cloned())
+    }
+
+    fn store(&self, key: &StateKey, value: Value) -> Result<(), Error> {
+        self.0.lock().unwrap().insert(key.clone(), value);
+        Ok(())
+    }
+
+    fn load_all(&self, repo: &str, namespace: &'static str) -> Result<Vec<(u64, Value)>, Error> {
+        let mut all = self
+            .0
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(key, _)| key.repo == repo && key.namespace == namespace)
+            .map(|(key, value)| (key.issue, value.clone()))
+            .collect::<Vec<_>>();
+        all.sort_by_key(|(issue, _)| *issue);
+        Ok(all)
+    }
+
+    fn check(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+#[test]
+fn in_memory_roundtrip() {
+    let store = StateStore::in_memory();
+    let key = StateKey::new("rust-lang/rust", 1, "TEST");
+    assert_eq!(store.get::<String>(&key).unwrap(), None);
+    store.set(&key, &"hello").unwrap();
+    assert_eq!(store.get::<String>(&key).unwrap(), Some("hello".to_owned()));
+    store.set(&key, &"world").unwrap();
+    assert_eq!(store.get::<String>(&key).unwrap(), Some("world".to_owned()));
+    let other = StateKey::new("rust-lang/rust", 1, "OTHER");
+    assert_eq!(store.get::<String>(&other).unwrap(), None);
+}
+
+#[test]
+fn in_memory_get_all() {
+    let store = StateStore::in_memory();
+    store
+        .set(&StateKey::new("rust-lang/rust", 2, "TEST"), &"two")
+        .unwrap();
+    store
+        .set(&StateKey::new("rust-lang/rust", 1, "TEST"), &"one")
+        .unwrap();
+    store
+        .set(&StateKey::repo("rust-lang/rust", "TEST"), &"repo")
+        .unwrap();
+    store
+        .set(&StateKey::new("rust-lang/rust", 3, "OTHER"), &"other")
+        .unwrap();
+    store
+        .set(&StateKey::new("rust-lang/cargo", 4, "TEST"), &"cargo")
+        .unwrap();
+    assert_eq!(
+        store.get_all::<String>("rust-lang/rust", "TEST").unwrap(),
+        [(1, "one".to_owned()), (2, "two".to_owned())]
+    );
+}
@@ -0,0 +1,181 @@
+//! Deduplication of webhook deliveries.
+//!
+//! GitHub redelivers a webhook if we don't respond in time, even though we may well be (or have
+//! finished) handling it, which would run its commands twice. We remember the ids of recent
+//! deliveries so that we handle each only once.
+//!
+//! Ids are forgotten after a while (or once there are too many), as redeliveries happen soon
+//! after the original delivery.
+//!
+//! A signed payload can still be replayed, by GitHub redelivering it on request or by anyone who
+//! captured it. Events older than a maximum age (going by the timestamps in the payload) are
+//! dropped, and ids are remembered for at least that long, so a replay is either deduplicated or
+//! dropped.
+
+use chrono::{DateTime, Utc};
+use failure::Error;
+#[cfg(test)]
+use std::cell::Cell;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long ids are remembered, unless configured otherwise.
+pub const DEFAULT_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// How old an event can be before it's dropped, unless configured otherwise.
+pub const DEFAULT_MAX_AGE: Duration = DEFAULT_TTL;
+
+/// The most ids we remember at once.
+const MAX_ENTRIES: usize = 10_000;
+
+pub struct DeliveryLog {
+    ttl: Duration,
+    max_age: Option<Duration>,
+    seen: Mutex<Seen>,
+}
+
+#[derive(Default)]
+struct Seen {
+    ids: HashMap<String, Instant>,
+    /// The ids in `ids`, oldest first.
+    order: VecDeque<(Instant, String)>,
+}
+
+impl Seen {
+    fn expire(&mut self, now: Instant, ttl: Duration) {
+        while let Some((at, _)) = self.order.front() {
+            if now.duration_since(*at) < ttl && self.order.len() <= MAX_ENTRIES {
+                break;
+            }
+            let (at, id) = self.order.pop_front().unwrap();
+            // The id may have been removed and then seen again since.
+            if self.ids.get(&id) == Some(&at) {
+                self.ids.remove(&id);
+            }
+        }
+    }
+}
+
+impl DeliveryLog {
+    pub fn new(ttl: Duration) -> DeliveryLog {
+        DeliveryLog {
+            ttl,
+            max_age: None,
+            seen: Mutex::new(Seen::default()),
+        }
+    }
+
+    /// Drops events which happened more than `max_age` ago; ids are remembered for at least as
+    /// long, so that replays within `max_age` are still deduplicated.
+    pub fn with_max_age(self, max_age: Duration) -> DeliveryLog {
+        DeliveryLog {
+            ttl: self.ttl.max(max_age),
+            max_age: Some(max_age),
+            ..self
+        }
+    }
+
+    /// Whether an event which happened at `occurred_at` is too old to be handled.
+    ///
+    /// Events from the future (as far as our clock is concerned) aren't.
+    pub fn too_old(&self, occurred_at: DateTime<Utc>) -> bool {
+        self.too_old_at(occurred_at, Utc::now())
+    }
+
+    fn too_old_at(&self, occurred_at: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+        let max_age = match self.max_age {
+            Some(max_age) => max_age,
+            None => return false,
+        };
+        match now.signed_duration_since(occurred_at).to_std() {
+            Ok(age) => age > max_age,
+            Err(_) => false,
+        }
+    }
+
+    /// Runs `handle` for the delivery `id`, unless it has already been run for it.
+    ///
+    /// If `handle` fails, the id is forgotten again, so that a redelivery is retried.
+    pub fn run_once<F>(&self, id: &str, handle: F) -> Result<(), Error>
+    where
+        F: FnOnce() -> Result<(), Error>,
+    {
+        let now = Instant::now();
+        {
+            let mut seen = self.seen.lock().unwrap();
+            seen.expire(now, self.ttl);
+            if seen.ids.contains_key(id) {
+                log::info!("skipping delivery {}, which was already handled", id);
+                return Ok(());
+            }
+            seen.ids.insert(id.to_owned(), now);
+            seen.order.push_back((now, id.to_owned()));
+        }
+        let result = handle();
+        if result.is_err() {
+            self.seen.lock().unwrap().ids.remove(id);
+        }
+        result
+    }
+}
+
+#[test]
+fn same_delivery_runs_once() {
+    let log = DeliveryLog::new(DEFAULT_TTL);
+    let runs = Cell::new(0);
+    let handle = || {
+        runs.set(runs.get() + 1);
+        Ok(())
+    };
+    log.run_once("a", handle).unwrap();
+    log.run_once("a", handle).unwrap();
+    log.run_once("b", handle).unwrap();
+    assert_eq!(runs.get(), 2);
+}
+
+#[test]
+fn failed_delivery_is_retried() {
+    let log = DeliveryLog::new(DEFAULT_TTL);
+    let runs = Cell::new(0);
+    assert!(log
+        .run_once("a", || {
+            runs.set(runs.get() + 1);
+            failure::bail!("failed")
+        })
+        .is_err());
+    log.run_once("a", || {
+        runs.set(runs.get() + 1);
+        Ok(())
+    })
+    .unwrap();
+    assert_eq!(runs.get(), 2);
+}
+
+#[test]
+fn expired_delivery_runs_again() {
+    let log = DeliveryLog::new(Duration::from_secs(0));
+    let runs = Cell::new(0);
+    let handle = || {
+        runs.set(runs.get() + 1);
+        Ok(())
+    };
+    log.run_once("a", handle).unwrap();
+    log.run_once("a", handle).unwrap();
+    assert_eq!(runs.get(), 2);
+    assert_eq!(log.seen.lock().unwrap().order.len(), 1);
+}
+
+#[test]
+fn old_events_are_too_old() {
+    let now: DateTime<Utc> = "2019-05-01T12:00:00Z".parse().unwrap();
+    let log = DeliveryLog::new(Duration::from_secs(60)).with_max_age(Duration::from_secs(600));
+    assert_eq!(log.ttl, Duration::from_secs(600));
+    assert!(!log.too_old_at("2019-05-01T11:55:00Z".parse().unwrap(), now));
+    assert!(!log.too_old_at("2019-05-01T12:05:00Z".parse().unwrap(), now));
+    assert!(log.too_old_at("2019-05-01T11:45:00Z".parse().unwrap(), now));
+
+    // Without a maximum age, nothing is.
+    let log = DeliveryLog::new(Duration::from_secs(60));
+    assert!(!log.too_old_at("2000-01-01T00:00:00Z".parse().unwrap(), now));
+}
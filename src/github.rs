@@ -1,36 +1,104 @@
+use chrono::{DateTime, Utc};
 use failure::{Error, ResultExt};
-use reqwest::header::{AUTHORIZATION, USER_AGENT};
+use parser::command::lock::LockReason;
+use parser::command::relabel::LabelDelta;
+use rand::Rng;
+use reqwest::header::{ACCEPT, AUTHORIZATION, LINK, RETRY_AFTER, USER_AGENT};
 use reqwest::{Client, Error as HttpError, RequestBuilder, Response, StatusCode};
+use std::collections::HashMap;
 use std::fmt;
 use std::io::Read;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-#[derive(Debug, serde::Deserialize)]
+pub mod graphql;
+
+const DEFAULT_MAX_RETRIES: u32 = 3;
+/// The most times requests may be retried (see `GithubClient::with_max_retries`).
+pub const MAX_RETRIES: u32 = 10;
+const DEFAULT_RATE_LIMIT_THRESHOLD: u64 = 10;
+const RATE_LIMIT_MARGIN: Duration = Duration::from_secs(1);
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+/// The backoff between retries stops doubling after this many attempts, at about four minutes.
+const MAX_BACKOFF_EXPONENT: u32 = 9;
+const DEFAULT_TEAM_CACHE_TTL: Duration = Duration::from_secs(10 * 60);
+/// Labels change rarely, but when they do the change should be picked up quickly.
+const LABEL_CACHE_TTL: Duration = Duration::from_secs(60);
+/// Ids never change, but a user may be renamed, freeing up their old login.
+const USER_CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Clone, serde::Deserialize)]
 pub struct User {
     pub login: String,
+    /// `User`, `Bot` or `Organization`.
+    #[serde(rename = "type", default)]
+    pub kind: Option<String>,
 }
 
 impl User {
+    /// Whether this is a bot account (such as a GitHub App's).
+    pub fn is_bot(&self) -> bool {
+        self.kind.as_ref().map_or(false, |kind| kind == "Bot")
+    }
+
     pub fn current(client: &GithubClient) -> Result<Self, Error> {
         Ok(client
-            .get("https://api.github.com/user")
-            .send_req()?
+            .send_req(client.get("https://api.github.com/user"))?
             .json()?)
     }
 
+    /// Whether this user is a member of any Rust team.
+    ///
+    /// Answers are cached by the client for a while (see `GithubClient::with_team_cache_ttl`).
     pub fn is_team_member(&self, client: &GithubClient) -> Result<bool, Error> {
-        let client = client.raw();
-        let url = format!("{}/teams.json", rust_team_data::v1::BASE_URL);
-        let permission: rust_team_data::v1::Teams = client
-            .get(&url)
-            .send()
-            .and_then(Response::error_for_status)
-            .and_then(|mut r| r.json())
-            .context("could not get team data")?;
-        let map = permission.teams;
-        Ok(map["all"].members.iter().any(|g| g.github == self.login))
+        if let Some(is_member) = client.team_cache.get(&self.login) {
+            return Ok(is_member);
+        }
+        let is_member = client.get_team("all")?.contains(&self.login);
+        client.team_cache.insert(&self.login, is_member);
+        Ok(is_member)
+    }
+
+    /// The node id of this user, which is how GraphQL refers to them.
+    pub fn node_id(&self, client: &GithubClient) -> Result<String, Error> {
+        Ok(client.get_user(&self.login)?.node_id.clone())
+    }
+}
+
+/// The ids of a user, as looked up by `GithubClient::get_user`.
+#[derive(Debug, serde::Deserialize)]
+pub struct UserInfo {
+    pub id: u64,
+    pub node_id: String,
+    pub login: String,
+}
+
+/// A login which no GitHub user has (any more).
+#[derive(Debug)]
+pub struct UnknownUser(pub String);
+
+impl fmt::Display for UnknownUser {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "there is no GitHub user called `@{}`", self.0)
+    }
+}
+
+impl std::error::Error for UnknownUser {}
+
+/// A team which the team data doesn't know about.
+#[derive(Debug)]
+pub struct UnknownTeam(pub String);
+
+impl fmt::Display for UnknownTeam {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "there is no Rust team called `{}`", self.0)
     }
 }
 
+impl std::error::Error for UnknownTeam {}
+
 #[derive(Debug, Clone, serde::Deserialize)]
 pub struct Label {
     pub name: String,
@@ -39,10 +107,7 @@ pub struct Label {
 impl Label {
     fn exists(&self, repo_api_prefix: &str, client: &GithubClient) -> bool {
         #[allow(clippy::redundant_pattern_matching)]
-        match client
-            .get(&format!("{}/labels/{}", repo_api_prefix, self.name))
-            .send_req()
-        {
+        match client.send_req(client.get(&format!("{}/labels/{}", repo_api_prefix, self.name))) {
             Ok(_) => true,
             // XXX: Error handling if the request failed for reasons beyond 'label didn't exist'
             Err(_) => false,
@@ -50,30 +115,154 @@ impl Label {
     }
 }
 
-#[derive(Debug, serde::Deserialize)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IssueState {
+    Open,
+    Closed,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
 pub struct Issue {
     pub number: u64,
     pub body: String,
+    pub state: IssueState,
     title: String,
-    user: User,
+    pub user: User,
     labels: Vec<Label>,
     assignees: Vec<User>,
+    #[serde(default)]
+    milestone: Option<Milestone>,
     // API URL
     repository_url: String,
     comments_url: String,
+    /// Only present if the issue is a pull request.
+    #[serde(default)]
+    pull_request: Option<PullRequestMarker>,
+    #[serde(default)]
+    pub updated_at: Option<DateTime<Utc>>,
+    /// Whether the conversation is locked, so that only collaborators can comment.
+    #[serde(default)]
+    pub locked: bool,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+struct PullRequestMarker {}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct Milestone {
+    pub number: u64,
+    pub title: String,
+    pub state: IssueState,
+}
+
+/// A commit in a pull request.
+#[derive(Debug, serde::Deserialize)]
+pub struct PullRequestCommit {
+    pub sha: String,
+    pub parents: Vec<CommitParent>,
+    /// The GitHub user who wrote the commit, unless its email address isn't linked to one.
+    #[serde(default)]
+    pub author: Option<User>,
+    pub commit: CommitDetails,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct CommitDetails {
+    pub committer: CommitSignature,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct CommitSignature {
+    pub date: DateTime<Utc>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct CommitParent {
+    pub sha: String,
+}
+
+/// A file changed by a pull request.
+#[derive(Debug, serde::Deserialize)]
+pub struct PullRequestFile {
+    pub filename: String,
+    /// The file's path before the pull request, if it renames the file.
+    #[serde(default)]
+    pub previous_filename: Option<String>,
+}
+
+impl PullRequestFile {
+    /// Whether the file's path, or its path before being renamed, matches `pattern`.
+    pub fn matches(&self, pattern: &glob::Pattern) -> bool {
+        pattern.matches(&self.filename)
+            || self
+                .previous_filename
+                .as_ref()
+                .map_or(false, |previous| pattern.matches(previous))
+    }
 }
 
 #[derive(Debug, serde::Deserialize)]
 pub struct Comment {
+    pub id: usize,
     pub body: String,
+    /// The API URL of the comment.
+    pub url: String,
     pub html_url: String,
     pub user: User,
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// A reaction to a comment.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, serde::Serialize)]
+pub enum Reaction {
+    #[serde(rename = "eyes")]
+    Eyes,
+    #[serde(rename = "+1")]
+    PlusOne,
+    #[serde(rename = "-1")]
+    MinusOne,
+}
+
+impl Comment {
+    /// Reacts to the comment with `reaction`. Each user can only react to a comment once with
+    /// each reaction, so doing this again does nothing.
+    pub fn add_reaction(&self, client: &GithubClient, reaction: Reaction) -> Result<(), Error> {
+        if client.dry_run(format_args!(
+            "react to comment {} with {:?}",
+            self.id, reaction
+        )) {
+            return Ok(());
+        }
+        // POST /repos/:owner/:repo/issues/comments/:id/reactions, or .../pulls/comments/... for
+        // review comments.
+        let url = format!("{}/reactions", self.url);
+
+        #[derive(serde::Serialize)]
+        struct ReactionReq {
+            content: Reaction,
+        }
+        client
+            .send_req(
+                client
+                    .post(&url)
+                    // Reactions are still in preview.
+                    .header(ACCEPT, "application/vnd.github.squirrel-girl-preview+json")
+                    .json(&ReactionReq { content: reaction }),
+            )
+            .context("failed to add reaction")?;
+        Ok(())
+    }
 }
 
 #[derive(Debug)]
 pub enum AssignmentError {
     InvalidAssignee,
     Http(HttpError),
+    /// GitHub kept failing transiently, and we gave up retrying.
+    RetriesExhausted(HttpError),
 }
 
 impl fmt::Display for AssignmentError {
@@ -81,6 +270,9 @@ impl fmt::Display for AssignmentError {
         match self {
             AssignmentError::InvalidAssignee => write!(f, "invalid assignee"),
             AssignmentError::Http(e) => write!(f, "cannot assign: {}", e),
+            AssignmentError::RetriesExhausted(e) => {
+                write!(f, "cannot assign, GitHub is not responding: {}", e)
+            }
         }
     }
 }
@@ -93,63 +285,248 @@ impl From<HttpError> for AssignmentError {
     }
 }
 
+impl From<RetryError> for AssignmentError {
+    fn from(e: RetryError) -> AssignmentError {
+        match e {
+            RetryError::Permanent(e) => AssignmentError::Http(e),
+            RetryError::Exhausted(e) => AssignmentError::RetriesExhausted(e),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum RetryError {
+    /// The request failed in a way that retrying would not fix (e.g., a 404 or 422).
+    Permanent(HttpError),
+    /// The request failed transiently on every attempt.
+    Exhausted(HttpError),
+}
+
+impl fmt::Display for RetryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RetryError::Permanent(e) => write!(f, "{}", e),
+            RetryError::Exhausted(e) => write!(f, "gave up after retrying: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for RetryError {}
+
 impl Issue {
     pub fn get_comment(&self, client: &GithubClient, id: usize) -> Result<Comment, Error> {
         let comment_url = format!("{}/issues/comments/{}", self.repository_url, id);
         let comment = client
-            .get(&comment_url)
-            .send_req()
+            .send_req(client.get(&comment_url))
             .context("failed to get comment")?
             .json()?;
         Ok(comment)
     }
 
+    /// The comments on this issue created or updated after `since`.
+    pub fn comments_since(
+        &self,
+        client: &GithubClient,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<Comment>, Error> {
+        let url = format!(
+            "{}?since={}",
+            self.comments_url,
+            since.format("%Y-%m-%dT%H:%M:%SZ")
+        );
+        Ok(client
+            .get_all_pages(&url)
+            .context("failed to get comments")?)
+    }
+
+    /// The pull requests in this issue's repository which refer to it, by number.
+    pub fn linked_pull_requests(&self, client: &GithubClient) -> Result<Vec<u64>, Error> {
+        let url = format!(
+            "{}/issues/{}/timeline?per_page=100",
+            self.repository_url, self.number
+        );
+        #[derive(serde::Deserialize)]
+        struct TimelineEvent {
+            event: String,
+            #[serde(default)]
+            source: Option<Source>,
+        }
+        #[derive(serde::Deserialize)]
+        struct Source {
+            #[serde(default)]
+            issue: Option<SourceIssue>,
+        }
+        #[derive(serde::Deserialize)]
+        struct SourceIssue {
+            number: u64,
+            repository_url: String,
+            #[serde(default)]
+            pull_request: Option<PullRequestMarker>,
+        }
+        let events: Vec<TimelineEvent> = client
+            .get_all_pages_with(&url, |req| {
+                // The timeline API is still in preview.
+                req.header(ACCEPT, "application/vnd.github.mockingbird-preview+json")
+            })
+            .context("failed to get the timeline")?;
+        let mut numbers = events
+            .into_iter()
+            .filter(|event| event.event == "cross-referenced")
+            .filter_map(|event| event.source.and_then(|source| source.issue))
+            .filter(|issue| {
+                issue.pull_request.is_some() && issue.repository_url == self.repository_url
+            })
+            .map(|issue| issue.number)
+            .collect::<Vec<_>>();
+        numbers.sort();
+        numbers.dedup();
+        Ok(numbers)
+    }
+
+    /// Fetches the current body of this issue (which may have changed since the event was sent).
+    pub fn fetch_body(&self, client: &GithubClient) -> Result<String, Error> {
+        let url = format!("{}/issues/{}", self.repository_url, self.number);
+        #[derive(serde::Deserialize)]
+        struct IssueBody {
+            // The body is null when it is empty.
+            body: Option<String>,
+        }
+        let issue: IssueBody = client
+            .send_req(client.get(&url))
+            .context("failed to get issue body")?
+            .json()?;
+        Ok(issue.body.unwrap_or_default())
+    }
+
+    /// Fetches the current assignees of this issue (which may have changed since the event was
+    /// sent).
+    pub fn fetch_assignees(&self, client: &GithubClient) -> Result<Vec<User>, Error> {
+        let url = format!("{}/issues/{}", self.repository_url, self.number);
+        #[derive(serde::Deserialize)]
+        struct IssueAssignees {
+            assignees: Vec<User>,
+        }
+        let issue: IssueAssignees = client
+            .send_req(client.get(&url))
+            .context("failed to get issue assignees")?
+            .json()?;
+        Ok(issue.assignees)
+    }
+
     pub fn edit_body(&self, client: &GithubClient, body: &str) -> Result<(), Error> {
+        if client.dry_run(format_args!("edit the body of #{}", self.number)) {
+            return Ok(());
+        }
         let edit_url = format!("{}/issues/{}", self.repository_url, self.number);
         #[derive(serde::Serialize)]
         struct ChangedIssue<'a> {
             body: &'a str,
         }
         client
-            .patch(&edit_url)
-            .json(&ChangedIssue { body })
-            .send_req()
+            .send_req(client.patch(&edit_url).json(&ChangedIssue { body }))
             .context("failed to edit issue body")?;
         Ok(())
     }
 
+    pub fn set_state(&self, client: &GithubClient, state: IssueState) -> Result<(), Error> {
+        if client.dry_run(format_args!(
+            "set the state of #{} to {:?}",
+            self.number, state
+        )) {
+            return Ok(());
+        }
+        let edit_url = format!("{}/issues/{}", self.repository_url, self.number);
+        #[derive(serde::Serialize)]
+        struct ChangedIssue {
+            state: IssueState,
+        }
+        client
+            .send_req(client.patch(&edit_url).json(&ChangedIssue { state }))
+            .context("failed to change issue state")?;
+        Ok(())
+    }
+
+    /// Locks the conversation on this issue, so that only collaborators can comment.
+    pub fn lock(&self, client: &GithubClient, reason: Option<LockReason>) -> Result<(), Error> {
+        if client.dry_run(format_args!("lock #{} ({:?})", self.number, reason)) {
+            return Ok(());
+        }
+        let lock_url = format!("{}/issues/{}/lock", self.repository_url, self.number);
+        #[derive(serde::Serialize)]
+        struct LockReq {
+            #[serde(skip_serializing_if = "Option::is_none")]
+            lock_reason: Option<&'static str>,
+        }
+        client
+            .send_req(
+                client
+                    .put(&lock_url)
+                    // Lock reasons are still in preview.
+                    .header(ACCEPT, "application/vnd.github.sailor-v-preview+json")
+                    .json(&LockReq {
+                        lock_reason: reason.map(LockReason::as_str),
+                    }),
+            )
+            .context("failed to lock issue")?;
+        Ok(())
+    }
+
+    pub fn unlock(&self, client: &GithubClient) -> Result<(), Error> {
+        if client.dry_run(format_args!("unlock #{}", self.number)) {
+            return Ok(());
+        }
+        let lock_url = format!("{}/issues/{}/lock", self.repository_url, self.number);
+        client
+            .send_req(client.delete(&lock_url))
+            .context("failed to unlock issue")?;
+        Ok(())
+    }
+
     pub fn edit_comment(
         &self,
         client: &GithubClient,
         id: usize,
         new_body: &str,
     ) -> Result<(), Error> {
+        if client.dry_run(format_args!("edit comment {} to {:?}", id, new_body)) {
+            return Ok(());
+        }
         let comment_url = format!("{}/issues/comments/{}", self.repository_url, id);
         #[derive(serde::Serialize)]
         struct NewComment<'a> {
             body: &'a str,
         }
         client
-            .patch(&comment_url)
-            .json(&NewComment { body: new_body })
-            .send_req()
+            .send_req(
+                client
+                    .patch(&comment_url)
+                    .json(&NewComment { body: new_body }),
+            )
             .context("failed to edit comment")?;
         Ok(())
     }
 
     pub fn post_comment(&self, client: &GithubClient, body: &str) -> Result<(), Error> {
-        #[derive(serde::Serialize)]
-        struct PostComment<'a> {
-            body: &'a str,
+        if client.dry_run(format_args!("comment on #{}: {:?}", self.number, body)) {
+            return Ok(());
         }
-        client
-            .post(&self.comments_url)
-            .json(&PostComment { body })
-            .send_req()
-            .context("failed to post comment")?;
+        post_comment(client, &self.comments_url, body)?;
         Ok(())
     }
 
+    /// Like `post_comment`, but returns the new comment's id so that it can be edited later
+    /// (or `None` in a dry run, where no comment is posted).
+    pub fn post_editable_comment(
+        &self,
+        client: &GithubClient,
+        body: &str,
+    ) -> Result<Option<usize>, Error> {
+        if client.dry_run(format_args!("comment on #{}: {:?}", self.number, body)) {
+            return Ok(None);
+        }
+        Ok(Some(post_comment(client, &self.comments_url, body)?.id))
+    }
+
     pub fn set_labels(&self, client: &GithubClient, mut labels: Vec<Label>) -> Result<(), Error> {
         // PUT /repos/:owner/:repo/issues/:number/labels
         // repo_url = https://api.github.com/repos/Codertocat/Hello-World
@@ -160,27 +537,167 @@ impl Issue {
         );
 
         labels.retain(|label| label.exists(&self.repository_url, &client));
+        if client.dry_run(format_args!(
+            "set the labels of #{} to {:?}",
+            self.number, labels
+        )) {
+            return Ok(());
+        }
 
         #[derive(serde::Serialize)]
         struct LabelsReq {
             labels: Vec<String>,
         }
         client
-            .put(&url)
-            .json(&LabelsReq {
+            .send_req(client.put(&url).json(&LabelsReq {
                 labels: labels.iter().map(|l| l.name.clone()).collect(),
-            })
-            .send_req()
+            }))
             .context("failed to set labels")?;
 
         Ok(())
     }
 
+    pub fn add_label(&self, client: &GithubClient, label: &str) -> Result<(), Error> {
+        self.add_labels(client, &[label.to_owned()])
+    }
+
+    /// Adds all of `labels` to the issue, in a single request.
+    pub fn add_labels(&self, client: &GithubClient, labels: &[String]) -> Result<(), Error> {
+        if labels.is_empty() {
+            return Ok(());
+        }
+        if client.dry_run(format_args!("add labels {:?} to #{}", labels, self.number)) {
+            return Ok(());
+        }
+        // POST /repos/:owner/:repo/issues/:number/labels
+        let url = format!(
+            "{repo_url}/issues/{number}/labels",
+            repo_url = self.repository_url,
+            number = self.number
+        );
+
+        #[derive(serde::Serialize)]
+        struct LabelsReq<'a> {
+            labels: &'a [String],
+        }
+        client
+            .send_req(client.post(&url).json(&LabelsReq { labels }))
+            .context("failed to add labels")?;
+
+        Ok(())
+    }
+
+    /// Removes `label` from the issue; it's not an error if the issue doesn't have the label.
+    pub fn remove_label(&self, client: &GithubClient, label: &str) -> Result<(), Error> {
+        if client.dry_run(format_args!(
+            "remove label {:?} from #{}",
+            label, self.number
+        )) {
+            return Ok(());
+        }
+        // DELETE /repos/:owner/:repo/issues/:number/labels/:name
+        let mut url = reqwest::Url::parse(&format!(
+            "{repo_url}/issues/{number}/labels",
+            repo_url = self.repository_url,
+            number = self.number
+        ))?;
+        // Pushing the label as a path segment takes care of escaping it.
+        url.path_segments_mut()
+            .expect("API URLs have a path")
+            .push(label);
+
+        let resp = client
+            .send(client.delete(url.as_str()))
+            .context("failed to remove label")?;
+        if resp.status() == StatusCode::NOT_FOUND {
+            // The label is already absent.
+            return Ok(());
+        }
+        resp.error_for_status().context("failed to remove label")?;
+
+        Ok(())
+    }
+
+    /// Applies `deltas` (in order) to the issue's labels, with as few requests as possible: all
+    /// labels to add are added at once, and only labels the issue has are removed.
+    pub fn apply_label_deltas(
+        &self,
+        client: &GithubClient,
+        deltas: &[LabelDelta],
+    ) -> Result<(), Error> {
+        let (add, remove) = label_changes(&self.labels, deltas);
+        self.add_labels(client, &add)?;
+        for label in &remove {
+            self.remove_label(client, label)?;
+        }
+        Ok(())
+    }
+
     pub fn labels(&self) -> &[Label] {
         &self.labels
     }
 
+    pub fn assignees(&self) -> &[User] {
+        &self.assignees
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn set_title(&self, client: &GithubClient, title: &str) -> Result<(), Error> {
+        if client.dry_run(format_args!(
+            "set the title of #{} to {:?}",
+            self.number, title
+        )) {
+            return Ok(());
+        }
+        let edit_url = format!("{}/issues/{}", self.repository_url, self.number);
+        #[derive(serde::Serialize)]
+        struct ChangedIssue<'a> {
+            title: &'a str,
+        }
+        client
+            .send_req(client.patch(&edit_url).json(&ChangedIssue { title }))
+            .context("failed to set title")?;
+        Ok(())
+    }
+
+    pub fn milestone(&self) -> Option<&Milestone> {
+        self.milestone.as_ref()
+    }
+
+    /// Puts the issue in the milestone `number`, or takes it out of its milestone if `None`.
+    pub fn set_milestone(&self, client: &GithubClient, number: Option<u64>) -> Result<(), Error> {
+        if client.dry_run(format_args!(
+            "set the milestone of #{} to {:?}",
+            self.number, number
+        )) {
+            return Ok(());
+        }
+        let edit_url = format!("{}/issues/{}", self.repository_url, self.number);
+        #[derive(serde::Serialize)]
+        struct ChangedIssue {
+            milestone: Option<u64>,
+        }
+        client
+            .send_req(
+                client
+                    .patch(&edit_url)
+                    .json(&ChangedIssue { milestone: number }),
+            )
+            .context("failed to set milestone")?;
+        Ok(())
+    }
+
+    pub fn is_pr(&self) -> bool {
+        self.pull_request.is_some()
+    }
+
     pub fn remove_assignees(&self, client: &GithubClient) -> Result<(), AssignmentError> {
+        if client.dry_run(format_args!("remove the assignees of #{}", self.number)) {
+            return Ok(());
+        }
         let url = format!(
             "{repo_url}/issues/{number}/assignees",
             repo_url = self.repository_url,
@@ -191,56 +708,180 @@ impl Issue {
         struct AssigneeReq<'a> {
             assignees: &'a [&'a str],
         }
-        client
-            .delete(&url)
-            .json(&AssigneeReq {
-                assignees: &self
-                    .assignees
-                    .iter()
-                    .map(|u| u.login.as_str())
-                    .collect::<Vec<_>>()[..],
+        let assignees = self
+            .assignees
+            .iter()
+            .map(|u| u.login.as_str())
+            .collect::<Vec<_>>();
+        client.send_retrying(|| {
+            client.delete(&url).json(&AssigneeReq {
+                assignees: &assignees[..],
             })
-            .send_req()
-            .map_err(AssignmentError::Http)?;
+        })?;
         Ok(())
     }
 
-    pub fn set_assignee(&self, client: &GithubClient, user: &str) -> Result<(), AssignmentError> {
+    /// Requests a review of this pull request from `user`.
+    pub fn request_review(&self, client: &GithubClient, user: &str) -> Result<(), Error> {
+        if client.dry_run(format_args!(
+            "request a review of #{} from {}",
+            self.number, user
+        )) {
+            return Ok(());
+        }
         let url = format!(
-            "{repo_url}/issues/{number}/assignees",
+            "{repo_url}/pulls/{number}/requested_reviewers",
             repo_url = self.repository_url,
             number = self.number
         );
 
+        #[derive(serde::Serialize)]
+        struct ReviewersReq<'a> {
+            reviewers: &'a [&'a str],
+        }
+
+        client
+            .send_retrying(|| client.post(&url).json(&ReviewersReq { reviewers: &[user] }))
+            .context("failed to request review")?;
+        Ok(())
+    }
+
+    /// Moves the issue, which is in `repo`, to the repository called `target`, returning its
+    /// number there (or `None` in a dry run).
+    pub fn transfer(
+        &self,
+        client: &GithubClient,
+        repo: &Repository,
+        target: &Repository,
+    ) -> Result<Option<u64>, Error> {
+        let graphql = client.graphql();
+        let issue = graphql.issue_id(repo, self.number)?;
+        let target_id = graphql.repository_id(target)?;
+
+        if client.dry_run(format_args!("transfer #{} to {}", self.number, target)) {
+            return Ok(None);
+        }
+        #[derive(serde::Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Transferred {
+            transfer_issue: TransferPayload,
+        }
+        #[derive(serde::Deserialize)]
+        struct TransferPayload {
+            issue: TransferredIssue,
+        }
+        #[derive(serde::Deserialize)]
+        struct TransferredIssue {
+            number: u64,
+        }
+        let transferred: Transferred = graphql
+            .query(
+                "mutation($issue: ID!, $repository: ID!) {
+                    transferIssue(input: {issueId: $issue, repositoryId: $repository}) {
+                        issue { number }
+                    }
+                }",
+                serde_json::json!({ "issue": issue, "repository": target_id }),
+            )
+            .context("failed to transfer issue")?;
+        Ok(Some(transferred.transfer_issue.issue.number))
+    }
+
+    /// Marks the review thread starting with the review comment `root_comment` as resolved,
+    /// returning whether it wasn't already.
+    pub fn resolve_review_thread(
+        &self,
+        client: &GithubClient,
+        repo: &Repository,
+        root_comment: usize,
+    ) -> Result<bool, Error> {
+        let graphql = client.graphql();
+        let thread = match graphql.review_thread(repo, self.number, root_comment)? {
+            Some(thread) => thread,
+            None => failure::bail!("could not find the review thread of this comment"),
+        };
+        if thread.is_resolved {
+            return Ok(false);
+        }
+
+        if client.dry_run(format_args!(
+            "resolve review thread {} on #{}",
+            thread.id, self.number
+        )) {
+            return Ok(true);
+        }
+        let _: serde_json::Value = graphql
+            .query(
+                "mutation($thread: ID!) {
+                    resolveReviewThread(input: {threadId: $thread}) { thread { id } }
+                }",
+                serde_json::json!({ "thread": thread.id }),
+            )
+            .context("failed to resolve review thread")?;
+        Ok(true)
+    }
+
+    pub fn set_assignee(&self, client: &GithubClient, user: &str) -> Result<(), AssignmentError> {
+        if !self.can_assign(client, user)? {
+            return Err(AssignmentError::InvalidAssignee);
+        }
+        self.set_assignees(client, &[user])
+    }
+
+    /// Whether GitHub lets `user` be assigned to issues in this repository.
+    pub fn can_assign(&self, client: &GithubClient, user: &str) -> Result<bool, AssignmentError> {
         let check_url = format!(
             "{repo_url}/assignees/{name}",
             repo_url = self.repository_url,
             name = user,
         );
+        let resp = client.execute_retrying(|| client.get(&check_url))?;
+        Ok(resp.status() != reqwest::StatusCode::NOT_FOUND)
+    }
 
-        match client.get(&check_url).send() {
-            Ok(resp) => {
-                if resp.status() == reqwest::StatusCode::NO_CONTENT {
-                    // all okay
-                } else if resp.status() == reqwest::StatusCode::NOT_FOUND {
-                    return Err(AssignmentError::InvalidAssignee);
-                }
-            }
-            Err(e) => return Err(AssignmentError::Http(e)),
-        }
+    /// Replaces the assignees of the issue with `users`, who must be assignable (see
+    /// `can_assign`).
+    ///
+    /// The new assignees are added before the stale ones are removed, so that the issue is not
+    /// left unassigned if adding them fails.
+    pub fn set_assignees(
+        &self,
+        client: &GithubClient,
+        users: &[&str],
+    ) -> Result<(), AssignmentError> {
+        let url = format!(
+            "{repo_url}/issues/{number}/assignees",
+            repo_url = self.repository_url,
+            number = self.number
+        );
 
-        self.remove_assignees(client)?;
+        let stale = self
+            .assignees
+            .iter()
+            .map(|u| u.login.as_str())
+            .filter(|login| !users.iter().any(|user| user.eq_ignore_ascii_case(login)))
+            .collect::<Vec<_>>();
+        if client.dry_run(format_args!(
+            "assign {} to #{}",
+            users.join(", "),
+            self.number
+        )) {
+            return Ok(());
+        }
 
         #[derive(serde::Serialize)]
         struct AssigneeReq<'a> {
             assignees: &'a [&'a str],
         }
 
-        client
-            .post(&url)
-            .json(&AssigneeReq { assignees: &[user] })
-            .send_req()
-            .map_err(AssignmentError::Http)?;
+        client.send_retrying(|| client.post(&url).json(&AssigneeReq { assignees: users }))?;
+        if !stale.is_empty() {
+            client.send_retrying(|| {
+                client.delete(&url).json(&AssigneeReq {
+                    assignees: &stale[..],
+                })
+            })?;
+        }
 
         Ok(())
     }
@@ -257,38 +898,482 @@ pub enum IssueCommentAction {
 #[derive(Debug, serde::Deserialize)]
 pub struct IssueCommentEvent {
     pub action: IssueCommentAction,
+    /// What an edit changed; only present for the `edited` action.
+    #[serde(default)]
+    pub changes: Option<CommentChanges>,
     pub issue: Issue,
     pub comment: Comment,
     pub repository: Repository,
+    /// For review comments, the id of the comment which started the review thread the comment
+    /// is in (which may be the comment itself).
+    #[serde(skip)]
+    pub review_thread: Option<usize>,
+}
+
+impl IssueCommentEvent {
+    /// The body of the comment before it was edited, if this is an edit of the body.
+    pub fn previous_body(&self) -> Option<&str> {
+        self.changes
+            .as_ref()
+            .and_then(|changes| changes.body.as_ref())
+            .map(|body| body.from.as_str())
+    }
 }
 
 #[derive(Debug, serde::Deserialize)]
-pub struct Repository {
-    pub full_name: String,
+pub struct CommentChanges {
+    pub body: Option<ChangedFrom>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, serde::Deserialize)]
+pub struct ChangedFrom {
+    pub from: String,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct PushEvent {
+    /// The full name of the ref pushed to, e.g. `refs/heads/master`.
+    #[serde(rename = "ref")]
+    pub git_ref: String,
+    /// The commit the ref now points to.
+    pub after: String,
+    pub commits: Vec<PushCommit>,
+    pub repository: Repository,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct PushCommit {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub modified: Vec<String>,
+}
+
+impl PushEvent {
+    /// Whether any of the pushed commits changed the file at `path`.
+    pub fn touches(&self, path: &str) -> bool {
+        self.commits.iter().any(|commit| {
+            commit
+                .added
+                .iter()
+                .chain(&commit.removed)
+                .chain(&commit.modified)
+                .any(|changed| changed == path)
+        })
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IssuesAction {
+    Opened,
+    Edited,
+    Closed,
+    Reopened,
+    Labeled,
+    Unlabeled,
+    Assigned,
+    Unassigned,
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct IssuesEvent {
+    pub action: IssuesAction,
+    pub issue: Issue,
+    pub repository: Repository,
+    /// Whoever triggered the event.
+    pub sender: User,
+    /// The label added or removed, for the `labeled` and `unlabeled` actions.
+    #[serde(default)]
+    pub label: Option<Label>,
+    /// What an edit changed; only present for the `edited` action.
+    #[serde(default)]
+    pub changes: Option<CommentChanges>,
+}
+
+impl IssuesEvent {
+    /// The body of the issue before it was edited, if this is an edit of the body.
+    pub fn previous_body(&self) -> Option<&str> {
+        self.changes
+            .as_ref()
+            .and_then(|changes| changes.body.as_ref())
+            .map(|body| body.from.as_str())
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PullRequestAction {
+    Opened,
+    Closed,
+    Reopened,
+    Synchronize,
+    Labeled,
+    Unlabeled,
+    #[serde(other)]
+    Other,
+}
+
+/// A `pull_request` event, with the pull request represented as the issue it also is.
+#[derive(Debug, serde::Deserialize)]
+#[serde(from = "PullRequestEventData")]
+pub struct PullRequestEvent {
+    pub action: PullRequestAction,
+    pub issue: Issue,
+    pub repository: Repository,
+    /// Whoever triggered the event.
+    pub sender: User,
+    /// The label added or removed, for the `labeled` and `unlabeled` actions.
+    pub label: Option<Label>,
+}
+
+#[derive(serde::Deserialize)]
+struct PullRequestEventData {
+    action: PullRequestAction,
+    pull_request: PullRequestData,
+    repository: Repository,
+    sender: User,
+    #[serde(default)]
+    label: Option<Label>,
+}
+
+/// The fields of a pull request which it shares with issues.
+#[derive(serde::Deserialize)]
+struct PullRequestData {
+    number: u64,
+    // Unlike for issues, an empty body is `null`.
+    body: Option<String>,
+    state: IssueState,
+    title: String,
+    user: User,
+    labels: Vec<Label>,
+    assignees: Vec<User>,
+    #[serde(default)]
+    milestone: Option<Milestone>,
+    comments_url: String,
+    #[serde(default)]
+    updated_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    locked: bool,
+}
+
+impl PullRequestData {
+    fn into_issue(self, repo: &Repository) -> Issue {
+        Issue {
+            number: self.number,
+            body: self.body.unwrap_or_default(),
+            state: self.state,
+            title: self.title,
+            user: self.user,
+            labels: self.labels,
+            assignees: self.assignees,
+            milestone: self.milestone,
+            repository_url: repo.url(),
+            comments_url: self.comments_url,
+            pull_request: Some(PullRequestMarker {}),
+            updated_at: self.updated_at,
+            locked: self.locked,
+        }
+    }
+}
+
+impl From<PullRequestEventData> for PullRequestEvent {
+    fn from(data: PullRequestEventData) -> PullRequestEvent {
+        PullRequestEvent {
+            action: data.action,
+            issue: data.pull_request.into_issue(&data.repository),
+            repository: data.repository,
+            sender: data.sender,
+            label: data.label,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PullRequestReviewAction {
+    Submitted,
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct Review {
+    pub state: ReviewState,
+    pub user: User,
+    /// When the review was submitted; pending reviews haven't been yet.
+    #[serde(default)]
+    pub submitted_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewState {
+    Approved,
+    ChangesRequested,
+    Commented,
+    #[serde(other)]
+    Other,
+}
+
+/// A `pull_request_review` event, with the pull request represented as the issue it also is.
+#[derive(Debug, serde::Deserialize)]
+#[serde(from = "PullRequestReviewEventData")]
+pub struct PullRequestReviewEvent {
+    pub action: PullRequestReviewAction,
+    pub review: Review,
+    pub issue: Issue,
+    pub repository: Repository,
+}
+
+#[derive(serde::Deserialize)]
+struct PullRequestReviewEventData {
+    action: PullRequestReviewAction,
+    review: Review,
+    pull_request: PullRequestData,
+    repository: Repository,
+}
+
+impl From<PullRequestReviewEventData> for PullRequestReviewEvent {
+    fn from(data: PullRequestReviewEventData) -> PullRequestReviewEvent {
+        PullRequestReviewEvent {
+            action: data.action,
+            review: data.review,
+            issue: data.pull_request.into_issue(&data.repository),
+            repository: data.repository,
+        }
+    }
+}
+
+/// A `pull_request_review_comment` event, for a comment on a line of a pull request's diff.
+///
+/// We handle these as if the comment had been made on the pull request's conversation (see the
+/// conversion into `IssueCommentEvent`), so that commands can be given in review comments too.
+#[derive(serde::Deserialize)]
+pub struct ReviewCommentEvent {
+    action: IssueCommentAction,
+    #[serde(default)]
+    changes: Option<CommentChanges>,
+    pull_request: PullRequestData,
+    comment: ReviewComment,
+    repository: Repository,
+}
+
+#[derive(serde::Deserialize)]
+struct ReviewComment {
+    #[serde(flatten)]
+    comment: Comment,
+    /// The comment this is a reply to, which is the one starting the thread.
+    #[serde(default)]
+    in_reply_to_id: Option<usize>,
+}
+
+impl From<ReviewCommentEvent> for IssueCommentEvent {
+    fn from(event: ReviewCommentEvent) -> IssueCommentEvent {
+        IssueCommentEvent {
+            action: event.action,
+            changes: event.changes,
+            issue: event.pull_request.into_issue(&event.repository),
+            review_thread: Some(
+                event
+                    .comment
+                    .in_reply_to_id
+                    .unwrap_or(event.comment.comment.id),
+            ),
+            comment: event.comment.comment,
+            repository: event.repository,
+        }
+    }
+}
+
+/// A repository, known by its full name: `owner/name`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Repository {
+    full_name: String,
+    /// Where the `/` between the owner and the name is in `full_name`.
+    slash: usize,
+}
+
+impl FromStr for Repository {
+    type Err = Error;
+
+    fn from_str(full_name: &str) -> Result<Repository, Error> {
+        if !parser::command::transfer::is_valid_repo(full_name) {
+            failure::bail!("`{}` is not the full name of a repository", full_name);
+        }
+        Ok(Repository {
+            full_name: full_name.to_owned(),
+            slash: full_name.find('/').unwrap(),
+        })
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Repository {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Repository, D::Error> {
+        #[derive(serde::Deserialize)]
+        struct RepositoryData {
+            full_name: String,
+        }
+        // GitHub knows its repositories' names better than `is_valid_repo` does (there are
+        // older accounts with names which can't be registered any more), so those it sends only
+        // need to have an owner and a name.
+        let data = RepositoryData::deserialize(deserializer)?;
+        match data.full_name.find('/') {
+            Some(slash) if slash > 0 && slash + 1 < data.full_name.len() => Ok(Repository {
+                full_name: data.full_name,
+                slash,
+            }),
+            _ => Err(serde::de::Error::custom(format!(
+                "`{}` is not the full name of a repository",
+                data.full_name
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for Repository {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.full_name)
+    }
+}
+
+impl Repository {
+    pub fn owner(&self) -> &str {
+        &self.full_name[..self.slash]
+    }
+
+    pub fn name(&self) -> &str {
+        &self.full_name[self.slash + 1..]
+    }
+
+    /// The full name of the repository, `owner/name`.
+    pub fn full_name(&self) -> &str {
+        &self.full_name
+    }
+
+    /// The API URL of the repository, which those of everything in it start with.
+    pub fn url(&self) -> String {
+        format!("https://api.github.com/repos/{}", self.full_name)
+    }
+
+    /// All of the labels defined in this repository.
+    pub fn all_labels(&self, client: &GithubClient) -> Result<Vec<Label>, Error> {
+        let url = format!("{}/labels?per_page=100", self.url());
+        Ok(client
+            .get_all_pages(&url)
+            .context("failed to list labels")?)
+    }
+
+    /// All of the milestones in this repository, open or closed.
+    pub fn milestones(&self, client: &GithubClient) -> Result<Vec<Milestone>, Error> {
+        let url = format!("{}/milestones?state=all&per_page=100", self.url());
+        Ok(client
+            .get_all_pages(&url)
+            .context("failed to list milestones")?)
+    }
+
+    /// Like `all_labels`, but the labels are cached by the client for a short while.
+    pub fn cached_labels(&self, client: &GithubClient) -> Result<Arc<Vec<Label>>, Error> {
+        if let Some(labels) = client.label_cache.get(&self.full_name) {
+            return Ok(labels);
+        }
+        let labels = Arc::new(self.all_labels(client)?);
+        client.label_cache.insert(&self.full_name, labels.clone());
+        Ok(labels)
+    }
+
+    /// Posts a comment on issue (or pull request) `number` in this repository.
+    pub fn post_comment(
+        &self,
+        client: &GithubClient,
+        number: u64,
+        body: &str,
+    ) -> Result<(), Error> {
+        if client.dry_run(format_args!(
+            "comment on {}#{}: {:?}",
+            self.full_name, number, body
+        )) {
+            return Ok(());
+        }
+        let url = format!("{}/issues/{}/comments", self.url(), number);
+        post_comment(client, &url, body)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
 pub enum Event {
     IssueComment(IssueCommentEvent),
+    Issue(IssuesEvent),
+    PullRequest(PullRequestEvent),
+    PullRequestReview(PullRequestReviewEvent),
 }
 
 impl Event {
-    pub fn repo_name(&self) -> &str {
+    /// The name of the webhook event, as in the `X-GitHub-Event` header.
+    pub fn kind(&self) -> &'static str {
         match self {
-            Event::IssueComment(event) => &event.repository.full_name,
+            Event::IssueComment(_) => "issue_comment",
+            Event::Issue(_) => "issues",
+            Event::PullRequest(_) => "pull_request",
+            Event::PullRequestReview(_) => "pull_request_review",
         }
     }
 
+    pub fn repository(&self) -> &Repository {
+        match self {
+            Event::IssueComment(event) => &event.repository,
+            Event::Issue(event) => &event.repository,
+            Event::PullRequest(event) => &event.repository,
+            Event::PullRequestReview(event) => &event.repository,
+        }
+    }
+
+    pub fn repo_name(&self) -> &str {
+        self.repository().full_name()
+    }
+
     pub fn issue(&self) -> Option<&Issue> {
         match self {
             Event::IssueComment(event) => Some(&event.issue),
+            Event::Issue(event) => Some(&event.issue),
+            Event::PullRequest(event) => Some(&event.issue),
+            Event::PullRequestReview(event) => Some(&event.issue),
+        }
+    }
+
+    /// Who the event was caused by. For comments, that's their author (rather than whoever
+    /// edited them), as it's their commands which are run.
+    pub fn actor(&self) -> &str {
+        match self {
+            Event::IssueComment(event) => &event.comment.user.login,
+            Event::Issue(event) => &event.sender.login,
+            Event::PullRequest(event) => &event.sender.login,
+            Event::PullRequestReview(event) => &event.review.user.login,
+        }
+    }
+
+    /// When what the event is about happened, going by the payload, if it says.
+    ///
+    /// A redelivery carries the original payload, so this says how old the event is, however
+    /// recently it was delivered.
+    pub fn occurred_at(&self) -> Option<DateTime<Utc>> {
+        match self {
+            // Nothing in the payload says when a comment was deleted.
+            Event::IssueComment(event) if event.action == IssueCommentAction::Deleted => None,
+            Event::IssueComment(event) => {
+                Some(event.comment.updated_at.unwrap_or(event.comment.created_at))
+            }
+            Event::Issue(event) => event.issue.updated_at,
+            Event::PullRequest(event) => event.issue.updated_at,
+            Event::PullRequestReview(event) => event.review.submitted_at.or(event.issue.updated_at),
         }
     }
 }
 
 trait RequestSend: Sized {
     fn configure(self, g: &GithubClient) -> Self;
-    fn send_req(self) -> Result<Response, HttpError>;
 }
 
 impl RequestSend for RequestBuilder {
@@ -296,24 +1381,232 @@ impl RequestSend for RequestBuilder {
         self.header(USER_AGENT, "rust-lang-triagebot")
             .header(AUTHORIZATION, format!("token {}", g.token))
     }
+}
+
+/// The most recently observed state of our GitHub API rate limit.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct RateLimitState {
+    /// Requests remaining in the current window (`X-RateLimit-Remaining`).
+    pub remaining: Option<u64>,
+    /// When the current window resets, in seconds since the Unix epoch (`X-RateLimit-Reset`).
+    pub reset: Option<u64>,
+}
+
+impl RateLimitState {
+    fn update(&mut self, resp: &Response) {
+        let header = |name: &str| {
+            resp.headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse::<u64>().ok())
+        };
+        if let Some(remaining) = header("X-RateLimit-Remaining") {
+            self.remaining = Some(remaining);
+        }
+        if let Some(reset) = header("X-RateLimit-Reset") {
+            self.reset = Some(reset);
+        }
+    }
+
+    /// How long to wait before sending another request, if we're at (or below) `threshold`
+    /// remaining requests and the window hasn't reset yet.
+    fn wait_time(&self, threshold: u64) -> Option<Duration> {
+        let (remaining, reset) = (self.remaining?, self.reset?);
+        if remaining > threshold {
+            return None;
+        }
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        if reset < now {
+            return None;
+        }
+        Some(Duration::from_secs(reset - now) + RATE_LIMIT_MARGIN)
+    }
+}
+
+/// Answers we've recently looked up (e.g., the team membership of users), shared by all clones
+/// of a client.
+struct Cache<V> {
+    ttl: Duration,
+    entries: Mutex<HashMap<String, (V, Instant)>>,
+}
+
+type TeamCache = Cache<bool>;
 
-    fn send_req(self) -> Result<Response, HttpError> {
-        match self.send() {
-            Ok(r) => r.error_for_status(),
-            Err(e) => Err(e),
+impl<V: Clone> Cache<V> {
+    fn new(ttl: Duration) -> Cache<V> {
+        Cache {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
         }
     }
+
+    fn get(&self, key: &str) -> Option<V> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(key) {
+            Some((value, at)) if at.elapsed() < self.ttl => Some(value.clone()),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn insert(&self, key: &str, value: V) {
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(key.to_owned(), (value, Instant::now()));
+    }
 }
 
 #[derive(Clone)]
 pub struct GithubClient {
     token: String,
     client: Client,
+    max_retries: u32,
+    rate_limit: Arc<Mutex<RateLimitState>>,
+    rate_limit_threshold: u64,
+    dry_run: bool,
+    team_cache: Arc<TeamCache>,
+    /// The GitHub usernames of the members of each team, keyed by the team's name.
+    roster_cache: Arc<Cache<Arc<Vec<String>>>>,
+    /// The labels of each repository, keyed by its full name.
+    label_cache: Arc<Cache<Arc<Vec<Label>>>>,
+    /// Users, keyed by their login in lowercase.
+    user_cache: Arc<Cache<Arc<UserInfo>>>,
 }
 
 impl GithubClient {
     pub fn new(client: Client, token: String) -> Self {
-        GithubClient { client, token }
+        GithubClient {
+            client,
+            token,
+            max_retries: DEFAULT_MAX_RETRIES,
+            rate_limit: Arc::new(Mutex::new(RateLimitState::default())),
+            rate_limit_threshold: DEFAULT_RATE_LIMIT_THRESHOLD,
+            dry_run: false,
+            team_cache: Arc::new(TeamCache::new(DEFAULT_TEAM_CACHE_TTL)),
+            roster_cache: Arc::new(Cache::new(DEFAULT_TEAM_CACHE_TTL)),
+            label_cache: Arc::new(Cache::new(LABEL_CACHE_TTL)),
+            user_cache: Arc::new(Cache::new(USER_CACHE_TTL)),
+        }
+    }
+
+    /// Sets how many times idempotent requests are retried after failing transiently, at most
+    /// `MAX_RETRIES`.
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        assert!(max_retries <= MAX_RETRIES);
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Sets how few remaining requests in the rate limit window cause us to pause until the
+    /// window resets, rather than sending requests which would be rejected.
+    pub fn with_rate_limit_threshold(mut self, threshold: u64) -> Self {
+        self.rate_limit_threshold = threshold;
+        self
+    }
+
+    /// Sets whether requests which would modify anything on GitHub are only logged, rather than
+    /// sent. Read-only requests are always sent.
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Sets how long a user's team membership (and the members of each team) is remembered
+    /// before we look it up again.
+    pub fn with_team_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.team_cache = Arc::new(TeamCache::new(ttl));
+        self.roster_cache = Arc::new(Cache::new(ttl));
+        self
+    }
+
+    /// Remembers whether `login` is a team member, as if we'd looked it up, for tests.
+    #[cfg(test)]
+    pub(crate) fn assume_team_member(&self, login: &str, is_member: bool) {
+        self.team_cache.insert(login, is_member);
+    }
+
+    /// The GitHub usernames of the members of the Rust team called `name` (where `all` is
+    /// everyone on any team).
+    ///
+    /// The team data comes in one piece, so looking up one team caches all of them. If there's
+    /// no such team, the error is an `UnknownTeam`.
+    pub fn get_team(&self, name: &str) -> Result<Arc<Vec<String>>, Error> {
+        if let Some(members) = self.roster_cache.get(name) {
+            return Ok(members);
+        }
+        let url = format!("{}/teams.json", rust_team_data::v1::BASE_URL);
+        let teams: rust_team_data::v1::Teams = self
+            .raw()
+            .get(&url)
+            .send()
+            .and_then(Response::error_for_status)
+            .and_then(|mut r| r.json())
+            .context("could not get team data")?;
+        let mut found = None;
+        for (team, data) in teams.teams {
+            let members = Arc::new(
+                data.members
+                    .into_iter()
+                    .map(|member| member.github)
+                    .collect::<Vec<_>>(),
+            );
+            if team == name {
+                found = Some(members.clone());
+            }
+            self.roster_cache.insert(&team, members);
+        }
+        found.ok_or_else(|| UnknownTeam(name.to_owned()).into())
+    }
+
+    /// Looks up the user whose login is `login` (ignoring case), remembering them for a while.
+    ///
+    /// If there's no such user (say, because they were deleted or renamed), the error is an
+    /// `UnknownUser`.
+    pub fn get_user(&self, login: &str) -> Result<Arc<UserInfo>, Error> {
+        let key = login.to_lowercase();
+        if let Some(user) = self.user_cache.get(&key) {
+            return Ok(user);
+        }
+        if !parser::command::is_valid_username(login) {
+            return Err(UnknownUser(login.to_owned()).into());
+        }
+        let url = format!("https://api.github.com/users/{}", login);
+        let resp = self.execute_retrying(|| self.get(&url))?;
+        if resp.status() == StatusCode::NOT_FOUND {
+            return Err(UnknownUser(login.to_owned()).into());
+        }
+        let user: UserInfo = resp
+            .error_for_status()
+            .and_then(|mut resp| resp.json())
+            .context("failed to look up user")?;
+        let user = Arc::new(user);
+        self.user_cache.insert(&key, user.clone());
+        Ok(user)
+    }
+
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    /// If we're in dry-run mode, logs `action` as what we would have done and returns `true`;
+    /// the caller should then skip doing it.
+    fn dry_run(&self, action: fmt::Arguments) -> bool {
+        if self.dry_run {
+            tracing::info!("dry run: would {}", action);
+        }
+        self.dry_run
+    }
+
+    /// The rate limit as of the latest response from GitHub.
+    pub fn rate_limit(&self) -> RateLimitState {
+        *self.rate_limit.lock().unwrap()
     }
 
     pub fn raw(&self) -> &Client {
@@ -325,7 +1618,7 @@ impl GithubClient {
             "https://raw.githubusercontent.com/{}/{}/{}",
             repo, branch, path
         );
-        let mut resp = self.get(&url).send()?;
+        let mut resp = self.send(self.get(&url))?;
         match resp.status() {
             StatusCode::OK => {
                 let mut buf = Vec::with_capacity(resp.content_length().unwrap_or(4) as usize);
@@ -337,6 +1630,210 @@ impl GithubClient {
         }
     }
 
+    /// Sends `req`, first waiting for the rate limit to reset if we're close to exhausting it.
+    ///
+    /// The wait happens with the rate limit lock held, so concurrent requests queue up behind
+    /// the first one to notice instead of all waking up at once when the window resets.
+    fn send(&self, req: RequestBuilder) -> Result<Response, HttpError> {
+        {
+            let state = self.rate_limit.lock().unwrap();
+            if let Some(wait) = state.wait_time(self.rate_limit_threshold) {
+                log::warn!(
+                    "{:?} GitHub requests remaining, pausing for {:?}",
+                    state.remaining,
+                    wait
+                );
+                thread::sleep(wait);
+            }
+        }
+        let resp = req.send()?;
+        self.rate_limit.lock().unwrap().update(&resp);
+        Ok(resp)
+    }
+
+    fn send_req(&self, req: RequestBuilder) -> Result<Response, HttpError> {
+        self.send(req)?.error_for_status()
+    }
+
+    /// Sends the request produced by `build`, retrying with exponential backoff (and jitter) if
+    /// GitHub fails transiently: on 502, 503 or 504 responses, and on 403s which are really
+    /// rate limits (those carrying `Retry-After`, or `X-RateLimit-Remaining: 0`).
+    ///
+    /// Any other response, successful or not, is returned as-is. This must only be used for
+    /// idempotent requests.
+    fn execute_retrying<F>(&self, build: F) -> Result<Response, RetryError>
+    where
+        F: Fn() -> RequestBuilder,
+    {
+        let mut attempt = 0;
+        loop {
+            let resp = self.send(build()).map_err(RetryError::Permanent)?;
+            if !is_transient_failure(&resp) {
+                return Ok(resp);
+            }
+            if attempt >= self.max_retries {
+                log::warn!(
+                    "giving up on {} after {} retries: {}",
+                    resp.url(),
+                    attempt,
+                    resp.status()
+                );
+                return Err(RetryError::Exhausted(resp.error_for_status().unwrap_err()));
+            }
+            let delay = retry_delay(&resp, attempt);
+            log::debug!(
+                "retrying {} in {:?} after {}",
+                resp.url(),
+                delay,
+                resp.status()
+            );
+            thread::sleep(delay);
+            attempt += 1;
+        }
+    }
+
+    /// Like `execute_retrying`, but treats any non-success response as an error.
+    fn send_retrying<F>(&self, build: F) -> Result<Response, RetryError>
+    where
+        F: Fn() -> RequestBuilder,
+    {
+        self.execute_retrying(build)?
+            .error_for_status()
+            .map_err(RetryError::Permanent)
+    }
+
+    /// The open issues (and pull requests) in `repo` which are assigned to `assignee`, or to
+    /// anyone if that's `None`.
+    pub fn assigned_issues(
+        &self,
+        repo: &Repository,
+        assignee: Option<&str>,
+    ) -> Result<Vec<Issue>, Error> {
+        let url = format!(
+            "{}/issues?state=open&assignee={}&per_page=100",
+            repo.url(),
+            assignee.unwrap_or("*")
+        );
+        Ok(self
+            .get_all_pages(&url)
+            .context("failed to list assigned issues")?)
+    }
+
+    /// Posts a comment on the commit `sha` in `repo`.
+    pub fn post_commit_comment(
+        &self,
+        repo: &Repository,
+        sha: &str,
+        body: &str,
+    ) -> Result<(), Error> {
+        if self.dry_run(format_args!("comment on {}@{}: {:?}", repo, sha, body)) {
+            return Ok(());
+        }
+        let url = format!("{}/commits/{}/comments", repo.url(), sha);
+        post_comment(self, &url, body)?;
+        Ok(())
+    }
+
+    /// The files changed by the pull request `number` in `repo`.
+    pub fn pull_request_files(
+        &self,
+        repo: &Repository,
+        number: u64,
+    ) -> Result<Vec<PullRequestFile>, Error> {
+        let url = format!("{}/pulls/{}/files?per_page=100", repo.url(), number);
+        Ok(self
+            .get_all_pages(&url)
+            .context("failed to list pull request files")?)
+    }
+
+    /// The commits of the pull request `number` in `repo`.
+    pub fn pull_request_commits(
+        &self,
+        repo: &Repository,
+        number: u64,
+    ) -> Result<Vec<PullRequestCommit>, Error> {
+        let url = format!("{}/pulls/{}/commits?per_page=100", repo.url(), number);
+        Ok(self
+            .get_all_pages(&url)
+            .context("failed to list pull request commits")?)
+    }
+
+    /// The reviews of the pull request `number` in `repo`.
+    pub fn pull_request_reviews(
+        &self,
+        repo: &Repository,
+        number: u64,
+    ) -> Result<Vec<Review>, Error> {
+        let url = format!("{}/pulls/{}/reviews?per_page=100", repo.url(), number);
+        Ok(self
+            .get_all_pages(&url)
+            .context("failed to list pull request reviews")?)
+    }
+
+    /// The number of open pull requests in `repo` which `user` is assigned to review.
+    pub fn open_reviews(&self, repo: &Repository, user: &str) -> Result<usize, Error> {
+        let url = format!(
+            "{}/issues?state=open&assignee={}&per_page=100",
+            repo.url(),
+            user
+        );
+        let issues: Vec<Issue> = self
+            .get_all_pages(&url)
+            .context("failed to list assigned pull requests")?;
+        Ok(issues.iter().filter(|issue| issue.is_pr()).count())
+    }
+
+    /// Whether `user` has opened any issues or pull requests in `repo` other than `except`.
+    pub fn has_other_issues(
+        &self,
+        repo: &Repository,
+        user: &str,
+        except: u64,
+    ) -> Result<bool, Error> {
+        // Two are enough to find one which isn't `except`.
+        let url = format!(
+            "{}/issues?state=all&creator={}&per_page=2",
+            repo.url(),
+            user
+        );
+        #[derive(serde::Deserialize)]
+        struct IssueNumber {
+            number: u64,
+        }
+        let issues: Vec<IssueNumber> = self
+            .send_req(self.get(&url))
+            .context("failed to list issues by creator")?
+            .json()?;
+        Ok(issues.iter().any(|issue| issue.number != except))
+    }
+
+    /// Fetches every page of the paginated list at `url`, following the `Link` header's `next`
+    /// links until there are none left.
+    pub fn get_all_pages<T: serde::de::DeserializeOwned>(
+        &self,
+        url: &str,
+    ) -> Result<Vec<T>, Error> {
+        self.get_all_pages_with(url, |req| req)
+    }
+
+    /// Like `get_all_pages`, but with each request changed by `configure` (e.g., to opt into a
+    /// preview API).
+    fn get_all_pages_with<T, F>(&self, url: &str, configure: F) -> Result<Vec<T>, Error>
+    where
+        T: serde::de::DeserializeOwned,
+        F: Fn(RequestBuilder) -> RequestBuilder,
+    {
+        collect_pages(url, |url| {
+            let mut resp = self.send_req(configure(self.get(url)))?;
+            let next = resp
+                .headers()
+                .get(LINK)
+                .and_then(|v| v.to_str().ok())
+                .and_then(next_page_url);
+            Ok((resp.json()?, next))
+        })
+    }
+
     fn get(&self, url: &str) -> RequestBuilder {
         log::trace!("get {:?}", url);
         self.client.get(url).configure(self)
@@ -362,3 +1859,333 @@ impl GithubClient {
         self.client.put(url).configure(self)
     }
 }
+
+fn post_comment(client: &GithubClient, comments_url: &str, body: &str) -> Result<Comment, Error> {
+    #[derive(serde::Serialize)]
+    struct PostComment<'a> {
+        body: &'a str,
+    }
+    Ok(client
+        .send_req(client.post(comments_url).json(&PostComment { body }))
+        .context("failed to post comment")?
+        .json()?)
+}
+
+/// The labels to add to, and remove from, an issue labeled with `current` to apply `deltas`.
+fn label_changes(current: &[Label], deltas: &[LabelDelta]) -> (Vec<String>, Vec<String>) {
+    let has = |name: &str| current.iter().any(|l| l.name == name);
+    let mut add: Vec<String> = Vec::new();
+    let mut remove: Vec<String> = Vec::new();
+    for delta in deltas {
+        let name = delta.label().as_str();
+        add.retain(|l| l != name);
+        remove.retain(|l| l != name);
+        match delta {
+            LabelDelta::Add(_) if !has(name) => add.push(name.to_owned()),
+            LabelDelta::Remove(_) if has(name) => remove.push(name.to_owned()),
+            _ => {}
+        }
+    }
+    (add, remove)
+}
+
+/// Collects the items of each page, starting at `url`, where `fetch` returns a page's items
+/// along with the URL of the next page (if any).
+fn collect_pages<T, F>(url: &str, mut fetch: F) -> Result<Vec<T>, Error>
+where
+    F: FnMut(&str) -> Result<(Vec<T>, Option<String>), Error>,
+{
+    let mut items = Vec::new();
+    let mut next = Some(url.to_owned());
+    while let Some(url) = next {
+        let (page, next_url) = fetch(&url)?;
+        items.extend(page);
+        next = next_url;
+    }
+    Ok(items)
+}
+
+/// Extracts the `rel="next"` URL from a `Link` header, such as
+/// `<https://api.github.com/...&page=2>; rel="next", <https://api.github.com/...&page=5>; rel="last"`.
+fn next_page_url(link: &str) -> Option<String> {
+    link.split(',').find_map(|part| {
+        let mut pieces = part.split(';').map(str::trim);
+        let url = pieces.next()?;
+        if pieces.any(|p| p == r#"rel="next""#) && url.starts_with('<') && url.ends_with('>') {
+            Some(url[1..url.len() - 1].to_owned())
+        } else {
+            None
+        }
+    })
+}
+
+/// Only the headers are looked at, so that the body of a response which isn't retried is still
+/// there for the caller, with GitHub's explanation of what went wrong.
+fn is_transient_failure(resp: &Response) -> bool {
+    match resp.status() {
+        StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT => {
+            true
+        }
+        StatusCode::FORBIDDEN => {
+            resp.headers().contains_key(RETRY_AFTER)
+                || resp
+                    .headers()
+                    .get("X-RateLimit-Remaining")
+                    .map_or(false, |remaining| remaining == "0")
+        }
+        _ => false,
+    }
+}
+
+fn retry_delay(resp: &Response, attempt: u32) -> Duration {
+    let retry_after = resp
+        .headers()
+        .get(RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok());
+    if let Some(secs) = retry_after {
+        return Duration::from_secs(secs);
+    }
+    let jitter = rand::thread_rng().gen_range(0, RETRY_BASE_DELAY.as_millis() as u64);
+    backoff(attempt) + Duration::from_millis(jitter)
+}
+
+/// How long to wait before retrying after `attempt` attempts, without jitter.
+fn backoff(attempt: u32) -> Duration {
+    RETRY_BASE_DELAY * 2u32.pow(attempt.min(MAX_BACKOFF_EXPONENT))
+}
+
+#[test]
+fn backoff_capped() {
+    assert_eq!(backoff(0), Duration::from_millis(500));
+    assert_eq!(backoff(3), Duration::from_secs(4));
+    assert_eq!(backoff(9), Duration::from_secs(256));
+    assert_eq!(backoff(40), backoff(9));
+}
+
+#[test]
+fn link_header_next() {
+    assert_eq!(
+        next_page_url(
+            r#"<https://api.github.com/repositories/1/labels?page=2>; rel="next", <https://api.github.com/repositories/1/labels?page=3>; rel="last""#
+        ),
+        Some("https://api.github.com/repositories/1/labels?page=2".to_owned())
+    );
+}
+
+#[test]
+fn link_header_last_page() {
+    assert_eq!(
+        next_page_url(
+            r#"<https://api.github.com/repositories/1/labels?page=1>; rel="prev", <https://api.github.com/repositories/1/labels?page=1>; rel="first""#
+        ),
+        None
+    );
+}
+
+#[test]
+fn collect_two_pages() {
+    let mut requested = Vec::new();
+    let items = collect_pages("https://example.com/items", |url| {
+        requested.push(url.to_owned());
+        Ok(match url {
+            "https://example.com/items" => (
+                vec![1, 2],
+                Some("https://example.com/items?page=2".to_owned()),
+            ),
+            "https://example.com/items?page=2" => (vec![3], None),
+            _ => panic!("unexpected url {}", url),
+        })
+    })
+    .unwrap();
+    assert_eq!(items, [1, 2, 3]);
+    assert_eq!(
+        requested,
+        [
+            "https://example.com/items",
+            "https://example.com/items?page=2"
+        ]
+    );
+}
+
+/// Labels with the given `names`, for tests.
+#[cfg(test)]
+pub(crate) fn labels(names: &[&str]) -> Vec<Label> {
+    names
+        .iter()
+        .map(|name| Label {
+            name: name.to_string(),
+        })
+        .collect()
+}
+
+#[test]
+fn label_changes_batches() {
+    let deltas = [
+        LabelDelta::Add("A".into()),
+        LabelDelta::Add("B".into()),
+        LabelDelta::Remove("C".into()),
+        LabelDelta::Remove("D".into()),
+    ];
+    assert_eq!(
+        label_changes(&labels(&["B", "C"]), &deltas),
+        (vec!["A".to_owned()], vec!["C".to_owned()])
+    );
+}
+
+#[test]
+fn label_changes_last_delta_wins() {
+    // Aliases can make several deltas refer to the same label.
+    let deltas = [LabelDelta::Add("A".into()), LabelDelta::Remove("A".into())];
+    assert_eq!(label_changes(&labels(&[]), &deltas), (vec![], vec![]));
+    let deltas = [LabelDelta::Remove("A".into()), LabelDelta::Add("A".into())];
+    assert_eq!(label_changes(&labels(&["A"]), &deltas), (vec![], vec![]));
+}
+
+#[test]
+fn team_cache_hit() {
+    let cache = TeamCache::new(Duration::from_secs(60));
+    assert_eq!(cache.get("alice"), None);
+    cache.insert("alice", true);
+    cache.insert("bob", false);
+    assert_eq!(cache.get("alice"), Some(true));
+    assert_eq!(cache.get("bob"), Some(false));
+}
+
+#[test]
+fn team_cache_expired() {
+    let cache = TeamCache::new(Duration::from_secs(0));
+    cache.insert("alice", true);
+    assert_eq!(cache.get("alice"), None);
+    assert!(cache.entries.lock().unwrap().is_empty());
+}
+
+#[test]
+fn push_touches() {
+    let event: PushEvent = serde_json::from_str(
+        r#"{
+            "ref": "refs/heads/master",
+            "after": "0123abc",
+            "commits": [
+                {"added": [], "removed": [], "modified": ["README.md"]},
+                {"added": ["src/lib.rs"], "removed": [], "modified": ["triagebot.toml"]}
+            ],
+            "repository": {"full_name": "rust-lang/rust"}
+        }"#,
+    )
+    .unwrap();
+    assert!(event.touches("triagebot.toml"));
+    assert!(event.touches("src/lib.rs"));
+    assert!(!event.touches("Cargo.toml"));
+}
+
+#[test]
+fn review_comment_as_issue_comment() {
+    let event: ReviewCommentEvent = serde_json::from_str(
+        r#"{
+            "action": "created",
+            "comment": {
+                "id": 7,
+                "body": "@rustbot claim",
+                "url": "https://api.github.com/repos/rust-lang/rust/pulls/comments/7",
+                "html_url": "https://github.com/rust-lang/rust/pull/12#discussion_r7",
+                "user": {"login": "alice"},
+                "created_at": "2019-05-01T12:00:00Z"
+            },
+            "pull_request": {
+                "number": 12,
+                "body": null,
+                "state": "open",
+                "title": "Fix the thing",
+                "user": {"login": "bob"},
+                "labels": [],
+                "assignees": [],
+                "comments_url": "https://api.github.com/repos/rust-lang/rust/issues/12/comments"
+            },
+            "repository": {"full_name": "rust-lang/rust"}
+        }"#,
+    )
+    .unwrap();
+    let event = IssueCommentEvent::from(event);
+    assert_eq!(event.action, IssueCommentAction::Created);
+    assert!(event.issue.is_pr());
+    assert_eq!(event.issue.number, 12);
+    assert_eq!(event.issue.user.login, "bob");
+    assert_eq!(event.issue.body, "");
+    assert_eq!(
+        event.issue.repository_url,
+        "https://api.github.com/repos/rust-lang/rust"
+    );
+    assert_eq!(event.comment.user.login, "alice");
+    assert_eq!(event.review_thread, Some(7));
+    assert_eq!(
+        Event::IssueComment(event).occurred_at(),
+        Some("2019-05-01T12:00:00Z".parse().unwrap())
+    );
+}
+
+#[test]
+fn pull_request_review() {
+    let event: PullRequestReviewEvent = serde_json::from_str(
+        r#"{
+            "action": "submitted",
+            "review": {
+                "id": 3,
+                "state": "changes_requested",
+                "user": {"login": "alice"}
+            },
+            "pull_request": {
+                "number": 12,
+                "body": "Fixes #11",
+                "state": "open",
+                "title": "Fix the thing",
+                "user": {"login": "bob"},
+                "labels": [{"name": "S-waiting-on-review"}],
+                "assignees": [],
+                "comments_url": "https://api.github.com/repos/rust-lang/rust/issues/12/comments"
+            },
+            "repository": {"full_name": "rust-lang/rust"}
+        }"#,
+    )
+    .unwrap();
+    assert_eq!(event.action, PullRequestReviewAction::Submitted);
+    assert_eq!(event.review.state, ReviewState::ChangesRequested);
+    assert_eq!(event.review.user.login, "alice");
+    assert!(event.issue.is_pr());
+    assert_eq!(event.issue.number, 12);
+    assert_eq!(event.issue.labels()[0].name, "S-waiting-on-review");
+}
+
+#[test]
+fn repository_names() {
+    let repo: Repository = "rust-lang/rust.vim".parse().unwrap();
+    assert_eq!(repo.owner(), "rust-lang");
+    assert_eq!(repo.name(), "rust.vim");
+    assert_eq!(repo.full_name(), "rust-lang/rust.vim");
+    assert_eq!(
+        repo.url(),
+        "https://api.github.com/repos/rust-lang/rust.vim"
+    );
+    for invalid in &[
+        "rust",
+        "rust-lang/",
+        "/rust",
+        "rust-lang/rust/issues",
+        "-rust-lang/rust",
+    ] {
+        assert!(invalid.parse::<Repository>().is_err(), "{}", invalid);
+    }
+    let repo: Repository = serde_json::from_str(r#"{"full_name": "rust-lang/cargo"}"#).unwrap();
+    assert_eq!(repo.name(), "cargo");
+    assert!(serde_json::from_str::<Repository>(r#"{"full_name": "cargo"}"#).is_err());
+}
+
+#[test]
+fn invalid_login_is_unknown_user() {
+    let client = GithubClient::new(Client::new(), String::new());
+    let err = client.get_user("ghost/../../repos").err().unwrap();
+    assert_eq!(
+        err.downcast::<UnknownUser>().unwrap().to_string(),
+        "there is no GitHub user called `@ghost/../../repos`"
+    );
+}
@@ -0,0 +1,346 @@
+//! Access to GitHub's GraphQL API, for what the REST API can't do (like transferring issues).
+//!
+//! Requests go through the same client as REST requests, and so are authenticated and rate
+//! limited the same way.
+
+use super::{GithubClient, Repository};
+use failure::{Error, ResultExt};
+use std::fmt;
+
+const GRAPHQL_URL: &str = "https://api.github.com/graphql";
+
+/// Runs GraphQL queries and mutations; see `GithubClient::graphql`.
+pub struct Graphql<'a> {
+    client: &'a GithubClient,
+}
+
+/// The errors GitHub reported for a GraphQL request.
+///
+/// GitHub reports these with a successful status (and possibly some of the data), so they aren't
+/// HTTP errors.
+#[derive(Debug)]
+pub struct GraphqlErrors {
+    pub errors: Vec<GraphqlError>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct GraphqlError {
+    pub message: String,
+    /// The kind of error (e.g., `NOT_FOUND`), if GitHub says.
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+}
+
+impl fmt::Display for GraphqlErrors {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "GraphQL request failed: {}",
+            self.errors
+                .iter()
+                .map(|e| e.message.as_str())
+                .collect::<Vec<_>>()
+                .join("; ")
+        )
+    }
+}
+
+impl std::error::Error for GraphqlErrors {}
+
+impl GithubClient {
+    pub fn graphql(&self) -> Graphql {
+        Graphql { client: self }
+    }
+}
+
+impl<'a> Graphql<'a> {
+    /// Runs a GraphQL `query` (or mutation) with `variables`, returning its data.
+    ///
+    /// Since mutations go through here too, it's up to the caller to skip them in a dry run.
+    pub fn query<T>(&self, query: &str, variables: serde_json::Value) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        #[derive(serde::Serialize)]
+        struct Request<'a> {
+            query: &'a str,
+            variables: serde_json::Value,
+        }
+        let body = self
+            .client
+            .send_req(
+                self.client
+                    .post(GRAPHQL_URL)
+                    .json(&Request { query, variables }),
+            )
+            .context("failed to send GraphQL request")?
+            .text()?;
+        parse_response(&body)
+    }
+
+    /// The node id of issue `number` in `repo`, which is how GraphQL refers to it.
+    pub fn issue_id(&self, repo: &Repository, number: u64) -> Result<String, Error> {
+        let data: IssueIdData = self.query(
+            "query($owner: String!, $name: String!, $number: Int!) {
+                repository(owner: $owner, name: $name) {
+                    issue(number: $number) { id }
+                }
+            }",
+            serde_json::json!({ "owner": repo.owner(), "name": repo.name(), "number": number }),
+        )?;
+        data.into_id(repo, number)
+    }
+
+    /// The node id of `repo`.
+    pub fn repository_id(&self, repo: &Repository) -> Result<String, Error> {
+        #[derive(serde::Deserialize)]
+        struct Data {
+            repository: Option<Node>,
+        }
+        let data: Data = self.query(
+            "query($owner: String!, $name: String!) {
+                repository(owner: $owner, name: $name) { id }
+            }",
+            serde_json::json!({ "owner": repo.owner(), "name": repo.name() }),
+        )?;
+        match data.repository {
+            Some(repository) => Ok(repository.id),
+            None => failure::bail!("could not find the repository {}", repo),
+        }
+    }
+
+    /// The review thread of pull request `number` in `repo` which the comment with the (REST)
+    /// id `root_comment` starts, if there is one.
+    pub fn review_thread(
+        &self,
+        repo: &Repository,
+        number: u64,
+        root_comment: usize,
+    ) -> Result<Option<ReviewThread>, Error> {
+        let mut after = None;
+        loop {
+            let data: ReviewThreadsData = self.query(
+                "query($owner: String!, $name: String!, $number: Int!, $after: String) {
+                    repository(owner: $owner, name: $name) {
+                        pullRequest(number: $number) {
+                            reviewThreads(first: 100, after: $after) {
+                                nodes {
+                                    id
+                                    isResolved
+                                    comments(first: 1) { nodes { databaseId } }
+                                }
+                                pageInfo { hasNextPage endCursor }
+                            }
+                        }
+                    }
+                }",
+                serde_json::json!({
+                    "owner": repo.owner(),
+                    "name": repo.name(),
+                    "number": number,
+                    "after": after,
+                }),
+            )?;
+            let threads = match data.repository.and_then(|r| r.pull_request) {
+                Some(pull_request) => pull_request.review_threads,
+                None => failure::bail!("could not find the pull request {}#{}", repo, number),
+            };
+            if let Some(thread) = threads
+                .nodes
+                .into_iter()
+                .find(|thread| thread.starts_with(root_comment))
+            {
+                return Ok(Some(ReviewThread {
+                    id: thread.id,
+                    is_resolved: thread.is_resolved,
+                }));
+            }
+            if !threads.page_info.has_next_page {
+                return Ok(None);
+            }
+            after = threads.page_info.end_cursor;
+        }
+    }
+}
+
+/// A review thread on a pull request.
+#[derive(Debug)]
+pub struct ReviewThread {
+    /// The node id of the thread.
+    pub id: String,
+    pub is_resolved: bool,
+}
+
+#[derive(serde::Deserialize)]
+struct Node {
+    id: String,
+}
+
+#[derive(serde::Deserialize)]
+struct IssueIdData {
+    repository: Option<IssueIdRepository>,
+}
+
+#[derive(serde::Deserialize)]
+struct IssueIdRepository {
+    issue: Option<Node>,
+}
+
+impl IssueIdData {
+    fn into_id(self, repo: &Repository, number: u64) -> Result<String, Error> {
+        match self.repository.and_then(|repository| repository.issue) {
+            Some(issue) => Ok(issue.id),
+            None => failure::bail!("could not find {}#{}", repo, number),
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct ReviewThreadsData {
+    repository: Option<ReviewThreadsRepository>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ReviewThreadsRepository {
+    pull_request: Option<ReviewThreadsPullRequest>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ReviewThreadsPullRequest {
+    review_threads: Connection<ReviewThreadNode>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Connection<T> {
+    nodes: Vec<T>,
+    page_info: PageInfo,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PageInfo {
+    has_next_page: bool,
+    end_cursor: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ReviewThreadNode {
+    id: String,
+    is_resolved: bool,
+    comments: Nodes<CommentNode>,
+}
+
+#[derive(serde::Deserialize)]
+struct Nodes<T> {
+    nodes: Vec<T>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CommentNode {
+    database_id: Option<usize>,
+}
+
+impl ReviewThreadNode {
+    /// Whether the comment with the (REST) id `comment` is the first in this thread.
+    fn starts_with(&self, comment: usize) -> bool {
+        self.comments
+            .nodes
+            .first()
+            .map_or(false, |first| first.database_id == Some(comment))
+    }
+}
+
+/// The data in a GraphQL response, or its errors as `GraphqlErrors`.
+fn parse_response<T>(body: &str) -> Result<T, Error>
+where
+    T: serde::de::DeserializeOwned,
+{
+    #[derive(serde::Deserialize)]
+    struct Response<T> {
+        data: Option<T>,
+        #[serde(default)]
+        errors: Vec<GraphqlError>,
+    }
+    let response: Response<T> =
+        serde_json::from_str(body).context("could not parse GraphQL response")?;
+    if !response.errors.is_empty() {
+        return Err(GraphqlErrors {
+            errors: response.errors,
+        }
+        .into());
+    }
+    match response.data {
+        Some(data) => Ok(data),
+        None => failure::bail!("GraphQL response has no data"),
+    }
+}
+
+#[test]
+fn issue_id_response() {
+    let data: IssueIdData =
+        parse_response(r#"{"data":{"repository":{"issue":{"id":"MDU6SXNzdWU0NDI2MjY4NjI="}}}}"#)
+            .unwrap();
+    assert_eq!(
+        data.into_id(&"rust-lang/rust".parse().unwrap(), 60000)
+            .unwrap(),
+        "MDU6SXNzdWU0NDI2MjY4NjI="
+    );
+}
+
+#[test]
+fn issue_id_missing() {
+    let err = parse_response::<IssueIdData>(
+        r#"{
+            "data": {"repository": {"issue": null}},
+            "errors": [{
+                "type": "NOT_FOUND",
+                "path": ["repository", "issue"],
+                "locations": [{"line": 3, "column": 21}],
+                "message": "Could not resolve to an Issue with the number of 99999999."
+            }]
+        }"#,
+    )
+    .err()
+    .unwrap();
+    let errors = err.downcast::<GraphqlErrors>().unwrap();
+    assert_eq!(errors.errors.len(), 1);
+    assert_eq!(errors.errors[0].kind.as_ref().unwrap(), "NOT_FOUND");
+    assert_eq!(
+        errors.to_string(),
+        "GraphQL request failed: Could not resolve to an Issue with the number of 99999999."
+    );
+
+    let data: IssueIdData = parse_response(r#"{"data":{"repository":{"issue":null}}}"#).unwrap();
+    assert!(data
+        .into_id(&"rust-lang/rust".parse().unwrap(), 99999999)
+        .is_err());
+}
+
+#[test]
+fn review_thread_response() {
+    let data: ReviewThreadsData = parse_response(
+        r#"{"data": {"repository": {"pullRequest": {"reviewThreads": {
+            "nodes": [
+                {"id": "T1", "isResolved": true, "comments": {"nodes": [{"databaseId": 7}]}},
+                {"id": "T2", "isResolved": false, "comments": {"nodes": [{"databaseId": 9}]}}
+            ],
+            "pageInfo": {"hasNextPage": false, "endCursor": "Y3Vyc29y"}
+        }}}}}"#,
+    )
+    .unwrap();
+    let threads = data
+        .repository
+        .unwrap()
+        .pull_request
+        .unwrap()
+        .review_threads;
+    assert!(!threads.page_info.has_next_page);
+    assert!(threads.nodes[0].starts_with(7));
+    assert!(!threads.nodes[0].starts_with(9));
+    assert!(threads.nodes[1].starts_with(9));
+}
@@ -1,45 +1,779 @@
-use crate::github::{Event, GithubClient};
+use crate::audit::AuditEvent;
+use crate::config::{Config, ParseErrorsConfig};
+use crate::db::{StateKey, StateStore};
+use crate::github::{
+    AssignmentError, Comment, Event, GithubClient, Issue, IssueCommentAction, IssueCommentEvent,
+    PullRequestEvent, PullRequestFile, Reaction, RetryError, UnknownUser, User,
+};
+use crate::label_batch::LabelBatcher;
+use crate::metrics::METRICS;
 use failure::Error;
+use parser::command::{Command, Input};
+use parser::error::Error as ParseError;
+use reqwest::Error as HttpError;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashSet};
+use std::env;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 
 macro_rules! handlers {
     ($($name:ident = $handler:expr,)*) => {
         $(mod $name;)*
 
-        pub fn handle(ctx: &Context, event: &Event) -> Result<(), Error> {
-            $(if let Some(input) = Handler::parse_input(&$handler, ctx, event)? {
-                let config = crate::config::get(&ctx.github, event.repo_name())?;
-                if let Some(config) = &config.$name {
-                    Handler::handle_input(&$handler, ctx, config, event, input)?;
-                } else {
-                    failure::bail!(
-                        "The feature `{}` is not enabled in this repository.\n\
-                         To enable it add its section in the `triagebot.toml` \
-                         in the root of the repository.",
-                        stringify!($name)
-                    );
+        /// Runs every handler, even when some of them fail; their errors are reported together.
+        fn run_handlers(ctx: &Context, event: &Event) -> Result<(), Error> {
+            let mut errors = Vec::new();
+            $(
+                if let Err(err) = run_handler(ctx, event, stringify!($name), &$handler, |config| {
+                    config.$name.as_ref()
+                }) {
+                    errors.push(err);
                 }
-            })*
-            Ok(())
+            )*
+            HandlerErrors::join(errors)
         }
+
+        /// The names of the handlers which are enabled in the repository configured by `config`.
+        fn enabled_handlers(config: &Config) -> Vec<&'static str> {
+            let mut enabled = Vec::new();
+            $(
+                if config.$name.is_some() && !DISABLED_HANDLERS.contains(stringify!($name)) {
+                    enabled.push(stringify!($name));
+                }
+            )*
+            enabled
+        }
+    }
+}
+
+/// Runs every handler on `event`.
+///
+/// Handlers never run concurrently for events on the same issue (see `crate::lock`), so they may
+/// read and then update the issue without racing with each other.
+pub fn handle(ctx: &Context, event: &Event) -> Result<(), Error> {
+    let _guard = event
+        .issue()
+        .map(|issue| crate::lock::lock_issue(event.repo_name(), issue.number));
+    let reacting = reaction_target(ctx, event);
+    if let Some((comment, true, _)) = reacting {
+        react(ctx, comment, Reaction::Eyes);
+    }
+    let result = HandlerErrors::join(
+        vec![report_parse_errors(ctx, event), run_handlers(ctx, event)]
+            .into_iter()
+            .filter_map(Result::err)
+            .collect(),
+    );
+    if let Some((comment, _, all_parsed)) = reacting {
+        let reaction = if all_parsed && result.is_ok() {
+            Reaction::PlusOne
+        } else {
+            Reaction::MinusOne
+        };
+        react(ctx, comment, reaction);
+    }
+    match result {
+        Err(err) => HandlerErrors::join(
+            HandlerErrors::split(err)
+                .into_iter()
+                .filter_map(|err| reject(ctx, event, err, reacting.is_some()).err())
+                .collect(),
+        ),
+        Ok(()) => Ok(()),
+    }
+}
+
+/// Tells whoever asked that a command of theirs was refused, as the repository's
+/// `[permission_denied]` section says, if `err` is a `CommandError::Permission` and there is one;
+/// the error is then taken care of, rather than replied to as usual. Otherwise, it's returned.
+///
+/// The reply is posted at most once per comment, however often GitHub delivers it; `reacted` says
+/// whether the comment has been reacted to already, for `[reactions]`.
+fn reject(ctx: &Context, event: &Event, err: Error, reacted: bool) -> Result<(), Error> {
+    let reason = permission_denied(&err).map(|reason| reason.to_owned());
+    let (event, reason) = match (event, reason) {
+        (Event::IssueComment(event), Some(reason)) => (event, reason),
+        _ => return Err(err),
+    };
+    let repo = event.repository.full_name();
+    let config = match crate::config::get(&ctx.github, repo) {
+        Ok(config) => config,
+        Err(_) => return Err(err),
+    };
+    let permission_denied = match &config.permission_denied {
+        Some(permission_denied) => permission_denied,
+        None => return Err(err),
+    };
+    log::info!("refused command in {}: {}", event.comment.html_url, reason);
+    if permission_denied.reaction && !reacted {
+        react(ctx, &event.comment, Reaction::MinusOne);
+    }
+    if permission_denied.comment {
+        let message = config.render_template(
+            "permission-denied",
+            &[("user", &event.comment.user.login), ("reason", &reason)],
+        );
+        post_comment_once(
+            ctx,
+            repo,
+            &event.issue,
+            &format!("permission-denied-{}", event.comment.id),
+            &message,
+        )?;
+    }
+    Ok(())
+}
+
+/// Why the command was refused, if `err` says it's not allowed.
+fn permission_denied(err: &Error) -> Option<&str> {
+    match err.downcast_ref::<CommandError>() {
+        Some(CommandError::Permission(reason)) => Some(reason.as_str()),
+        _ => None,
+    }
+}
+
+/// The comment of `event` to react to, if it has commands and the repository opted into
+/// reactions with a `[reactions]` section, along with whether any of its commands could be
+/// parsed, and whether all of them could.
+fn reaction_target<'a>(ctx: &Context, event: &'a Event) -> Option<(&'a Comment, bool, bool)> {
+    let event = if let Event::IssueComment(e) = event {
+        e
+    } else {
+        return None;
+    };
+    let mut any_parsed = false;
+    let mut all_parsed = true;
+    let mut commands = 0;
+    let _ = comment_commands(ctx, event, |command| {
+        commands += 1;
+        any_parsed |= command.is_ok();
+        all_parsed &= command.is_ok();
+        Ok(())
+    });
+    if commands == 0 {
+        return None;
+    }
+    match crate::config::get(&ctx.github, event.repository.full_name()) {
+        Ok(ref config) if config.reactions.is_some() => {
+            Some((&event.comment, any_parsed, all_parsed))
+        }
+        _ => None,
+    }
+}
+
+/// Reacts to `comment`; that's only a courtesy, so failing to is logged rather than reported.
+fn react(ctx: &Context, comment: &Comment, reaction: Reaction) {
+    if let Err(err) = comment.add_reaction(&ctx.github, reaction) {
+        log::error!(
+            "failed to react to {} with {:?}: {:?}",
+            comment.html_url,
+            reaction,
+            err
+        );
+    }
+}
+
+lazy_static::lazy_static! {
+    /// The handlers which are turned off in every repository, whatever their configuration:
+    /// a comma-separated list of their names in `TRIAGEBOT_DISABLED_HANDLERS`.
+    static ref DISABLED_HANDLERS: HashSet<String> =
+        disabled_handlers(&env::var("TRIAGEBOT_DISABLED_HANDLERS").unwrap_or_default());
+}
+
+fn disabled_handlers(list: &str) -> HashSet<String> {
+    list.split(',')
+        .map(|name| name.trim())
+        .filter(|name| !name.is_empty())
+        .map(|name| name.to_owned())
+        .collect()
+}
+
+/// The other names the bot answers to, from a comma-separated list like `TRIAGEBOT_ALIASES`
+/// (where each may start with an `@`).
+pub fn bot_aliases(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(|name| name.trim().trim_start_matches('@'))
+        .filter(|name| !name.is_empty())
+        .map(|name| name.to_owned())
+        .collect()
+}
+
+/// Runs `handler`, called `name`, on `event`, if it's enabled in the event's repository: that is,
+/// if `section` finds its section in the configuration, and it hasn't been disabled everywhere.
+///
+/// If the handler isn't enabled, inputs which were asked for get a reply saying so, and others
+/// are ignored.
+fn run_handler<H, F>(
+    ctx: &Context,
+    event: &Event,
+    name: &'static str,
+    handler: &H,
+    section: F,
+) -> Result<(), Error>
+where
+    H: Handler,
+    F: FnOnce(&Config) -> Option<&H::Config>,
+{
+    let span = handler_span(ctx, event, name);
+    let _enter = span.enter();
+    let inputs = match handler.parse_input(ctx, event) {
+        Ok(inputs) => inputs,
+        Err(err) => {
+            tracing::warn!("failed to parse input: {}", err);
+            METRICS.handler_error();
+            return Err(err);
+        }
+    };
+    METRICS.commands_parsed(name, inputs.len());
+    if inputs.is_empty() {
+        return Ok(());
+    }
+    let requested = inputs.iter().any(|input| handler.requested(input));
+    let config = match crate::config::get(&ctx.github, event.repo_name()) {
+        Ok(config) => config,
+        // Nobody needs to hear about the repository not being set up.
+        Err(_) if !requested => return Ok(()),
+        Err(err) => return Err(err),
+    };
+    let disabled = DISABLED_HANDLERS.contains(name);
+    let handler_config = match section(&config) {
+        Some(handler_config) if !disabled => handler_config,
+        _ if !requested => return Ok(()),
+        Some(_) => failure::bail!("The `{}` feature is currently disabled.", name),
+        None if !config.errors(name).is_empty() => failure::bail!(
+            "The `{}` feature is disabled, as its section in the `triagebot.toml` is \
+             invalid:\n\n{}",
+            name,
+            config
+                .errors(name)
+                .iter()
+                .map(|error| format!("- {}", error.message))
+                .collect::<Vec<_>>()
+                .join("\n")
+        ),
+        None => failure::bail!(
+            "The `{}` feature is not enabled in this repository; add a `[{}]` section to \
+             its `triagebot.toml` to enable it.",
+            name,
+            name
+        ),
+    };
+    let mut errors = Vec::new();
+    for input in inputs {
+        tracing::info!("parsed command: {:?}", input);
+        let action = format!("{:?}", input);
+        // Only what people asked for goes in the issue's history; everything else would bury it.
+        let asked = handler.requested(&input);
+        let result = handler.handle_input(ctx, handler_config, event, input);
+        let delivery = ctx.delivery_id.as_ref().map(|id| id.as_str());
+        let audit = AuditEvent::new(event, delivery, name, action, &result);
+        audit.record();
+        if asked {
+            if let Err(err) = audit.store(&ctx.state) {
+                log::error!("failed to store audit event {:?}: {:?}", audit, err);
+            }
+        }
+        if let Err(err) = result {
+            METRICS.handler_error();
+            errors.push(err);
+        }
+    }
+    HandlerErrors::join(errors)
+}
+
+/// Replies to the comment of `event`, explaining the commands in it which couldn't be parsed, if
+/// the repository opted into that with a `[parse_errors]` section. The handlers then skip those
+/// commands (see `comment_commands`) rather than failing, so the rest of the comment still runs.
+///
+/// There's at most one reply per comment, explaining at most `max-errors` of its commands, so
+/// that a comment full of bad commands doesn't get a flood of replies.
+fn report_parse_errors(ctx: &Context, event: &Event) -> Result<(), Error> {
+    let event = if let Event::IssueComment(e) = event {
+        e
+    } else {
+        return Ok(());
+    };
+    let mut errors = Vec::new();
+    comment_commands(ctx, event, |command| {
+        if let Some(err) = command.error() {
+            errors.push(explain_parse_error(&ctx.username, &command, err));
+        }
+        Ok(())
+    })?;
+    if errors.is_empty() {
+        return Ok(());
     }
+    let repo = event.repository.full_name();
+    // Without the configuration, the handlers report the errors as usual.
+    let config = match crate::config::get(&ctx.github, repo) {
+        Ok(config) => config,
+        Err(_) => return Ok(()),
+    };
+    let config = match &config.parse_errors {
+        Some(config) => config,
+        None => return Ok(()),
+    };
+    ctx.cache
+        .parse_errors_reported
+        .store(true, Ordering::SeqCst);
+    post_comment_once(
+        ctx,
+        repo,
+        &event.issue,
+        &format!("parse-errors-{}", event.comment.id),
+        &render_parse_errors(config, &event.comment.html_url, &errors),
+    )
+}
+
+/// What went wrong with `command`, and how it should have been written.
+fn explain_parse_error(bot: &str, command: &Command, err: &ParseError) -> String {
+    format!(
+        "**{}**\n\n{}\n\nUsage: {}",
+        err.source,
+        err.snippet(),
+        usage(bot, command.syntax())
+    )
+}
+
+/// The forms of a command with the given `syntax`, ready to be used in a comment.
+fn usage(bot: &str, syntax: &[&str]) -> String {
+    syntax
+        .iter()
+        .map(|form| format!("`@{} {}`", bot, form))
+        .collect::<Vec<_>>()
+        .join(" or ")
+}
+
+fn render_parse_errors(config: &ParseErrorsConfig, comment_url: &str, errors: &[String]) -> String {
+    let mut text = format!(
+        "{} in [this comment]({}) could not be parsed:\n\n",
+        if errors.len() == 1 {
+            "A command"
+        } else {
+            "Some commands"
+        },
+        comment_url
+    );
+    text.push_str(
+        &errors
+            .iter()
+            .take(config.max_errors)
+            .map(|error| error.as_str())
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+    );
+    if errors.len() > config.max_errors {
+        text.push_str(&format!(
+            "\n\n...and {} more.",
+            errors.len() - config.max_errors
+        ));
+    }
+    text.push('\n');
+    text
+}
+
+/// The span within which a handler processes `event`, so that everything it logs can be traced
+/// back to the event (and its delivery).
+fn handler_span(ctx: &Context, event: &Event, handler: &'static str) -> tracing::Span {
+    tracing::info_span!(
+        "handler",
+        handler = handler,
+        delivery = ctx.delivery_id.as_ref().map_or("none", |id| id.as_str()),
+        event = event.kind(),
+        repo = event.repo_name(),
+        issue = tracing::field::debug(event.issue().map(|issue| issue.number)),
+    )
 }
 
 handlers! {
     assign = assign::AssignmentHandler,
+    autolabel = autolabel::AutolabelHandler,
+    blocked = blocked::BlockedHandler,
+    checkbox_labels = checkbox_labels::CheckboxLabelsHandler,
+    close = close::CloseHandler,
+    exclusive_labels = exclusive_labels::ExclusiveLabelsHandler,
+    hello = hello::HelloHandler,
+    help = help::HelpHandler,
+    history = history::HistoryHandler,
+    label_effects = label_effects::LabelEffectsHandler,
+    lock = lock::LockHandler,
+    mentions = mentions::MentionsHandler,
+    milestone = milestone::MilestoneHandler,
+    minimize = minimize::MinimizeHandler,
+    no_merges = no_merges::NoMergesHandler,
+    note = note::NoteHandler,
+    ping = ping::PingHandler,
+    poll = poll::PollHandler,
     relabel = relabel::RelabelHandler,
+    rename = rename::RenameHandler,
+    resolve = resolve::ResolveHandler,
+    review_labels = review_labels::ReviewLabelsHandler,
+    second = second::SecondHandler,
+    status = status::StatusHandler,
+    subscribe = subscribe::SubscribeHandler,
+    transfer = transfer::TransferHandler,
+    triage = triage::TriageHandler,
+    welcome = welcome::WelcomeHandler,
     //tracking_issue = tracking_issue::TrackingIssueHandler,
 }
 
+/// Runs the work which handlers do periodically rather than in response to events, for every
+/// repository we know about.
+pub fn run_periodic(ctx: &Context) {
+    for repo in crate::config::known_repos() {
+        let config = match crate::config::get(&ctx.github, &repo) {
+            Ok(config) => config,
+            Err(err) => {
+                log::error!("failed to load config for {}: {:?}", repo, err);
+                continue;
+            }
+        };
+        if let Some(config) = &config.assign {
+            let repository = match repo.parse() {
+                Ok(repository) => repository,
+                Err(err) => {
+                    log::error!("failed to check inactive assignees in {}: {:?}", repo, err);
+                    continue;
+                }
+            };
+            if let Err(err) = assign::check_inactive(ctx, config, &repository) {
+                log::error!("failed to check inactive assignees in {}: {:?}", repo, err);
+            }
+        }
+    }
+}
+
+const POSTED_NAMESPACE: &str = "POSTED_COMMENTS";
+
+/// Posts `body` on `issue` in `repo`, unless we've already posted that comment for `key`.
+///
+/// What was posted for each key is recorded in the state store, so that running a handler again
+/// (because GitHub redelivered the event, or a request was retried) doesn't post the same comment
+/// twice. A different body for the same key is posted, and replaces the recorded one.
+fn post_comment_once(
+    ctx: &Context,
+    repo: &str,
+    issue: &Issue,
+    key: &str,
+    body: &str,
+) -> Result<(), Error> {
+    let state_key = StateKey::new(repo, issue.number, POSTED_NAMESPACE);
+    let mut posted = ctx
+        .state
+        .get::<BTreeMap<String, String>>(&state_key)?
+        .unwrap_or_default();
+    let hash = comment_hash(key, body);
+    if posted.get(key) == Some(&hash) {
+        log::info!(
+            "skipping comment {:?} on #{}, already posted",
+            key,
+            issue.number
+        );
+        return Ok(());
+    }
+    issue.post_comment(&ctx.github, body)?;
+    posted.insert(key.to_owned(), hash);
+    ctx.state.set(&state_key, &posted)
+}
+
+/// Renders the message called `name` for `repo`, as customized by its configuration (see
+/// `crate::template`).
+fn render_template(
+    ctx: &Context,
+    repo: &str,
+    name: &str,
+    vars: &[(&str, &str)],
+) -> Result<String, Error> {
+    Ok(crate::config::get(&ctx.github, repo)?.render_template(name, vars))
+}
+
+/// A short hash of a comment, which (unlike `std`'s hashers) is stable across releases, as it's
+/// stored.
+fn comment_hash(key: &str, body: &str) -> String {
+    // 64-bit FNV-1a.
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in key.bytes().chain(Some(0)).chain(body.bytes()) {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    format!("{:016x}", hash)
+}
+
+/// The text of `comment` in `repo`, with the repository's shortcuts expanded, ready to be parsed
+/// for commands.
+fn expand_shortcuts<'a>(ctx: &Context, repo: &str, comment: &'a str) -> Cow<'a, str> {
+    let bot = ctx.bot_names();
+    if parser::command::find_commmand_start(comment, &bot).is_none() {
+        return Cow::Borrowed(comment);
+    }
+    // If the configuration can't be loaded, that is reported when a command is run.
+    match crate::config::get(&ctx.github, repo) {
+        Ok(config) => match &config.shortcut {
+            Some(shortcut) => {
+                parser::shortcut::expand_shortcuts(comment, &bot, &shortcut.shortcuts)
+            }
+            None => Cow::Borrowed(comment),
+        },
+        Err(_) => Cow::Borrowed(comment),
+    }
+}
+
+/// Calls `f` with each command in the comment of `event` which is to be run.
+///
+/// That's every command in a new comment. For an edited comment, it's only the commands which
+/// weren't there before the edit, so that fixing a typo in one command doesn't run the others
+/// again; deleted comments have no commands to run. Commands which couldn't be parsed are left
+/// out once `report_parse_errors` has replied about them.
+fn comment_commands<F>(ctx: &Context, event: &IssueCommentEvent, mut f: F) -> Result<(), Error>
+where
+    F: FnMut(Command) -> Result<(), Error>,
+{
+    let repo = event.repository.full_name();
+    let author = &event.comment.user;
+    let config = if author.is_bot() {
+        crate::config::get(&ctx.github, repo).ok()
+    } else {
+        None
+    };
+    if ignores_author(
+        &ctx.username,
+        author,
+        config.as_ref().map(|config| &**config),
+    ) {
+        return Ok(());
+    }
+    let bot = ctx.bot_names();
+    let body = expand_shortcuts(ctx, repo, &event.comment.body);
+    let commands = match event.action {
+        IssueCommentAction::Created => Input::new(&body, &bot).commands().collect(),
+        IssueCommentAction::Edited => match event.previous_body() {
+            Some(previous) => {
+                let previous = expand_shortcuts(ctx, repo, previous);
+                parser::command::parse_added(&previous, &body, &bot)
+            }
+            // Something other than the body was edited.
+            None => Vec::new(),
+        },
+        IssueCommentAction::Deleted => Vec::new(),
+    };
+    let skip_errors = ctx.cache.parse_errors_reported.load(Ordering::SeqCst);
+    for command in commands {
+        if skip_errors && command.is_err() {
+            continue;
+        }
+        f(command)?;
+    }
+    Ok(())
+}
+
+/// Whether to ignore the commands in comments by `author`: our own always are, as our replies
+/// may quote commands, and other bots' are if the repository's `config` says so.
+fn ignores_author(bot: &str, author: &User, config: Option<&Config>) -> bool {
+    author.login.eq_ignore_ascii_case(bot)
+        || (author.is_bot() && config.map_or(false, |config| config.ignores_bot(&author.login)))
+}
+
+#[derive(Clone)]
 pub struct Context {
     pub github: GithubClient,
     pub username: String,
+    /// Other names the bot answers to when mentioned, besides `username`.
+    pub aliases: Vec<String>,
+    pub state: StateStore,
+    /// The `X-GitHub-Delivery` id of the webhook delivery being handled, if any.
+    pub delivery_id: Option<String>,
+    /// This must be fresh for each event.
+    pub cache: EventCache,
+    /// Label changes should go through this, rather than straight to GitHub.
+    pub labels: LabelBatcher,
+}
+
+impl Context {
+    /// The names the bot answers to when mentioned.
+    pub fn bot_names(&self) -> Vec<&str> {
+        std::iter::once(self.username.as_str())
+            .chain(self.aliases.iter().map(|alias| alias.as_str()))
+            .collect()
+    }
+
+    /// The files changed by the pull request of `event`, fetched at most once per event.
+    pub fn pull_request_files(
+        &self,
+        event: &PullRequestEvent,
+    ) -> Result<Arc<Vec<PullRequestFile>>, Error> {
+        let mut cached = self.cache.pull_request_files.lock().unwrap();
+        if let Some(files) = &*cached {
+            return Ok(files.clone());
+        }
+        let files = Arc::new(
+            self.github
+                .pull_request_files(&event.repository, event.issue.number)?,
+        );
+        *cached = Some(files.clone());
+        Ok(files)
+    }
+}
+
+/// What we've fetched while handling an event, which several handlers need.
+#[derive(Clone, Default)]
+pub struct EventCache {
+    pull_request_files: Arc<Mutex<Option<Arc<Vec<PullRequestFile>>>>>,
+    /// Whether the commands which couldn't be parsed have been replied to already.
+    parse_errors_reported: Arc<AtomicBool>,
+}
+
+/// Why a command failed, which decides what becomes of the error (see `error_response`).
+///
+/// Handlers which don't say fail with plain errors, which are replied to as before.
+#[derive(Debug)]
+pub enum CommandError {
+    /// The command couldn't be parsed.
+    Parse(String),
+    /// Whoever asked isn't allowed to do this.
+    Permission(String),
+    /// It can't be done as asked, whoever asks: say, it names a user who doesn't exist.
+    Invalid(String),
+    /// A request to GitHub failed, which may well work if tried again.
+    Api(Error),
+    /// Anything else, which shouldn't have happened.
+    Unexpected(Error),
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CommandError::Parse(message)
+            | CommandError::Permission(message)
+            | CommandError::Invalid(message) => write!(f, "{}", message),
+            CommandError::Api(err) => write!(f, "GitHub request failed: {}", err),
+            CommandError::Unexpected(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+impl From<Error> for CommandError {
+    /// Failed requests to GitHub are `Api` errors, wherever they are in the chain of causes,
+    /// and asking for users who don't exist is an `Invalid` error; anything else is
+    /// `Unexpected`.
+    fn from(err: Error) -> CommandError {
+        if let Some(unknown) = err.downcast_ref::<UnknownUser>() {
+            return CommandError::Invalid(unknown.to_string());
+        }
+        let from_github = err.iter_chain().any(|cause| {
+            cause.downcast_ref::<HttpError>().is_some()
+                || cause.downcast_ref::<RetryError>().is_some()
+                || match cause.downcast_ref::<AssignmentError>() {
+                    Some(AssignmentError::InvalidAssignee) | None => false,
+                    Some(_) => true,
+                }
+        });
+        if from_github {
+            CommandError::Api(err)
+        } else {
+            CommandError::Unexpected(err)
+        }
+    }
+}
+
+impl From<AssignmentError> for CommandError {
+    fn from(err: AssignmentError) -> CommandError {
+        CommandError::from(Error::from(err))
+    }
+}
+
+/// The errors of several handlers (or inputs) which failed on the same event; the others still
+/// ran. Each error is reported on its own (see `errors`).
+#[derive(Debug)]
+pub struct HandlerErrors(Vec<Error>);
+
+impl HandlerErrors {
+    /// Combines `errors`: nothing went wrong if there are none, and a single error stays as it is.
+    fn join(errors: Vec<Error>) -> Result<(), Error> {
+        let mut errors = errors
+            .into_iter()
+            .flat_map(HandlerErrors::split)
+            .collect::<Vec<_>>();
+        match errors.len() {
+            0 => Ok(()),
+            1 => Err(errors.pop().unwrap()),
+            _ => Err(HandlerErrors(errors).into()),
+        }
+    }
+
+    fn split(err: Error) -> Vec<Error> {
+        match err.downcast::<HandlerErrors>() {
+            Ok(errors) => errors.0,
+            Err(err) => vec![err],
+        }
+    }
+}
+
+impl fmt::Display for HandlerErrors {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, err) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, "\n\n")?;
+            }
+            write!(f, "{}", err)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for HandlerErrors {}
+
+/// The errors handling an event failed with: each of them, if several handlers failed.
+pub fn errors(err: &Error) -> Vec<&Error> {
+    match err.downcast_ref::<HandlerErrors>() {
+        Some(errors) => errors.0.iter().collect(),
+        None => vec![err],
+    }
+}
+
+/// What to do about a handler failing with `err`.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ErrorResponse {
+    /// Tell whoever asked, in a reply.
+    Reply,
+    /// Only log it, as there's nothing they could do about it.
+    Log,
+    /// Log it as something which needs looking into, and reply as well.
+    Alert,
+}
+
+pub fn error_response(err: &Error) -> ErrorResponse {
+    match err.downcast_ref::<CommandError>() {
+        Some(CommandError::Parse(_))
+        | Some(CommandError::Permission(_))
+        | Some(CommandError::Invalid(_))
+        | None => ErrorResponse::Reply,
+        Some(CommandError::Api(_)) => ErrorResponse::Log,
+        Some(CommandError::Unexpected(_)) => ErrorResponse::Alert,
+    }
 }
 
 pub trait Handler: Sync + Send {
-    type Input;
+    type Input: std::fmt::Debug;
     type Config;
 
-    fn parse_input(&self, ctx: &Context, event: &Event) -> Result<Option<Self::Input>, Error>;
+    /// Returns every input (typically, a parsed command) in the event which this handler is
+    /// interested in, in order.
+    fn parse_input(&self, ctx: &Context, event: &Event) -> Result<Vec<Self::Input>, Error>;
+
+    /// Whether the handler only reacts to what happens on GitHub, rather than to commands, so
+    /// that nobody ever asked for its inputs: repositories which haven't configured it are just
+    /// left alone.
+    const AUTOMATIC: bool = false;
+
+    /// Whether someone asked for `input` (as they do for commands), and so should be told if
+    /// the handler isn't enabled. Other inputs are just dropped then.
+    fn requested(&self, _input: &Self::Input) -> bool {
+        !Self::AUTOMATIC
+    }
 
     fn handle_input(
         &self,
@@ -49,3 +783,268 @@ pub trait Handler: Sync + Send {
         input: Self::Input,
     ) -> Result<(), Error>;
 }
+
+#[test]
+fn disabled_handlers_list() {
+    assert_eq!(disabled_handlers(""), HashSet::new());
+    assert_eq!(
+        disabled_handlers(" relabel,, poll "),
+        ["relabel", "poll"].iter().map(|s| s.to_string()).collect()
+    );
+}
+
+#[test]
+fn bot_aliases_list() {
+    assert!(bot_aliases("").is_empty());
+    assert_eq!(
+        bot_aliases(" @rust-bot,, triagebot "),
+        ["rust-bot", "triagebot"]
+    );
+}
+
+#[cfg(test)]
+fn parse_errors_config(max_errors: usize) -> ParseErrorsConfig {
+    ParseErrorsConfig { max_errors }
+}
+
+#[test]
+fn explain_parse_errors() {
+    let mut input = Input::new("@rustbot poll close now", &["rustbot"]);
+    let command = input.parse_command();
+    let error = explain_parse_error("rustbot", &command, command.error().unwrap());
+    assert_eq!(
+        error,
+        "**expected end of command**\n\n\
+         ```text\n\
+         @rustbot poll close now\n\
+         \x20                   ^^^\n\
+         ```\n\n\
+         Usage: `@rustbot poll \"<question>\" <option> <option>...` or `@rustbot poll close`"
+    );
+    assert_eq!(
+        render_parse_errors(
+            &parse_errors_config(3),
+            "https://example.com",
+            &[error.clone()]
+        ),
+        format!(
+            "A command in [this comment](https://example.com) could not be parsed:\n\n{}\n",
+            error
+        )
+    );
+}
+
+#[test]
+fn parse_errors_limited() {
+    let errors = vec!["a".to_owned(), "b".to_owned(), "c".to_owned()];
+    assert_eq!(
+        render_parse_errors(&parse_errors_config(2), "https://example.com", &errors),
+        "Some commands in [this comment](https://example.com) could not be parsed:\n\n\
+         a\n\n\
+         b\n\n\
+         ...and 1 more.\n"
+    );
+    assert_eq!(
+        render_parse_errors(&parse_errors_config(3), "https://example.com", &errors),
+        "Some commands in [this comment](https://example.com) could not be parsed:\n\n\
+         a\n\n\
+         b\n\n\
+         c\n"
+    );
+}
+
+#[test]
+fn comment_hash_stable() {
+    assert_eq!(comment_hash("picked", "hello"), "60e0297cb7984509");
+    assert_eq!(comment_hash("", ""), "af63bd4c8601b7df");
+    assert_ne!(comment_hash("a", "b"), comment_hash("ab", ""));
+}
+
+#[cfg(test)]
+fn user(login: &str, kind: &str) -> User {
+    serde_json::from_value(serde_json::json!({ "login": login, "type": kind })).unwrap()
+}
+
+#[test]
+fn own_comments_ignored() {
+    assert!(ignores_author("rustbot", &user("rustbot", "User"), None));
+    assert!(ignores_author("rustbot", &user("RustBot", "User"), None));
+    assert!(!ignores_author("rustbot", &user("alice", "User"), None));
+    // Other bots only if the repository says so.
+    let bors = user("bors", "Bot");
+    assert!(!ignores_author("rustbot", &bors, None));
+    let config: Config = toml::from_str("ignore_bots = true").unwrap();
+    assert!(ignores_author("rustbot", &bors, Some(&config)));
+    assert!(!ignores_author(
+        "rustbot",
+        &user("alice", "User"),
+        Some(&config)
+    ));
+}
+
+#[test]
+fn error_responses() {
+    let err = |err: CommandError| Error::from(err);
+    assert_eq!(
+        error_response(&err(CommandError::Parse("expected end of command".into()))),
+        ErrorResponse::Reply
+    );
+    assert_eq!(
+        error_response(&err(CommandError::Permission("no".into()))),
+        ErrorResponse::Reply
+    );
+    assert_eq!(
+        error_response(&err(CommandError::Invalid(
+            "Cannot release unassigned issue".into()
+        ))),
+        ErrorResponse::Reply
+    );
+    assert_eq!(
+        error_response(&err(CommandError::Unexpected(failure::err_msg("oops")))),
+        ErrorResponse::Alert
+    );
+    // Handlers which don't say why they failed are replied to.
+    assert_eq!(
+        error_response(&failure::err_msg("Only Rust team members can add notes")),
+        ErrorResponse::Reply
+    );
+}
+
+#[test]
+fn handler_errors_joined() {
+    assert!(HandlerErrors::join(Vec::new()).is_ok());
+    let one = HandlerErrors::join(vec![CommandError::Permission("no".into()).into()]).unwrap_err();
+    assert_eq!(errors(&one).len(), 1);
+    assert_eq!(error_response(&one), ErrorResponse::Reply);
+    let nested = HandlerErrors::join(vec![
+        failure::err_msg("first"),
+        HandlerErrors::join(vec![failure::err_msg("second"), failure::err_msg("third")])
+            .unwrap_err(),
+    ])
+    .unwrap_err();
+    assert_eq!(
+        errors(&nested)
+            .iter()
+            .map(|err| err.to_string())
+            .collect::<Vec<_>>(),
+        ["first", "second", "third"]
+    );
+    assert_eq!(nested.to_string(), "first\n\nsecond\n\nthird");
+}
+
+#[test]
+fn github_failures_classified() {
+    let http = reqwest::Client::new().get("not a url").build().unwrap_err();
+    let err = Error::from(failure::Error::from(http).context("failed to get issue"));
+    match CommandError::from(err) {
+        CommandError::Api(_) => {}
+        other => panic!("expected a GitHub failure, got {:?}", other),
+    }
+    match CommandError::from(Error::from(UnknownUser("ghost".to_owned()))) {
+        CommandError::Invalid(message) => {
+            assert_eq!(message, "there is no GitHub user called `@ghost`")
+        }
+        other => panic!("expected an invalid command, got {:?}", other),
+    }
+    // GitHub refusing an assignee is an answer rather than a failed request.
+    match CommandError::from(Error::from(AssignmentError::InvalidAssignee)) {
+        CommandError::Unexpected(_) => {}
+        other => panic!("expected an unexpected error, got {:?}", other),
+    }
+}
+
+#[test]
+fn permission_denied_reasons() {
+    let reason = "Only Rust team members can assign other users";
+    let err = Error::from(CommandError::Permission(reason.to_owned()));
+    assert_eq!(permission_denied(&err), Some(reason));
+    let err = Error::from(CommandError::Parse("expected end of command".to_owned()));
+    assert_eq!(permission_denied(&err), None);
+    let err = Error::from(CommandError::Invalid(
+        "there is no GitHub user called `@ghost`".to_owned(),
+    ));
+    assert_eq!(permission_denied(&err), None);
+    assert_eq!(permission_denied(&failure::err_msg(reason)), None);
+}
+
+#[cfg(test)]
+fn test_context() -> Context {
+    Context {
+        github: GithubClient::new(reqwest::Client::new(), String::new()).with_dry_run(true),
+        username: "rustbot".to_owned(),
+        aliases: Vec::new(),
+        state: StateStore::in_memory(),
+        delivery_id: None,
+        cache: EventCache::default(),
+        labels: LabelBatcher::new(std::time::Duration::from_secs(0)),
+    }
+}
+
+#[cfg(test)]
+fn comment_event(repo: &str, author: &str, body: &str) -> Event {
+    Event::IssueComment(
+        serde_json::from_value(serde_json::json!({
+            "action": "created",
+            "comment": {
+                "id": 7,
+                "body": body,
+                "url": format!("https://api.github.com/repos/{}/issues/comments/7", repo),
+                "html_url": format!("https://github.com/{}/issues/12#issuecomment-7", repo),
+                "user": {"login": author},
+                "created_at": "2019-05-01T12:00:00Z"
+            },
+            "issue": {
+                "number": 12,
+                "body": "",
+                "state": "open",
+                "title": "ICE",
+                "user": {"login": "bob"},
+                "labels": [],
+                "assignees": [],
+                "repository_url": format!("https://api.github.com/repos/{}", repo),
+                "comments_url": format!("https://api.github.com/repos/{}/issues/12/comments", repo)
+            },
+            "repository": {"full_name": repo}
+        }))
+        .unwrap(),
+    )
+}
+
+#[test]
+fn team_only_command_refused() {
+    let repo = "rust-lang/team-only-command";
+    crate::config::assume(repo, "[close]\nteam-only = true\n");
+    let ctx = test_context();
+    ctx.github.assume_team_member("alice", false);
+    let event = comment_event(repo, "alice", "@rustbot close");
+    let err = run_handler(&ctx, &event, "close", &close::CloseHandler, |config| {
+        config.close.as_ref()
+    })
+    .unwrap_err();
+    assert_eq!(
+        permission_denied(&err),
+        Some("Only Rust team members can close issues")
+    );
+}
+
+#[test]
+fn alias_mentioned() {
+    let repo = "rust-lang/bot-alias";
+    crate::config::assume(repo, "");
+    let ctx = Context {
+        aliases: vec!["triagebot".to_owned()],
+        ..test_context()
+    };
+    let event = match comment_event(repo, "alice", "@triagebot hello\n\n@rustbot resolve") {
+        Event::IssueComment(event) => event,
+        _ => unreachable!(),
+    };
+    let mut commands = 0;
+    comment_commands(&ctx, &event, |command| {
+        assert!(command.is_ok());
+        commands += 1;
+        Ok(())
+    })
+    .unwrap();
+    assert_eq!(commands, 2);
+}
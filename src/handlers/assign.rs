@@ -1,134 +1,1258 @@
 //! Permit assignment of any user to issues, without requiring "write" access to the repository.
 //!
 //! We need to fake-assign ourselves and add a 'claimed by' section to the top-level comment.
+//! The assignment itself is recorded in the state store; older versions of the bot kept it in a
+//! hidden section of the issue body, which we migrate to the store when we first see it.
 //!
-//! Such assigned issues should also be placed in a queue to ensure that the user remains
-//! active; the assigned user will be asked for a status report every 2 weeks (XXX: timing).
+//! Such assigned issues are periodically checked to ensure that the user remains active; if the
+//! assigned user hasn't been active for a while (`status-report-after-days`, 2 weeks by
+//! default), they will be asked for a status report.
 //!
-//! If we're intending to ask for a status report but no comments from the assigned user have
-//! been given for the past 2 weeks, the bot will de-assign the user. They can once more claim
-//! the issue if necessary.
+//! If the assigned user still hasn't been active some time after being asked for a status report
+//! (`release-after-days`, 2 weeks by default), the bot will de-assign the user. They can once
+//! more claim the issue if necessary.
 //!
-//! Assign users with `@rustbot assign @gh-user` or `@rustbot claim` (self-claim).
+//! By default, only commenting on the issue counts as activity. `activity-sources` can add
+//! pushing commits and reviewing, on the pull request itself or on pull requests referring to
+//! the issue, so that people busy working on the code aren't nagged for not commenting.
+//!
+//! Assign users with `@rustbot assign @gh-user` or `@rustbot claim` (self-claim). On pull
+//! requests, a bare `@rustbot assign` picks the next reviewer from the configured
+//! `review-rotation`, skipping the author and (if `max-open-reviews` is set) anyone with too
+//! many open reviews already.
+//!
+//! Several users can be assigned at once, with `@rustbot assign @gh-user @other-user`, as long as
+//! GitHub lets them be assigned (we can't stand in for more than one user). Those who can't be
+//! assigned are reported, and the others are assigned anyway, unless `all-or-nothing` is set.
+//! Such joint assignments are left to GitHub, so nobody is asked for status reports on them.
+//!
+//! Assigned users can release the issue again with `@rustbot release-assignment` (or
+//! `@rustbot unassign`); team members can also release someone else's assignment, with
+//! `@rustbot unassign @gh-user`.
+//!
+//! Only team members may assign other users, or take over an issue someone else has claimed.
+//! Repositories may also restrict who can be assigned at all (`allowed-assignees`), in which case
+//! not even team members can assign anyone else.
+//!
+//! If `max-claims` is set, nobody may claim more than that many open issues at once, whether with
+//! `claim` or by assigning themselves (unless they are a team member and `max-claims-exempt-team`
+//! is set).
+//!
+//! If `claimable-only-with` is set, only issues with one of those labels (like `E-mentor`) may be
+//! claimed, or self-assigned; again, `claimable-exempt-team` lets team members claim any issue.
+//!
+//! All of this works the same on pull requests, whose assignees GitHub treats like an issue's,
+//! including from review comments. With `request-review`, whoever is assigned to a pull request
+//! is also requested as a reviewer (unless they're its author, who can't review it).
+//!
+//! People can also (un)assign issues on GitHub directly, so when someone other than the bot does,
+//! the recorded assignment is brought in line with whoever is assigned now: the sole assignee, or
+//! nobody if there are several (or none). An issue we're standing in on keeps its assignment for
+//! as long as the bot remains assigned.
 
 use crate::{
-    config::AssignConfig,
-    github::{self, Event},
-    handlers::{Context, Handler},
+    config::{ActivitySource, AssignConfig},
+    db::StateKey,
+    github::{self, Event, Issue, IssueCommentEvent, IssuesAction, Label, Repository},
+    handlers::{CommandError, Context, Handler},
     interactions::EditIssueBody,
 };
+use chrono::{DateTime, Duration, Utc};
 use failure::{Error, ResultExt};
 use parser::command::assign::AssignCommand;
-use parser::command::{Command, Input};
+use parser::command::Command;
+use std::collections::HashSet;
 
 pub(super) struct AssignmentHandler;
 
+#[derive(Debug)]
+pub(super) enum AssignInput {
+    Command(AssignCommand),
+    /// Someone other than the bot changed the issue's assignees on GitHub.
+    AssigneesChanged,
+}
+
+const NAMESPACE: &str = "ASSIGN";
+/// The index in the review rotation of whoever's turn is next.
+const ROTATION_NAMESPACE: &str = "REVIEW_ROTATION";
+
 #[derive(Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 struct AssignData {
     user: Option<String>,
+    #[serde(default = "Utc::now")]
+    assigned_at: DateTime<Utc>,
+    /// The last time we saw the assigned user be active on the issue (see `latest_activity`).
+    #[serde(default = "Utc::now")]
+    last_activity: DateTime<Utc>,
+    /// When we asked the assigned user for a status report, if they haven't been active since.
+    #[serde(default)]
+    status_requested_at: Option<DateTime<Utc>>,
+}
+
+impl AssignData {
+    fn new(user: Option<String>) -> AssignData {
+        let now = Utc::now();
+        AssignData {
+            user,
+            assigned_at: now,
+            last_activity: now,
+            status_requested_at: None,
+        }
+    }
+}
+
+/// Loads the assignment data for `issue`, migrating it out of the issue body on first access.
+fn load_data(ctx: &Context, repo: &str, issue: &Issue) -> Result<Option<AssignData>, Error> {
+    let key = StateKey::new(repo, issue.number, NAMESPACE);
+    if let Some(data) = ctx.state.get(&key)? {
+        return Ok(Some(data));
+    }
+    let data = body_data(issue);
+    if let Some(data) = &data {
+        ctx.state.set(&key, data)?;
+    }
+    Ok(data)
+}
+
+/// Like `load_data`, but leaves data still in the issue body there.
+fn peek_data(ctx: &Context, repo: &str, issue: &Issue) -> Result<Option<AssignData>, Error> {
+    match ctx
+        .state
+        .get(&StateKey::new(repo, issue.number, NAMESPACE))?
+    {
+        Some(data) => Ok(Some(data)),
+        None => Ok(body_data(issue)),
+    }
+}
+
+fn body_data(issue: &Issue) -> Option<AssignData> {
+    EditIssueBody::new(issue, NAMESPACE)
+        .current_data::<Option<AssignData>>()
+        .and_then(|data| data)
+}
+
+fn store_data(ctx: &Context, repo: &str, issue: &Issue, data: &AssignData) -> Result<(), Error> {
+    ctx.state
+        .set(&StateKey::new(repo, issue.number, NAMESPACE), data)
+}
+
+/// Sets the text of our section of the issue body, if it's not already `text`.
+fn set_text(ctx: &Context, issue: &Issue, text: String) -> Result<(), Error> {
+    let e = EditIssueBody::new(issue, NAMESPACE);
+    if e.current_text().unwrap_or("") != text {
+        e.apply(&ctx.github, text, ())?;
+    }
+    Ok(())
 }
 
 impl Handler for AssignmentHandler {
-    type Input = AssignCommand;
+    type Input = AssignInput;
     type Config = AssignConfig;
 
-    fn parse_input(&self, ctx: &Context, event: &Event) -> Result<Option<Self::Input>, Error> {
-        #[allow(irrefutable_let_patterns)]
-        let event = if let Event::IssueComment(e) = event {
-            e
-        } else {
+    fn parse_input(&self, ctx: &Context, event: &Event) -> Result<Vec<Self::Input>, Error> {
+        let event = match event {
+            Event::IssueComment(e) => e,
+            Event::Issue(e) => {
+                let changed =
+                    e.action == IssuesAction::Assigned || e.action == IssuesAction::Unassigned;
+                // Our own changes are already recorded.
+                if changed && !e.sender.login.eq_ignore_ascii_case(&ctx.username) {
+                    return Ok(vec![AssignInput::AssigneesChanged]);
+                }
+                return Ok(Vec::new());
+            }
             // not interested in other events
-            return Ok(None);
+            _ => return Ok(Vec::new()),
         };
 
-        let mut input = Input::new(&event.comment.body, &ctx.username);
-        match input.parse_command() {
-            Command::Assign(Ok(command)) => Ok(Some(command)),
-            Command::Assign(Err(err)) => {
-                failure::bail!(
-                    "Parsing assign command in [comment]({}) failed: {}",
-                    event.comment.html_url,
-                    err
-                );
+        let mut commands = Vec::new();
+        super::comment_commands(ctx, event, |command| {
+            match command {
+                Command::Assign(Ok(command)) => commands.push(AssignInput::Command(command)),
+                Command::Assign(Err(err)) => {
+                    return Err(CommandError::Parse(format!(
+                        "Parsing assign command in [comment]({}) failed: {}\n\n{}",
+                        event.comment.html_url,
+                        err,
+                        err.snippet()
+                    ))
+                    .into());
+                }
+                _ => {}
             }
-            _ => Ok(None),
+            Ok(())
+        })?;
+        Ok(commands)
+    }
+
+    fn requested(&self, input: &AssignInput) -> bool {
+        match input {
+            AssignInput::Command(_) => true,
+            AssignInput::AssigneesChanged => false,
         }
     }
 
     fn handle_input(
         &self,
         ctx: &Context,
-        _config: &AssignConfig,
+        config: &AssignConfig,
         event: &Event,
-        cmd: AssignCommand,
+        input: AssignInput,
     ) -> Result<(), Error> {
-        #[allow(irrefutable_let_patterns)]
-        let event = if let Event::IssueComment(e) = event {
-            e
-        } else {
-            // not interested in other events
-            return Ok(());
+        let cmd = match input {
+            AssignInput::Command(cmd) => cmd,
+            AssignInput::AssigneesChanged => return sync_assignees(ctx, event),
         };
 
-        let is_team_member =
-            if let Err(_) | Ok(false) = event.comment.user.is_team_member(&ctx.github) {
-                false
-            } else {
-                true
-            };
+        handle_command(ctx, config, event, cmd).map_err(Error::from)
+    }
+}
+
+/// Runs the assignment command `cmd`, from the comment of `event`.
+fn handle_command(
+    ctx: &Context,
+    config: &AssignConfig,
+    event: &Event,
+    cmd: AssignCommand,
+) -> Result<(), CommandError> {
+    let event = if let Event::IssueComment(e) = event {
+        e
+    } else {
+        // not interested in other events
+        return Ok(());
+    };
 
-        let e = EditIssueBody::new(&event.issue, "ASSIGN");
+    let is_team_member = event
+        .comment
+        .user
+        .is_team_member(&ctx.github)
+        .unwrap_or(false);
 
-        let to_assign = match cmd {
-            AssignCommand::Own => event.comment.user.login.clone(),
-            AssignCommand::User { username } => {
-                if is_team_member {
-                    if username != event.comment.user.login {
-                        failure::bail!("Only Rust team members can assign other users");
+    let repo = event.repository.full_name();
+    let data = load_data(ctx, repo, &event.issue)?;
+    // Our own assignment takes precedence, as a fake-assigned issue is assigned to the bot on
+    // GitHub.
+    let current = data
+        .as_ref()
+        .and_then(|data| data.user.clone())
+        .or_else(|| {
+            event
+                .issue
+                .assignees()
+                .iter()
+                .map(|user| &user.login)
+                .find(|login| !login.eq_ignore_ascii_case(&ctx.username))
+                .cloned()
+        });
+    let mut rotation_cursor = None;
+    let to_assign = match cmd {
+        AssignCommand::Own => {
+            let user = &event.comment.user.login;
+            check_claimable(config, is_team_member, event.issue.labels())?;
+            check_claims(ctx, config, event, user, is_team_member)?;
+            user.clone()
+        }
+        AssignCommand::User { username } => {
+            // GitHub usernames are case-insensitive.
+            let own = username.eq_ignore_ascii_case(&event.comment.user.login);
+            if !is_team_member && !own {
+                return Err(CommandError::Permission(
+                    "Only Rust team members can assign other users".to_owned(),
+                ));
+            }
+            if own {
+                check_claimable(config, is_team_member, event.issue.labels())?;
+                check_claims(ctx, config, event, &username, is_team_member)?;
+            }
+            // GitHub won't assign users who don't exist either, but we'd stand in for them
+            // as if they just weren't allowed to be assigned.
+            ctx.github.get_user(&username)?;
+            username.clone()
+        }
+        AssignCommand::Users { usernames } => {
+            let (assignable, unassignable) =
+                split_assignees(config.all_or_nothing, &usernames, |user| {
+                    let own = user.eq_ignore_ascii_case(&event.comment.user.login);
+                    if !is_team_member && !own {
+                        failure::bail!("Only Rust team members can assign @{}", user);
                     }
+                    if own {
+                        check_claimable(config, is_team_member, event.issue.labels())?;
+                        check_claims(ctx, config, event, user, is_team_member)?;
+                    }
+                    check_claim(current.as_ref().map(|s| s.as_str()), user, is_team_member)?;
+                    ctx.github.get_user(user)?;
+                    check_allowed(config, user)?;
+                    if !event.issue.can_assign(&ctx.github, user)? {
+                        failure::bail!(
+                            "GitHub won't let @{} be assigned here, but they can claim the \
+                             issue themselves",
+                            user
+                        );
+                    }
+                    Ok(())
+                })?;
+            event.issue.set_assignees(&ctx.github, &assignable)?;
+            store_data(ctx, repo, &event.issue, &AssignData::new(None))?;
+            set_text(ctx, &event.issue, String::new())?;
+            for user in &assignable {
+                if wants_review(config, event.issue.is_pr(), &event.issue.user.login, user) {
+                    event.issue.request_review(&ctx.github, user)?;
                 }
-                username.clone()
             }
-            AssignCommand::Release => {
-                let current = if let Some(AssignData { user: Some(user) }) = e.current_data() {
-                    user
-                } else {
-                    failure::bail!("Cannot release unassigned issue");
-                };
-                if current == event.comment.user.login || is_team_member {
-                    event.issue.remove_assignees(&ctx.github)?;
-                    e.apply(&ctx.github, String::new(), AssignData { user: None })?;
-                    return Ok(());
-                } else {
-                    failure::bail!("Cannot release another user's assignment");
-                }
+            if let Some(unassignable) = unassignable {
+                return Err(CommandError::Permission(unassignable));
             }
-        };
-        let data = AssignData {
-            user: Some(to_assign.clone()),
-        };
+            return Ok(());
+        }
+        AssignCommand::Rotation => {
+            if !event.issue.is_pr() {
+                return Err(CommandError::Invalid(
+                    "Reviewers are only picked from the rotation for pull requests; \
+                     use `assign @user` to assign someone to an issue"
+                        .to_owned(),
+                ));
+            }
+            let key = StateKey::repo(repo, ROTATION_NAMESPACE);
+            let cursor = ctx.state.get::<usize>(&key)?.unwrap_or(0);
+            let (reviewer, cursor) = pick_reviewer(
+                &config.review_rotation,
+                cursor,
+                &event.issue.user.login,
+                |reviewer| match config.max_open_reviews {
+                    Some(max) => Ok(ctx.github.open_reviews(&event.repository, reviewer)? < max),
+                    None => Ok(true),
+                },
+            )?;
+            rotation_cursor = Some(cursor);
+            reviewer.to_owned()
+        }
+        AssignCommand::Release | AssignCommand::Unassign { .. } => {
+            let target = match &cmd {
+                AssignCommand::Unassign { username } => Some(username.as_str()),
+                _ => None,
+            };
+            check_release(
+                data.as_ref()
+                    .and_then(|data| data.user.as_ref().map(|u| u.as_str())),
+                target,
+                &event.comment.user.login,
+                is_team_member,
+            )?;
+            event.issue.remove_assignees(&ctx.github)?;
+            store_data(ctx, repo, &event.issue, &AssignData::new(None))?;
+            set_text(ctx, &event.issue, String::new())?;
+            return Ok(());
+        }
+    };
 
-        e.apply(&ctx.github, String::new(), &data)?;
+    check_allowed(config, &to_assign)?;
 
-        match event.issue.set_assignee(&ctx.github, &to_assign) {
-            Ok(()) => return Ok(()), // we are done
-            Err(github::AssignmentError::InvalidAssignee) => {
+    check_claim(
+        current.as_ref().map(|s| s.as_str()),
+        &to_assign,
+        is_team_member,
+    )?;
+
+    // The assignment is only recorded once it's been made on GitHub, one way or the other, so
+    // that failing to make it doesn't leave the issue claimed by someone who isn't assigned.
+    let data = AssignData::new(Some(to_assign.clone()));
+
+    match event.issue.set_assignee(&ctx.github, &to_assign) {
+        Ok(()) => {
+            store_data(ctx, repo, &event.issue, &data)?;
+            set_text(ctx, &event.issue, String::new())?;
+            if wants_review(
+                config,
+                event.issue.is_pr(),
+                &event.issue.user.login,
+                &to_assign,
+            ) {
+                event.issue.request_review(&ctx.github, &to_assign)?;
+            }
+        }
+        Err(github::AssignmentError::InvalidAssignee) => {
+            // We may be handling this again after failing partway through, so only do what's
+            // left to do.
+            let assignees = event.issue.fetch_assignees(&ctx.github)?;
+            if needs_self_assignment(&assignees, &ctx.username) {
                 event
                     .issue
                     .set_assignee(&ctx.github, &ctx.username)
-                    .context("self-assignment failed")?;
-                e.apply(
-                    &ctx.github,
-                    format!(
-                        "This issue has been assigned to @{} via [this comment]({}).",
-                        to_assign, event.comment.html_url
-                    ),
-                    &data,
-                )?;
+                    .context("self-assignment failed")
+                    .map_err(Error::from)?;
+            }
+            store_data(ctx, repo, &event.issue, &data)?;
+            let note = super::render_template(
+                ctx,
+                repo,
+                "assigned",
+                &[
+                    ("user", &to_assign),
+                    ("issue", &event.issue.number.to_string()),
+                    ("comment_url", &event.comment.html_url),
+                ],
+            )?;
+            set_text(ctx, &event.issue, note.clone())?;
+            super::post_comment_once(
+                ctx,
+                repo,
+                &event.issue,
+                &format!("assigned-via-{}", event.comment.id),
+                &note,
+            )?;
+        }
+        Err(e) => return Err(e.into()),
+    }
+
+    if let Some(cursor) = rotation_cursor {
+        ctx.state
+            .set(&StateKey::repo(repo, ROTATION_NAMESPACE), &cursor)?;
+        super::post_comment_once(
+            ctx,
+            repo,
+            &event.issue,
+            "rotation",
+            &super::render_template(ctx, repo, "review-picked", &[("user", &to_assign)])?,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Brings the recorded assignment of the issue of `event` in line with its assignees on GitHub.
+fn sync_assignees(ctx: &Context, event: &Event) -> Result<(), Error> {
+    let issue = match event.issue() {
+        Some(issue) => issue,
+        None => return Ok(()),
+    };
+    let repo = event.repo_name();
+    let data = load_data(ctx, repo, issue)?;
+    let current = data
+        .as_ref()
+        .and_then(|data| data.user.as_ref().map(|u| u.as_str()));
+    // The assignees in the event may already have changed again by the time we handle it.
+    let assignees = issue.fetch_assignees(&ctx.github)?;
+    let user = match synced_assignee(current, &assignees, &ctx.username) {
+        Some(user) => user,
+        None => return Ok(()),
+    };
+    log::info!(
+        "recording {}#{} as assigned to {:?} rather than {:?}, following GitHub",
+        repo,
+        issue.number,
+        user,
+        current
+    );
+    store_data(ctx, repo, issue, &AssignData::new(user))?;
+    // We aren't standing in for anyone anymore.
+    set_text(ctx, issue, String::new())
+}
+
+/// Who the issue should be recorded as assigned to, now that its `assignees` on GitHub have
+/// changed, if that's not who it is recorded as assigned to (`current`) already.
+///
+/// While the bot is assigned, it's standing in for whoever is recorded. Otherwise, that's the
+/// sole human assignee; nobody is recorded for several of them, as such joint assignments are
+/// left to GitHub.
+fn synced_assignee(
+    current: Option<&str>,
+    assignees: &[github::User],
+    bot: &str,
+) -> Option<Option<String>> {
+    let stood_in = assignees
+        .iter()
+        .any(|user| user.login.eq_ignore_ascii_case(bot));
+    if stood_in && current.is_some() {
+        return None;
+    }
+    let humans = assignees
+        .iter()
+        .filter(|user| !user.login.eq_ignore_ascii_case(bot))
+        .collect::<Vec<_>>();
+    let user = match humans.as_slice() {
+        [user] => Some(user.login.as_str()),
+        _ => None,
+    };
+    // GitHub usernames are case-insensitive.
+    let same = match (current, user) {
+        (Some(current), Some(user)) => current.eq_ignore_ascii_case(user),
+        (current, user) => current == user,
+    };
+    if same {
+        None
+    } else {
+        Some(user.map(|user| user.to_owned()))
+    }
+}
+
+/// Whether we need to assign ourselves to an issue assigned to `assignees`, standing in for
+/// someone GitHub won't let us assign.
+fn needs_self_assignment(assignees: &[github::User], bot: &str) -> bool {
+    !(assignees.len() == 1 && assignees[0].login.eq_ignore_ascii_case(bot))
+}
+
+/// Whether to request a review from `to_assign` on being assigned to the issue, which is a pull
+/// request if `is_pr`, opened by `author`.
+fn wants_review(config: &AssignConfig, is_pr: bool, author: &str, to_assign: &str) -> bool {
+    config.request_review && is_pr && !author.eq_ignore_ascii_case(to_assign)
+}
+
+/// Checks that the issue, currently assigned to `current`, may be assigned to `to_assign`.
+///
+/// Only team members may take an issue away from someone else.
+fn check_claim(
+    current: Option<&str>,
+    to_assign: &str,
+    is_team_member: bool,
+) -> Result<(), CommandError> {
+    match current {
+        Some(current) if !current.eq_ignore_ascii_case(to_assign) && !is_team_member => {
+            Err(CommandError::Permission(format!(
+                "This issue is already claimed by @{}; please ask them to release it first",
+                current
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Splits the users to assign at once into those who can be assigned, going by `check`, and the
+/// message reporting those who can't be (if any), which is to be posted after assigning the
+/// others.
+///
+/// With `all_or_nothing` (or if nobody can be assigned), anyone who can't be assigned is an
+/// error instead, so that nobody is.
+fn split_assignees<'a, F>(
+    all_or_nothing: bool,
+    usernames: &'a [String],
+    mut check: F,
+) -> Result<(Vec<&'a str>, Option<String>), CommandError>
+where
+    F: FnMut(&str) -> Result<(), Error>,
+{
+    let mut seen = HashSet::new();
+    let mut assignable = Vec::new();
+    let mut failures = Vec::new();
+    for user in usernames {
+        // GitHub usernames are case-insensitive.
+        if !seen.insert(user.to_lowercase()) {
+            continue;
+        }
+        match check(user) {
+            Ok(()) => assignable.push(user.as_str()),
+            Err(err) => failures.push(format!("- {}", err)),
+        }
+    }
+    if failures.is_empty() {
+        return Ok((assignable, None));
+    }
+    if all_or_nothing || assignable.is_empty() {
+        return Err(CommandError::Permission(format!(
+            "Nobody was assigned, as some of the users can't be:\n\n{}",
+            failures.join("\n")
+        )));
+    }
+    let message = format!(
+        "Assigned {}, but some of the users can't be:\n\n{}",
+        assignable
+            .iter()
+            .map(|user| format!("@{}", user))
+            .collect::<Vec<_>>()
+            .join(", "),
+        failures.join("\n")
+    );
+    Ok((assignable, Some(message)))
+}
+
+/// Checks that `user`, who is taking the issue of `event` for themselves, hasn't already claimed
+/// as many open issues as `max-claims` allows.
+fn check_claims(
+    ctx: &Context,
+    config: &AssignConfig,
+    event: &IssueCommentEvent,
+    user: &str,
+    is_team_member: bool,
+) -> Result<(), CommandError> {
+    let max = match config.max_claims {
+        Some(max) if !(is_team_member && config.max_claims_exempt_team) => max,
+        _ => return Ok(()),
+    };
+    // Their claims are assigned to them on GitHub, or to us where GitHub wouldn't let them be.
+    let mut open = HashSet::new();
+    for assignee in &[user, ctx.username.as_str()] {
+        let issues = ctx
+            .github
+            .assigned_issues(&event.repository, Some(*assignee))?;
+        open.extend(issues.iter().map(|issue| issue.number));
+    }
+    let claims = ctx
+        .state
+        .get_all::<AssignData>(event.repository.full_name(), NAMESPACE)?;
+    check_claim_limit(max, count_claims(&claims, &open, user, event.issue.number))
+}
+
+/// The number of issues among `open` which `user` has claimed, other than `except`.
+fn count_claims(
+    claims: &[(u64, AssignData)],
+    open: &HashSet<u64>,
+    user: &str,
+    except: u64,
+) -> usize {
+    claims
+        .iter()
+        .filter(|(issue, data)| {
+            *issue != except
+                && open.contains(issue)
+                && data
+                    .user
+                    .as_ref()
+                    .map_or(false, |u| u.eq_ignore_ascii_case(user))
+        })
+        .count()
+}
+
+/// Checks that an issue with `labels` may be claimed, going by `claimable-only-with`.
+fn check_claimable(
+    config: &AssignConfig,
+    is_team_member: bool,
+    labels: &[Label],
+) -> Result<(), CommandError> {
+    let claimable = match &config.claimable_only_with {
+        Some(claimable) if !(is_team_member && config.claimable_exempt_team) => claimable,
+        _ => return Ok(()),
+    };
+    if labels
+        .iter()
+        .any(|label| claimable.iter().any(|name| *name == label.name))
+    {
+        return Ok(());
+    }
+    Err(CommandError::Permission(format!(
+        "This issue can't be claimed; only issues labeled {} can be",
+        claimable
+            .iter()
+            .map(|name| format!("`{}`", name))
+            .collect::<Vec<_>>()
+            .join(" or ")
+    )))
+}
+
+/// Checks that someone already holding `held` issues may claim another.
+fn check_claim_limit(max: u32, held: usize) -> Result<(), CommandError> {
+    if held >= max as usize {
+        return Err(CommandError::Permission(format!(
+            "You already have {} open issues claimed in this repository, which is the most \
+             allowed; please finish or release one of them before claiming another",
+            held
+        )));
+    }
+    Ok(())
+}
+
+/// Checks that `releaser` may release the assignment of the issue, currently assigned to
+/// `current`; `target` is who they asked to unassign, if they named anyone.
+///
+/// Only team members may release someone else's assignment.
+fn check_release(
+    current: Option<&str>,
+    target: Option<&str>,
+    releaser: &str,
+    is_team_member: bool,
+) -> Result<(), CommandError> {
+    let current = match current {
+        Some(current) => current,
+        None => {
+            return Err(CommandError::Invalid(
+                "Cannot release unassigned issue".to_owned(),
+            ))
+        }
+    };
+    if let Some(target) = target {
+        if !target.eq_ignore_ascii_case(current) {
+            return Err(CommandError::Invalid(format!(
+                "@{} is not assigned to this issue; it's assigned to @{}",
+                target, current
+            )));
+        }
+    }
+    if !current.eq_ignore_ascii_case(releaser) && !is_team_member {
+        return Err(CommandError::Permission(
+            "Cannot release another user's assignment".to_owned(),
+        ));
+    }
+    Ok(())
+}
+
+/// Checks that `to_assign` is one of the users which may be assigned in this repository.
+fn check_allowed(config: &AssignConfig, to_assign: &str) -> Result<(), CommandError> {
+    let allowed = match &config.allowed_assignees {
+        Some(allowed) => allowed,
+        None => return Ok(()),
+    };
+    // GitHub usernames are case-insensitive.
+    if allowed
+        .iter()
+        .any(|user| user.eq_ignore_ascii_case(to_assign))
+    {
+        return Ok(());
+    }
+    Err(CommandError::Permission(format!(
+        "@{} cannot be assigned to issues in this repository; the eligible users are {}",
+        to_assign,
+        allowed
+            .iter()
+            .map(|user| format!("@{}", user))
+            .collect::<Vec<_>>()
+            .join(", ")
+    )))
+}
+
+/// Picks the reviewer from `rotation` whose turn it is: the first one, starting at `cursor`, who
+/// isn't the pull request's `author` and is `available`.
+///
+/// Returns the reviewer along with the cursor to start from next time.
+fn pick_reviewer<'a, F>(
+    rotation: &'a [String],
+    cursor: usize,
+    author: &str,
+    mut available: F,
+) -> Result<(&'a str, usize), CommandError>
+where
+    F: FnMut(&str) -> Result<bool, Error>,
+{
+    if rotation.is_empty() {
+        return Err(CommandError::Invalid(
+            "This repository has no review rotation configured; \
+             use `assign @user` to pick a reviewer"
+                .to_owned(),
+        ));
+    }
+    for offset in 0..rotation.len() {
+        let i = (cursor + offset) % rotation.len();
+        let reviewer = &rotation[i];
+        if reviewer.eq_ignore_ascii_case(author) {
+            continue;
+        }
+        if available(reviewer)? {
+            return Ok((reviewer, (i + 1) % rotation.len()));
+        }
+    }
+    Err(CommandError::Invalid(
+        "Nobody in the review rotation is available: they're either the author, \
+         or already reviewing too many pull requests"
+            .to_owned(),
+    ))
+}
+
+/// When `user` was last active on `issue` in `repo`, if they have been since `since`, counting
+/// only the kinds of activity in the repository's `activity-sources`.
+fn latest_activity(
+    ctx: &Context,
+    config: &AssignConfig,
+    repo: &Repository,
+    issue: &Issue,
+    user: &str,
+    since: DateTime<Utc>,
+) -> Result<Option<DateTime<Utc>>, Error> {
+    let counts = |source| config.activity_sources.contains(&source);
+    let mut times = Vec::new();
+    if counts(ActivitySource::Comments) {
+        times.extend(
+            issue
+                .comments_since(&ctx.github, since)?
+                .into_iter()
+                .filter(|c| c.user.login.eq_ignore_ascii_case(user))
+                .map(|c| c.created_at),
+        );
+    }
+    if counts(ActivitySource::Pushes) || counts(ActivitySource::Reviews) {
+        let pull_requests = if issue.is_pr() {
+            vec![issue.number]
+        } else {
+            issue.linked_pull_requests(&ctx.github)?
+        };
+        for number in pull_requests {
+            if counts(ActivitySource::Pushes) {
+                times.extend(
+                    ctx.github
+                        .pull_request_commits(repo, number)?
+                        .into_iter()
+                        .filter(|c| {
+                            c.author
+                                .as_ref()
+                                .map_or(false, |a| a.login.eq_ignore_ascii_case(user))
+                        })
+                        .map(|c| c.commit.committer.date),
+                );
+            }
+            if counts(ActivitySource::Reviews) {
+                times.extend(
+                    ctx.github
+                        .pull_request_reviews(repo, number)?
+                        .into_iter()
+                        .filter(|r| r.user.login.eq_ignore_ascii_case(user))
+                        .filter_map(|r| r.submitted_at),
+                );
             }
-            Err(e) => return Err(e.into()),
         }
+    }
+    Ok(times.into_iter().filter(|at| *at > since).max())
+}
+
+/// Who's assigned to `issue`, as recorded, for `@rustbot status`.
+pub(super) fn describe(
+    ctx: &Context,
+    config: &AssignConfig,
+    repo: &str,
+    issue: &Issue,
+) -> Result<String, Error> {
+    Ok(describe_data(config, peek_data(ctx, repo, issue)?.as_ref()))
+}
+
+/// Who's assigned, since when, and what happens next if they stay inactive.
+fn describe_data(config: &AssignConfig, data: Option<&AssignData>) -> String {
+    let format = "%Y-%m-%d";
+    let (data, user) = match data.and_then(|data| data.user.as_ref().map(|user| (data, user))) {
+        Some(assigned) => assigned,
+        None => return "nobody".to_owned(),
+    };
+    let next = match data.status_requested_at {
+        Some(requested_at) => format!(
+            "status report requested on {}; released on {} without a reply",
+            requested_at.format(format),
+            (requested_at + Duration::days(config.release_after_days)).format(format)
+        ),
+        None => format!(
+            "status report due {}",
+            (data.last_activity + Duration::days(config.status_report_after_days)).format(format)
+        ),
+    };
+    format!(
+        "@{} since {} (last active {}; {})",
+        user,
+        data.assigned_at.format(format),
+        data.last_activity.format(format),
+        next
+    )
+}
 
-        Ok(())
+/// Asks assignees who haven't been active in a while for a status report, and releases the
+/// assignment of those who didn't reply to such a request in time.
+///
+/// Failing to check one issue doesn't stop the others from being checked.
+pub(super) fn check_inactive(
+    ctx: &Context,
+    config: &AssignConfig,
+    repository: &Repository,
+) -> Result<(), Error> {
+    let now = Utc::now();
+    let repo = repository.full_name();
+    for issue in ctx.github.assigned_issues(repository, None)? {
+        let _guard = crate::lock::lock_issue(repo, issue.number);
+        if let Err(err) = check_issue_inactive(ctx, config, repository, &issue, now) {
+            log::error!(
+                "failed to check the assignment of {}#{} for inactivity: {:?}",
+                repo,
+                issue.number,
+                err
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Checks `issue` for `check_inactive`.
+///
+/// Its comments are posted at most once per status request (see `post_comment_once`), in case
+/// recording that they were posted fails and the issue is checked again.
+fn check_issue_inactive(
+    ctx: &Context,
+    config: &AssignConfig,
+    repository: &Repository,
+    issue: &Issue,
+    now: DateTime<Utc>,
+) -> Result<(), Error> {
+    let repo = repository.full_name();
+    let mut data = match load_data(ctx, repo, issue)? {
+        Some(data) => data,
+        None => return Ok(()),
+    };
+    let user = match &data.user {
+        Some(user) => user.clone(),
+        None => return Ok(()),
+    };
+
+    let mut changed = false;
+    if let Some(at) = latest_activity(ctx, config, repository, issue, &user, data.last_activity)? {
+        data.last_activity = at;
+        data.status_requested_at = None;
+        changed = true;
     }
+
+    if let Some(requested_at) = data.status_requested_at {
+        if now - requested_at >= Duration::days(config.release_after_days) {
+            log::info!(
+                "releasing inactive assignee {} from {}#{}",
+                user,
+                repo,
+                issue.number
+            );
+            issue.remove_assignees(&ctx.github)?;
+            store_data(ctx, repo, issue, &AssignData::new(None))?;
+            set_text(ctx, issue, String::new())?;
+            super::post_comment_once(
+                ctx,
+                repo,
+                issue,
+                &format!("inactive-release-{}", requested_at.timestamp()),
+                &format!(
+                    "@{} has been unassigned from this issue, as we didn't hear back \
+                     about its status. Feel free to claim it again if you're still \
+                     working on it!",
+                    user
+                ),
+            )?;
+            return Ok(());
+        }
+    } else if now - data.last_activity >= Duration::days(config.status_report_after_days) {
+        // Keyed on the activity we're asking about, as the request is only recorded afterwards.
+        super::post_comment_once(
+            ctx,
+            repo,
+            issue,
+            &format!("inactive-ping-{}", data.last_activity.timestamp()),
+            &format!(
+                "Hey @{}! It's been a while since we heard from you here; \
+                 could you give us a quick status report on this issue? \
+                 If you're no longer working on it, you can release it with \
+                 `@{} release-assignment`.",
+                user, ctx.username
+            ),
+        )?;
+        data.status_requested_at = Some(now);
+        changed = true;
+    }
+
+    if changed {
+        store_data(ctx, repo, issue, &data)?;
+    }
+    Ok(())
+}
+
+#[test]
+fn claim_unassigned() {
+    assert!(check_claim(None, "alice", false).is_ok());
+}
+
+#[test]
+fn claim_already_claimed() {
+    let err = check_claim(Some("bob"), "alice", false).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "This issue is already claimed by @bob; please ask them to release it first"
+    );
+}
+
+#[test]
+fn claim_already_claimed_by_self() {
+    assert!(check_claim(Some("alice"), "alice", false).is_ok());
+}
+
+#[test]
+fn claim_force_reassign() {
+    assert!(check_claim(Some("bob"), "alice", true).is_ok());
+}
+
+#[cfg(test)]
+fn config(allowed_assignees: Option<&[&str]>) -> AssignConfig {
+    AssignConfig {
+        status_report_after_days: 14,
+        release_after_days: 14,
+        allowed_assignees: allowed_assignees
+            .map(|users| users.iter().map(|user| user.to_string()).collect()),
+        review_rotation: Vec::new(),
+        max_open_reviews: None,
+        max_claims: None,
+        max_claims_exempt_team: false,
+        claimable_only_with: None,
+        claimable_exempt_team: false,
+        request_review: false,
+        all_or_nothing: false,
+        activity_sources: vec![ActivitySource::Comments],
+    }
+}
+
+#[test]
+fn review_requested_on_pull_requests() {
+    let mut config = config(None);
+    assert!(!wants_review(&config, true, "bob", "alice"));
+    config.request_review = true;
+    assert!(wants_review(&config, true, "bob", "alice"));
+    assert!(!wants_review(&config, false, "bob", "alice"));
+    assert!(!wants_review(&config, true, "Alice", "alice"));
+}
+
+#[cfg(test)]
+fn users(logins: &[&str]) -> Vec<github::User> {
+    logins
+        .iter()
+        .map(|login| github::User {
+            login: login.to_string(),
+            kind: None,
+        })
+        .collect()
+}
+
+#[test]
+fn self_assignment_fallback() {
+    // `set_assignee` failed with `InvalidAssignee`, and we haven't stood in yet.
+    assert!(needs_self_assignment(&users(&[]), "rustbot"));
+    assert!(needs_self_assignment(&users(&["alice"]), "rustbot"));
+    assert!(needs_self_assignment(
+        &users(&["rustbot", "alice"]),
+        "rustbot"
+    ));
+    // A redelivery, after we'd already self-assigned.
+    assert!(!needs_self_assignment(&users(&["rustbot"]), "rustbot"));
+}
+
+#[test]
+fn claims_counted() {
+    let claims = vec![
+        (1, AssignData::new(Some("alice".to_owned()))),
+        (2, AssignData::new(Some("alice".to_owned()))),
+        (3, AssignData::new(Some("bob".to_owned()))),
+        (4, AssignData::new(None)),
+        // Closed since.
+        (5, AssignData::new(Some("alice".to_owned()))),
+        (6, AssignData::new(Some("alice".to_owned()))),
+    ];
+    let open = [1, 2, 3, 4, 6].iter().cloned().collect();
+    assert_eq!(count_claims(&claims, &open, "alice", 10), 3);
+    assert_eq!(count_claims(&claims, &open, "alice", 6), 2);
+    assert_eq!(count_claims(&claims, &open, "carol", 10), 0);
+    assert_eq!(count_claims(&claims, &open, "Alice", 10), 3);
+}
+
+#[test]
+fn claim_limit() {
+    assert!(check_claim_limit(2, 1).is_ok());
+    assert!(check_claim_limit(2, 2).is_err());
+    assert!(check_claim_limit(0, 0).is_err());
+}
+
+#[test]
+fn release_own() {
+    assert!(check_release(Some("alice"), None, "alice", false).is_ok());
+    assert!(check_release(Some("alice"), Some("Alice"), "alice", false).is_ok());
+    assert!(check_release(Some("alice"), None, "ALICE", false).is_ok());
+}
+
+#[test]
+fn release_other_by_team_member() {
+    assert!(check_release(Some("bob"), Some("bob"), "alice", true).is_ok());
+}
+
+#[test]
+fn release_other_by_non_member() {
+    let err = check_release(Some("bob"), Some("bob"), "alice", false).unwrap_err();
+    assert_eq!(err.to_string(), "Cannot release another user's assignment");
+}
+
+#[test]
+fn release_wrong_user() {
+    let err = check_release(Some("bob"), Some("carol"), "alice", true).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "@carol is not assigned to this issue; it's assigned to @bob"
+    );
+    assert!(check_release(None, Some("carol"), "alice", true).is_err());
+}
+
+#[test]
+fn allowed_without_pool() {
+    assert!(check_allowed(&config(None), "alice").is_ok());
+}
+
+#[test]
+fn allowed_in_pool() {
+    let config = config(Some(&["alice", "bob"]));
+    assert!(check_allowed(&config, "bob").is_ok());
+    assert!(check_allowed(&config, "Alice").is_ok());
+}
+
+#[test]
+fn allowed_outside_pool() {
+    let err = check_allowed(&config(Some(&["alice", "bob"])), "carol").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "@carol cannot be assigned to issues in this repository; \
+         the eligible users are @alice, @bob"
+    );
+}
+
+#[test]
+fn assign_several() {
+    let usernames = ["alice", "bob", "Alice", "carol"]
+        .iter()
+        .map(|user| user.to_string())
+        .collect::<Vec<_>>();
+    let check = |user: &str| -> Result<(), Error> {
+        match user {
+            "bob" => failure::bail!("there is no GitHub user called `@bob`"),
+            _ => Ok(()),
+        }
+    };
+    assert_eq!(
+        split_assignees(false, &usernames, check).unwrap(),
+        (
+            vec!["alice", "carol"],
+            Some(
+                "Assigned @alice, @carol, but some of the users can't be:\n\n\
+                 - there is no GitHub user called `@bob`"
+                    .to_owned()
+            )
+        )
+    );
+    assert_eq!(
+        split_assignees(true, &usernames, check)
+            .unwrap_err()
+            .to_string(),
+        "Nobody was assigned, as some of the users can't be:\n\n\
+         - there is no GitHub user called `@bob`"
+    );
+    assert_eq!(
+        split_assignees(true, &usernames[..1], check).unwrap(),
+        (vec!["alice"], None)
+    );
+    assert!(split_assignees(false, &usernames[1..2], check).is_err());
+}
+
+#[cfg(test)]
+fn rotation() -> Vec<String> {
+    vec!["alice".to_owned(), "bob".to_owned(), "carol".to_owned()]
+}
+
+#[test]
+fn rotation_round_robin() {
+    let rotation = rotation();
+    assert_eq!(
+        pick_reviewer(&rotation, 0, "dave", |_| Ok(true)).unwrap(),
+        ("alice", 1)
+    );
+    assert_eq!(
+        pick_reviewer(&rotation, 2, "dave", |_| Ok(true)).unwrap(),
+        ("carol", 0)
+    );
+}
+
+#[test]
+fn rotation_skips_author() {
+    assert_eq!(
+        pick_reviewer(&rotation(), 1, "Bob", |_| Ok(true)).unwrap(),
+        ("carol", 0)
+    );
+}
+
+#[test]
+fn rotation_skips_unavailable() {
+    assert_eq!(
+        pick_reviewer(&rotation(), 0, "dave", |r| Ok(r == "bob")).unwrap(),
+        ("bob", 2)
+    );
+    assert!(pick_reviewer(&rotation(), 0, "alice", |r| Ok(r == "alice")).is_err());
+}
+
+#[test]
+fn rotation_empty() {
+    let err = pick_reviewer(&[], 0, "dave", |_| Ok(true)).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "This repository has no review rotation configured; use `assign @user` to pick a reviewer"
+    );
+}
+
+#[test]
+fn assignees_synced() {
+    // Assigned on GitHub, to one person or to several.
+    assert_eq!(
+        synced_assignee(None, &users(&["alice"]), "rustbot"),
+        Some(Some("alice".to_owned()))
+    );
+    assert_eq!(
+        synced_assignee(Some("alice"), &users(&["alice", "bob"]), "rustbot"),
+        Some(None)
+    );
+    // Reassigned, or unassigned.
+    assert_eq!(
+        synced_assignee(Some("alice"), &users(&["bob"]), "rustbot"),
+        Some(Some("bob".to_owned()))
+    );
+    assert_eq!(
+        synced_assignee(Some("alice"), &users(&[]), "rustbot"),
+        Some(None)
+    );
+    // Nothing changed for us.
+    assert_eq!(
+        synced_assignee(Some("alice"), &users(&["Alice"]), "rustbot"),
+        None
+    );
+    assert_eq!(synced_assignee(None, &users(&[]), "rustbot"), None);
+    // We're standing in for alice, whoever else is assigned.
+    assert_eq!(
+        synced_assignee(Some("alice"), &users(&["rustbot", "bob"]), "rustbot"),
+        None
+    );
+    // But the bot on its own stands in for nobody.
+    assert_eq!(synced_assignee(None, &users(&["rustbot"]), "rustbot"), None);
+}
+
+#[cfg(test)]
+use crate::github::labels;
+
+#[test]
+fn claimable_issues() {
+    let mut config = config(None);
+    assert!(check_claimable(&config, false, &[]).is_ok());
+    config.claimable_only_with = Some(vec!["E-mentor".to_owned(), "help wanted".to_owned()]);
+    assert!(check_claimable(&config, false, &labels(&["C-bug", "E-mentor"])).is_ok());
+    assert!(check_claimable(&config, false, &labels(&["help wanted"])).is_ok());
+    let err = check_claimable(&config, false, &labels(&["C-bug"])).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "This issue can't be claimed; only issues labeled `E-mentor` or `help wanted` can be"
+    );
+    // Team members are only exempt if the repository says so.
+    assert!(check_claimable(&config, true, &[]).is_err());
+    config.claimable_exempt_team = true;
+    assert!(check_claimable(&config, true, &[]).is_ok());
+    assert!(check_claimable(&config, false, &[]).is_err());
+}
+
+#[test]
+fn assignment_described() {
+    use chrono::TimeZone;
+    let config = config(None);
+    assert_eq!(describe_data(&config, None), "nobody");
+    assert_eq!(
+        describe_data(&config, Some(&AssignData::new(None))),
+        "nobody"
+    );
+    let mut data = AssignData {
+        user: Some("alice".to_owned()),
+        assigned_at: Utc.ymd(2019, 5, 1).and_hms(12, 0, 0),
+        last_activity: Utc.ymd(2019, 5, 3).and_hms(9, 0, 0),
+        status_requested_at: None,
+    };
+    assert_eq!(
+        describe_data(&config, Some(&data)),
+        "@alice since 2019-05-01 (last active 2019-05-03; status report due 2019-05-17)"
+    );
+    data.status_requested_at = Some(Utc.ymd(2019, 5, 17).and_hms(10, 0, 0));
+    assert_eq!(
+        describe_data(&config, Some(&data)),
+        "@alice since 2019-05-01 (last active 2019-05-03; status report requested on \
+         2019-05-17; released on 2019-05-31 without a reply)"
+    );
 }
@@ -0,0 +1,153 @@
+//! Purpose: Label pull requests by the files they change.
+//!
+//! The configuration maps globs of paths to the labels which pull requests changing a matching
+//! file get. When a pull request is opened or pushed to, we add any of those labels it doesn't
+//! have yet; labels are never removed, so people can still take off ones which don't fit.
+//!
+//! A renamed file matches by both its old and its new path.
+
+use crate::{
+    config::AutolabelConfig,
+    github::{Event, PullRequestAction, PullRequestFile},
+    handlers::{Context, Handler},
+};
+use failure::Error;
+use parser::command::relabel::LabelDelta;
+
+pub(super) struct AutolabelHandler;
+
+impl Handler for AutolabelHandler {
+    type Input = ();
+    type Config = AutolabelConfig;
+    const AUTOMATIC: bool = true;
+
+    fn parse_input(&self, _ctx: &Context, event: &Event) -> Result<Vec<Self::Input>, Error> {
+        let changed = match event {
+            Event::PullRequest(e) => {
+                e.action == PullRequestAction::Opened || e.action == PullRequestAction::Synchronize
+            }
+            _ => false,
+        };
+        if !changed {
+            return Ok(Vec::new());
+        }
+        Ok(vec![()])
+    }
+
+    fn handle_input(
+        &self,
+        ctx: &Context,
+        config: &AutolabelConfig,
+        event: &Event,
+        _input: (),
+    ) -> Result<(), Error> {
+        let event = if let Event::PullRequest(e) = event {
+            e
+        } else {
+            return Ok(());
+        };
+
+        let files = ctx.pull_request_files(event)?;
+        let deltas = matching_labels(config, &files)?
+            .into_iter()
+            .map(|label| LabelDelta::Add(label.as_str().into()))
+            .collect();
+        ctx.labels.apply(
+            &ctx.github,
+            event.repository.full_name(),
+            &event.issue,
+            deltas,
+        )?;
+
+        Ok(())
+    }
+}
+
+/// The labels for pull requests changing `files`, without duplicates.
+fn matching_labels(
+    config: &AutolabelConfig,
+    files: &[PullRequestFile],
+) -> Result<Vec<String>, Error> {
+    let mut labels = Vec::new();
+    for (pattern, pattern_labels) in &config.paths {
+        let pattern = glob::Pattern::new(pattern)?;
+        if !files.iter().any(|file| file.matches(&pattern)) {
+            continue;
+        }
+        for label in pattern_labels {
+            if !labels.contains(label) {
+                labels.push(label.clone());
+            }
+        }
+    }
+    Ok(labels)
+}
+
+#[cfg(test)]
+fn config(paths: &[(&str, &[&str])]) -> AutolabelConfig {
+    AutolabelConfig {
+        paths: paths
+            .iter()
+            .map(|(pattern, labels)| {
+                (
+                    pattern.to_string(),
+                    labels.iter().map(|l| l.to_string()).collect(),
+                )
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+fn file(filename: &str, previous_filename: Option<&str>) -> PullRequestFile {
+    PullRequestFile {
+        filename: filename.to_owned(),
+        previous_filename: previous_filename.map(|f| f.to_owned()),
+    }
+}
+
+#[test]
+fn labels_union() {
+    let config = config(&[
+        ("src/librustdoc/**", &["T-rustdoc"]),
+        ("src/libcore/**", &["T-libs", "A-core"]),
+        ("src/libstd/**", &["T-libs"]),
+        ("src/test/**", &["A-testsuite"]),
+    ]);
+    assert_eq!(
+        matching_labels(
+            &config,
+            &[
+                file("src/libcore/lib.rs", None),
+                file("src/libstd/lib.rs", None),
+                file("README.md", None),
+            ]
+        )
+        .unwrap(),
+        ["T-libs", "A-core"]
+    );
+}
+
+#[test]
+fn labels_renamed_file() {
+    let config = config(&[
+        ("src/libcore/**", &["T-libs"]),
+        ("library/**", &["A-library"]),
+    ]);
+    assert_eq!(
+        matching_labels(
+            &config,
+            &[file("library/core/lib.rs", Some("src/libcore/lib.rs"))]
+        )
+        .unwrap(),
+        ["A-library", "T-libs"]
+    );
+}
+
+#[test]
+fn labels_none_matching() {
+    let config = config(&[("src/librustdoc/**", &["T-rustdoc"])]);
+    assert!(matching_labels(&config, &[file("README.md", None)])
+        .unwrap()
+        .is_empty());
+}
@@ -0,0 +1,114 @@
+//! Purpose: Allow team members to mark issues as blocked on another issue.
+//!
+//! `@rustbot blocked on #1234` applies the configured label, and records the blocking issue in
+//! the state store; `@rustbot unblocked` removes the label again. If configured, we also comment
+//! on the blocking issue, so that it links back to the issues waiting on it.
+
+use crate::{
+    config::BlockedConfig,
+    db::StateKey,
+    github::Event,
+    handlers::{CommandError, Context, Handler},
+};
+use failure::Error;
+use parser::command::blocked::BlockedCommand;
+use parser::command::Command;
+
+pub(super) struct BlockedHandler;
+
+const NAMESPACE: &str = "BLOCKED";
+
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+struct BlockedData {
+    blocked_on: Option<u64>,
+}
+
+impl Handler for BlockedHandler {
+    type Input = BlockedCommand;
+    type Config = BlockedConfig;
+
+    fn parse_input(&self, ctx: &Context, event: &Event) -> Result<Vec<Self::Input>, Error> {
+        let event = if let Event::IssueComment(e) = event {
+            e
+        } else {
+            // not interested in other events
+            return Ok(Vec::new());
+        };
+
+        let mut commands = Vec::new();
+        super::comment_commands(ctx, event, |command| {
+            match command {
+                Command::Blocked(Ok(command)) => commands.push(command),
+                Command::Blocked(Err(err)) => {
+                    failure::bail!(
+                        "Parsing blocked command in [comment]({}) failed: {}\n\n{}",
+                        event.comment.html_url,
+                        err,
+                        err.snippet()
+                    );
+                }
+                _ => {}
+            }
+            Ok(())
+        })?;
+        Ok(commands)
+    }
+
+    fn handle_input(
+        &self,
+        ctx: &Context,
+        config: &BlockedConfig,
+        event: &Event,
+        cmd: BlockedCommand,
+    ) -> Result<(), Error> {
+        let event = if let Event::IssueComment(e) = event {
+            e
+        } else {
+            // not interested in other events
+            return Ok(());
+        };
+
+        let is_team_member = event
+            .comment
+            .user
+            .is_team_member(&ctx.github)
+            .unwrap_or(false);
+        if !is_team_member {
+            return Err(CommandError::Permission(
+                "Only Rust team members can mark issues as blocked".to_owned(),
+            )
+            .into());
+        }
+
+        let repo = event.repository.full_name();
+        let key = StateKey::new(repo, event.issue.number, NAMESPACE);
+        match cmd {
+            BlockedCommand::Blocked { issue } => {
+                if issue == event.issue.number {
+                    failure::bail!("An issue cannot be blocked on itself");
+                }
+                event.issue.add_label(&ctx.github, &config.label)?;
+                let previous = ctx.state.get::<BlockedData>(&key)?.unwrap_or_default();
+                ctx.state.set(
+                    &key,
+                    &BlockedData {
+                        blocked_on: Some(issue),
+                    },
+                )?;
+                if config.cross_reference && previous.blocked_on != Some(issue) {
+                    event.repository.post_comment(
+                        &ctx.github,
+                        issue,
+                        &format!("#{} is blocked on this issue.", event.issue.number),
+                    )?;
+                }
+            }
+            BlockedCommand::Unblocked => {
+                event.issue.remove_label(&ctx.github, &config.label)?;
+                ctx.state.set(&key, &BlockedData::default())?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,187 @@
+//! Purpose: Label issues by the checkboxes checked in their description, like those of an issue
+//! template asking whether it's a regression.
+//!
+//! The configuration maps the text of each checkbox (ignoring case) to the labels for issues with
+//! it checked. When an issue is opened, it gets the labels of its checked boxes. When its
+//! description is edited, checking a box adds its labels, and unchecking one which was checked
+//! removes them; boxes which didn't change (or were deleted) leave the labels alone, so people
+//! can still change the labels themselves. If several boxes have the same label, it's only
+//! removed once none of them are checked.
+//!
+//! Checkboxes are found by `parser::checkbox`. Our own edits of issue descriptions are ignored,
+//! so that they can't set off more of them.
+
+use crate::{
+    config::CheckboxLabelsConfig,
+    github::{Event, IssuesAction},
+    handlers::{Context, Handler},
+};
+use failure::Error;
+use parser::checkbox::checkboxes;
+use parser::command::relabel::LabelDelta;
+use std::collections::BTreeMap;
+
+pub(super) struct CheckboxLabelsHandler;
+
+impl Handler for CheckboxLabelsHandler {
+    type Input = ();
+    type Config = CheckboxLabelsConfig;
+    const AUTOMATIC: bool = true;
+
+    fn parse_input(&self, ctx: &Context, event: &Event) -> Result<Vec<Self::Input>, Error> {
+        let changed = match event {
+            Event::Issue(e) => {
+                (e.action == IssuesAction::Opened
+                    || (e.action == IssuesAction::Edited && e.previous_body().is_some()))
+                    && !e.sender.login.eq_ignore_ascii_case(&ctx.username)
+            }
+            _ => false,
+        };
+        if !changed {
+            return Ok(Vec::new());
+        }
+        Ok(vec![()])
+    }
+
+    fn handle_input(
+        &self,
+        ctx: &Context,
+        config: &CheckboxLabelsConfig,
+        event: &Event,
+        _input: (),
+    ) -> Result<(), Error> {
+        let event = if let Event::Issue(e) = event {
+            e
+        } else {
+            return Ok(());
+        };
+
+        let deltas = label_deltas(config, event.previous_body(), &event.issue.body);
+        ctx.labels.apply(
+            &ctx.github,
+            event.repository.full_name(),
+            &event.issue,
+            deltas,
+        )?;
+
+        Ok(())
+    }
+}
+
+/// Whether the labels of the configured checkboxes in `body` are checked, for each label of the
+/// boxes it has; a label is checked if any of its boxes are.
+fn checked_labels<'a>(config: &'a CheckboxLabelsConfig, body: &str) -> BTreeMap<&'a str, bool> {
+    let mut labels = BTreeMap::new();
+    for checkbox in checkboxes(body) {
+        for (text, box_labels) in &config.boxes {
+            if !text.trim().eq_ignore_ascii_case(checkbox.text) {
+                continue;
+            }
+            for label in box_labels {
+                *labels.entry(label.as_str()).or_insert(false) |= checkbox.checked;
+            }
+        }
+    }
+    labels
+}
+
+/// The label changes for an issue whose description is now `body`, and was `previous` (if this is
+/// an edit).
+fn label_deltas(
+    config: &CheckboxLabelsConfig,
+    previous: Option<&str>,
+    body: &str,
+) -> Vec<LabelDelta> {
+    let before = previous.map(|previous| checked_labels(config, previous));
+    let was_checked = |label: &str| {
+        before
+            .as_ref()
+            .map_or(false, |before| before.get(label) == Some(&true))
+    };
+    checked_labels(config, body)
+        .into_iter()
+        .filter_map(|(label, checked)| {
+            if checked && !was_checked(label) {
+                Some(LabelDelta::Add(label.into()))
+            } else if !checked && was_checked(label) {
+                Some(LabelDelta::Remove(label.into()))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+fn config() -> CheckboxLabelsConfig {
+    CheckboxLabelsConfig {
+        boxes: vec![
+            ("This is a regression", &["regression-untriaged"][..]),
+            (
+                "It only happens on nightly",
+                &["regression-untriaged", "E-nightly"][..],
+            ),
+            ("It's an ICE", &["I-ICE"][..]),
+        ]
+        .into_iter()
+        .map(|(text, labels)| {
+            (
+                text.to_owned(),
+                labels.iter().map(|l| l.to_string()).collect(),
+            )
+        })
+        .collect(),
+    }
+}
+
+#[cfg(test)]
+const TEMPLATE: &str = "### Checks\n\n\
+                        - [{regression}] This is a regression\n\
+                        - [{nightly}] It only happens on nightly\n\
+                        - [{ice}] it's an ice\n\
+                        - [x] I searched for duplicates\n";
+
+#[cfg(test)]
+fn body(regression: bool, nightly: bool, ice: bool) -> String {
+    let mark = |checked| if checked { "x" } else { " " };
+    TEMPLATE
+        .replace("{regression}", mark(regression))
+        .replace("{nightly}", mark(nightly))
+        .replace("{ice}", mark(ice))
+}
+
+#[test]
+fn labels_on_open() {
+    assert_eq!(
+        label_deltas(&config(), None, &body(false, false, true)),
+        [LabelDelta::Add("I-ICE".into())]
+    );
+    assert_eq!(
+        label_deltas(&config(), None, &body(true, true, false)),
+        [
+            LabelDelta::Add("E-nightly".into()),
+            LabelDelta::Add("regression-untriaged".into()),
+        ]
+    );
+    assert!(label_deltas(&config(), None, "No template here.").is_empty());
+}
+
+#[test]
+fn labels_on_edit() {
+    let config = config();
+    let before = body(true, false, true);
+    assert_eq!(
+        label_deltas(&config, Some(&before), &body(true, false, false)),
+        [LabelDelta::Remove("I-ICE".into())]
+    );
+    assert_eq!(
+        label_deltas(&config, Some(&before), &body(true, true, true)),
+        [LabelDelta::Add("E-nightly".into())]
+    );
+    // Still checked through the other box.
+    let before = body(true, true, false);
+    assert!(label_deltas(&config, Some(&before), &body(false, true, false)).is_empty());
+    assert!(label_deltas(&config, Some(&before), &before).is_empty());
+    // Deleting the boxes leaves the labels alone.
+    assert!(label_deltas(&config, Some(&before), "Never mind.").is_empty());
+}
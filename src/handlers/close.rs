@@ -0,0 +1,109 @@
+//! Permit closing and reopening issues without requiring "write" access to the repository.
+//!
+//! The issue author may close or reopen their own issue (unless the repository restricts this to
+//! team members); team members may close or reopen any issue.
+//!
+//! Close issues with `@rustbot close` and reopen them with `@rustbot reopen`.
+
+use crate::{
+    config::CloseConfig,
+    github::{Event, IssueState},
+    handlers::{CommandError, Context, Handler},
+};
+use failure::Error;
+use parser::command::close::CloseCommand;
+use parser::command::Command;
+
+pub(super) struct CloseHandler;
+
+impl Handler for CloseHandler {
+    type Input = CloseCommand;
+    type Config = CloseConfig;
+
+    fn parse_input(&self, ctx: &Context, event: &Event) -> Result<Vec<Self::Input>, Error> {
+        let event = if let Event::IssueComment(e) = event {
+            e
+        } else {
+            // not interested in other events
+            return Ok(Vec::new());
+        };
+
+        let mut commands = Vec::new();
+        super::comment_commands(ctx, event, |command| {
+            match command {
+                Command::Close(Ok(command)) => commands.push(command),
+                Command::Close(Err(err)) => {
+                    failure::bail!(
+                        "Parsing close command in [comment]({}) failed: {}\n\n{}",
+                        event.comment.html_url,
+                        err,
+                        err.snippet()
+                    );
+                }
+                _ => {}
+            }
+            Ok(())
+        })?;
+        Ok(commands)
+    }
+
+    fn handle_input(
+        &self,
+        ctx: &Context,
+        config: &CloseConfig,
+        event: &Event,
+        cmd: CloseCommand,
+    ) -> Result<(), Error> {
+        let event = if let Event::IssueComment(e) = event {
+            e
+        } else {
+            // not interested in other events
+            return Ok(());
+        };
+
+        let (state, verb, verbed) = match cmd {
+            CloseCommand::Close => (IssueState::Closed, "close", "closed"),
+            CloseCommand::Reopen => (IssueState::Open, "reopen", "reopened"),
+        };
+
+        let is_author = event.comment.user.login == event.issue.user.login;
+        if config.team_only || !is_author {
+            let is_team_member = event
+                .comment
+                .user
+                .is_team_member(&ctx.github)
+                .unwrap_or(false);
+            if !is_team_member {
+                if config.team_only {
+                    return Err(CommandError::Permission(format!(
+                        "Only Rust team members can {} issues",
+                        verb
+                    ))
+                    .into());
+                } else {
+                    return Err(CommandError::Permission(format!(
+                        "Only the issue author and Rust team members can {} this issue",
+                        verb
+                    ))
+                    .into());
+                }
+            }
+        }
+
+        if event.issue.state == state {
+            // Nothing to do; the issue is already in the requested state.
+            return Ok(());
+        }
+
+        event.issue.set_state(&ctx.github, state)?;
+        event.issue.post_comment(
+            &ctx.github,
+            &format!(
+                "This issue was {} by @{} via [this comment]({}).",
+                verbed, event.comment.user.login, event.comment.html_url
+            ),
+        )?;
+
+        Ok(())
+    }
+}
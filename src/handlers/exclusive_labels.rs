@@ -0,0 +1,110 @@
+//! Purpose: Keep issues from having more than one label of a kind, like two priorities.
+//!
+//! The configuration lists globs of labels (like `P-*` or `S-*`). When an issue or pull request
+//! is given a label matching one of them, however that happened, any other labels it has which
+//! match the same glob are removed, so the newest one wins. Labels we change ourselves are left
+//! alone, as the commands changing them take care of this already, and reacting to our own
+//! changes could go on forever.
+
+use crate::{
+    config::ExclusiveLabelsConfig,
+    github::{Event, IssuesAction, Label, PullRequestAction},
+    handlers::{Context, Handler},
+};
+use failure::Error;
+use parser::command::relabel::LabelDelta;
+
+pub(super) struct ExclusiveLabelsHandler;
+
+impl Handler for ExclusiveLabelsHandler {
+    /// The name of the label which was added.
+    type Input = String;
+    type Config = ExclusiveLabelsConfig;
+    const AUTOMATIC: bool = true;
+
+    fn parse_input(&self, ctx: &Context, event: &Event) -> Result<Vec<Self::Input>, Error> {
+        let label = match event {
+            Event::Issue(e) if e.action == IssuesAction::Labeled => &e.label,
+            Event::PullRequest(e) if e.action == PullRequestAction::Labeled => &e.label,
+            _ => return Ok(Vec::new()),
+        };
+        if event.actor() == ctx.username {
+            return Ok(Vec::new());
+        }
+        Ok(label.iter().map(|label| label.name.clone()).collect())
+    }
+
+    fn handle_input(
+        &self,
+        ctx: &Context,
+        config: &ExclusiveLabelsConfig,
+        event: &Event,
+        added: String,
+    ) -> Result<(), Error> {
+        let issue = match event.issue() {
+            Some(issue) => issue,
+            None => return Ok(()),
+        };
+
+        let deltas = displaced_labels(config, issue.labels(), &added)?
+            .into_iter()
+            .map(|label| LabelDelta::Remove(label.into()))
+            .collect();
+        ctx.labels
+            .apply(&ctx.github, event.repo_name(), issue, deltas)?;
+
+        Ok(())
+    }
+}
+
+/// The labels among `labels` to remove now that `added` has been added, as they're in one of its
+/// groups.
+fn displaced_labels<'a>(
+    config: &ExclusiveLabelsConfig,
+    labels: &'a [Label],
+    added: &str,
+) -> Result<Vec<&'a str>, Error> {
+    let mut displaced = Vec::new();
+    for pattern in &config.groups {
+        let pattern = glob::Pattern::new(pattern)?;
+        if !pattern.matches(added) {
+            continue;
+        }
+        for label in labels {
+            if label.name != added
+                && pattern.matches(&label.name)
+                && !displaced.contains(&label.name.as_str())
+            {
+                displaced.push(label.name.as_str());
+            }
+        }
+    }
+    Ok(displaced)
+}
+
+#[cfg(test)]
+use crate::github::labels;
+
+#[test]
+fn older_label_displaced() {
+    let config = ExclusiveLabelsConfig {
+        groups: vec!["P-*".to_owned(), "S-*".to_owned()],
+    };
+    assert_eq!(
+        displaced_labels(
+            &config,
+            &labels(&["C-bug", "P-low", "S-blocked", "P-high"]),
+            "P-high"
+        )
+        .unwrap(),
+        ["P-low"]
+    );
+    assert!(
+        displaced_labels(&config, &labels(&["P-low", "C-bug"]), "C-bug")
+            .unwrap()
+            .is_empty()
+    );
+    assert!(displaced_labels(&config, &labels(&["P-low"]), "P-low")
+        .unwrap()
+        .is_empty());
+}
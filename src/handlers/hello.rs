@@ -0,0 +1,149 @@
+//! Purpose: Let maintainers check that a deployment works.
+//!
+//! `@rustbot hello` replies with the version of the bot and the commit it was built from, along
+//! with the sections of the repository's `triagebot.toml` it has picked up. Unlike the health
+//! check, that goes all the way from receiving the webhook to posting a comment. Only team
+//! members can use it, so that it can't be used to spam issues.
+
+use crate::{
+    config::HelloConfig,
+    github::Event,
+    handlers::{CommandError, Context, Handler},
+};
+use failure::Error;
+use parser::command::hello::HelloCommand;
+use parser::command::Command;
+
+/// The commit the bot was built from; set `TRIAGEBOT_GIT_SHA` when building to fill it in.
+const GIT_SHA: Option<&str> = option_env!("TRIAGEBOT_GIT_SHA");
+
+pub(super) struct HelloHandler;
+
+impl Handler for HelloHandler {
+    type Input = HelloCommand;
+    type Config = HelloConfig;
+
+    fn parse_input(&self, ctx: &Context, event: &Event) -> Result<Vec<Self::Input>, Error> {
+        let event = if let Event::IssueComment(e) = event {
+            e
+        } else {
+            // not interested in other events
+            return Ok(Vec::new());
+        };
+
+        let mut commands = Vec::new();
+        super::comment_commands(ctx, event, |command| {
+            match command {
+                Command::Hello(Ok(command)) => commands.push(command),
+                Command::Hello(Err(err)) => {
+                    failure::bail!(
+                        "Parsing hello command in [comment]({}) failed: {}\n\n{}",
+                        event.comment.html_url,
+                        err,
+                        err.snippet()
+                    );
+                }
+                _ => {}
+            }
+            Ok(())
+        })?;
+        Ok(commands)
+    }
+
+    fn handle_input(
+        &self,
+        ctx: &Context,
+        _config: &HelloConfig,
+        event: &Event,
+        _cmd: HelloCommand,
+    ) -> Result<(), Error> {
+        let event = if let Event::IssueComment(e) = event {
+            e
+        } else {
+            // not interested in other events
+            return Ok(());
+        };
+
+        let is_team_member = event
+            .comment
+            .user
+            .is_team_member(&ctx.github)
+            .unwrap_or(false);
+        if !is_team_member {
+            return Err(CommandError::Permission(
+                "Only Rust team members can use `hello`".to_owned(),
+            )
+            .into());
+        }
+
+        let config = crate::config::get(&ctx.github, event.repository.full_name())?;
+        let text = render(
+            &ctx.username,
+            GIT_SHA.unwrap_or("unknown"),
+            &super::enabled_handlers(&config),
+            &config.settings(),
+            &config.invalid_sections(),
+        );
+        event.issue.post_comment(&ctx.github, &text)?;
+
+        Ok(())
+    }
+}
+
+fn render(
+    bot: &str,
+    commit: &str,
+    enabled: &[&str],
+    settings: &[&str],
+    invalid: &[&str],
+) -> String {
+    let list = |sections: &[&str]| {
+        sections
+            .iter()
+            .map(|section| format!("`{}`", section))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let mut text = format!(
+        "Hello! This is triagebot {} (commit `{}`), listening to `@{}` here.\n\n\
+         Enabled features: {}\n",
+        env!("CARGO_PKG_VERSION"),
+        commit,
+        bot,
+        list(enabled)
+    );
+    if !settings.is_empty() {
+        text.push_str(&format!("Other settings: {}\n", list(settings)));
+    }
+    if !invalid.is_empty() {
+        text.push_str(&format!(
+            "Disabled, as their configuration is invalid: {}\n",
+            list(invalid)
+        ));
+    }
+    text
+}
+
+#[test]
+fn render_sections() {
+    assert_eq!(
+        render("rustbot", "0123abc", &["hello", "relabel"], &[], &[]),
+        format!(
+            "Hello! This is triagebot {} (commit `0123abc`), listening to `@rustbot` here.\n\n\
+             Enabled features: `hello`, `relabel`\n",
+            env!("CARGO_PKG_VERSION")
+        )
+    );
+    assert!(render(
+        "rustbot",
+        "unknown",
+        &["hello"],
+        &["shortcut", "templates"],
+        &["ping"]
+    )
+    .ends_with(
+        "Enabled features: `hello`\n\
+         Other settings: `shortcut`, `templates`\n\
+         Disabled, as their configuration is invalid: `ping`\n"
+    ));
+}
@@ -0,0 +1,125 @@
+//! Purpose: Tell people which commands they can use.
+//!
+//! `@rustbot help` lists the commands enabled in the repository, and `@rustbot help <command>`
+//! shows how one of them is used. The help is generated from `parser::command::help::COMMANDS`,
+//! so commands which aren't enabled (or have been disabled everywhere) are left out.
+
+use crate::{
+    config::HelpConfig,
+    github::Event,
+    handlers::{usage, Context, Handler},
+};
+use failure::Error;
+use parser::command::help::{self, CommandHelp, HelpCommand};
+use parser::command::Command;
+
+pub(super) struct HelpHandler;
+
+impl Handler for HelpHandler {
+    type Input = HelpCommand;
+    type Config = HelpConfig;
+
+    fn parse_input(&self, ctx: &Context, event: &Event) -> Result<Vec<Self::Input>, Error> {
+        let event = if let Event::IssueComment(e) = event {
+            e
+        } else {
+            // not interested in other events
+            return Ok(Vec::new());
+        };
+
+        let mut commands = Vec::new();
+        super::comment_commands(ctx, event, |command| {
+            match command {
+                Command::Help(Ok(command)) => commands.push(command),
+                Command::Help(Err(err)) => {
+                    failure::bail!(
+                        "Parsing help command in [comment]({}) failed: {}\n\n{}",
+                        event.comment.html_url,
+                        err,
+                        err.snippet()
+                    );
+                }
+                _ => {}
+            }
+            Ok(())
+        })?;
+        Ok(commands)
+    }
+
+    fn handle_input(
+        &self,
+        ctx: &Context,
+        _config: &HelpConfig,
+        event: &Event,
+        cmd: HelpCommand,
+    ) -> Result<(), Error> {
+        let event = if let Event::IssueComment(e) = event {
+            e
+        } else {
+            // not interested in other events
+            return Ok(());
+        };
+
+        let config = crate::config::get(&ctx.github, event.repository.full_name())?;
+        let enabled = super::enabled_handlers(&config);
+        let text = match &cmd.command {
+            None => render_all(&ctx.username, &enabled),
+            Some(name) => match help::find(name) {
+                Some(command) if enabled.contains(&command.name) => {
+                    render_one(&ctx.username, command)
+                }
+                Some(command) => failure::bail!(
+                    "The `{}` command is not enabled in this repository",
+                    command.name
+                ),
+                None => failure::bail!(
+                    "There is no `{}` command; `@{} help` lists the commands",
+                    name,
+                    ctx.username
+                ),
+            },
+        };
+        event.issue.post_comment(&ctx.github, &text)?;
+
+        Ok(())
+    }
+}
+
+/// The help for the commands of the `enabled` handlers.
+fn render_all(bot: &str, enabled: &[&str]) -> String {
+    let mut text = "These are the commands you can use here:\n\n".to_owned();
+    for command in help::COMMANDS {
+        if enabled.contains(&command.name) {
+            text.push_str(&format!(
+                "- {}: {}\n",
+                usage(bot, command.syntax),
+                command.description
+            ));
+        }
+    }
+    text
+}
+
+fn render_one(bot: &str, command: &CommandHelp) -> String {
+    format!("{}: {}\n", usage(bot, command.syntax), command.description)
+}
+
+#[test]
+fn render_enabled_only() {
+    assert_eq!(
+        render_all("rustbot", &["close", "help", "welcome"]),
+        "These are the commands you can use here:\n\n\
+         - `@rustbot close` or `@rustbot reopen`: close or reopen the issue\n\
+         - `@rustbot help` or `@rustbot help <command>`: list the commands, or show how one is \
+         used\n"
+    );
+}
+
+#[test]
+fn render_single() {
+    assert_eq!(
+        render_one("rustbot", help::find("claim").unwrap()),
+        "`@rustbot claim` or `@rustbot release-assignment` or `@rustbot assign @<user>` or \
+         `@rustbot unassign @<user>`: assign someone to the issue, or release the assignment\n"
+    );
+}
@@ -0,0 +1,138 @@
+//! Purpose: Show what the bot has been asked to do on an issue, for when someone wonders why it
+//! was closed or labeled.
+//!
+//! `@rustbot log` replies with the commands run on the issue (as kept by `crate::audit`), with
+//! when they ran, who asked for them and whether they failed. Only the most recent `MAX_SHOWN`
+//! are listed, to keep the reply short. Everything in it came from comments on the issue, so
+//! anyone can ask.
+
+use crate::{
+    audit::{self, HistoryEntry, Outcome},
+    config::HistoryConfig,
+    github::Event,
+    handlers::{Context, Handler},
+};
+use failure::Error;
+use parser::command::log::LogCommand;
+use parser::command::Command;
+
+/// The most commands listed in a reply.
+const MAX_SHOWN: usize = 20;
+
+pub(super) struct HistoryHandler;
+
+impl Handler for HistoryHandler {
+    type Input = LogCommand;
+    type Config = HistoryConfig;
+
+    fn parse_input(&self, ctx: &Context, event: &Event) -> Result<Vec<Self::Input>, Error> {
+        let event = if let Event::IssueComment(e) = event {
+            e
+        } else {
+            // not interested in other events
+            return Ok(Vec::new());
+        };
+
+        let mut commands = Vec::new();
+        super::comment_commands(ctx, event, |command| {
+            match command {
+                Command::Log(Ok(command)) => commands.push(command),
+                Command::Log(Err(err)) => {
+                    failure::bail!(
+                        "Parsing log command in [comment]({}) failed: {}\n\n{}",
+                        event.comment.html_url,
+                        err,
+                        err.snippet()
+                    );
+                }
+                _ => {}
+            }
+            Ok(())
+        })?;
+        Ok(commands)
+    }
+
+    fn handle_input(
+        &self,
+        ctx: &Context,
+        _config: &HistoryConfig,
+        event: &Event,
+        _cmd: LogCommand,
+    ) -> Result<(), Error> {
+        let event = if let Event::IssueComment(e) = event {
+            e
+        } else {
+            // not interested in other events
+            return Ok(());
+        };
+
+        let entries = audit::history(&ctx.state, event.repository.full_name(), event.issue.number)?;
+        event.issue.post_comment(&ctx.github, &render(&entries))?;
+
+        Ok(())
+    }
+}
+
+fn render(entries: &[HistoryEntry]) -> String {
+    if entries.is_empty() {
+        return "No commands have been run on this issue.".to_owned();
+    }
+    let mut text = String::from("Commands run on this issue, most recent last:\n\n");
+    let hidden = entries.len().saturating_sub(MAX_SHOWN);
+    if hidden > 0 {
+        text.push_str(&format!("- ({} earlier commands not shown)\n", hidden));
+    }
+    for entry in &entries[hidden..] {
+        text.push_str(&format!(
+            "- {}: `{}`, by @{}",
+            entry.at.format("%Y-%m-%d %H:%M UTC"),
+            entry.handler,
+            entry.actor
+        ));
+        if let Outcome::Failed(error) = &entry.outcome {
+            // Errors may go on for paragraphs; the first line says what went wrong.
+            text.push_str(&format!(
+                " (failed: {})",
+                error.lines().next().unwrap_or_default()
+            ));
+        }
+        text.push('\n');
+    }
+    text
+}
+
+#[cfg(test)]
+fn entry(minute: u32, handler: &str, outcome: Outcome) -> HistoryEntry {
+    use chrono::TimeZone;
+    HistoryEntry {
+        at: chrono::Utc.ymd(2019, 5, 1).and_hms(12, minute, 0),
+        actor: "alice".to_owned(),
+        handler: handler.to_owned(),
+        action: String::new(),
+        outcome,
+    }
+}
+
+#[test]
+fn render_history() {
+    assert_eq!(render(&[]), "No commands have been run on this issue.");
+    assert_eq!(
+        render(&[
+            entry(0, "relabel", Outcome::Succeeded),
+            entry(
+                5,
+                "close",
+                Outcome::Failed("Only team members\n\nmore".to_owned())
+            ),
+        ]),
+        "Commands run on this issue, most recent last:\n\n\
+         - 2019-05-01 12:00 UTC: `relabel`, by @alice\n\
+         - 2019-05-01 12:05 UTC: `close`, by @alice (failed: Only team members)\n"
+    );
+    let entries = (0..MAX_SHOWN as u32 + 3)
+        .map(|minute| entry(minute, "ping", Outcome::Succeeded))
+        .collect::<Vec<_>>();
+    let text = render(&entries);
+    assert!(text.contains("- (3 earlier commands not shown)\n- 2019-05-01 12:03 UTC"));
+    assert_eq!(text.lines().count(), 2 + 1 + MAX_SHOWN);
+}
@@ -0,0 +1,101 @@
+//! Purpose: Do more when certain labels are added, as configured per label.
+//!
+//! The configuration maps labels to their effects: a comment to post, other labels to add, and
+//! who to assign. The effects of a label happen the first time it's added to an issue, whether
+//! by a command or by hand, and never again for that issue; which labels' effects have happened
+//! is recorded in the state store.
+//!
+//! Since adding labels is one of the effects, one label's effects can lead to another's. That
+//! always ends, as each label's effects happen at most once per issue.
+
+use crate::{
+    config::LabelEffectsConfig,
+    db::StateKey,
+    github::{Event, IssuesAction, PullRequestAction},
+    handlers::{Context, Handler},
+};
+use failure::Error;
+use parser::command::relabel::LabelDelta;
+
+pub(super) struct LabelEffectsHandler;
+
+const NAMESPACE: &str = "LABEL_EFFECTS";
+
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+struct LabelEffectsData {
+    /// The labels whose effects have happened.
+    applied: Vec<String>,
+}
+
+impl Handler for LabelEffectsHandler {
+    /// The name of the label which was added.
+    type Input = String;
+    type Config = LabelEffectsConfig;
+    const AUTOMATIC: bool = true;
+
+    fn parse_input(&self, _ctx: &Context, event: &Event) -> Result<Vec<Self::Input>, Error> {
+        // Unlike for most events, ours count too: adding a label with a command should have the
+        // same effects as adding it by hand.
+        let label = match event {
+            Event::Issue(e) if e.action == IssuesAction::Labeled => &e.label,
+            Event::PullRequest(e) if e.action == PullRequestAction::Labeled => &e.label,
+            _ => return Ok(Vec::new()),
+        };
+        Ok(label.iter().map(|label| label.name.clone()).collect())
+    }
+
+    fn handle_input(
+        &self,
+        ctx: &Context,
+        config: &LabelEffectsConfig,
+        event: &Event,
+        label: String,
+    ) -> Result<(), Error> {
+        let effects = match config.labels.get(&label) {
+            Some(effects) => effects,
+            None => return Ok(()),
+        };
+        let issue = match event.issue() {
+            Some(issue) => issue,
+            None => return Ok(()),
+        };
+        let repo = event.repo_name();
+        let key = StateKey::new(repo, issue.number, NAMESPACE);
+        let mut data: LabelEffectsData = ctx.state.get(&key)?.unwrap_or_default();
+        if data.applied.contains(&label) {
+            return Ok(());
+        }
+
+        if let Some(comment) = &effects.comment {
+            super::post_comment_once(
+                ctx,
+                repo,
+                issue,
+                &format!("label-effects-{}", label),
+                &comment.replace("{user}", event.actor()),
+            )?;
+        }
+        if !effects.add_labels.is_empty() {
+            let deltas = effects
+                .add_labels
+                .iter()
+                .map(|label| LabelDelta::Add(label.as_str().into()))
+                .collect();
+            ctx.labels.apply(&ctx.github, repo, issue, deltas)?;
+        }
+        if let Some(user) = &effects.assign {
+            if !issue
+                .assignees()
+                .iter()
+                .any(|assignee| &assignee.login == user)
+            {
+                issue.set_assignee(&ctx.github, user)?;
+            }
+        }
+
+        data.applied.push(label);
+        ctx.state.set(&key, &data)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,113 @@
+//! Purpose: Allow team members to lock heated (or otherwise derailed) conversations.
+//!
+//! `@rustbot lock` locks the issue, so that only collaborators can comment on it, optionally with
+//! one of GitHub's reasons (`@rustbot lock as too heated`), and posts the `locked` message saying
+//! so. `@rustbot unlock` unlocks the issue again. Locking a locked issue, or unlocking an
+//! unlocked one, does nothing.
+
+use crate::{
+    config::LockConfig,
+    github::Event,
+    handlers::{CommandError, Context, Handler},
+};
+use failure::Error;
+use parser::command::lock::{LockCommand, LockReason};
+use parser::command::Command;
+
+pub(super) struct LockHandler;
+
+impl Handler for LockHandler {
+    type Input = LockCommand;
+    type Config = LockConfig;
+
+    fn parse_input(&self, ctx: &Context, event: &Event) -> Result<Vec<Self::Input>, Error> {
+        let event = if let Event::IssueComment(e) = event {
+            e
+        } else {
+            // not interested in other events
+            return Ok(Vec::new());
+        };
+
+        let mut commands = Vec::new();
+        super::comment_commands(ctx, event, |command| {
+            match command {
+                Command::Lock(Ok(command)) => commands.push(command),
+                Command::Lock(Err(err)) => {
+                    failure::bail!(
+                        "Parsing lock command in [comment]({}) failed: {}\n\n{}",
+                        event.comment.html_url,
+                        err,
+                        err.snippet()
+                    );
+                }
+                _ => {}
+            }
+            Ok(())
+        })?;
+        Ok(commands)
+    }
+
+    fn handle_input(
+        &self,
+        ctx: &Context,
+        _config: &LockConfig,
+        event: &Event,
+        cmd: LockCommand,
+    ) -> Result<(), Error> {
+        let event = if let Event::IssueComment(e) = event {
+            e
+        } else {
+            // not interested in other events
+            return Ok(());
+        };
+
+        let is_team_member = event
+            .comment
+            .user
+            .is_team_member(&ctx.github)
+            .unwrap_or(false);
+        if !is_team_member {
+            return Err(CommandError::Permission(
+                "Only Rust team members can lock and unlock conversations".to_owned(),
+            )
+            .into());
+        }
+
+        match cmd {
+            LockCommand::Lock(reason) => {
+                if event.issue.locked {
+                    return Ok(());
+                }
+                event.issue.lock(&ctx.github, reason)?;
+                let message = super::render_template(
+                    ctx,
+                    event.repository.full_name(),
+                    "locked",
+                    &[
+                        ("user", &event.comment.user.login),
+                        ("reason", describe(reason)),
+                    ],
+                )?;
+                event.issue.post_comment(&ctx.github, &message)?;
+            }
+            LockCommand::Unlock => {
+                if event.issue.locked {
+                    event.issue.unlock(&ctx.github)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// How the `locked` message puts the reason for locking.
+fn describe(reason: Option<LockReason>) -> &'static str {
+    reason.map_or("no reason given", LockReason::as_str)
+}
+
+#[test]
+fn describe_reasons() {
+    assert_eq!(describe(None), "no reason given");
+    assert_eq!(describe(Some(LockReason::TooHeated)), "too heated");
+}
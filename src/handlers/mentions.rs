@@ -0,0 +1,176 @@
+//! Purpose: Ping people when pull requests change the files they look after.
+//!
+//! The configuration maps globs of paths to the people to ping (and, optionally, a message for
+//! them) when a pull request changes a matching file. We post a single comment for all the
+//! paths which matched, mentioning each person once.
+//!
+//! The paths we've mentioned people for are recorded in the state store, so that pushing to the
+//! pull request only pings for paths it hadn't touched before.
+
+use crate::{
+    config::MentionsConfig,
+    db::StateKey,
+    github::{Event, PullRequestAction, PullRequestFile},
+    handlers::{Context, Handler},
+};
+use failure::Error;
+
+pub(super) struct MentionsHandler;
+
+const NAMESPACE: &str = "MENTIONS";
+
+impl Handler for MentionsHandler {
+    type Input = ();
+    type Config = MentionsConfig;
+    const AUTOMATIC: bool = true;
+
+    fn parse_input(&self, _ctx: &Context, event: &Event) -> Result<Vec<Self::Input>, Error> {
+        let changed = match event {
+            Event::PullRequest(e) => {
+                e.action == PullRequestAction::Opened || e.action == PullRequestAction::Synchronize
+            }
+            _ => false,
+        };
+        if !changed {
+            return Ok(Vec::new());
+        }
+        Ok(vec![()])
+    }
+
+    fn handle_input(
+        &self,
+        ctx: &Context,
+        config: &MentionsConfig,
+        event: &Event,
+        _input: (),
+    ) -> Result<(), Error> {
+        let event = if let Event::PullRequest(e) = event {
+            e
+        } else {
+            return Ok(());
+        };
+
+        let key = StateKey::new(event.repository.full_name(), event.issue.number, NAMESPACE);
+        let mut mentioned = ctx.state.get::<Vec<String>>(&key)?.unwrap_or_default();
+        let files = ctx.pull_request_files(event)?;
+        let paths = matching_paths(config, &files)?
+            .into_iter()
+            .filter(|path| !mentioned.contains(path))
+            .collect::<Vec<_>>();
+        if paths.is_empty() {
+            return Ok(());
+        }
+
+        event
+            .issue
+            .post_comment(&ctx.github, &render(config, &paths))?;
+        mentioned.extend(paths);
+        ctx.state.set(&key, &mentioned)?;
+
+        Ok(())
+    }
+}
+
+/// The configured globs matching any of `files`.
+fn matching_paths(
+    config: &MentionsConfig,
+    files: &[PullRequestFile],
+) -> Result<Vec<String>, Error> {
+    let mut paths = Vec::new();
+    for path in config.paths.keys() {
+        let pattern = glob::Pattern::new(path)?;
+        if files.iter().any(|file| file.matches(&pattern)) {
+            paths.push(path.clone());
+        }
+    }
+    Ok(paths)
+}
+
+/// The comment for changes to `paths`, which must all be configured.
+fn render(config: &MentionsConfig, paths: &[String]) -> String {
+    let mut text = String::from("Some changes occurred in:\n\n");
+    let mut cc: Vec<&str> = Vec::new();
+    for path in paths {
+        let path_config = &config.paths[path];
+        match &path_config.message {
+            Some(message) => text.push_str(&format!("- `{}`: {}\n", path, message)),
+            None => text.push_str(&format!("- `{}`\n", path)),
+        }
+        for user in &path_config.cc {
+            if !cc.iter().any(|u| u.eq_ignore_ascii_case(user)) {
+                cc.push(user);
+            }
+        }
+    }
+    if !cc.is_empty() {
+        text.push_str(&format!(
+            "\ncc {}\n",
+            cc.iter()
+                .map(|user| format!("@{}", user))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+    text
+}
+
+#[cfg(test)]
+fn config() -> MentionsConfig {
+    let path = |message: Option<&str>, cc: &[&str]| crate::config::MentionsPathConfig {
+        message: message.map(|m| m.to_owned()),
+        cc: cc.iter().map(|u| u.to_string()).collect(),
+    };
+    MentionsConfig {
+        paths: vec![
+            (
+                "src/librustdoc/**".to_owned(),
+                path(Some("rustdoc was changed"), &["alice", "bob"]),
+            ),
+            ("src/libcore/**".to_owned(), path(None, &["Bob", "carol"])),
+            ("src/libstd/**".to_owned(), path(None, &[])),
+        ]
+        .into_iter()
+        .collect(),
+    }
+}
+
+#[test]
+fn paths_matching() {
+    let files = vec![
+        PullRequestFile {
+            filename: "src/libcore/lib.rs".to_owned(),
+            previous_filename: None,
+        },
+        PullRequestFile {
+            filename: "README.md".to_owned(),
+            previous_filename: Some("src/librustdoc/README.md".to_owned()),
+        },
+    ];
+    assert_eq!(
+        matching_paths(&config(), &files).unwrap(),
+        ["src/libcore/**", "src/librustdoc/**"]
+    );
+}
+
+#[test]
+fn render_dedups_mentions() {
+    assert_eq!(
+        render(
+            &config(),
+            &["src/libcore/**".to_owned(), "src/librustdoc/**".to_owned()]
+        ),
+        "Some changes occurred in:\n\n\
+         - `src/libcore/**`\n\
+         - `src/librustdoc/**`: rustdoc was changed\n\
+         \n\
+         cc @Bob, @carol, @alice\n"
+    );
+}
+
+#[test]
+fn render_without_mentions() {
+    assert_eq!(
+        render(&config(), &["src/libstd/**".to_owned()]),
+        "Some changes occurred in:\n\n- `src/libstd/**`\n"
+    );
+}
@@ -0,0 +1,152 @@
+//! Purpose: Allow team members to set the milestone of issues.
+//!
+//! `@rustbot milestone 1.40.0` puts the issue in the milestone with that title (quote titles
+//! with spaces in them), and `@rustbot milestone none` takes it out of its milestone.
+
+use crate::{
+    config::MilestoneConfig,
+    github::{Event, IssueState, Milestone},
+    handlers::{CommandError, Context, Handler},
+};
+use failure::Error;
+use parser::command::milestone::MilestoneCommand;
+use parser::command::Command;
+
+pub(super) struct MilestoneHandler;
+
+impl Handler for MilestoneHandler {
+    type Input = MilestoneCommand;
+    type Config = MilestoneConfig;
+
+    fn parse_input(&self, ctx: &Context, event: &Event) -> Result<Vec<Self::Input>, Error> {
+        let event = if let Event::IssueComment(e) = event {
+            e
+        } else {
+            // not interested in other events
+            return Ok(Vec::new());
+        };
+
+        let mut commands = Vec::new();
+        super::comment_commands(ctx, event, |command| {
+            match command {
+                Command::Milestone(Ok(command)) => commands.push(command),
+                Command::Milestone(Err(err)) => {
+                    failure::bail!(
+                        "Parsing milestone command in [comment]({}) failed: {}\n\n{}",
+                        event.comment.html_url,
+                        err,
+                        err.snippet()
+                    );
+                }
+                _ => {}
+            }
+            Ok(())
+        })?;
+        Ok(commands)
+    }
+
+    fn handle_input(
+        &self,
+        ctx: &Context,
+        _config: &MilestoneConfig,
+        event: &Event,
+        cmd: MilestoneCommand,
+    ) -> Result<(), Error> {
+        let event = if let Event::IssueComment(e) = event {
+            e
+        } else {
+            // not interested in other events
+            return Ok(());
+        };
+
+        let is_team_member = event
+            .comment
+            .user
+            .is_team_member(&ctx.github)
+            .unwrap_or(false);
+        if !is_team_member {
+            return Err(CommandError::Permission(
+                "Only Rust team members can set milestones".to_owned(),
+            )
+            .into());
+        }
+
+        let current = event.issue.milestone().map(|m| m.number);
+        let number = match cmd {
+            MilestoneCommand::Set { title } => {
+                let milestones = event.repository.milestones(&ctx.github)?;
+                match find_milestone(&milestones, &title) {
+                    Some(milestone) => Some(milestone.number),
+                    None => failure::bail!(
+                        "`{}` is not a milestone in this repository; the open milestones are {}",
+                        title,
+                        open_milestones(&milestones)
+                    ),
+                }
+            }
+            MilestoneCommand::Clear => None,
+        };
+        if number != current {
+            event.issue.set_milestone(&ctx.github, number)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The milestone titled `title`, preferring an exact match to one differing only in case.
+fn find_milestone<'a>(milestones: &'a [Milestone], title: &str) -> Option<&'a Milestone> {
+    milestones.iter().find(|m| m.title == title).or_else(|| {
+        milestones
+            .iter()
+            .find(|m| m.title.eq_ignore_ascii_case(title))
+    })
+}
+
+fn open_milestones(milestones: &[Milestone]) -> String {
+    let open = milestones
+        .iter()
+        .filter(|m| m.state == IssueState::Open)
+        .map(|m| format!("`{}`", m.title))
+        .collect::<Vec<_>>();
+    if open.is_empty() {
+        "none".to_owned()
+    } else {
+        open.join(", ")
+    }
+}
+
+#[cfg(test)]
+fn milestones() -> Vec<Milestone> {
+    vec![
+        Milestone {
+            number: 1,
+            title: "1.39.0".to_owned(),
+            state: IssueState::Closed,
+        },
+        Milestone {
+            number: 2,
+            title: "1.40.0".to_owned(),
+            state: IssueState::Open,
+        },
+        Milestone {
+            number: 3,
+            title: "Rust 2018".to_owned(),
+            state: IssueState::Open,
+        },
+    ]
+}
+
+#[test]
+fn find_by_title() {
+    let milestones = milestones();
+    assert_eq!(find_milestone(&milestones, "1.40.0").unwrap().number, 2);
+    assert_eq!(find_milestone(&milestones, "rust 2018").unwrap().number, 3);
+    assert!(find_milestone(&milestones, "1.41.0").is_none());
+}
+
+#[test]
+fn lists_open_milestones() {
+    assert_eq!(open_milestones(&milestones()), "`1.40.0`, `Rust 2018`");
+    assert_eq!(open_milestones(&[]), "none");
+}
@@ -0,0 +1,173 @@
+//! Purpose: Allow team members to ask for a minimal reproduction of an issue (typically, an ICE).
+//!
+//! `@rustbot minimize` applies the configured label (`E-needs-mcve` by default) and posts the
+//! `minimize` message, asking the reporter to cut the code triggering the issue down as far as
+//! they can. The request is recorded in the state store until someone posts a comment
+//! containing the configured marker (`MCVE` by default, ignoring case), at which point the label
+//! is removed again.
+
+use crate::{
+    config::MinimizeConfig,
+    db::StateKey,
+    github::{Event, IssueCommentAction},
+    handlers::{CommandError, Context, Handler},
+};
+use chrono::{DateTime, Utc};
+use failure::Error;
+use parser::command::minimize::MinimizeCommand;
+use parser::command::Command;
+
+pub(super) struct MinimizeHandler;
+
+const NAMESPACE: &str = "MINIMIZE";
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct MinimizeData {
+    /// Who asked for the reproduction, and when; `None` once it has been posted.
+    requested: Option<(String, DateTime<Utc>)>,
+}
+
+#[derive(Debug)]
+pub(super) enum MinimizeInput {
+    Request(MinimizeCommand),
+    /// Someone commented, possibly with the reproduction.
+    Commented,
+}
+
+impl Handler for MinimizeHandler {
+    type Input = MinimizeInput;
+    type Config = MinimizeConfig;
+
+    fn parse_input(&self, ctx: &Context, event: &Event) -> Result<Vec<Self::Input>, Error> {
+        let event = if let Event::IssueComment(e) = event {
+            e
+        } else {
+            // not interested in other events
+            return Ok(Vec::new());
+        };
+
+        let mut commands = Vec::new();
+        super::comment_commands(ctx, event, |command| {
+            match command {
+                Command::Minimize(Ok(command)) => commands.push(MinimizeInput::Request(command)),
+                Command::Minimize(Err(err)) => {
+                    failure::bail!(
+                        "Parsing minimize command in [comment]({}) failed: {}\n\n{}",
+                        event.comment.html_url,
+                        err,
+                        err.snippet()
+                    );
+                }
+                _ => {}
+            }
+            Ok(())
+        })?;
+
+        if commands.is_empty()
+            && event.action == IssueCommentAction::Created
+            && event.comment.user.login != ctx.username
+        {
+            commands.push(MinimizeInput::Commented);
+        }
+        Ok(commands)
+    }
+
+    fn requested(&self, input: &MinimizeInput) -> bool {
+        // Nobody asked for their comments to be checked, so repositories which haven't
+        // configured this are left alone.
+        match input {
+            MinimizeInput::Request(_) => true,
+            MinimizeInput::Commented => false,
+        }
+    }
+
+    fn handle_input(
+        &self,
+        ctx: &Context,
+        config: &MinimizeConfig,
+        event: &Event,
+        input: MinimizeInput,
+    ) -> Result<(), Error> {
+        let event = if let Event::IssueComment(e) = event {
+            e
+        } else {
+            // not interested in other events
+            return Ok(());
+        };
+
+        let repo = event.repository.full_name();
+        let key = StateKey::new(repo, event.issue.number, NAMESPACE);
+        match input {
+            MinimizeInput::Request(_) => {
+                let is_team_member = event
+                    .comment
+                    .user
+                    .is_team_member(&ctx.github)
+                    .unwrap_or(false);
+                if !is_team_member {
+                    return Err(CommandError::Permission(
+                        "Only Rust team members can ask for a minimal reproduction".to_owned(),
+                    )
+                    .into());
+                }
+                if let Some(MinimizeData { requested: Some(_) }) = ctx.state.get(&key)? {
+                    // Already asked for.
+                    return Ok(());
+                }
+
+                event.issue.add_label(&ctx.github, &config.label)?;
+                let message = super::render_template(
+                    ctx,
+                    repo,
+                    "minimize",
+                    &[
+                        ("user", &event.comment.user.login),
+                        ("reporter", &event.issue.user.login),
+                        ("marker", &config.marker),
+                    ],
+                )?;
+                event.issue.post_comment(&ctx.github, &message)?;
+                ctx.state.set(
+                    &key,
+                    &MinimizeData {
+                        requested: Some((event.comment.user.login.clone(), Utc::now())),
+                    },
+                )?;
+            }
+            MinimizeInput::Commented => {
+                if !has_marker(config, &event.comment.body) {
+                    return Ok(());
+                }
+                match ctx.state.get::<MinimizeData>(&key)? {
+                    Some(MinimizeData { requested: Some(_) }) => {}
+                    _ => return Ok(()),
+                }
+                event.issue.remove_label(&ctx.github, &config.label)?;
+                ctx.state.set(&key, &MinimizeData { requested: None })?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether `comment` has the reproduction, by containing the configured marker.
+fn has_marker(config: &MinimizeConfig, comment: &str) -> bool {
+    comment
+        .to_lowercase()
+        .contains(&config.marker.to_lowercase())
+}
+
+#[test]
+fn marker_found() {
+    let config = MinimizeConfig {
+        label: "E-needs-mcve".to_owned(),
+        marker: "MCVE".to_owned(),
+    };
+    assert!(has_marker(
+        &config,
+        "Here's an mcve:\n\n```rust\nfn main() {}\n```"
+    ));
+    assert!(has_marker(&config, "MCVE: https://play.rust-lang.org/"));
+    assert!(!has_marker(&config, "I can't reproduce this any more."));
+}
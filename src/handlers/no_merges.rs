@@ -0,0 +1,143 @@
+//! Purpose: Warn about merge commits in pull requests.
+//!
+//! When a pull request is opened or pushed to and has commits with more than one parent, we
+//! post the configured message (which should explain how to rebase them away). We keep a single
+//! warning comment per pull request: later pushes edit it, and once the merge commits are gone
+//! it says so instead.
+
+use crate::{
+    config::NoMergesConfig,
+    db::StateKey,
+    github::{Event, PullRequestAction, PullRequestCommit},
+    handlers::{Context, Handler},
+};
+use failure::Error;
+
+pub(super) struct NoMergesHandler;
+
+const NAMESPACE: &str = "NO_MERGES";
+
+const RESOLVED: &str = "The merge commits have been removed, thanks!";
+
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+struct NoMergesData {
+    /// The warning comment, if we've posted one.
+    comment_id: Option<usize>,
+    /// The merge commits the comment currently lists.
+    merges: Vec<String>,
+}
+
+impl Handler for NoMergesHandler {
+    type Input = ();
+    type Config = NoMergesConfig;
+    const AUTOMATIC: bool = true;
+
+    fn parse_input(&self, _ctx: &Context, event: &Event) -> Result<Vec<Self::Input>, Error> {
+        let changed = match event {
+            Event::PullRequest(e) => {
+                e.action == PullRequestAction::Opened || e.action == PullRequestAction::Synchronize
+            }
+            _ => false,
+        };
+        if !changed {
+            return Ok(Vec::new());
+        }
+        Ok(vec![()])
+    }
+
+    fn handle_input(
+        &self,
+        ctx: &Context,
+        config: &NoMergesConfig,
+        event: &Event,
+        _input: (),
+    ) -> Result<(), Error> {
+        let event = if let Event::PullRequest(e) = event {
+            e
+        } else {
+            return Ok(());
+        };
+
+        let key = StateKey::new(event.repository.full_name(), event.issue.number, NAMESPACE);
+        let mut data = ctx.state.get::<NoMergesData>(&key)?.unwrap_or_default();
+        let commits = ctx
+            .github
+            .pull_request_commits(&event.repository, event.issue.number)?;
+        let merges = merge_commits(&commits);
+        if merges == data.merges {
+            return Ok(());
+        }
+
+        let body = if merges.is_empty() {
+            RESOLVED.to_owned()
+        } else {
+            render(config, &merges)
+        };
+        match data.comment_id {
+            Some(id) => event.issue.edit_comment(&ctx.github, id, &body)?,
+            None => data.comment_id = event.issue.post_editable_comment(&ctx.github, &body)?,
+        }
+        data.merges = merges;
+        ctx.state.set(&key, &data)?;
+
+        Ok(())
+    }
+}
+
+/// The shas of the merge commits among `commits`.
+fn merge_commits(commits: &[PullRequestCommit]) -> Vec<String> {
+    commits
+        .iter()
+        .filter(|commit| commit.parents.len() > 1)
+        .map(|commit| commit.sha.clone())
+        .collect()
+}
+
+fn render(config: &NoMergesConfig, merges: &[String]) -> String {
+    let mut text = format!("{}\n\nThe merge commits are:\n\n", config.message);
+    for sha in merges {
+        text.push_str(&format!("- {}\n", sha));
+    }
+    text
+}
+
+#[cfg(test)]
+fn commit(sha: &str, parents: &[&str]) -> PullRequestCommit {
+    PullRequestCommit {
+        sha: sha.to_owned(),
+        parents: parents
+            .iter()
+            .map(|sha| crate::github::CommitParent {
+                sha: sha.to_string(),
+            })
+            .collect(),
+        author: None,
+        commit: crate::github::CommitDetails {
+            committer: crate::github::CommitSignature {
+                date: chrono::Utc::now(),
+            },
+        },
+    }
+}
+
+#[test]
+fn finds_merge_commits() {
+    let commits = [
+        commit("b", &["a"]),
+        commit("c", &["b", "x"]),
+        commit("d", &["c"]),
+    ];
+    assert_eq!(merge_commits(&commits), ["c"]);
+    assert!(merge_commits(&commits[..1]).is_empty());
+}
+
+#[test]
+fn render_lists_merges() {
+    let config = NoMergesConfig {
+        message: "Please rebase.".to_owned(),
+    };
+    assert_eq!(
+        render(&config, &["c".to_owned(), "e".to_owned()]),
+        "Please rebase.\n\nThe merge commits are:\n\n- c\n- e\n"
+    );
+}
@@ -0,0 +1,166 @@
+//! Purpose: Allow team members to keep a summary of an issue in its top comment.
+//!
+//! Each `@rustbot note <text>` appends a bullet with the text (and who wrote it, and when) to a
+//! list of notes in the issue body. The notes themselves are kept in the state store, and the
+//! list is re-rendered from them.
+
+use crate::{
+    config::NoteConfig,
+    db::StateKey,
+    github::Event,
+    handlers::{CommandError, Context, Handler},
+    interactions::EditIssueBody,
+};
+use chrono::{DateTime, Utc};
+use failure::Error;
+use parser::command::note::NoteCommand;
+use parser::command::Command;
+
+pub(super) struct NoteHandler;
+
+const NAMESPACE: &str = "NOTES";
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct Note {
+    author: String,
+    at: DateTime<Utc>,
+    /// The comment the note was made in.
+    url: String,
+    text: String,
+}
+
+impl Handler for NoteHandler {
+    type Input = NoteCommand;
+    type Config = NoteConfig;
+
+    fn parse_input(&self, ctx: &Context, event: &Event) -> Result<Vec<Self::Input>, Error> {
+        let event = if let Event::IssueComment(e) = event {
+            e
+        } else {
+            // not interested in other events
+            return Ok(Vec::new());
+        };
+
+        let mut commands = Vec::new();
+        super::comment_commands(ctx, event, |command| {
+            match command {
+                Command::Note(Ok(command)) => commands.push(command),
+                Command::Note(Err(err)) => {
+                    failure::bail!(
+                        "Parsing note command in [comment]({}) failed: {}\n\n{}",
+                        event.comment.html_url,
+                        err,
+                        err.snippet()
+                    );
+                }
+                _ => {}
+            }
+            Ok(())
+        })?;
+        Ok(commands)
+    }
+
+    fn handle_input(
+        &self,
+        ctx: &Context,
+        _config: &NoteConfig,
+        event: &Event,
+        cmd: NoteCommand,
+    ) -> Result<(), Error> {
+        let event = if let Event::IssueComment(e) = event {
+            e
+        } else {
+            // not interested in other events
+            return Ok(());
+        };
+
+        let is_team_member = event
+            .comment
+            .user
+            .is_team_member(&ctx.github)
+            .unwrap_or(false);
+        if !is_team_member {
+            return Err(CommandError::Permission(
+                "Only Rust team members can add notes".to_owned(),
+            )
+            .into());
+        }
+
+        let key = StateKey::new(event.repository.full_name(), event.issue.number, NAMESPACE);
+        let mut notes = ctx.state.get::<Vec<Note>>(&key)?.unwrap_or_default();
+        notes.push(Note {
+            author: event.comment.user.login.clone(),
+            at: event.comment.created_at,
+            url: event.comment.html_url.clone(),
+            text: cmd.text,
+        });
+        ctx.state.set(&key, &notes)?;
+
+        EditIssueBody::new(&event.issue, NAMESPACE).apply(&ctx.github, render(&notes), ())?;
+
+        Ok(())
+    }
+}
+
+fn render(notes: &[Note]) -> String {
+    let mut text = String::from("Notes:\n\n");
+    for note in notes {
+        text.push_str(&format!(
+            "- {} (@{}, [{}]({}))\n",
+            escape(&note.text),
+            note.author,
+            note.at.format("%Y-%m-%d"),
+            note.url
+        ));
+    }
+    text
+}
+
+/// Escapes `text` for inclusion in a list item: it mustn't contain HTML (which could, for
+/// example, end the comments delimiting our section of the body), nor start a new block.
+pub(super) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    if text.starts_with(|c| "#-+*=|`~".contains(c)) {
+        escaped.push('\\');
+    }
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[test]
+fn escape_html() {
+    assert_eq!(
+        escape("see <!-- TRIAGEBOT_END --> & <b>this</b>"),
+        "see &lt;!-- TRIAGEBOT_END --&gt; &amp; &lt;b&gt;this&lt;/b&gt;"
+    );
+}
+
+#[test]
+fn escape_block_start() {
+    assert_eq!(escape("# not a heading"), "\\# not a heading");
+    assert_eq!(escape("> not a quote"), "&gt; not a quote");
+    assert_eq!(escape("plain *emphasis*"), "plain *emphasis*");
+}
+
+#[test]
+fn render_appends() {
+    let note = |author: &str, text: &str| Note {
+        author: author.to_owned(),
+        at: "2019-05-12T10:00:00Z".parse().unwrap(),
+        url: "https://github.com/rust-lang/rust/issues/1#issuecomment-1".to_owned(),
+        text: text.to_owned(),
+    };
+    assert_eq!(
+        render(&[note("alice", "needs a crater run"), note("bob", "<3")]),
+        "Notes:\n\n\
+         - needs a crater run (@alice, [2019-05-12](https://github.com/rust-lang/rust/issues/1#issuecomment-1))\n\
+         - &lt;3 (@bob, [2019-05-12](https://github.com/rust-lang/rust/issues/1#issuecomment-1))\n"
+    );
+}
@@ -0,0 +1,133 @@
+//! Purpose: Allow any user to ping a pre-selected group of people on GitHub via comments.
+//!
+//! The set of "teams" which can be pinged is intentionally restricted via configuration. A group
+//! is either a list of users, or a Rust team, whose current members are looked up when it's
+//! pinged.
+//!
+//! Parsing is done in the `parser::command::ping` module.
+
+use crate::{
+    config::{PingConfig, PingGroup},
+    github::{Event, UnknownTeam},
+    handlers::{Context, Handler},
+};
+use failure::Error;
+use parser::command::ping::PingCommand;
+use parser::command::Command;
+
+pub(super) struct PingHandler;
+
+impl Handler for PingHandler {
+    type Input = PingCommand;
+    type Config = PingConfig;
+
+    fn parse_input(&self, ctx: &Context, event: &Event) -> Result<Vec<Self::Input>, Error> {
+        let event = if let Event::IssueComment(e) = event {
+            e
+        } else {
+            // not interested in other events
+            return Ok(Vec::new());
+        };
+
+        let mut commands = Vec::new();
+        super::comment_commands(ctx, event, |command| {
+            match command {
+                Command::Ping(Ok(command)) => commands.push(command),
+                Command::Ping(Err(err)) => {
+                    failure::bail!(
+                        "Parsing ping command in [comment]({}) failed: {}\n\n{}",
+                        event.comment.html_url,
+                        err,
+                        err.snippet()
+                    );
+                }
+                _ => {}
+            }
+            Ok(())
+        })?;
+        Ok(commands)
+    }
+
+    fn handle_input(
+        &self,
+        ctx: &Context,
+        config: &PingConfig,
+        event: &Event,
+        input: PingCommand,
+    ) -> Result<(), Error> {
+        let event = if let Event::IssueComment(e) = event {
+            e
+        } else {
+            // not interested in other events
+            return Ok(());
+        };
+
+        let members = match config.groups.get(&input.group) {
+            Some(PingGroup::Members(members)) => members.clone(),
+            Some(PingGroup::Team { team }) => match ctx.github.get_team(team) {
+                Ok(members) => members.to_vec(),
+                Err(err) => match err.downcast::<UnknownTeam>() {
+                    Ok(_) => failure::bail!(
+                        "The ping group `{}` refers to the team `{}`, which does not exist; \
+                         please update this repository's `triagebot.toml`",
+                        input.group,
+                        team
+                    ),
+                    Err(err) => return Err(err),
+                },
+            },
+            None => {
+                let mut groups = config
+                    .groups
+                    .keys()
+                    .map(|g| format!("`{}`", g))
+                    .collect::<Vec<_>>();
+                groups.sort();
+                failure::bail!(
+                    "Unknown ping group `{}`; the groups configured for this repository are: {}",
+                    input.group,
+                    groups.join(", ")
+                );
+            }
+        };
+
+        // Those already involved in the issue are notified anyway, so don't ping them again.
+        let mut already_notified = vec![
+            ctx.username.as_str(),
+            event.comment.user.login.as_str(),
+            event.issue.user.login.as_str(),
+        ];
+        already_notified.extend(event.issue.assignees().iter().map(|u| u.login.as_str()));
+
+        let mut to_ping = Vec::new();
+        for member in &members {
+            if !already_notified.contains(&member.as_str()) && !to_ping.contains(&member) {
+                to_ping.push(member);
+            }
+        }
+        if to_ping.is_empty() {
+            return Ok(());
+        }
+
+        let mentions = to_ping
+            .iter()
+            .map(|m| format!("@{}", m))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let message = super::render_template(
+            ctx,
+            event.repository.full_name(),
+            "ping",
+            &[
+                ("group", &input.group),
+                ("issue", &event.issue.number.to_string()),
+                ("comment_url", &event.comment.html_url),
+                ("user", &event.comment.user.login),
+                ("mentions", &mentions),
+            ],
+        )?;
+        event.issue.post_comment(&ctx.github, &message)?;
+
+        Ok(())
+    }
+}
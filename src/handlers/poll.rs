@@ -0,0 +1,292 @@
+//! Purpose: Allow team members to run quick polls on issues.
+//!
+//! `@rustbot poll "Ship it?" yes no "not yet"` adds the poll to the issue body, and people vote by
+//! replying with a comment consisting of just one of the options; only each voter's latest vote
+//! counts. `@rustbot poll close` tallies the votes and posts the results. There is at most one open
+//! poll per issue.
+
+use crate::{
+    config::PollConfig,
+    db::StateKey,
+    github::{Comment, Event},
+    handlers::{note::escape, CommandError, Context, Handler},
+    interactions::EditIssueBody,
+};
+use chrono::{DateTime, Utc};
+use failure::Error;
+use parser::command::poll::PollCommand;
+use parser::command::Command;
+use std::collections::BTreeMap;
+
+pub(super) struct PollHandler;
+
+const NAMESPACE: &str = "POLL";
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct Poll {
+    question: String,
+    options: Vec<String>,
+    opened_by: String,
+    opened_at: DateTime<Utc>,
+}
+
+impl Handler for PollHandler {
+    type Input = PollCommand;
+    type Config = PollConfig;
+
+    fn parse_input(&self, ctx: &Context, event: &Event) -> Result<Vec<Self::Input>, Error> {
+        let event = if let Event::IssueComment(e) = event {
+            e
+        } else {
+            // not interested in other events
+            return Ok(Vec::new());
+        };
+
+        let mut commands = Vec::new();
+        super::comment_commands(ctx, event, |command| {
+            match command {
+                Command::Poll(Ok(command)) => commands.push(command),
+                Command::Poll(Err(err)) => {
+                    failure::bail!(
+                        "Parsing poll command in [comment]({}) failed: {}\n\n{}",
+                        event.comment.html_url,
+                        err,
+                        err.snippet()
+                    );
+                }
+                _ => {}
+            }
+            Ok(())
+        })?;
+        Ok(commands)
+    }
+
+    fn handle_input(
+        &self,
+        ctx: &Context,
+        _config: &PollConfig,
+        event: &Event,
+        cmd: PollCommand,
+    ) -> Result<(), Error> {
+        let event = if let Event::IssueComment(e) = event {
+            e
+        } else {
+            // not interested in other events
+            return Ok(());
+        };
+
+        let is_team_member = event
+            .comment
+            .user
+            .is_team_member(&ctx.github)
+            .unwrap_or(false);
+        if !is_team_member {
+            return Err(CommandError::Permission(
+                "Only Rust team members can open and close polls".to_owned(),
+            )
+            .into());
+        }
+
+        let key = StateKey::new(event.repository.full_name(), event.issue.number, NAMESPACE);
+        let current = ctx.state.get::<Option<Poll>>(&key)?.and_then(|poll| poll);
+        match cmd {
+            PollCommand::Open { question, options } => {
+                if current.is_some() {
+                    failure::bail!(
+                        "There is already an open poll on this issue; close it first with \
+                         `@{} poll close`",
+                        ctx.username
+                    );
+                }
+                let poll = Poll {
+                    question,
+                    options,
+                    opened_by: event.comment.user.login.clone(),
+                    opened_at: event.comment.created_at,
+                };
+                ctx.state.set(&key, &Some(&poll))?;
+                EditIssueBody::new(&event.issue, NAMESPACE).apply(
+                    &ctx.github,
+                    render_open(&poll, &ctx.username),
+                    (),
+                )?;
+            }
+            PollCommand::Close => {
+                let poll = match current {
+                    Some(poll) => poll,
+                    None => failure::bail!("There is no open poll on this issue"),
+                };
+                let comments = event.issue.comments_since(&ctx.github, poll.opened_at)?;
+                let results = render_results(&poll, &tally(&poll, &comments, &ctx.username));
+                ctx.state.set(&key, &None::<Poll>)?;
+                EditIssueBody::new(&event.issue, NAMESPACE).apply(
+                    &ctx.github,
+                    results.clone(),
+                    (),
+                )?;
+                event.issue.post_comment(&ctx.github, &results)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The option `body` votes for, if it consists of just one of the `options` (ignoring case, and a
+/// trailing period).
+fn vote<'a>(options: &'a [String], body: &str) -> Option<&'a str> {
+    let body = body.trim();
+    let body = body.trim_end_matches('.').trim_end();
+    options
+        .iter()
+        .find(|option| option.eq_ignore_ascii_case(body))
+        .map(|option| option.as_str())
+}
+
+/// The voters for each of the poll's options, going by each voter's latest vote among
+/// `comments`.
+fn tally<'a>(poll: &'a Poll, comments: &[Comment], bot: &str) -> BTreeMap<&'a str, Vec<String>> {
+    let mut votes: BTreeMap<&str, (DateTime<Utc>, &str)> = BTreeMap::new();
+    for comment in comments {
+        // Comments are only filtered by when they were last updated.
+        if comment.created_at < poll.opened_at || comment.user.login == bot {
+            continue;
+        }
+        if let Some(option) = vote(&poll.options, &comment.body) {
+            let latest = votes
+                .entry(comment.user.login.as_str())
+                .or_insert((comment.created_at, option));
+            if comment.created_at >= latest.0 {
+                *latest = (comment.created_at, option);
+            }
+        }
+    }
+    let mut voters: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for (user, (_, option)) in votes {
+        voters.entry(option).or_default().push(user.to_owned());
+    }
+    poll.options
+        .iter()
+        .map(|option| {
+            let option = option.as_str();
+            (option, voters.remove(option).unwrap_or_default())
+        })
+        .collect()
+}
+
+fn render_open(poll: &Poll, bot: &str) -> String {
+    let mut text = format!(
+        "Poll opened by @{}: **{}**\n\n",
+        poll.opened_by,
+        escape(&poll.question)
+    );
+    for option in &poll.options {
+        text.push_str(&format!("- {}\n", escape(option)));
+    }
+    text.push_str(&format!(
+        "\nTo vote, reply with a comment consisting of just one of the options; only your \
+         latest vote counts. Team members can close the poll with `@{} poll close`.\n",
+        bot
+    ));
+    text
+}
+
+fn render_results(poll: &Poll, voters: &BTreeMap<&str, Vec<String>>) -> String {
+    let mut text = format!("Poll closed: **{}**\n\n", escape(&poll.question));
+    for option in &poll.options {
+        let voters = &voters[option.as_str()];
+        text.push_str(&format!(
+            "- {}: {}{}\n",
+            escape(option),
+            voters.len(),
+            if voters.is_empty() {
+                String::new()
+            } else {
+                format!(
+                    " ({})",
+                    voters
+                        .iter()
+                        .map(|user| format!("@{}", user))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            }
+        ));
+    }
+    text
+}
+
+#[cfg(test)]
+fn poll() -> Poll {
+    Poll {
+        question: "Ship it?".to_owned(),
+        options: vec!["yes".to_owned(), "no".to_owned(), "not yet".to_owned()],
+        opened_by: "alice".to_owned(),
+        opened_at: "2019-05-12T10:00:00Z".parse().unwrap(),
+    }
+}
+
+#[cfg(test)]
+fn comment(user: &str, at: &str, body: &str) -> Comment {
+    serde_json::from_value(serde_json::json!({
+        "id": 1,
+        "body": body,
+        "url": "https://api.github.com/repos/rust-lang/rust/issues/comments/1",
+        "html_url": "https://github.com/rust-lang/rust/issues/1#issuecomment-1",
+        "user": {"login": user},
+        "created_at": at,
+    }))
+    .unwrap()
+}
+
+#[test]
+fn votes() {
+    let options = poll().options;
+    assert_eq!(vote(&options, "Yes"), Some("yes"));
+    assert_eq!(vote(&options, " not yet.\n"), Some("not yet"));
+    assert_eq!(vote(&options, "yes, but later"), None);
+}
+
+#[test]
+fn tally_latest_votes() {
+    let poll = poll();
+    let comments = vec![
+        comment("bob", "2019-05-12T11:00:00Z", "yes"),
+        comment("carol", "2019-05-12T11:00:00Z", "no"),
+        comment("bob", "2019-05-12T12:00:00Z", "Not yet."),
+        comment("dave", "2019-05-12T12:00:00Z", "I'm not sure"),
+        // Before the poll was opened.
+        comment("erin", "2019-05-11T12:00:00Z", "yes"),
+        comment("rustbot", "2019-05-12T12:00:00Z", "yes"),
+    ];
+    let voters = tally(&poll, &comments, "rustbot");
+    assert_eq!(voters["yes"], Vec::<String>::new());
+    assert_eq!(voters["no"], vec!["carol"]);
+    assert_eq!(voters["not yet"], vec!["bob"]);
+}
+
+#[test]
+fn render() {
+    let poll = poll();
+    assert_eq!(
+        render_open(&poll, "rustbot"),
+        "Poll opened by @alice: **Ship it?**\n\n\
+         - yes\n\
+         - no\n\
+         - not yet\n\
+         \n\
+         To vote, reply with a comment consisting of just one of the options; only your latest \
+         vote counts. Team members can close the poll with `@rustbot poll close`.\n"
+    );
+    let comments = vec![
+        comment("bob", "2019-05-12T11:00:00Z", "yes"),
+        comment("carol", "2019-05-12T11:00:00Z", "yes"),
+    ];
+    assert_eq!(
+        render_results(&poll, &tally(&poll, &comments, "rustbot")),
+        "Poll closed: **Ship it?**\n\n\
+         - yes: 2 (@bob, @carol)\n\
+         - no: 0\n\
+         - not yet: 0\n"
+    );
+}
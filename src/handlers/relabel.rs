@@ -1,22 +1,30 @@
 //! Purpose: Allow any user to modify issue labels on GitHub via comments.
 //!
 //! Labels are checked against the labels in the project; the bot does not support creating new
-//! labels.
+//! labels. For labels which don't exist, we suggest the closest existing one, in case of a typo.
+//! Removing a label which the issue doesn't have does nothing.
+//!
+//! Labels not matching any `allow-unauthenticated` pattern, or matching a `restricted` pattern,
+//! may only be changed by team members. When anyone else tries to change them, the other labels
+//! in the command are still changed.
 //!
 //! Parsing is done in the `parser::command::relabel` module.
 //!
 //! If the command was successful, there will be no feedback beyond the label change to reduce
 //! notification noise.
+//!
+//! With a trailing `dry` (or `preview`), as in `@rustbot label +T-compiler -T-lang dry`, nothing
+//! is changed; instead, the bot replies with the labels the command would add and remove, given
+//! the issue's current labels and what the user may change.
 
 use crate::{
     config::RelabelConfig,
-    github::{self, Event, GithubClient},
-    handlers::{Context, Handler},
-    interactions::ErrorComment,
+    github::{self, Event},
+    handlers::{CommandError, Context, Handler},
 };
 use failure::Error;
-use parser::command::relabel::{RelabelCommand, LabelDelta};
-use parser::command::{Command, Input};
+use parser::command::relabel::{LabelDelta, RelabelCommand};
+use parser::command::Command;
 
 pub(super) struct RelabelHandler;
 
@@ -24,26 +32,31 @@ impl Handler for RelabelHandler {
     type Input = RelabelCommand;
     type Config = RelabelConfig;
 
-    fn parse_input(&self, ctx: &Context, event: &Event) -> Result<Option<Self::Input>, Error> {
-        #[allow(irrefutable_let_patterns)]
+    fn parse_input(&self, ctx: &Context, event: &Event) -> Result<Vec<Self::Input>, Error> {
         let event = if let Event::IssueComment(e) = event {
             e
         } else {
             // not interested in other events
-            return Ok(None);
+            return Ok(Vec::new());
         };
 
-        let mut input = Input::new(&event.comment.body, &ctx.username);
-        match input.parse_command() {
-            Command::Relabel(Ok(command)) => Ok(Some(command)),
-            Command::Relabel(Err(err)) => {
-                failure::bail!(
-                    "Parsing label command in [comment]({}) failed: {}",
-                    event.comment.html_url, err
-                );
+        let mut commands = Vec::new();
+        super::comment_commands(ctx, event, |command| {
+            match command {
+                Command::Relabel(Ok(command)) => commands.push(command),
+                Command::Relabel(Err(err)) => {
+                    failure::bail!(
+                        "Parsing label command in [comment]({}) failed: {}\n\n{}",
+                        event.comment.html_url,
+                        err,
+                        err.snippet()
+                    );
+                }
+                _ => {}
             }
-            _ => Ok(None),
-        }
+            Ok(())
+        })?;
+        Ok(commands)
     }
 
     fn handle_input(
@@ -53,7 +66,6 @@ impl Handler for RelabelHandler {
         event: &Event,
         input: RelabelCommand,
     ) -> Result<(), Error> {
-        #[allow(irrefutable_let_patterns)]
         let event = if let Event::IssueComment(e) = event {
             e
         } else {
@@ -61,71 +73,303 @@ impl Handler for RelabelHandler {
             return Ok(());
         };
 
-        let mut issue_labels = event.issue.labels().to_owned();
-        let mut changed = false;
-        for delta in &input.0 {
-            let name = delta.label().as_str();
-            if let Err(msg) = check_filter(name, config, &event.comment.user, &ctx.github) {
-                ErrorComment::new(&event.issue, msg.to_string()).post(&ctx.github)?;
-                return Ok(());
-            }
-            match delta {
-                LabelDelta::Add(label) => {
-                    if !issue_labels.iter().any(|l| l.name == label.as_str()) {
-                        changed = true;
-                        issue_labels.push(github::Label {
-                            name: label.to_string(),
-                        });
-                    }
+        let labels = event.repository.cached_labels(&ctx.github)?;
+        let mut deltas = Vec::with_capacity(input.deltas.len());
+        let mut unknown = Vec::new();
+        for delta in &input.deltas {
+            let name = resolve_alias(delta.label().as_str(), config)?;
+            match (find_label(&labels, name), delta) {
+                (Some(label), _) => deltas.push((delta, label)),
+                (None, LabelDelta::Add(_)) => unknown.push(match closest_label(&labels, name) {
+                    Some(closest) => format!("`{}` (did you mean `{}`?)", name, closest),
+                    None => format!("`{}`", name),
+                }),
+                // A label which doesn't exist can't be on the issue, so there's nothing to remove.
+                (None, LabelDelta::Remove(_)) => {
+                    log::info!("ignoring removal of unknown label {}", name);
                 }
-                LabelDelta::Remove(label) => {
-                    if let Some(pos) = issue_labels.iter().position(|l| l.name == label.as_str()) {
-                        changed = true;
-                        issue_labels.remove(pos);
-                    }
+            }
+        }
+        if !unknown.is_empty() {
+            let msg = format!(
+                "Unknown label(s) {}; labels must already exist in this repository or be \
+                 configured as aliases.",
+                unknown.join(", ")
+            );
+            return Err(CommandError::Invalid(msg).into());
+        }
+
+        // Team membership is only looked up if it matters.
+        let mut is_team_member = None;
+        let mut allowed = Vec::with_capacity(deltas.len());
+        let mut rejected = Vec::new();
+        for (delta, name) in deltas {
+            if is_restricted(name, config)? {
+                let is_team_member = *is_team_member.get_or_insert_with(|| {
+                    event
+                        .comment
+                        .user
+                        .is_team_member(&ctx.github)
+                        .map_err(|err| {
+                            log::warn!("failed to check team membership: {:?}", err);
+                        })
+                });
+                if is_team_member != Ok(true) {
+                    rejected.push(name);
+                    continue;
                 }
             }
+            allowed.push(match delta {
+                LabelDelta::Add(_) => LabelDelta::Add(name.into()),
+                LabelDelta::Remove(_) => LabelDelta::Remove(name.into()),
+            });
+        }
+
+        if input.preview {
+            let (added, removed) = preview(event.issue.labels(), &allowed);
+            let msg = preview_message(&added, &removed, &rejected);
+            return event.issue.post_comment(&ctx.github, &msg);
         }
 
-        if changed {
-            event.issue.set_labels(&ctx.github, issue_labels)?;
+        let changed = !allowed.is_empty();
+        ctx.labels.apply_now(
+            &ctx.github,
+            event.repository.full_name(),
+            &event.issue,
+            allowed,
+        )?;
+
+        if !rejected.is_empty() {
+            let mut msg = format!(
+                "Only Rust team members can change the label(s) {}",
+                code_list(&rejected)
+            );
+            if is_team_member == Some(Err(())) {
+                msg.push_str("; we were unable to check if you are a team member");
+            }
+            if changed {
+                msg.push_str(". The other labels were changed.");
+            } else {
+                msg.push('.');
+            }
+            return Err(CommandError::Permission(msg).into());
         }
 
         Ok(())
     }
 }
 
-fn check_filter(
-    label: &str,
-    config: &RelabelConfig,
-    user: &github::User,
-    client: &GithubClient,
-) -> Result<(), Error> {
-    let is_team_member;
-    match user.is_team_member(client) {
-        Ok(true) => return Ok(()),
-        Ok(false) => {
-            is_team_member = Ok(());
+/// The labels which applying `deltas` to an issue labeled with `current` would add and remove.
+fn preview<'a>(
+    current: &[github::Label],
+    deltas: &'a [LabelDelta],
+) -> (Vec<&'a str>, Vec<&'a str>) {
+    let has = |name: &str| current.iter().any(|l| l.name == name);
+    let mut added = Vec::new();
+    let mut removed = Vec::new();
+    for delta in deltas {
+        let name = delta.label().as_str();
+        let (changes, list) = match delta {
+            LabelDelta::Add(_) => (!has(name), &mut added),
+            LabelDelta::Remove(_) => (has(name), &mut removed),
+        };
+        if changes && !list.contains(&name) {
+            list.push(name);
         }
-        Err(err) => {
-            eprintln!("failed to check team membership: {:?}", err);
-            is_team_member = Err(());
-            // continue on; if we failed to check their membership assume that they are not members.
+    }
+    (added, removed)
+}
+
+/// The reply to a `dry` command, which would add `added` and remove `removed` but not change
+/// the `rejected` labels, which the user may not change.
+fn preview_message(added: &[&str], removed: &[&str], rejected: &[&str]) -> String {
+    let mut changes = Vec::new();
+    if !added.is_empty() {
+        changes.push(format!("add {}", code_list(added)));
+    }
+    if !removed.is_empty() {
+        changes.push(format!("remove {}", code_list(removed)));
+    }
+    let mut msg = if changes.is_empty() {
+        "This wouldn't change any labels.".to_owned()
+    } else {
+        format!("This would {}.", changes.join(" and "))
+    };
+    if !rejected.is_empty() {
+        msg.push_str(&format!(
+            " Only Rust team members can change {}, so those would be left as they are.",
+            code_list(rejected)
+        ));
+    }
+    msg
+}
+
+/// `names`, each in code, separated by commas.
+fn code_list(names: &[&str]) -> String {
+    names
+        .iter()
+        .map(|name| format!("`{}`", name))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The name of the label called `name` (ignoring case, like GitHub does) among `labels`.
+fn find_label<'a>(labels: &'a [github::Label], name: &str) -> Option<&'a str> {
+    labels
+        .iter()
+        .find(|l| l.name == name)
+        .or_else(|| labels.iter().find(|l| l.name.eq_ignore_ascii_case(name)))
+        .map(|l| l.name.as_str())
+}
+
+/// The label among `labels` which `name` is most likely a typo of, if any is close enough.
+fn closest_label<'a>(labels: &'a [github::Label], name: &str) -> Option<&'a str> {
+    let name = name.to_lowercase();
+    let max_distance = std::cmp::max(1, name.chars().count() / 3);
+    labels
+        .iter()
+        .map(|l| (levenshtein(&l.name.to_lowercase(), &name), l.name.as_str()))
+        .filter(|&(distance, _)| distance <= max_distance)
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, label)| label)
+}
+
+/// The number of single character insertions, deletions and substitutions needed to turn `a`
+/// into `b`.
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    // The distances from the prefix of `a` processed so far to each prefix of `b`.
+    let mut distances = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous_diagonal = distances[0];
+        distances[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous_diagonal + if ca == *cb { 0 } else { 1 };
+            previous_diagonal = distances[j + 1];
+            distances[j + 1] = std::cmp::min(
+                substitution,
+                std::cmp::min(distances[j + 1], distances[j]) + 1,
+            );
+        }
+    }
+    distances[b.len()]
+}
+
+/// Expands `label` through the configured aliases (see `RelabelConfig::resolve_alias`).
+fn resolve_alias<'a>(label: &'a str, config: &'a RelabelConfig) -> Result<&'a str, Error> {
+    config.resolve_alias(label).map_err(|chain| {
+        failure::format_err!("Label alias `{}` is cyclic: {}", label, chain.join(" -> "))
+    })
+}
+
+/// Whether only team members may add or remove `label`: those matching a `restricted`
+/// pattern, and those not matching any `allow-unauthenticated` pattern.
+fn is_restricted(label: &str, config: &RelabelConfig) -> Result<bool, Error> {
+    for pattern in &config.restricted {
+        if glob::Pattern::new(pattern)?.matches(label) {
+            return Ok(true);
         }
     }
     for pattern in &config.allow_unauthenticated {
-        let pattern = glob::Pattern::new(pattern)?;
-        if pattern.matches(label) {
-            return Ok(());
+        if glob::Pattern::new(pattern)?.matches(label) {
+            return Ok(false);
         }
     }
-    if is_team_member.is_ok() {
-        failure::bail!("Label {} can only be set by Rust team members", label);
-    } else {
-        failure::bail!(
-            "Label {} can only be set by Rust team members;\
-             we were unable to check if you are a team member.",
-            label
-        );
+    Ok(true)
+}
+
+#[cfg(test)]
+use crate::github::labels;
+
+#[test]
+fn levenshtein_distance() {
+    assert_eq!(levenshtein("", ""), 0);
+    assert_eq!(levenshtein("bug", ""), 3);
+    assert_eq!(levenshtein("C-bug", "C-bug"), 0);
+    assert_eq!(levenshtein("kitten", "sitting"), 3);
+    assert_eq!(levenshtein("T-compiler", "T-complier"), 2);
+}
+
+#[test]
+fn find_label_ignores_case() {
+    let labels = labels(&["C-bug", "T-compiler"]);
+    assert_eq!(find_label(&labels, "C-bug"), Some("C-bug"));
+    assert_eq!(find_label(&labels, "t-compiler"), Some("T-compiler"));
+    assert_eq!(find_label(&labels, "C-feature"), None);
+}
+
+#[test]
+fn closest_label_suggestion() {
+    let labels = labels(&["C-bug", "T-compiler", "T-lang", "good first issue"]);
+    assert_eq!(closest_label(&labels, "T-complier"), Some("T-compiler"));
+    assert_eq!(closest_label(&labels, "t-lnag"), Some("T-lang"));
+    assert_eq!(
+        closest_label(&labels, "good-first-issue"),
+        Some("good first issue")
+    );
+    assert_eq!(closest_label(&labels, "A-diagnostics"), None);
+}
+
+#[cfg(test)]
+fn config(allow_unauthenticated: &[&str], restricted: &[&str]) -> RelabelConfig {
+    RelabelConfig {
+        allow_unauthenticated: allow_unauthenticated
+            .iter()
+            .map(|p| p.to_string())
+            .collect(),
+        restricted: restricted.iter().map(|p| p.to_string()).collect(),
+        aliases: Default::default(),
     }
 }
+
+#[test]
+fn restricted_by_default() {
+    let config = config(&["C-*", "S-*"], &[]);
+    assert!(!is_restricted("S-waiting-on-review", &config).unwrap());
+    assert!(is_restricted("beta-nominated", &config).unwrap());
+}
+
+#[test]
+fn restricted_patterns() {
+    let config = config(&["*"], &["beta-*", "I-unsound"]);
+    assert!(!is_restricted("C-bug", &config).unwrap());
+    assert!(is_restricted("beta-nominated", &config).unwrap());
+    assert!(is_restricted("I-unsound", &config).unwrap());
+}
+
+#[test]
+fn preview_changes() {
+    let current = labels(&["C-bug", "T-lang"]);
+    let deltas = vec![
+        LabelDelta::Add("T-compiler".into()),
+        LabelDelta::Add("C-bug".into()),
+        LabelDelta::Remove("T-lang".into()),
+        LabelDelta::Remove("I-ICE".into()),
+        LabelDelta::Add("T-compiler".into()),
+    ];
+    assert_eq!(
+        preview(&current, &deltas),
+        (vec!["T-compiler"], vec!["T-lang"])
+    );
+    assert_eq!(
+        preview(&current, &[LabelDelta::Add("C-bug".into())]),
+        (Vec::<&str>::new(), Vec::<&str>::new())
+    );
+}
+
+#[test]
+fn preview_replies() {
+    assert_eq!(
+        preview_message(&["T-compiler", "A-lint"], &["T-lang"], &[]),
+        "This would add `T-compiler`, `A-lint` and remove `T-lang`."
+    );
+    assert_eq!(
+        preview_message(&[], &["T-lang"], &[]),
+        "This would remove `T-lang`."
+    );
+    assert_eq!(
+        preview_message(&[], &[], &["beta-nominated"]),
+        "This wouldn't change any labels. Only Rust team members can change \
+         `beta-nominated`, so those would be left as they are."
+    );
+}
@@ -0,0 +1,149 @@
+//! Purpose: Allow team members to fix the titles of issues.
+//!
+//! `@rustbot rename <title>` sets the title to the rest of the line. If the old title started
+//! with one of the configured `keep-prefixes` (like `[beta]`) and the new one doesn't, the prefix
+//! is kept.
+
+use crate::{
+    config::RenameConfig,
+    github::Event,
+    handlers::{CommandError, Context, Handler},
+};
+use failure::Error;
+use parser::command::rename::RenameCommand;
+use parser::command::Command;
+
+pub(super) struct RenameHandler;
+
+impl Handler for RenameHandler {
+    type Input = RenameCommand;
+    type Config = RenameConfig;
+
+    fn parse_input(&self, ctx: &Context, event: &Event) -> Result<Vec<Self::Input>, Error> {
+        let event = if let Event::IssueComment(e) = event {
+            e
+        } else {
+            // not interested in other events
+            return Ok(Vec::new());
+        };
+
+        let mut commands = Vec::new();
+        super::comment_commands(ctx, event, |command| {
+            match command {
+                Command::Rename(Ok(command)) => commands.push(command),
+                Command::Rename(Err(err)) => {
+                    failure::bail!(
+                        "Parsing rename command in [comment]({}) failed: {}\n\n{}",
+                        event.comment.html_url,
+                        err,
+                        err.snippet()
+                    );
+                }
+                _ => {}
+            }
+            Ok(())
+        })?;
+        Ok(commands)
+    }
+
+    fn handle_input(
+        &self,
+        ctx: &Context,
+        config: &RenameConfig,
+        event: &Event,
+        cmd: RenameCommand,
+    ) -> Result<(), Error> {
+        let event = if let Event::IssueComment(e) = event {
+            e
+        } else {
+            // not interested in other events
+            return Ok(());
+        };
+
+        let is_team_member = event
+            .comment
+            .user
+            .is_team_member(&ctx.github)
+            .unwrap_or(false);
+        if !is_team_member {
+            return Err(CommandError::Permission(
+                "Only Rust team members can rename issues".to_owned(),
+            )
+            .into());
+        }
+
+        let old = event.issue.title();
+        let new = new_title(config, old, &cmd.title)?;
+        if new == old {
+            return Ok(());
+        }
+        event.issue.set_title(&ctx.github, &new)?;
+        let message = super::render_template(
+            ctx,
+            event.repository.full_name(),
+            "renamed",
+            &[("old", old), ("new", &new)],
+        )?;
+        event.issue.post_comment(&ctx.github, &message)?;
+
+        Ok(())
+    }
+}
+
+/// The title to give an issue titled `old` when asked to rename it to `title`, keeping the
+/// configured prefix of the old title (if any).
+fn new_title(config: &RenameConfig, old: &str, title: &str) -> Result<String, Error> {
+    let title = title.trim();
+    if title.is_empty() {
+        failure::bail!("Issues cannot have an empty title");
+    }
+    let kept = config
+        .keep_prefixes
+        .iter()
+        .find(|prefix| old.starts_with(prefix.as_str()) && !title.starts_with(prefix.as_str()));
+    Ok(match kept {
+        Some(prefix) => format!("{} {}", prefix, title),
+        None => title.to_owned(),
+    })
+}
+
+#[cfg(test)]
+fn config() -> RenameConfig {
+    RenameConfig {
+        keep_prefixes: vec!["[beta]".to_owned(), "[stable]".to_owned()],
+    }
+}
+
+#[test]
+fn rename_plain() {
+    assert_eq!(
+        new_title(&config(), "it broke", "  ICE in typeck ").unwrap(),
+        "ICE in typeck"
+    );
+}
+
+#[test]
+fn rename_keeps_prefix() {
+    assert_eq!(
+        new_title(&config(), "[beta] it broke", "ICE in typeck").unwrap(),
+        "[beta] ICE in typeck"
+    );
+    // Unless the new title has it already.
+    assert_eq!(
+        new_title(&config(), "[beta] it broke", "[beta] ICE in typeck").unwrap(),
+        "[beta] ICE in typeck"
+    );
+}
+
+#[test]
+fn rename_only_keeps_old_prefix() {
+    assert_eq!(
+        new_title(&config(), "it broke", "[stable] ICE in typeck").unwrap(),
+        "[stable] ICE in typeck"
+    );
+}
+
+#[test]
+fn rename_empty() {
+    assert!(new_title(&config(), "[beta] it broke", " ").is_err());
+}
@@ -0,0 +1,91 @@
+//! Purpose: Let pull request authors resolve review threads once they've addressed them.
+//!
+//! `@rustbot resolve`, in a reply to a review comment, marks the review thread as resolved. Only
+//! team members and the author of the pull request can use it. Resolving threads isn't possible
+//! through the REST API, so this goes through GraphQL.
+
+use crate::{
+    config::ResolveConfig,
+    github::Event,
+    handlers::{CommandError, Context, Handler},
+};
+use failure::Error;
+use parser::command::resolve::ResolveCommand;
+use parser::command::Command;
+
+pub(super) struct ResolveHandler;
+
+impl Handler for ResolveHandler {
+    type Input = ResolveCommand;
+    type Config = ResolveConfig;
+
+    fn parse_input(&self, ctx: &Context, event: &Event) -> Result<Vec<Self::Input>, Error> {
+        let event = if let Event::IssueComment(e) = event {
+            e
+        } else {
+            // not interested in other events
+            return Ok(Vec::new());
+        };
+
+        let mut commands = Vec::new();
+        super::comment_commands(ctx, event, |command| {
+            match command {
+                Command::Resolve(Ok(command)) => commands.push(command),
+                Command::Resolve(Err(err)) => {
+                    failure::bail!(
+                        "Parsing resolve command in [comment]({}) failed: {}\n\n{}",
+                        event.comment.html_url,
+                        err,
+                        err.snippet()
+                    );
+                }
+                _ => {}
+            }
+            Ok(())
+        })?;
+        Ok(commands)
+    }
+
+    fn handle_input(
+        &self,
+        ctx: &Context,
+        _config: &ResolveConfig,
+        event: &Event,
+        _cmd: ResolveCommand,
+    ) -> Result<(), Error> {
+        let event = if let Event::IssueComment(e) = event {
+            e
+        } else {
+            // not interested in other events
+            return Ok(());
+        };
+
+        let thread = match event.review_thread {
+            Some(thread) => thread,
+            None => failure::bail!(
+                "`resolve` only works in replies to review comments on a pull request's diff"
+            ),
+        };
+        if event.comment.user.login != event.issue.user.login {
+            let is_team_member = event
+                .comment
+                .user
+                .is_team_member(&ctx.github)
+                .unwrap_or(false);
+            if !is_team_member {
+                return Err(CommandError::Permission(
+                    "Only Rust team members and the author of the pull request can resolve \
+                     review threads"
+                        .to_owned(),
+                )
+                .into());
+            }
+        }
+
+        event
+            .issue
+            .resolve_review_thread(&ctx.github, &event.repository, thread)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,130 @@
+//! Purpose: Keep the status labels of pull requests in step with their reviews.
+//!
+//! When a review requesting changes is submitted, the pull request is relabeled from waiting on
+//! review (`S-waiting-on-review` by default) to waiting on its author (`S-waiting-on-author`).
+//! An approval removes both, and adds the configured `approved` label, if any. Reviews which
+//! only comment leave the labels alone.
+
+use crate::{
+    config::ReviewLabelsConfig,
+    github::{Event, PullRequestReviewAction, ReviewState},
+    handlers::{Context, Handler},
+};
+use failure::Error;
+use parser::command::relabel::{Label, LabelDelta};
+
+pub(super) struct ReviewLabelsHandler;
+
+impl Handler for ReviewLabelsHandler {
+    type Input = ReviewState;
+    type Config = ReviewLabelsConfig;
+    const AUTOMATIC: bool = true;
+
+    fn parse_input(&self, _ctx: &Context, event: &Event) -> Result<Vec<Self::Input>, Error> {
+        match event {
+            Event::PullRequestReview(e) if e.action == PullRequestReviewAction::Submitted => {
+                Ok(vec![e.review.state])
+            }
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    fn handle_input(
+        &self,
+        ctx: &Context,
+        config: &ReviewLabelsConfig,
+        event: &Event,
+        state: ReviewState,
+    ) -> Result<(), Error> {
+        let event = if let Event::PullRequestReview(e) = event {
+            e
+        } else {
+            return Ok(());
+        };
+
+        ctx.labels.apply(
+            &ctx.github,
+            event.repository.full_name(),
+            &event.issue,
+            review_deltas(config, state),
+        )?;
+
+        Ok(())
+    }
+}
+
+/// The label changes for a review in `state`.
+fn review_deltas(config: &ReviewLabelsConfig, state: ReviewState) -> Vec<LabelDelta> {
+    let label = |name: &String| -> Label { name.as_str().into() };
+    let mut deltas = Vec::new();
+    match state {
+        ReviewState::ChangesRequested => {
+            deltas.push(LabelDelta::Remove(label(&config.waiting_on_review)));
+            if let Some(approved) = &config.approved {
+                deltas.push(LabelDelta::Remove(label(approved)));
+            }
+            deltas.push(LabelDelta::Add(label(&config.waiting_on_author)));
+        }
+        ReviewState::Approved => {
+            deltas.push(LabelDelta::Remove(label(&config.waiting_on_review)));
+            deltas.push(LabelDelta::Remove(label(&config.waiting_on_author)));
+            if let Some(approved) = &config.approved {
+                deltas.push(LabelDelta::Add(label(approved)));
+            }
+        }
+        ReviewState::Commented | ReviewState::Other => {}
+    }
+    deltas
+}
+
+#[cfg(test)]
+fn config(approved: Option<&str>) -> ReviewLabelsConfig {
+    ReviewLabelsConfig {
+        waiting_on_review: "S-waiting-on-review".to_owned(),
+        waiting_on_author: "S-waiting-on-author".to_owned(),
+        approved: approved.map(|label| label.to_owned()),
+    }
+}
+
+#[test]
+fn changes_requested() {
+    assert_eq!(
+        review_deltas(&config(None), ReviewState::ChangesRequested),
+        vec![
+            LabelDelta::Remove("S-waiting-on-review".into()),
+            LabelDelta::Add("S-waiting-on-author".into()),
+        ]
+    );
+    assert_eq!(
+        review_deltas(&config(Some("S-approved")), ReviewState::ChangesRequested),
+        vec![
+            LabelDelta::Remove("S-waiting-on-review".into()),
+            LabelDelta::Remove("S-approved".into()),
+            LabelDelta::Add("S-waiting-on-author".into()),
+        ]
+    );
+}
+
+#[test]
+fn approved() {
+    assert_eq!(
+        review_deltas(&config(Some("S-approved")), ReviewState::Approved),
+        vec![
+            LabelDelta::Remove("S-waiting-on-review".into()),
+            LabelDelta::Remove("S-waiting-on-author".into()),
+            LabelDelta::Add("S-approved".into()),
+        ]
+    );
+    assert_eq!(
+        review_deltas(&config(None), ReviewState::Approved),
+        vec![
+            LabelDelta::Remove("S-waiting-on-review".into()),
+            LabelDelta::Remove("S-waiting-on-author".into()),
+        ]
+    );
+}
+
+#[test]
+fn commented() {
+    assert!(review_deltas(&config(Some("S-approved")), ReviewState::Commented).is_empty());
+}
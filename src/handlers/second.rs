@@ -0,0 +1,183 @@
+//! Purpose: Allow team members to second proposals, tallying their votes.
+//!
+//! Each `@rustbot second` from a team member is recorded in the state store, and a checklist of
+//! who has seconded is kept in the issue body. Once `quorum` members have seconded, the
+//! configured label (`finished-final-comment-period` by default) is applied.
+//!
+//! If `members` is configured, only those team members may second, and the checklist shows who
+//! has yet to.
+
+use crate::{
+    config::SecondConfig,
+    db::StateKey,
+    github::Event,
+    handlers::{CommandError, Context, Handler},
+    interactions::EditIssueBody,
+};
+use failure::Error;
+use parser::command::second::SecondCommand;
+use parser::command::Command;
+
+pub(super) struct SecondHandler;
+
+const NAMESPACE: &str = "SECOND";
+
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+struct SecondData {
+    /// Who has seconded, in order.
+    seconded: Vec<String>,
+}
+
+impl Handler for SecondHandler {
+    type Input = SecondCommand;
+    type Config = SecondConfig;
+
+    fn parse_input(&self, ctx: &Context, event: &Event) -> Result<Vec<Self::Input>, Error> {
+        let event = if let Event::IssueComment(e) = event {
+            e
+        } else {
+            // not interested in other events
+            return Ok(Vec::new());
+        };
+
+        let mut commands = Vec::new();
+        super::comment_commands(ctx, event, |command| {
+            match command {
+                Command::Second(Ok(command)) => commands.push(command),
+                Command::Second(Err(err)) => {
+                    failure::bail!(
+                        "Parsing second command in [comment]({}) failed: {}\n\n{}",
+                        event.comment.html_url,
+                        err,
+                        err.snippet()
+                    );
+                }
+                _ => {}
+            }
+            Ok(())
+        })?;
+        Ok(commands)
+    }
+
+    fn handle_input(
+        &self,
+        ctx: &Context,
+        config: &SecondConfig,
+        event: &Event,
+        _cmd: SecondCommand,
+    ) -> Result<(), Error> {
+        let event = if let Event::IssueComment(e) = event {
+            e
+        } else {
+            // not interested in other events
+            return Ok(());
+        };
+
+        let user = &event.comment.user;
+        let is_team_member = user.is_team_member(&ctx.github).unwrap_or(false);
+        if !is_team_member {
+            return Err(CommandError::Permission(
+                "Only Rust team members can second proposals".to_owned(),
+            )
+            .into());
+        }
+        if !config.members.is_empty()
+            && !config
+                .members
+                .iter()
+                .any(|member| member.eq_ignore_ascii_case(&user.login))
+        {
+            return Err(CommandError::Permission(
+                "Only the members listed in the `[second]` configuration can second".to_owned(),
+            )
+            .into());
+        }
+
+        let key = StateKey::new(event.repository.full_name(), event.issue.number, NAMESPACE);
+        let mut data = ctx.state.get::<SecondData>(&key)?.unwrap_or_default();
+        if !record(&mut data, &user.login) {
+            // Already seconded.
+            return Ok(());
+        }
+        ctx.state.set(&key, &data)?;
+
+        let text = render(config, &data.seconded);
+        let section = EditIssueBody::new(&event.issue, NAMESPACE);
+        if section.current_text() != Some(text.as_str()) {
+            section.apply(&ctx.github, text, ())?;
+        }
+
+        if data.seconded.len() >= config.quorum
+            && !event.issue.labels().iter().any(|l| l.name == config.label)
+        {
+            event.issue.add_label(&ctx.github, &config.label)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Records that `user` seconded, returning whether they hadn't already.
+fn record(data: &mut SecondData, user: &str) -> bool {
+    if data.seconded.iter().any(|u| u.eq_ignore_ascii_case(user)) {
+        return false;
+    }
+    data.seconded.push(user.to_owned());
+    true
+}
+
+/// The checklist of who has `seconded`, and who (of the configured members) hasn't yet.
+fn render(config: &SecondConfig, seconded: &[String]) -> String {
+    let mut text = format!(
+        "Seconded by {} of the {} members needed:\n\n",
+        seconded.len(),
+        config.quorum
+    );
+    for user in seconded {
+        text.push_str(&format!("- [x] @{}\n", user));
+    }
+    for member in &config.members {
+        if !seconded.iter().any(|u| u.eq_ignore_ascii_case(member)) {
+            text.push_str(&format!("- [ ] @{}\n", member));
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+fn config(members: &[&str]) -> SecondConfig {
+    SecondConfig {
+        quorum: 2,
+        label: "finished-final-comment-period".to_owned(),
+        members: members.iter().map(|m| m.to_string()).collect(),
+    }
+}
+
+#[test]
+fn record_is_idempotent() {
+    let mut data = SecondData::default();
+    assert!(record(&mut data, "alice"));
+    assert!(!record(&mut data, "alice"));
+    assert!(!record(&mut data, "Alice"));
+    assert!(record(&mut data, "bob"));
+    assert_eq!(data.seconded, ["alice", "bob"]);
+}
+
+#[test]
+fn render_with_members() {
+    assert_eq!(
+        render(&config(&["alice", "bob", "carol"]), &["bob".to_owned()]),
+        "Seconded by 1 of the 2 members needed:\n\n\
+         - [x] @bob\n\
+         - [ ] @alice\n\
+         - [ ] @carol\n"
+    );
+}
+
+#[test]
+fn render_without_members() {
+    assert_eq!(
+        render(&config(&[]), &["alice".to_owned(), "bob".to_owned()]),
+        "Seconded by 2 of the 2 members needed:\n\n- [x] @alice\n- [x] @bob\n"
+    );
+}
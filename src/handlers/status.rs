@@ -0,0 +1,129 @@
+//! Purpose: Show what the bot is keeping track of on an issue, without digging through the
+//! comments for it.
+//!
+//! `@rustbot status` replies with who's assigned to the issue (as `assign` records it, with when
+//! they'll next be asked for a status report) and, if the repository uses `triage`, the issue's
+//! priority and status labels. It changes nothing, so anyone can ask.
+
+use crate::{
+    config::{self, StatusConfig},
+    github::Event,
+    handlers::{assign, triage, Context, Handler},
+};
+use failure::Error;
+use parser::command::status::StatusCommand;
+use parser::command::Command;
+
+pub(super) struct StatusHandler;
+
+impl Handler for StatusHandler {
+    type Input = StatusCommand;
+    type Config = StatusConfig;
+
+    fn parse_input(&self, ctx: &Context, event: &Event) -> Result<Vec<Self::Input>, Error> {
+        let event = if let Event::IssueComment(e) = event {
+            e
+        } else {
+            // not interested in other events
+            return Ok(Vec::new());
+        };
+
+        let mut commands = Vec::new();
+        super::comment_commands(ctx, event, |command| {
+            match command {
+                Command::Status(Ok(command)) => commands.push(command),
+                Command::Status(Err(err)) => {
+                    failure::bail!(
+                        "Parsing status command in [comment]({}) failed: {}\n\n{}",
+                        event.comment.html_url,
+                        err,
+                        err.snippet()
+                    );
+                }
+                _ => {}
+            }
+            Ok(())
+        })?;
+        Ok(commands)
+    }
+
+    fn handle_input(
+        &self,
+        ctx: &Context,
+        _config: &StatusConfig,
+        event: &Event,
+        _cmd: StatusCommand,
+    ) -> Result<(), Error> {
+        let event = if let Event::IssueComment(e) = event {
+            e
+        } else {
+            // not interested in other events
+            return Ok(());
+        };
+
+        let repo = event.repository.full_name();
+        let config = config::get(&ctx.github, repo)?;
+        let mut lines = Vec::new();
+        if let Some(assign) = &config.assign {
+            lines.push((
+                "Assigned",
+                assign::describe(ctx, assign, repo, &event.issue)?,
+            ));
+        }
+        if let Some(triage) = &config.triage {
+            let labels = event.issue.labels();
+            lines.push((
+                "Priority",
+                labeled(&triage::present(&triage.priorities, labels)),
+            ));
+            if !triage.statuses.is_empty() {
+                lines.push((
+                    "Status",
+                    labeled(&triage::present(&triage.statuses, labels)),
+                ));
+            }
+        }
+        event.issue.post_comment(&ctx.github, &render(&lines))?;
+
+        Ok(())
+    }
+}
+
+/// The labels an issue has from one of the triage groups, for a line of the reply.
+fn labeled(labels: &[&str]) -> String {
+    if labels.is_empty() {
+        return "none".to_owned();
+    }
+    labels
+        .iter()
+        .map(|label| format!("`{}`", label))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn render(lines: &[(&str, String)]) -> String {
+    if lines.is_empty() {
+        return "Nothing is tracked on this issue.".to_owned();
+    }
+    let mut text = String::from("Status of this issue:\n\n");
+    for (name, value) in lines {
+        text.push_str(&format!("- **{}**: {}\n", name, value));
+    }
+    text
+}
+
+#[test]
+fn render_status() {
+    assert_eq!(render(&[]), "Nothing is tracked on this issue.");
+    assert_eq!(
+        render(&[
+            ("Assigned", "nobody".to_owned()),
+            ("Priority", labeled(&["P-high"])),
+            ("Status", labeled(&[])),
+        ]),
+        "Status of this issue:\n\n\
+         - **Assigned**: nobody\n\
+         - **Priority**: `P-high`\n\
+         - **Status**: none\n"
+    );
+}
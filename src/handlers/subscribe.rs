@@ -0,0 +1,256 @@
+//! Purpose: Let people be notified of changes to an issue, without watching the whole repository.
+//!
+//! `@rustbot subscribe` adds whoever asked to the issue's list of subscribers, and
+//! `@rustbot unsubscribe` removes them again; team members can also (un)subscribe others, with
+//! `@rustbot subscribe @gh-user`. The list is kept in the state store.
+//!
+//! Whenever the issue is closed or reopened, or a label is added or removed (as picked by
+//! `events`), the subscribers are mentioned in the `subscribers` message saying so. Whoever made
+//! the change isn't, since they already know.
+
+use crate::{
+    config::{SubscribeConfig, SubscribeEvent},
+    db::StateKey,
+    github::{Event, IssuesAction, Label, PullRequestAction},
+    handlers::{CommandError, Context, Handler},
+};
+use failure::Error;
+use parser::command::subscribe::SubscribeCommand;
+use parser::command::Command;
+
+pub(super) struct SubscribeHandler;
+
+const NAMESPACE: &str = "SUBSCRIBERS";
+
+#[derive(Debug)]
+pub(super) enum SubscribeInput {
+    Command(SubscribeCommand),
+    /// The issue changed in a way subscribers may want to hear about.
+    Changed(Change),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(super) enum Change {
+    Closed,
+    Reopened,
+    Labeled(String),
+    Unlabeled(String),
+}
+
+impl Change {
+    fn from_event(event: &Event) -> Option<Change> {
+        let label = |label: &Option<Label>| label.as_ref().map(|label| label.name.clone());
+        match event {
+            Event::Issue(e) => match e.action {
+                IssuesAction::Closed => Some(Change::Closed),
+                IssuesAction::Reopened => Some(Change::Reopened),
+                IssuesAction::Labeled => label(&e.label).map(Change::Labeled),
+                IssuesAction::Unlabeled => label(&e.label).map(Change::Unlabeled),
+                _ => None,
+            },
+            Event::PullRequest(e) => match e.action {
+                PullRequestAction::Closed => Some(Change::Closed),
+                PullRequestAction::Reopened => Some(Change::Reopened),
+                PullRequestAction::Labeled => label(&e.label).map(Change::Labeled),
+                PullRequestAction::Unlabeled => label(&e.label).map(Change::Unlabeled),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn kind(&self) -> SubscribeEvent {
+        match self {
+            Change::Closed | Change::Reopened => SubscribeEvent::State,
+            Change::Labeled(_) | Change::Unlabeled(_) => SubscribeEvent::Labels,
+        }
+    }
+
+    /// What happened, as the `change` in the `subscribers` message.
+    fn describe(&self) -> String {
+        match self {
+            Change::Closed => "closed this".to_owned(),
+            Change::Reopened => "reopened this".to_owned(),
+            Change::Labeled(label) => format!("added the `{}` label", label),
+            Change::Unlabeled(label) => format!("removed the `{}` label", label),
+        }
+    }
+}
+
+impl Handler for SubscribeHandler {
+    type Input = SubscribeInput;
+    type Config = SubscribeConfig;
+
+    fn parse_input(&self, ctx: &Context, event: &Event) -> Result<Vec<Self::Input>, Error> {
+        let event = if let Event::IssueComment(e) = event {
+            e
+        } else {
+            return Ok(Change::from_event(event)
+                .map(SubscribeInput::Changed)
+                .into_iter()
+                .collect());
+        };
+
+        let mut commands = Vec::new();
+        super::comment_commands(ctx, event, |command| {
+            match command {
+                Command::Subscribe(Ok(command)) => commands.push(SubscribeInput::Command(command)),
+                Command::Subscribe(Err(err)) => {
+                    failure::bail!(
+                        "Parsing subscribe command in [comment]({}) failed: {}\n\n{}",
+                        event.comment.html_url,
+                        err,
+                        err.snippet()
+                    );
+                }
+                _ => {}
+            }
+            Ok(())
+        })?;
+        Ok(commands)
+    }
+
+    fn requested(&self, input: &SubscribeInput) -> bool {
+        match input {
+            SubscribeInput::Command(_) => true,
+            SubscribeInput::Changed(_) => false,
+        }
+    }
+
+    fn handle_input(
+        &self,
+        ctx: &Context,
+        config: &SubscribeConfig,
+        event: &Event,
+        input: SubscribeInput,
+    ) -> Result<(), Error> {
+        let issue = match event.issue() {
+            Some(issue) => issue,
+            None => return Ok(()),
+        };
+        let repo = event.repo_name();
+        let key = StateKey::new(repo, issue.number, NAMESPACE);
+        let mut subscribers = ctx.state.get::<Vec<String>>(&key)?.unwrap_or_default();
+
+        let cmd = match input {
+            SubscribeInput::Command(cmd) => cmd,
+            SubscribeInput::Changed(change) => {
+                if !config.events.contains(&change.kind()) {
+                    return Ok(());
+                }
+                let actor = event.actor();
+                let mentions = subscribers
+                    .iter()
+                    .filter(|user| !user.eq_ignore_ascii_case(actor))
+                    .map(|user| format!("@{}", user))
+                    .collect::<Vec<_>>();
+                if mentions.is_empty() {
+                    return Ok(());
+                }
+                let message = super::render_template(
+                    ctx,
+                    repo,
+                    "subscribers",
+                    &[
+                        ("user", actor),
+                        ("change", &change.describe()),
+                        ("mentions", &mentions.join(" ")),
+                        ("bot", &ctx.username),
+                    ],
+                )?;
+                return issue.post_comment(&ctx.github, &message);
+            }
+        };
+
+        let requester = event.actor();
+        let (username, subscribe) = match &cmd {
+            SubscribeCommand::Subscribe { username } => (username, true),
+            SubscribeCommand::Unsubscribe { username } => (username, false),
+        };
+        let user = username.as_ref().map_or(requester, |user| user.as_str());
+        if !user.eq_ignore_ascii_case(requester) {
+            let is_team_member = match event {
+                Event::IssueComment(e) => e.comment.user.is_team_member(&ctx.github),
+                _ => Ok(false),
+            };
+            if !is_team_member.unwrap_or(false) {
+                return Err(CommandError::Permission(
+                    "Only Rust team members can subscribe or unsubscribe other users".to_owned(),
+                )
+                .into());
+            }
+        }
+        if user.eq_ignore_ascii_case(&ctx.username) {
+            failure::bail!("I can't subscribe to issues myself");
+        }
+
+        let changed = if subscribe {
+            add_subscriber(&mut subscribers, user)
+        } else {
+            remove_subscriber(&mut subscribers, user)
+        };
+        if changed {
+            ctx.state.set(&key, &subscribers)?;
+        }
+        Ok(())
+    }
+}
+
+/// Adds `user` to `subscribers`, unless they're already there; returns whether they weren't.
+fn add_subscriber(subscribers: &mut Vec<String>, user: &str) -> bool {
+    // GitHub usernames are case-insensitive.
+    if subscribers.iter().any(|s| s.eq_ignore_ascii_case(user)) {
+        return false;
+    }
+    subscribers.push(user.to_owned());
+    true
+}
+
+/// Removes `user` from `subscribers`; returns whether they were there.
+fn remove_subscriber(subscribers: &mut Vec<String>, user: &str) -> bool {
+    let before = subscribers.len();
+    subscribers.retain(|s| !s.eq_ignore_ascii_case(user));
+    subscribers.len() != before
+}
+
+#[test]
+fn subscribe_round_trip() {
+    let mut subscribers = Vec::new();
+    assert!(add_subscriber(&mut subscribers, "alice"));
+    assert!(add_subscriber(&mut subscribers, "bob"));
+    assert!(!add_subscriber(&mut subscribers, "Alice"));
+    assert_eq!(subscribers, ["alice", "bob"]);
+    assert!(remove_subscriber(&mut subscribers, "ALICE"));
+    assert!(!remove_subscriber(&mut subscribers, "alice"));
+    assert_eq!(subscribers, ["bob"]);
+    assert!(add_subscriber(&mut subscribers, "alice"));
+    assert_eq!(subscribers, ["bob", "alice"]);
+}
+
+#[test]
+fn subscribers_stored() {
+    let state = crate::db::StateStore::in_memory();
+    let key = StateKey::new("rust-lang/rust", 12, NAMESPACE);
+    let mut subscribers = state.get::<Vec<String>>(&key).unwrap().unwrap_or_default();
+    add_subscriber(&mut subscribers, "alice");
+    state.set(&key, &subscribers).unwrap();
+    let mut subscribers = state.get::<Vec<String>>(&key).unwrap().unwrap();
+    assert_eq!(subscribers, ["alice"]);
+    remove_subscriber(&mut subscribers, "alice");
+    state.set(&key, &subscribers).unwrap();
+    assert!(state.get::<Vec<String>>(&key).unwrap().unwrap().is_empty());
+}
+
+#[test]
+fn changes_described() {
+    assert_eq!(Change::Closed.describe(), "closed this");
+    assert_eq!(
+        Change::Labeled("T-compiler".to_owned()).describe(),
+        "added the `T-compiler` label"
+    );
+    assert_eq!(
+        Change::Unlabeled("C-bug".to_owned()).kind(),
+        SubscribeEvent::Labels
+    );
+    assert_eq!(Change::Reopened.kind(), SubscribeEvent::State);
+}
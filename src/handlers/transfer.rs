@@ -0,0 +1,143 @@
+//! Purpose: Allow team members to move issues to the right repository.
+//!
+//! `@rustbot transfer rust-lang/cargo` moves the issue to that repository (GitHub keeps a
+//! redirect from its old location), and then comments on it there, saying where it came from.
+//! With `same-owner-only`, issues can only be moved between repositories of the same owner.
+//! Either way, the target must be one of the repository's `trusted_repos`, so that the bot
+//! can't be used to post to arbitrary repositories.
+
+use crate::{
+    config::TransferConfig,
+    github::{Event, Repository},
+    handlers::{CommandError, Context, Handler},
+};
+use failure::Error;
+use parser::command::transfer::TransferCommand;
+use parser::command::Command;
+
+pub(super) struct TransferHandler;
+
+impl Handler for TransferHandler {
+    type Input = TransferCommand;
+    type Config = TransferConfig;
+
+    fn parse_input(&self, ctx: &Context, event: &Event) -> Result<Vec<Self::Input>, Error> {
+        let event = if let Event::IssueComment(e) = event {
+            e
+        } else {
+            // not interested in other events
+            return Ok(Vec::new());
+        };
+
+        let mut commands = Vec::new();
+        super::comment_commands(ctx, event, |command| {
+            match command {
+                Command::Transfer(Ok(command)) => commands.push(command),
+                Command::Transfer(Err(err)) => {
+                    failure::bail!(
+                        "Parsing transfer command in [comment]({}) failed: {}\n\n{}",
+                        event.comment.html_url,
+                        err,
+                        err.snippet()
+                    );
+                }
+                _ => {}
+            }
+            Ok(())
+        })?;
+        Ok(commands)
+    }
+
+    fn handle_input(
+        &self,
+        ctx: &Context,
+        config: &TransferConfig,
+        event: &Event,
+        cmd: TransferCommand,
+    ) -> Result<(), Error> {
+        let event = if let Event::IssueComment(e) = event {
+            e
+        } else {
+            // not interested in other events
+            return Ok(());
+        };
+
+        let is_team_member = event
+            .comment
+            .user
+            .is_team_member(&ctx.github)
+            .unwrap_or(false);
+        if !is_team_member {
+            return Err(CommandError::Permission(
+                "Only Rust team members can transfer issues".to_owned(),
+            )
+            .into());
+        }
+        if event.issue.is_pr() {
+            failure::bail!("Pull requests cannot be transferred");
+        }
+
+        let repo = &event.repository;
+        let target: Repository = cmd.repo.parse()?;
+        check_target(config, repo, &target)?;
+        crate::config::get(&ctx.github, repo.full_name())?.check_trusted(repo, &target)?;
+        let number = match event.issue.transfer(&ctx.github, repo, &target)? {
+            Some(number) => number,
+            // A dry run.
+            None => return Ok(()),
+        };
+        let message = super::render_template(
+            ctx,
+            repo.full_name(),
+            "transferred",
+            &[
+                ("source", &format!("{}#{}", repo, event.issue.number)),
+                ("user", &event.comment.user.login),
+            ],
+        )?;
+        target.post_comment(&ctx.github, number, &message)?;
+
+        Ok(())
+    }
+}
+
+/// Checks that an issue in `repo` may be moved to `target`.
+fn check_target(
+    config: &TransferConfig,
+    repo: &Repository,
+    target: &Repository,
+) -> Result<(), Error> {
+    if repo.full_name().eq_ignore_ascii_case(target.full_name()) {
+        failure::bail!("This issue is already in `{}`", repo);
+    }
+    if config.same_owner_only && !repo.owner().eq_ignore_ascii_case(target.owner()) {
+        failure::bail!(
+            "Issues in `{}` can only be transferred to other repositories of `{}`",
+            repo,
+            repo.owner()
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn transfer_targets() {
+    let anywhere = TransferConfig {
+        same_owner_only: false,
+    };
+    let same_owner = TransferConfig {
+        same_owner_only: true,
+    };
+    let check = |config, target: &str| {
+        check_target(
+            config,
+            &"rust-lang/rust".parse().unwrap(),
+            &target.parse().unwrap(),
+        )
+    };
+    assert!(check(&anywhere, "rust-lang/cargo").is_ok());
+    assert!(check(&anywhere, "rust-lang-nursery/rls").is_ok());
+    assert!(check(&same_owner, "Rust-Lang/cargo").is_ok());
+    assert!(check(&same_owner, "rust-lang-nursery/rls").is_err());
+    assert!(check(&anywhere, "rust-lang/Rust").is_err());
+}
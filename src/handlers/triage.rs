@@ -0,0 +1,464 @@
+//! Purpose: Allow team members to set the priority of issues.
+//!
+//! The valid priorities are configured per repository as an ordered list of labels. An issue
+//! carries at most one of them: when a new priority is applied, any other priority label from
+//! the list is removed.
+//!
+//! Set the priority with `@rustbot prioritize P-high`, or remove it with `@rustbot prioritize
+//! none` (which says which priority was removed, if any). If the repository also configures
+//! status labels, which work the same way, both can be set at once with
+//! `@rustbot prioritize P-high status S-needs-decision`; either both are applied, together, or
+//! neither is.
+//!
+//! If `[triage.waiting]` is configured, an issue labeled as waiting on its author
+//! (`S-waiting-on-author` by default) is relabeled as waiting on review (`S-waiting-on-review`)
+//! when the author comments on it.
+
+use crate::{
+    config::{TriageConfig, WaitingConfig},
+    github::{self, Event, IssueCommentAction},
+    handlers::{CommandError, Context, Handler},
+};
+use failure::Error;
+use parser::command::relabel::LabelDelta;
+use parser::command::triage::TriageCommand;
+use parser::command::Command;
+
+pub(super) struct TriageHandler;
+
+#[derive(Debug)]
+pub(super) enum TriageInput {
+    Prioritize(TriageCommand),
+    /// The issue's author commented on it.
+    AuthorReplied,
+}
+
+impl Handler for TriageHandler {
+    type Input = TriageInput;
+    type Config = TriageConfig;
+
+    fn parse_input(&self, ctx: &Context, event: &Event) -> Result<Vec<Self::Input>, Error> {
+        let event = if let Event::IssueComment(e) = event {
+            e
+        } else {
+            // not interested in other events
+            return Ok(Vec::new());
+        };
+
+        let mut commands = Vec::new();
+        super::comment_commands(ctx, event, |command| {
+            match command {
+                Command::Triage(Ok(command)) => commands.push(TriageInput::Prioritize(command)),
+                Command::Triage(Err(err)) => {
+                    failure::bail!(
+                        "Parsing triage command in [comment]({}) failed: {}\n\n{}",
+                        event.comment.html_url,
+                        err,
+                        err.snippet()
+                    );
+                }
+                _ => {}
+            }
+            Ok(())
+        })?;
+
+        if event.action == IssueCommentAction::Created
+            && event.comment.user.login == event.issue.user.login
+        {
+            commands.push(TriageInput::AuthorReplied);
+        }
+        Ok(commands)
+    }
+
+    fn requested(&self, input: &TriageInput) -> bool {
+        // Nobody asks for relabeling on replies, so repositories which haven't configured it are
+        // left alone.
+        match input {
+            TriageInput::Prioritize(_) => true,
+            TriageInput::AuthorReplied => false,
+        }
+    }
+
+    fn handle_input(
+        &self,
+        ctx: &Context,
+        config: &TriageConfig,
+        event: &Event,
+        input: TriageInput,
+    ) -> Result<(), Error> {
+        let event = if let Event::IssueComment(e) = event {
+            e
+        } else {
+            // not interested in other events
+            return Ok(());
+        };
+
+        let cmd = match input {
+            TriageInput::Prioritize(cmd) => cmd,
+            TriageInput::AuthorReplied => {
+                let waiting = match &config.waiting {
+                    Some(waiting) => waiting,
+                    None => return Ok(()),
+                };
+                let (remove, add) = author_reply_changes(waiting, event.issue.labels());
+                let deltas = remove
+                    .map(|label| LabelDelta::Remove(label.into()))
+                    .into_iter()
+                    .chain(add.map(|label| LabelDelta::Add(label.into())))
+                    .collect();
+                return ctx.labels.apply(
+                    &ctx.github,
+                    event.repository.full_name(),
+                    &event.issue,
+                    deltas,
+                );
+            }
+        };
+
+        check_command(config, &cmd)?;
+
+        let is_team_member = event
+            .comment
+            .user
+            .is_team_member(&ctx.github)
+            .unwrap_or(false);
+        if !is_team_member {
+            return Err(CommandError::Permission(
+                "Only Rust team members can prioritize issues".to_owned(),
+            )
+            .into());
+        }
+
+        let cleared = match cmd.priority {
+            Some(_) => None,
+            None => Some(present(&config.priorities, event.issue.labels())),
+        };
+        let deltas = triage_deltas(config, event.issue.labels(), &cmd);
+        ctx.labels.apply_now(
+            &ctx.github,
+            event.repository.full_name(),
+            &event.issue,
+            deltas,
+        )?;
+        if let Some(cleared) = cleared {
+            event
+                .issue
+                .post_comment(&ctx.github, &cleared_message(&cleared))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Checks that the priority and status `cmd` sets are both valid, so that it's applied either
+/// entirely or not at all.
+fn check_command(config: &TriageConfig, cmd: &TriageCommand) -> Result<(), Error> {
+    if let Some(priority) = &cmd.priority {
+        if !config.priorities.contains(priority) {
+            failure::bail!(
+                "`{}` is not a valid priority; the valid priorities are {}",
+                priority,
+                config
+                    .priorities
+                    .iter()
+                    .map(|p| format!("`{}`", p))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+    }
+    if let Some(status) = &cmd.status {
+        if config.statuses.is_empty() {
+            failure::bail!("No statuses are configured for this repository");
+        }
+        if !config.statuses.contains(status) {
+            failure::bail!(
+                "`{}` is not a valid status; the valid statuses are {}",
+                status,
+                config
+                    .statuses
+                    .iter()
+                    .map(|s| format!("`{}`", s))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Returns the labels from `group` to remove from an issue labeled with `labels` when giving it
+/// `label` (one of the group), and whether `label` itself still needs to be added.
+fn exclusive_changes<'a>(
+    group: &'a [String],
+    labels: &[github::Label],
+    label: &str,
+) -> (Vec<&'a str>, bool) {
+    let remove = group
+        .iter()
+        .filter(|l| *l != label && labels.iter().any(|existing| &existing.name == *l))
+        .map(|l| l.as_str())
+        .collect();
+    let add = !labels.iter().any(|l| l.name == label);
+    (remove, add)
+}
+
+/// Returns the labels from `group` which an issue labeled with `labels` has.
+pub(super) fn present<'a>(group: &'a [String], labels: &[github::Label]) -> Vec<&'a str> {
+    group
+        .iter()
+        .filter(|l| labels.iter().any(|existing| &existing.name == *l))
+        .map(|l| l.as_str())
+        .collect()
+}
+
+/// Returns the changes which give an issue labeled with `labels` the priority and status `cmd`
+/// sets, removing the other labels of each group; they're empty if it has the right ones already.
+fn triage_deltas(
+    config: &TriageConfig,
+    labels: &[github::Label],
+    cmd: &TriageCommand,
+) -> Vec<LabelDelta> {
+    let status = cmd
+        .status
+        .as_ref()
+        .map(|status| (&config.statuses, Some(status)));
+    let mut deltas = Vec::new();
+    for (group, label) in Some((&config.priorities, cmd.priority.as_ref()))
+        .into_iter()
+        .chain(status)
+    {
+        let label = match label {
+            Some(label) => label,
+            None => {
+                deltas.extend(
+                    present(group, labels)
+                        .into_iter()
+                        .map(|l| LabelDelta::Remove(l.into())),
+                );
+                continue;
+            }
+        };
+        let (remove, add) = exclusive_changes(group, labels, label);
+        deltas.extend(remove.into_iter().map(|l| LabelDelta::Remove(l.into())));
+        if add {
+            deltas.push(LabelDelta::Add(label.as_str().into()));
+        }
+    }
+    deltas
+}
+
+/// The reply to clearing the priority, when the issue had the priorities `cleared`.
+fn cleared_message(cleared: &[&str]) -> String {
+    if cleared.is_empty() {
+        return "No priority was set.".to_owned();
+    }
+    format!(
+        "Removed the priority {}.",
+        cleared
+            .iter()
+            .map(|p| format!("`{}`", p))
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+}
+
+/// Returns the label to remove from an issue labeled with `labels` when its author replies, and
+/// the label to add: none if the issue wasn't waiting on its author.
+fn author_reply_changes<'a>(
+    waiting: &'a WaitingConfig,
+    labels: &[github::Label],
+) -> (Option<&'a str>, Option<&'a str>) {
+    let has = |name: &str| labels.iter().any(|l| l.name == name);
+    if !has(&waiting.on_author) {
+        return (None, None);
+    }
+    let add = if has(&waiting.on_review) {
+        None
+    } else {
+        Some(waiting.on_review.as_str())
+    };
+    (Some(waiting.on_author.as_str()), add)
+}
+
+#[cfg(test)]
+use crate::github::labels;
+
+#[cfg(test)]
+fn config() -> TriageConfig {
+    TriageConfig {
+        priorities: vec![
+            "P-low".to_owned(),
+            "P-medium".to_owned(),
+            "P-high".to_owned(),
+            "P-critical".to_owned(),
+        ],
+        statuses: vec!["S-needs-decision".to_owned(), "S-blocked".to_owned()],
+        waiting: None,
+    }
+}
+
+#[cfg(test)]
+fn triage(priority: &str, status: Option<&str>) -> TriageCommand {
+    TriageCommand {
+        priority: Some(priority.to_owned()),
+        status: status.map(|s| s.to_owned()),
+    }
+}
+
+#[test]
+fn priority_new() {
+    let config = config();
+    assert_eq!(
+        exclusive_changes(&config.priorities, &labels(&["C-bug"]), "P-high"),
+        (vec![], true)
+    );
+}
+
+#[test]
+fn priority_swap_existing() {
+    let config = config();
+    assert_eq!(
+        exclusive_changes(&config.priorities, &labels(&["C-bug", "P-low"]), "P-high"),
+        (vec!["P-low"], true)
+    );
+}
+
+#[test]
+fn priority_swap_multiple_existing() {
+    let config = config();
+    assert_eq!(
+        exclusive_changes(
+            &config.priorities,
+            &labels(&["P-critical", "P-low"]),
+            "P-medium"
+        ),
+        (vec!["P-low", "P-critical"], true)
+    );
+}
+
+#[test]
+fn priority_already_set() {
+    let config = config();
+    assert_eq!(
+        exclusive_changes(&config.priorities, &labels(&["P-high"]), "P-high"),
+        (vec![], false)
+    );
+}
+
+#[test]
+fn triage_priority_and_status() {
+    let config = config();
+    assert_eq!(
+        triage_deltas(
+            &config,
+            &labels(&["C-bug", "P-low", "S-blocked"]),
+            &triage("P-high", Some("S-needs-decision"))
+        ),
+        [
+            LabelDelta::Remove("P-low".into()),
+            LabelDelta::Add("P-high".into()),
+            LabelDelta::Remove("S-blocked".into()),
+            LabelDelta::Add("S-needs-decision".into()),
+        ]
+    );
+}
+
+#[test]
+fn triage_priority_keeps_status() {
+    let config = config();
+    assert_eq!(
+        triage_deltas(
+            &config,
+            &labels(&["P-low", "S-blocked"]),
+            &triage("P-high", None)
+        ),
+        [
+            LabelDelta::Remove("P-low".into()),
+            LabelDelta::Add("P-high".into()),
+        ]
+    );
+}
+
+#[test]
+fn triage_already_applied() {
+    let config = config();
+    assert!(triage_deltas(
+        &config,
+        &labels(&["P-high", "S-blocked"]),
+        &triage("P-high", Some("S-blocked"))
+    )
+    .is_empty());
+}
+
+#[test]
+fn triage_clear() {
+    let config = config();
+    let clear = TriageCommand {
+        priority: None,
+        status: None,
+    };
+    let issue = labels(&["C-bug", "P-low", "S-blocked"]);
+    assert_eq!(
+        triage_deltas(&config, &issue, &clear),
+        [LabelDelta::Remove("P-low".into())]
+    );
+    assert_eq!(
+        cleared_message(&present(&config.priorities, &issue)),
+        "Removed the priority `P-low`."
+    );
+    let issue = labels(&["C-bug"]);
+    assert!(triage_deltas(&config, &issue, &clear).is_empty());
+    assert_eq!(
+        cleared_message(&present(&config.priorities, &issue)),
+        "No priority was set."
+    );
+    assert!(check_command(&config, &clear).is_ok());
+}
+
+#[test]
+fn triage_rejects_invalid() {
+    let config = config();
+    assert!(check_command(&config, &triage("P-high", Some("S-needs-decision"))).is_ok());
+    assert!(check_command(&config, &triage("P-high", Some("S-bogus"))).is_err());
+    assert!(check_command(&config, &triage("P-bogus", Some("S-blocked"))).is_err());
+    assert!(check_command(&config, &triage("P-bogus", None)).is_err());
+}
+
+#[cfg(test)]
+fn waiting() -> WaitingConfig {
+    WaitingConfig {
+        on_author: "S-waiting-on-author".to_owned(),
+        on_review: "S-waiting-on-review".to_owned(),
+    }
+}
+
+#[test]
+fn author_reply_swaps_status() {
+    let waiting = waiting();
+    assert_eq!(
+        author_reply_changes(&waiting, &labels(&["C-bug", "S-waiting-on-author"])),
+        (Some("S-waiting-on-author"), Some("S-waiting-on-review"))
+    );
+}
+
+#[test]
+fn author_reply_not_waiting() {
+    let waiting = waiting();
+    assert_eq!(
+        author_reply_changes(&waiting, &labels(&["C-bug", "S-waiting-on-review"])),
+        (None, None)
+    );
+}
+
+#[test]
+fn author_reply_already_waiting_on_review() {
+    let waiting = waiting();
+    assert_eq!(
+        author_reply_changes(
+            &waiting,
+            &labels(&["S-waiting-on-author", "S-waiting-on-review"])
+        ),
+        (Some("S-waiting-on-author"), None)
+    );
+}
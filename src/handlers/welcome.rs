@@ -0,0 +1,70 @@
+//! Purpose: Welcome first-time contributors.
+//!
+//! When someone opens their first issue or pull request in a repository, we post the configured
+//! welcome message (pointing them to the contribution guide, for example) on it.
+//!
+//! We record that we've welcomed someone on an issue in the state store, so that handling the
+//! same event twice doesn't post the message twice.
+
+use crate::{
+    config::WelcomeConfig,
+    db::StateKey,
+    github::{Event, IssuesAction, PullRequestAction},
+    handlers::{Context, Handler},
+};
+use failure::Error;
+
+pub(super) struct WelcomeHandler;
+
+const NAMESPACE: &str = "WELCOME";
+
+impl Handler for WelcomeHandler {
+    type Input = ();
+    type Config = WelcomeConfig;
+    const AUTOMATIC: bool = true;
+
+    fn parse_input(&self, _ctx: &Context, event: &Event) -> Result<Vec<Self::Input>, Error> {
+        let opened = match event {
+            Event::Issue(e) => e.action == IssuesAction::Opened,
+            Event::PullRequest(e) => e.action == PullRequestAction::Opened,
+            _ => false,
+        };
+        if !opened {
+            return Ok(Vec::new());
+        }
+        Ok(vec![()])
+    }
+
+    fn handle_input(
+        &self,
+        ctx: &Context,
+        config: &WelcomeConfig,
+        event: &Event,
+        _input: (),
+    ) -> Result<(), Error> {
+        let issue = match event.issue() {
+            Some(issue) => issue,
+            None => return Ok(()),
+        };
+        let repo = event.repo_name();
+        let key = StateKey::new(repo, issue.number, NAMESPACE);
+        if ctx.state.get::<bool>(&key)?.unwrap_or(false) {
+            // Already welcomed.
+            return Ok(());
+        }
+
+        let user = &issue.user.login;
+        if ctx
+            .github
+            .has_other_issues(event.repository(), user, issue.number)?
+        {
+            return Ok(());
+        }
+
+        let message = config.message.replace("{user}", user);
+        issue.post_comment(&ctx.github, &message)?;
+        ctx.state.set(&key, &true)?;
+
+        Ok(())
+    }
+}
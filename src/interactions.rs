@@ -1,6 +1,9 @@
 use crate::github::{GithubClient, Issue};
 use failure::Error;
+use rand::Rng;
 use std::fmt::Write;
+use std::thread;
+use std::time::Duration;
 
 pub struct ErrorComment<'a> {
     issue: &'a Issue,
@@ -38,53 +41,53 @@ pub struct EditIssueBody<'a> {
 static START_BOT: &str = "<!-- TRIAGEBOT_START -->\n\n";
 static END_BOT: &str = "<!-- TRIAGEBOT_END -->";
 
+/// How many times the body is edited again when someone else's edit overwrites ours.
+const MAX_EDIT_ATTEMPTS: u32 = 3;
+
 impl<'a> EditIssueBody<'a> {
     pub fn new(issue: &'a Issue, id: &'static str) -> EditIssueBody<'a> {
         EditIssueBody { issue, id }
     }
 
     fn get_current(&self) -> Option<&str> {
-        let start_section = self.start_section();
-        let end_section = self.end_section();
-        if self.issue.body.contains(START_BOT) {
-            if self.issue.body.contains(&start_section) {
-                let start_idx = self.issue.body.find(&start_section).unwrap();
-                let end_idx = self.issue.body.find(&end_section).unwrap();
-                Some(&self.issue.body[start_idx..(end_idx + end_section.len())])
-            } else {
-                None
-            }
-        } else {
-            None
-        }
+        let (start, end) = find_section(&self.issue.body, self.id)?;
+        Some(&self.issue.body[start..end])
     }
 
-    pub fn current_data<T: serde::de::DeserializeOwned>(&self) -> Option<T> {
+    /// The text currently in this section, excluding its data.
+    ///
+    /// `None` if there's no such section, or it has been edited out of shape.
+    pub fn current_text(&self) -> Option<&str> {
         let all = self.get_current()?;
-        let start = self.data_section_start();
-        let end = self.data_section_end();
-        let start_idx = all.find(&start).unwrap();
-        let end_idx = all.find(&end).unwrap();
-        let text = &all[(start_idx + start.len())..end_idx];
-        Some(serde_json::from_str(text).unwrap_or_else(|e| {
-            panic!("deserializing data {:?} failed: {:?}", text, e);
-        }))
-    }
-
-    fn start_section(&self) -> String {
-        format!("<!-- TRIAGEBOT_{}_START -->\n", self.id)
+        let start = start_section(self.id);
+        let end_idx = all.find(&data_section_start(self.id))?;
+        all.get(start.len()..end_idx)
     }
 
-    fn end_section(&self) -> String {
-        format!("\n<!-- TRIAGEBOT_{}_END -->\n", self.id)
-    }
-
-    fn data_section_start(&self) -> String {
-        format!("\n<!-- TRIAGEBOT_{}_DATA_START$$", self.id)
-    }
-
-    fn data_section_end(&self) -> String {
-        format!("$$TRIAGEBOT_{}_DATA_END -->\n", self.id)
+    /// The data currently in this section.
+    ///
+    /// `None` if there's no such section, or its data can't be read, e.g. because someone edited
+    /// it by hand; the next `apply` replaces it.
+    pub fn current_data<T: serde::de::DeserializeOwned>(&self) -> Option<T> {
+        let all = self.get_current()?;
+        let start = data_section_start(self.id);
+        let end = data_section_end(self.id);
+        let start_idx = all.find(&start)? + start.len();
+        let end_idx = start_idx + all[start_idx..].find(&end)?;
+        let text = &all[start_idx..end_idx];
+        match serde_json::from_str(text) {
+            Ok(data) => Some(data),
+            Err(e) => {
+                log::warn!(
+                    "ignoring unreadable {} data on #{}: {:?}: {}",
+                    self.id,
+                    self.issue.number,
+                    text,
+                    e
+                );
+                None
+            }
+        }
     }
 
     fn data_section<T>(&self, data: T) -> String
@@ -93,51 +96,191 @@ impl<'a> EditIssueBody<'a> {
     {
         format!(
             "{}{}{}",
-            self.data_section_start(),
+            data_section_start(self.id),
             serde_json::to_string(&data).unwrap(),
-            self.data_section_end()
+            data_section_end(self.id)
         )
     }
 
+    /// Replaces this section of the issue body with `text` and `data`.
+    ///
+    /// The body is re-fetched rather than taken from the event, as it (or other sections) may
+    /// have been edited since. GitHub has no way of making the edit conditional on the body not
+    /// having changed in the meantime, so it's read once more afterwards: if someone else's
+    /// edit replaced ours, this section won't be as we left it, and the edit is redone against
+    /// the new body a few times before giving up.
     pub fn apply<T>(&self, client: &GithubClient, text: String, data: T) -> Result<(), Error>
     where
         T: serde::Serialize,
     {
-        let mut current_body = self.issue.body.clone();
-        let start_section = self.start_section();
-        let end_section = self.end_section();
-
         let bot_section = format!(
             "{}{}{}{}",
-            start_section,
+            start_section(self.id),
             text,
             self.data_section(data),
-            end_section
+            end_section(self.id)
         );
-        let empty_bot_section = format!("{}{}", start_section, end_section);
-
-        let all_new = format!("\n\n{}{}{}", START_BOT, bot_section, END_BOT);
-        if current_body.contains(START_BOT) {
-            if current_body.contains(&start_section) {
-                let start_idx = current_body.find(&start_section).unwrap();
-                let end_idx = current_body.find(&end_section).unwrap();
-                current_body.replace_range(start_idx..(end_idx + end_section.len()), &bot_section);
-                if current_body.contains(&all_new) && bot_section == empty_bot_section {
-                    let start_idx = current_body.find(&all_new).unwrap();
-                    let end_idx = start_idx + all_new.len();
-                    current_body.replace_range(start_idx..end_idx, "");
-                }
-                self.issue.edit_body(&client, &current_body)?;
-            } else {
-                let end_idx = current_body.find(&END_BOT).unwrap();
-                current_body.insert_str(end_idx, &bot_section);
-                self.issue.edit_body(&client, &current_body)?;
+
+        let mut attempt = 1;
+        loop {
+            let current_body = self.issue.fetch_body(client)?;
+            let new_body = splice(&current_body, self.id, &bot_section);
+            if new_body == current_body {
+                return Ok(());
             }
-        } else {
-            let new_body = format!("{}{}", current_body, all_new);
+            self.issue.edit_body(client, &new_body)?;
+            if client.is_dry_run()
+                || has_section(&self.issue.fetch_body(client)?, self.id, &bot_section)
+            {
+                return Ok(());
+            }
+            if attempt >= MAX_EDIT_ATTEMPTS {
+                failure::bail!(
+                    "the body of #{} kept changing while editing it",
+                    self.issue.number
+                );
+            }
+            log::debug!(
+                "edit of the {} section of #{} was overwritten, retrying",
+                self.id,
+                self.issue.number
+            );
+            thread::sleep(Duration::from_millis(
+                rand::thread_rng().gen_range(100, 500),
+            ));
+            attempt += 1;
+        }
+    }
+}
+
+fn start_section(id: &str) -> String {
+    format!("<!-- TRIAGEBOT_{}_START -->\n", id)
+}
+
+fn end_section(id: &str) -> String {
+    format!("\n<!-- TRIAGEBOT_{}_END -->\n", id)
+}
+
+fn data_section_start(id: &str) -> String {
+    format!("\n<!-- TRIAGEBOT_{}_DATA_START$$", id)
+}
+
+fn data_section_end(id: &str) -> String {
+    format!("$$TRIAGEBOT_{}_DATA_END -->\n", id)
+}
+
+/// The range of the bot's block of `body`, excluding its end marker.
+fn find_bot_block(body: &str) -> Option<(usize, usize)> {
+    let start = body.find(START_BOT)?;
+    let end = start + body[start..].find(END_BOT)?;
+    Some((start, end))
+}
+
+/// The range of the section `id` within the bot's block of `body`, including its markers.
+fn find_section(body: &str, id: &str) -> Option<(usize, usize)> {
+    let (block_start, block_end) = find_bot_block(body)?;
+    let block = &body[block_start..block_end];
+    let start_marker = start_section(id);
+    let end_marker = end_section(id);
+    let start = block.find(&start_marker)?;
+    let end = start + block[start..].find(&end_marker)? + end_marker.len();
+    Some((block_start + start, block_start + end))
+}
 
-            self.issue.edit_body(&client, &new_body)?;
+/// Whether the section `id` of `body` is exactly `section` (with its markers).
+fn has_section(body: &str, id: &str, section: &str) -> bool {
+    find_section(body, id).map_or(false, |(start, end)| &body[start..end] == section)
+}
+
+/// Puts `section` (the section `id`, with its markers) into `body`, replacing any previous
+/// version of it and leaving other sections alone.
+fn splice(body: &str, id: &str, section: &str) -> String {
+    let mut body = body.to_owned();
+    if let Some((start, end)) = find_section(&body, id) {
+        body.replace_range(start..end, section);
+        // Drop the bot's block entirely if all that's left is this section, empty.
+        let empty = format!(
+            "\n\n{}{}{}{}",
+            START_BOT,
+            start_section(id),
+            end_section(id),
+            END_BOT
+        );
+        if let Some(idx) = body.find(&empty) {
+            body.replace_range(idx..(idx + empty.len()), "");
         }
-        Ok(())
+    } else if let Some((_, block_end)) = find_bot_block(&body) {
+        body.insert_str(block_end, section);
+    } else {
+        body.push_str(&format!("\n\n{}{}{}", START_BOT, section, END_BOT));
     }
+    body
+}
+
+#[cfg(test)]
+fn section(id: &str, text: &str) -> String {
+    format!("{}{}{}", start_section(id), text, end_section(id))
+}
+
+#[test]
+fn splice_first_write() {
+    assert_eq!(
+        splice("Some text.", "A", &section("A", "a")),
+        format!(
+            "Some text.\n\n{}{}{}",
+            START_BOT,
+            section("A", "a"),
+            END_BOT
+        )
+    );
+}
+
+#[test]
+fn splice_replaces_section() {
+    let body = splice("Some text.", "A", &section("A", "a"));
+    assert_eq!(
+        splice(&body, "A", &section("A", "b")),
+        format!(
+            "Some text.\n\n{}{}{}",
+            START_BOT,
+            section("A", "b"),
+            END_BOT
+        )
+    );
+}
+
+#[test]
+fn splice_sections_coexist() {
+    let body = splice("Some text.", "A", &section("A", "a"));
+    let body = splice(&body, "B", &section("B", "b"));
+    let body = splice(&body, "A", &section("A", "c"));
+    assert_eq!(
+        body,
+        format!(
+            "Some text.\n\n{}{}{}{}",
+            START_BOT,
+            section("A", "c"),
+            section("B", "b"),
+            END_BOT
+        )
+    );
+    let (start, end) = find_section(&body, "B").unwrap();
+    assert_eq!(&body[start..end], section("B", "b"));
+}
+
+#[test]
+fn splice_removes_empty_block() {
+    let body = splice("Some text.", "A", &section("A", "a"));
+    assert_eq!(splice(&body, "A", &section("A", "")), "Some text.");
+}
+
+#[test]
+fn section_overwritten() {
+    let ours = splice("Some text.", "A", &section("A", "a"));
+    assert!(has_section(&ours, "A", &section("A", "a")));
+    // Someone else's edit, made from the body as it was before ours, wins.
+    let theirs = splice("Some other text.", "B", &section("B", "b"));
+    assert!(!has_section(&theirs, "A", &section("A", "a")));
+    let stale = splice("Some text.", "A", &section("A", "old"));
+    assert!(!has_section(&stale, "A", &section("A", "a")));
 }
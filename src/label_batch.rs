@@ -0,0 +1,254 @@
+//! Batching of label changes.
+//!
+//! Several handlers may change an issue's labels in quick succession (say, autolabel when a pull
+//! request is opened, and then someone relabeling it right away), each with requests of their
+//! own. Instead, label changes are held back until there have been none for the same issue for a
+//! short while, and then applied together; changes which cancel out (a label added and then
+//! removed again) aren't made at all.
+//!
+//! Pending changes are flushed by a background thread, and on shutdown. Failures there can only be
+//! logged, so changes people ask for with commands are made right away instead (see
+//! `apply_now`), and they hear about it if that fails.
+
+use crate::github::{GithubClient, Issue};
+use crate::shutdown::Shutdown;
+use failure::Error;
+use parser::command::relabel::LabelDelta;
+use std::collections::HashMap;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long an issue's labels must go unchanged before the changes are applied, unless
+/// configured otherwise.
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(500);
+
+#[derive(Clone)]
+pub struct LabelBatcher {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    debounce: Duration,
+    /// The changes waiting to be applied, keyed by repository and issue number.
+    pending: Mutex<HashMap<(String, u64), Batch>>,
+    changed: Condvar,
+}
+
+struct Batch {
+    /// The most recent version of the issue we've seen.
+    issue: Issue,
+    deltas: Vec<LabelDelta>,
+    due: Instant,
+}
+
+impl LabelBatcher {
+    /// With a `debounce` of zero, changes are applied right away.
+    pub fn new(debounce: Duration) -> LabelBatcher {
+        LabelBatcher {
+            inner: Arc::new(Inner {
+                debounce,
+                pending: Mutex::new(HashMap::new()),
+                changed: Condvar::new(),
+            }),
+        }
+    }
+
+    /// Applies `deltas` to the labels of `issue` in `repo`, soon.
+    pub fn apply(
+        &self,
+        client: &GithubClient,
+        repo: &str,
+        issue: &Issue,
+        deltas: Vec<LabelDelta>,
+    ) -> Result<(), Error> {
+        if self.inner.debounce == Duration::from_secs(0) {
+            return issue.apply_label_deltas(client, &deltas);
+        }
+        self.push(repo, issue, deltas, Instant::now());
+        Ok(())
+    }
+
+    /// Applies `deltas` to the labels of `issue` in `repo` right away, after any changes still
+    /// pending for it.
+    ///
+    /// Like the handlers, the caller must hold the issue's lock (see `crate::lock`).
+    pub fn apply_now(
+        &self,
+        client: &GithubClient,
+        repo: &str,
+        issue: &Issue,
+        deltas: Vec<LabelDelta>,
+    ) -> Result<(), Error> {
+        let mut all = self.take_issue(repo, issue.number);
+        all.extend(deltas);
+        if all.is_empty() {
+            return Ok(());
+        }
+        issue.apply_label_deltas(client, &all)
+    }
+
+    /// Takes the changes pending for the issue `number` in `repo`.
+    fn take_issue(&self, repo: &str, number: u64) -> Vec<LabelDelta> {
+        self.inner
+            .pending
+            .lock()
+            .unwrap()
+            .remove(&(repo.to_owned(), number))
+            .map_or_else(Vec::new, |batch| batch.deltas)
+    }
+
+    fn push(&self, repo: &str, issue: &Issue, deltas: Vec<LabelDelta>, now: Instant) {
+        if deltas.is_empty() {
+            return;
+        }
+        let due = now + self.inner.debounce;
+        let mut pending = self.inner.pending.lock().unwrap();
+        let batch = pending
+            .entry((repo.to_owned(), issue.number))
+            .or_insert_with(|| Batch {
+                issue: issue.clone(),
+                deltas: Vec::new(),
+                due,
+            });
+        batch.issue = issue.clone();
+        batch.deltas.extend(deltas);
+        batch.due = due;
+        self.inner.changed.notify_all();
+    }
+
+    /// Takes the batches which are due by `now`, or all of them if `all`.
+    fn take(&self, now: Instant, all: bool) -> Vec<((String, u64), Batch)> {
+        let mut pending = self.inner.pending.lock().unwrap();
+        let keys = pending
+            .iter()
+            .filter(|(_, batch)| all || batch.due <= now)
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>();
+        keys.into_iter()
+            .map(|key| {
+                let batch = pending.remove(&key).unwrap();
+                (key, batch)
+            })
+            .collect()
+    }
+
+    /// Applies batches as they become due, forever, or until we start shutting down.
+    pub fn run(&self, client: &GithubClient, shutdown: &Shutdown) {
+        loop {
+            {
+                let pending = self.inner.pending.lock().unwrap();
+                let now = Instant::now();
+                match pending.values().map(|batch| batch.due).min() {
+                    Some(due) if due <= now => {}
+                    Some(due) => {
+                        drop(self.inner.changed.wait_timeout(pending, due - now).unwrap());
+                        continue;
+                    }
+                    None => {
+                        drop(self.inner.changed.wait(pending).unwrap());
+                        continue;
+                    }
+                }
+            }
+            // Whatever is left once we're shutting down is flushed by `flush_all`.
+            let _task = match shutdown.start() {
+                Some(task) => task,
+                None => return,
+            };
+            for (key, batch) in self.take(Instant::now(), false) {
+                flush(client, key, batch);
+            }
+        }
+    }
+
+    /// Applies all pending changes right away.
+    pub fn flush_all(&self, client: &GithubClient) {
+        let batches = self.take(Instant::now(), true);
+        log::info!(
+            "applying {} pending batches of label changes",
+            batches.len()
+        );
+        for (key, batch) in batches {
+            flush(client, key, batch);
+        }
+    }
+}
+
+fn flush(client: &GithubClient, (repo, number): (String, u64), batch: Batch) {
+    let _guard = crate::lock::lock_issue(&repo, number);
+    if let Err(err) = batch.issue.apply_label_deltas(client, &batch.deltas) {
+        log::error!(
+            "failed to change the labels of {}#{}: {:?}",
+            repo,
+            number,
+            err
+        );
+    }
+}
+
+#[cfg(test)]
+fn issue(number: u64) -> Issue {
+    serde_json::from_value(serde_json::json!({
+        "number": number,
+        "body": "",
+        "state": "open",
+        "title": "",
+        "user": {"login": "alice"},
+        "labels": [],
+        "assignees": [],
+        "repository_url": "https://api.github.com/repos/rust-lang/rust",
+        "comments_url": "https://api.github.com/repos/rust-lang/rust/issues/1/comments",
+    }))
+    .unwrap()
+}
+
+#[cfg(test)]
+fn add(label: &str) -> LabelDelta {
+    LabelDelta::Add(label.into())
+}
+
+#[test]
+fn batches_per_issue() {
+    let batcher = LabelBatcher::new(Duration::from_millis(100));
+    let start = Instant::now();
+    batcher.push("rust-lang/rust", &issue(1), vec![add("A")], start);
+    batcher.push("rust-lang/rust", &issue(2), vec![add("B")], start);
+    batcher.push("rust-lang/rust", &issue(1), vec![add("C")], start);
+    let mut batches = batcher.take(start, true);
+    batches.sort_by_key(|(key, _)| key.1);
+    assert_eq!(batches.len(), 2);
+    assert_eq!(batches[0].1.deltas, vec![add("A"), add("C")]);
+    assert_eq!(batches[1].1.deltas, vec![add("B")]);
+}
+
+#[test]
+fn batch_due_after_quiet() {
+    let batcher = LabelBatcher::new(Duration::from_millis(100));
+    let start = Instant::now();
+    batcher.push("rust-lang/rust", &issue(1), vec![add("A")], start);
+    // Another change pushes the batch back.
+    let later = start + Duration::from_millis(50);
+    batcher.push("rust-lang/rust", &issue(1), vec![add("B")], later);
+    assert!(batcher
+        .take(start + Duration::from_millis(120), false)
+        .is_empty());
+    let batches = batcher.take(later + Duration::from_millis(100), false);
+    assert_eq!(batches.len(), 1);
+    assert_eq!(batches[0].1.deltas, vec![add("A"), add("B")]);
+    assert!(batcher
+        .take(later + Duration::from_secs(1), true)
+        .is_empty());
+}
+
+#[test]
+fn issue_taken_early() {
+    let batcher = LabelBatcher::new(Duration::from_millis(100));
+    let start = Instant::now();
+    batcher.push("rust-lang/rust", &issue(1), vec![add("A")], start);
+    batcher.push("rust-lang/rust", &issue(2), vec![add("B")], start);
+    assert_eq!(batcher.take_issue("rust-lang/rust", 1), vec![add("A")]);
+    assert!(batcher.take_issue("rust-lang/rust", 1).is_empty());
+    let batches = batcher.take(start, true);
+    assert_eq!(batches.len(), 1);
+    assert_eq!((batches[0].0).1, 2);
+}
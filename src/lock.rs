@@ -0,0 +1,95 @@
+//! Per-issue locking, so that work on the same issue is serialized.
+//!
+//! Webhook deliveries are handled concurrently, and handlers typically read some state of the
+//! issue, and then modify it. Their invocations for the same issue must therefore not
+//! interleave, or one may clobber the changes of another; invocations for different issues
+//! still run in parallel.
+//!
+//! Only issues which are currently locked are tracked, so this doesn't grow with the number of
+//! issues we've seen.
+
+use std::collections::HashSet;
+use std::sync::{Condvar, Mutex};
+
+lazy_static::lazy_static! {
+    static ref ISSUE_LOCKS: IssueLocks = IssueLocks::new();
+}
+
+/// Blocks until no one else holds the lock for `repo#issue`, and then takes it until the returned
+/// guard is dropped.
+///
+/// This guarantees mutual exclusion only: when several callers are waiting for the same issue,
+/// the order in which they acquire the lock is unspecified (in particular, it need not match the
+/// order the webhooks were delivered in).
+pub fn lock_issue(repo: &str, issue: u64) -> IssueGuard<'static> {
+    ISSUE_LOCKS.lock(repo, issue)
+}
+
+struct IssueLocks {
+    locked: Mutex<HashSet<(String, u64)>>,
+    released: Condvar,
+}
+
+impl IssueLocks {
+    fn new() -> IssueLocks {
+        IssueLocks {
+            locked: Mutex::new(HashSet::new()),
+            released: Condvar::new(),
+        }
+    }
+
+    fn lock(&self, repo: &str, issue: u64) -> IssueGuard {
+        let key = (repo.to_owned(), issue);
+        let mut locked = self.locked.lock().unwrap();
+        while locked.contains(&key) {
+            locked = self.released.wait(locked).unwrap();
+        }
+        locked.insert(key.clone());
+        IssueGuard { locks: self, key }
+    }
+}
+
+pub struct IssueGuard<'a> {
+    locks: &'a IssueLocks,
+    key: (String, u64),
+}
+
+impl Drop for IssueGuard<'_> {
+    fn drop(&mut self) {
+        self.locks.locked.lock().unwrap().remove(&self.key);
+        self.locks.released.notify_all();
+    }
+}
+
+#[test]
+fn same_issue_serializes() {
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    let locks = Arc::new(IssueLocks::new());
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let guard = locks.lock("rust-lang/rust", 1);
+    let waiter = {
+        let (locks, log) = (locks.clone(), log.clone());
+        thread::spawn(move || {
+            let _guard = locks.lock("rust-lang/rust", 1);
+            log.lock().unwrap().push("second");
+        })
+    };
+    thread::sleep(Duration::from_millis(50));
+    log.lock().unwrap().push("first");
+    drop(guard);
+    waiter.join().unwrap();
+    assert_eq!(*log.lock().unwrap(), ["first", "second"]);
+    assert!(locks.locked.lock().unwrap().is_empty());
+}
+
+#[test]
+fn different_issues_in_parallel() {
+    let locks = IssueLocks::new();
+    let _first = locks.lock("rust-lang/rust", 1);
+    let _second = locks.lock("rust-lang/rust", 2);
+    let _third = locks.lock("rust-lang/cargo", 1);
+    assert_eq!(locks.locked.lock().unwrap().len(), 3);
+}
@@ -9,20 +9,40 @@ use reqwest::Client;
 use rocket::request;
 use rocket::State;
 use rocket::{http::Status, Outcome, Request};
+use rocket_contrib::json::Json;
 use std::env;
+use std::thread;
+use std::time::Duration;
 
+mod audit;
 mod config;
+mod db;
+mod delivery;
 mod github;
 mod handlers;
 mod interactions;
+mod label_batch;
+mod lock;
+mod metrics;
 mod payload;
+mod scheduler;
+mod shutdown;
 mod team;
+mod template;
 
 use interactions::ErrorComment;
 use payload::SignedPayload;
+use scheduler::Scheduler;
+
+const PERIODIC_INTERVAL: Duration = Duration::from_secs(60 * 60); // Every hour
 
 enum EventName {
     IssueComment,
+    Issues,
+    PullRequest,
+    PullRequestReview,
+    PullRequestReviewComment,
+    Push,
     Other,
 }
 
@@ -36,21 +56,46 @@ impl<'a, 'r> request::FromRequest<'a, 'r> for EventName {
         };
         let ev = match ev {
             "issue_comment" => EventName::IssueComment,
+            "issues" => EventName::Issues,
+            "pull_request" => EventName::PullRequest,
+            "pull_request_review" => EventName::PullRequestReview,
+            "pull_request_review_comment" => EventName::PullRequestReviewComment,
+            "push" => EventName::Push,
             _ => EventName::Other,
         };
         Outcome::Success(ev)
     }
 }
 
+/// The unique id GitHub assigns to each webhook delivery.
+struct DeliveryId(String);
+
+impl<'a, 'r> request::FromRequest<'a, 'r> for DeliveryId {
+    type Error = ();
+    fn from_request(req: &'a Request<'r>) -> request::Outcome<Self, Self::Error> {
+        match req.headers().get_one("X-GitHub-Delivery") {
+            Some(id) => Outcome::Success(DeliveryId(id.to_owned())),
+            None => Outcome::Forward(()),
+        }
+    }
+}
+
 #[derive(Debug)]
-struct WebhookError(Error);
+enum WebhookError {
+    Failed(Error),
+    /// We're shutting down, so GitHub should deliver the event again later.
+    ShuttingDown,
+}
 
 impl<'r> rocket::response::Responder<'r> for WebhookError {
     fn respond_to(self, _: &Request) -> rocket::response::Result<'r> {
-        let body = format!("{:?}", self.0);
+        let (status, body) = match self {
+            WebhookError::Failed(err) => (Status::InternalServerError, format!("{:?}", err)),
+            WebhookError::ShuttingDown => (Status::ServiceUnavailable, "shutting down".to_owned()),
+        };
         rocket::Response::build()
             .header(rocket::http::ContentType::Plain)
-            .status(rocket::http::Status::InternalServerError)
+            .status(status)
             .sized_body(std::io::Cursor::new(body))
             .ok()
     }
@@ -58,37 +103,155 @@ impl<'r> rocket::response::Responder<'r> for WebhookError {
 
 impl From<Error> for WebhookError {
     fn from(e: Error) -> WebhookError {
-        WebhookError(e)
+        WebhookError::Failed(e)
     }
 }
 
 #[post("/github-hook", data = "<payload>")]
 fn webhook(
     event: EventName,
+    delivery: Option<DeliveryId>,
     payload: SignedPayload,
     ctx: State<handlers::Context>,
+    deliveries: State<delivery::DeliveryLog>,
+    shutdown: State<shutdown::Shutdown>,
 ) -> Result<(), WebhookError> {
-    match event {
+    let _task = shutdown.start().ok_or(WebhookError::ShuttingDown)?;
+    metrics::METRICS.event_received();
+    let ctx = handlers::Context {
+        delivery_id: delivery.map(|d| d.0),
+        cache: Default::default(),
+        ..ctx.clone()
+    };
+    let event = match event {
         EventName::IssueComment => {
             let payload = payload
                 .deserialize::<github::IssueCommentEvent>()
                 .context("IssueCommentEvent failed to deserialize")
                 .map_err(Error::from)?;
-
-            let event = github::Event::IssueComment(payload);
-            if let Err(err) = handlers::handle(&ctx, &event) {
-                if let Some(issue) = event.issue() {
-                    ErrorComment::new(issue, err.to_string()).post(&ctx.github)?;
-                }
+            github::Event::IssueComment(payload)
+        }
+        EventName::Issues => {
+            let payload = payload
+                .deserialize::<github::IssuesEvent>()
+                .context("IssuesEvent failed to deserialize")
+                .map_err(Error::from)?;
+            github::Event::Issue(payload)
+        }
+        EventName::PullRequest => {
+            let payload = payload
+                .deserialize::<github::PullRequestEvent>()
+                .context("PullRequestEvent failed to deserialize")
+                .map_err(Error::from)?;
+            github::Event::PullRequest(payload)
+        }
+        EventName::PullRequestReview => {
+            let payload = payload
+                .deserialize::<github::PullRequestReviewEvent>()
+                .context("PullRequestReviewEvent failed to deserialize")
+                .map_err(Error::from)?;
+            github::Event::PullRequestReview(payload)
+        }
+        EventName::PullRequestReviewComment => {
+            let payload = payload
+                .deserialize::<github::ReviewCommentEvent>()
+                .context("ReviewCommentEvent failed to deserialize")
+                .map_err(Error::from)?;
+            github::Event::IssueComment(payload.into())
+        }
+        EventName::Push => {
+            let payload = payload
+                .deserialize::<github::PushEvent>()
+                .context("PushEvent failed to deserialize")
+                .map_err(Error::from)?;
+            if let Err(err) = config::reload_on_push(&ctx.github, &payload) {
+                ctx.github.post_commit_comment(
+                    &payload.repository,
+                    &payload.after,
+                    &format!("**Error**: {}", err),
+                )?;
                 return Err(err.into());
             }
+            return Ok(());
         }
         // Other events need not be handled
-        EventName::Other => {}
+        EventName::Other => return Ok(()),
+    };
+    if let Some(occurred_at) = event.occurred_at() {
+        if deliveries.too_old(occurred_at) {
+            log::warn!(
+                "dropping {} event from {}, which is too old to handle",
+                event.kind(),
+                occurred_at
+            );
+            return Ok(());
+        }
+    }
+    let result = match &ctx.delivery_id {
+        Some(id) => deliveries.run_once(id, || handlers::handle(&ctx, &event)),
+        None => handlers::handle(&ctx, &event),
+    };
+    if let Err(err) = result {
+        for err in handlers::errors(&err) {
+            report_error(&ctx, &event, err);
+        }
+        return Err(err.into());
     }
     Ok(())
 }
 
+/// Logs `err`, which handling `event` failed with, and replies to it on the issue if whoever
+/// asked should hear about it.
+fn report_error(ctx: &handlers::Context, event: &github::Event, err: &Error) {
+    let reply = match handlers::error_response(err) {
+        handlers::ErrorResponse::Reply => true,
+        handlers::ErrorResponse::Log => {
+            log::warn!("handling {} event failed: {:?}", event.kind(), err);
+            false
+        }
+        handlers::ErrorResponse::Alert => {
+            log::error!(
+                "unexpected error handling {} event: {:?}",
+                event.kind(),
+                err
+            );
+            true
+        }
+    };
+    if let (true, Some(issue)) = (reply, event.issue()) {
+        if let Err(post_err) = ErrorComment::new(issue, err.to_string()).post(&ctx.github) {
+            log::error!(
+                "failed to report error on #{}: {:?}",
+                issue.number,
+                post_err
+            );
+        }
+    }
+}
+
+/// Whether we're up and able to work: the state store must be reachable.
+#[get("/healthz")]
+fn healthz(ctx: State<handlers::Context>) -> Result<&'static str, Status> {
+    match ctx.state.check() {
+        Ok(()) => Ok("ok"),
+        Err(err) => {
+            log::error!("health check failed: {:?}", err);
+            Err(Status::ServiceUnavailable)
+        }
+    }
+}
+
+#[get("/metrics")]
+fn metrics_report(ctx: State<handlers::Context>) -> Json<serde_json::Value> {
+    let mut metrics = metrics::METRICS.snapshot();
+    let rate_limit = ctx.github.rate_limit();
+    metrics["github_rate_limit"] = serde_json::json!({
+        "remaining": rate_limit.remaining,
+        "reset": rate_limit.reset,
+    });
+    Json(metrics)
+}
+
 #[catch(404)]
 fn not_found(_: &Request) -> &'static str {
     "Not Found"
@@ -96,20 +259,125 @@ fn not_found(_: &Request) -> &'static str {
 
 fn main() {
     dotenv::dotenv().ok();
+    // Most of what we log goes through `log`, which is forwarded to the subscriber so that it's
+    // printed, tagged with the handler's span, along with everything else.
+    tracing_log::LogTracer::init().expect("failed to forward log records to tracing");
+    tracing::subscriber::set_global_default(tracing_subscriber::FmtSubscriber::new())
+        .expect("failed to install the tracing subscriber");
     let client = Client::new();
-    let gh = github::GithubClient::new(
+    let mut gh = github::GithubClient::new(
         client.clone(),
         env::var("GITHUB_API_TOKEN").expect("Missing GITHUB_API_TOKEN"),
     );
+    if let Ok(retries) = env::var("GITHUB_MAX_RETRIES") {
+        let retries = retries.parse().expect("GITHUB_MAX_RETRIES is not a number");
+        if retries > github::MAX_RETRIES {
+            panic!("GITHUB_MAX_RETRIES must be at most {}", github::MAX_RETRIES);
+        }
+        gh = gh.with_max_retries(retries);
+    }
+    if let Ok(threshold) = env::var("GITHUB_RATE_LIMIT_THRESHOLD") {
+        gh = gh.with_rate_limit_threshold(
+            threshold
+                .parse()
+                .expect("GITHUB_RATE_LIMIT_THRESHOLD is not a number"),
+        );
+    }
+    if let Ok(ttl) = env::var("TEAM_CACHE_TTL_SECS") {
+        gh = gh.with_team_cache_ttl(Duration::from_secs(
+            ttl.parse().expect("TEAM_CACHE_TTL_SECS is not a number"),
+        ));
+    }
+    if env::var("TRIAGEBOT_DRY_RUN")
+        .ok()
+        .map_or(false, |v| v == "1" || v == "true")
+    {
+        log::warn!("dry run: changes to GitHub will be logged, not made");
+        gh = gh.with_dry_run(true);
+    }
+    let state = match env::var("DATABASE_URL") {
+        Ok(url) => db::StateStore::postgres(&url).unwrap(),
+        Err(_) => {
+            log::warn!("DATABASE_URL is not set; handler state will be kept in memory");
+            db::StateStore::in_memory()
+        }
+    };
+    let label_debounce = match env::var("LABEL_DEBOUNCE_MS") {
+        Ok(ms) => Duration::from_millis(ms.parse().expect("LABEL_DEBOUNCE_MS is not a number")),
+        Err(_) => label_batch::DEFAULT_DEBOUNCE,
+    };
     let ctx = handlers::Context {
         github: gh.clone(),
         username: github::User::current(&gh).unwrap().login,
+        aliases: handlers::bot_aliases(&env::var("TRIAGEBOT_ALIASES").unwrap_or_default()),
+        state,
+        delivery_id: None,
+        cache: Default::default(),
+        labels: label_batch::LabelBatcher::new(label_debounce),
+    };
+
+    let delivery_ttl = match env::var("DELIVERY_ID_TTL_SECS") {
+        Ok(ttl) => Duration::from_secs(ttl.parse().expect("DELIVERY_ID_TTL_SECS is not a number")),
+        Err(_) => delivery::DEFAULT_TTL,
+    };
+    let max_event_age = match env::var("MAX_EVENT_AGE_SECS") {
+        Ok(age) => Duration::from_secs(age.parse().expect("MAX_EVENT_AGE_SECS is not a number")),
+        Err(_) => delivery::DEFAULT_MAX_AGE,
     };
 
+    let shutdown_timeout = match env::var("SHUTDOWN_TIMEOUT_SECS") {
+        Ok(timeout) => Duration::from_secs(
+            timeout
+                .parse()
+                .expect("SHUTDOWN_TIMEOUT_SECS is not a number"),
+        ),
+        Err(_) => shutdown::DEFAULT_TIMEOUT,
+    };
+    let max_payload = match env::var("MAX_PAYLOAD_BYTES") {
+        Ok(max) => max.parse().expect("MAX_PAYLOAD_BYTES is not a number"),
+        Err(_) => payload::DEFAULT_MAX_SIZE,
+    };
+    let shutdown = shutdown::Shutdown::new();
+    let signals = signal_hook::iterator::Signals::new(&[signal_hook::SIGTERM])
+        .expect("failed to listen for SIGTERM");
+    let draining = shutdown.clone();
+    let (pending_labels, flush_client) = (ctx.labels.clone(), gh.clone());
+    thread::spawn(move || {
+        signals.forever().next();
+        log::info!("shutting down, waiting for running handlers");
+        let drained = draining.drain(shutdown_timeout);
+        if drained.abandoned == 0 {
+            log::info!("all {} running handlers finished", drained.finished);
+        } else {
+            log::warn!(
+                "{} running handlers finished, {} did not finish in time and were cancelled",
+                drained.finished,
+                drained.abandoned
+            );
+        }
+        pending_labels.flush_all(&flush_client);
+        std::process::exit(0);
+    });
+
+    let (batcher, batch_client, batch_shutdown) =
+        (ctx.labels.clone(), gh.clone(), shutdown.clone());
+    thread::spawn(move || batcher.run(&batch_client, &batch_shutdown));
+
+    let mut scheduler = scheduler::ThreadScheduler::new();
+    let periodic_ctx = ctx.clone();
+    scheduler.register("handlers", PERIODIC_INTERVAL, move || {
+        handlers::run_periodic(&periodic_ctx);
+        Ok(())
+    });
+    scheduler.spawn(shutdown.clone());
+
     rocket::ignite()
         .manage(gh)
         .manage(ctx)
-        .mount("/", routes![webhook])
+        .manage(delivery::DeliveryLog::new(delivery_ttl).with_max_age(max_event_age))
+        .manage(shutdown)
+        .manage(payload::PayloadLimit(max_payload))
+        .mount("/", routes![webhook, healthz, metrics_report])
         .register(catchers![not_found])
         .launch();
 }
@@ -0,0 +1,77 @@
+//! Counters of what the bot has been doing, for monitoring.
+//!
+//! These are process-wide and start from zero whenever the bot starts; they're exposed as JSON
+//! on `/metrics`.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+lazy_static::lazy_static! {
+    pub static ref METRICS: Metrics = Metrics::new();
+}
+
+pub struct Metrics {
+    events_received: AtomicU64,
+    handler_errors: AtomicU64,
+    /// The number of inputs (usually commands) parsed by each handler.
+    commands_parsed: Mutex<BTreeMap<&'static str, u64>>,
+}
+
+impl Metrics {
+    fn new() -> Metrics {
+        Metrics {
+            events_received: AtomicU64::new(0),
+            handler_errors: AtomicU64::new(0),
+            commands_parsed: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn event_received(&self) {
+        self.events_received.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn handler_error(&self) {
+        self.handler_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn commands_parsed(&self, handler: &'static str, count: usize) {
+        if count == 0 {
+            return;
+        }
+        *self
+            .commands_parsed
+            .lock()
+            .unwrap()
+            .entry(handler)
+            .or_insert(0) += count as u64;
+    }
+
+    pub fn snapshot(&self) -> serde_json::Value {
+        serde_json::json!({
+            "events_received": self.events_received.load(Ordering::Relaxed),
+            "handler_errors": self.handler_errors.load(Ordering::Relaxed),
+            "commands_parsed": *self.commands_parsed.lock().unwrap(),
+        })
+    }
+}
+
+#[test]
+fn counts() {
+    let metrics = Metrics::new();
+    metrics.event_received();
+    metrics.event_received();
+    metrics.handler_error();
+    metrics.commands_parsed("relabel", 2);
+    metrics.commands_parsed("assign", 1);
+    metrics.commands_parsed("relabel", 1);
+    metrics.commands_parsed("close", 0);
+    assert_eq!(
+        metrics.snapshot(),
+        serde_json::json!({
+            "events_received": 2,
+            "handler_errors": 1,
+            "commands_parsed": { "assign": 1, "relabel": 3 },
+        })
+    );
+}
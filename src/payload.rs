@@ -1,63 +1,179 @@
 //! This module implements the payload verification for GitHub webhook events.
+//!
+//! Every delivery is signed by GitHub with HMAC-SHA256 over the raw request body, keyed on the
+//! webhook secret (from `GITHUB_WEBHOOK_SECRET`), and the signature is sent in the
+//! `X-Hub-Signature-256` header. Deliveries which are unsigned or whose signature doesn't match
+//! are rejected before we attempt to deserialize them.
+//!
+//! So are deliveries which aren't JSON, and those larger than the `PayloadLimit` (from
+//! `MAX_PAYLOAD_BYTES`, by default the 25 MB GitHub caps deliveries at); we stop reading the body
+//! once it's too large, rather than keeping whatever is sent in memory.
 
 use openssl::{hash::MessageDigest, memcmp, pkey::PKey, sign::Signer};
 use rocket::{
     data::{self, Data, FromDataSimple},
-    http::Status,
+    http::{ContentType, Status},
     request::Request,
-    Outcome,
+    Outcome, State,
 };
-use std::{env, io::Read};
+use std::{env, fmt, io::Read};
+
+/// The most deliveries are allowed to be, unless `MAX_PAYLOAD_BYTES` says otherwise.
+pub const DEFAULT_MAX_SIZE: u64 = 25 * 1024 * 1024;
 
 pub struct SignedPayload(Vec<u8>);
 
+/// The most bytes a delivery's body may be.
+pub struct PayloadLimit(pub u64);
+
+#[derive(Debug, PartialEq, Eq)]
+enum PayloadError {
+    NotJson(Option<String>),
+    TooLarge(u64),
+}
+
+impl fmt::Display for PayloadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PayloadError::NotJson(Some(content_type)) => {
+                write!(f, "expected application/json, not {}", content_type)
+            }
+            PayloadError::NotJson(None) => write!(f, "expected application/json"),
+            PayloadError::TooLarge(max) => write!(f, "payload is over {} bytes", max),
+        }
+    }
+}
+
+impl PayloadError {
+    fn status(&self) -> Status {
+        match self {
+            PayloadError::NotJson(_) => Status::UnsupportedMediaType,
+            PayloadError::TooLarge(_) => Status::PayloadTooLarge,
+        }
+    }
+}
+
+fn check_content_type(content_type: Option<&ContentType>) -> Result<(), PayloadError> {
+    match content_type {
+        Some(content_type) if content_type.is_json() => Ok(()),
+        other => Err(PayloadError::NotJson(other.map(|c| c.to_string()))),
+    }
+}
+
+/// Reads all of `body`, unless it's over `max` bytes.
+fn read_body(body: impl Read, max: u64) -> Result<Vec<u8>, ReadError> {
+    let mut buf = Vec::new();
+    body.take(max + 1)
+        .read_to_end(&mut buf)
+        .map_err(ReadError::Io)?;
+    if buf.len() as u64 > max {
+        return Err(ReadError::Payload(PayloadError::TooLarge(max)));
+    }
+    Ok(buf)
+}
+
+#[derive(Debug)]
+enum ReadError {
+    Payload(PayloadError),
+    Io(std::io::Error),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum SignatureError {
+    Malformed(String),
+    Mismatch,
+}
+
+impl fmt::Display for SignatureError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SignatureError::Malformed(reason) => write!(f, "malformed signature: {}", reason),
+            SignatureError::Mismatch => write!(f, "HMAC not correct"),
+        }
+    }
+}
+
+impl SignatureError {
+    fn status(&self) -> Status {
+        match self {
+            SignatureError::Malformed(_) => Status::BadRequest,
+            SignatureError::Mismatch => Status::Forbidden,
+        }
+    }
+}
+
+/// Checks `signature` (the value of the `X-Hub-Signature-256` header) against the HMAC-SHA256 of
+/// `body` keyed with `secret`, in constant time.
+fn verify_signature(secret: &[u8], signature: &str, body: &[u8]) -> Result<(), SignatureError> {
+    let prefix = "sha256=";
+    if !signature.starts_with(prefix) {
+        return Err(SignatureError::Malformed(format!(
+            "{:?} does not start with {:?}",
+            signature, prefix
+        )));
+    }
+    let signature = &signature[prefix.len()..];
+    let signature = hex::decode(&signature).map_err(|e| {
+        SignatureError::Malformed(format!(
+            "failed to convert signature {:?} from hex: {:?}",
+            signature, e
+        ))
+    })?;
+
+    let key = PKey::hmac(secret).unwrap();
+    let mut signer = Signer::new(MessageDigest::sha256(), &key).unwrap();
+    signer.update(&body).unwrap();
+    let hmac = signer.sign_to_vec().unwrap();
+
+    // `memcmp::eq` panics on slices of differing lengths.
+    if hmac.len() != signature.len() || !memcmp::eq(&hmac, &signature) {
+        return Err(SignatureError::Mismatch);
+    }
+    Ok(())
+}
+
 impl FromDataSimple for SignedPayload {
     type Error = String;
     fn from_data(req: &Request, data: Data) -> data::Outcome<Self, Self::Error> {
-        let signature = match req.headers().get_one("X-Hub-Signature") {
+        if let Err(err) = check_content_type(req.content_type()) {
+            return Outcome::Failure((err.status(), err.to_string()));
+        }
+        let max = req
+            .guard::<State<PayloadLimit>>()
+            .succeeded()
+            .map_or(DEFAULT_MAX_SIZE, |limit| limit.0);
+        let length = req
+            .headers()
+            .get_one("Content-Length")
+            .and_then(|length| length.parse::<u64>().ok());
+        if length.map_or(false, |length| length > max) {
+            let err = PayloadError::TooLarge(max);
+            return Outcome::Failure((err.status(), err.to_string()));
+        }
+
+        let signature = match req.headers().get_one("X-Hub-Signature-256") {
             Some(s) => s,
             None => {
-                return Outcome::Failure((
-                    Status::Unauthorized,
-                    "Unauthorized, no signature".into(),
-                ));
+                return Outcome::Failure((Status::Forbidden, "Forbidden, no signature".into()));
             }
         };
-        let signature = &signature["sha1=".len()..];
-        let signature = match hex::decode(&signature) {
-            Ok(e) => e,
-            Err(e) => {
+
+        let buf = match read_body(data.open(), max) {
+            Ok(buf) => buf,
+            Err(ReadError::Payload(err)) => {
+                return Outcome::Failure((err.status(), err.to_string()));
+            }
+            Err(ReadError::Io(err)) => {
                 return Outcome::Failure((
-                    Status::BadRequest,
-                    format!(
-                        "failed to convert signature {:?} from hex: {:?}",
-                        signature, e
-                    ),
+                    Status::InternalServerError,
+                    format!("failed to read request body to string: {:?}", err),
                 ));
             }
         };
 
-        let mut stream = data.open().take(1024 * 1024 * 5); // 5 Megabytes
-        let mut buf = Vec::new();
-        if let Err(err) = stream.read_to_end(&mut buf) {
-            return Outcome::Failure((
-                Status::InternalServerError,
-                format!("failed to read request body to string: {:?}", err),
-            ));
-        }
-
-        let key = PKey::hmac(
-            env::var("GITHUB_WEBHOOK_SECRET")
-                .expect("Missing GITHUB_WEBHOOK_SECRET")
-                .as_bytes(),
-        )
-        .unwrap();
-        let mut signer = Signer::new(MessageDigest::sha1(), &key).unwrap();
-        signer.update(&buf).unwrap();
-        let hmac = signer.sign_to_vec().unwrap();
-
-        if !memcmp::eq(&hmac, &signature) {
-            return Outcome::Failure((Status::Unauthorized, "HMAC not correct".into()));
+        let secret = env::var("GITHUB_WEBHOOK_SECRET").expect("Missing GITHUB_WEBHOOK_SECRET");
+        if let Err(err) = verify_signature(secret.as_bytes(), signature, &buf) {
+            return Outcome::Failure((err.status(), err.to_string()));
         }
 
         Outcome::Success(SignedPayload(buf))
@@ -69,3 +185,131 @@ impl SignedPayload {
         serde_json::from_slice(&self.0)
     }
 }
+
+#[cfg(test)]
+const SECRET: &[u8] = b"It's a Secret to Everybody";
+
+#[cfg(test)]
+const SIGNATURE: &str = "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17";
+
+#[test]
+fn signature_valid() {
+    assert_eq!(
+        verify_signature(SECRET, SIGNATURE, b"Hello, World!"),
+        Ok(())
+    );
+}
+
+#[test]
+fn signature_wrong_body() {
+    assert_eq!(
+        verify_signature(SECRET, SIGNATURE, b"Hello, World?"),
+        Err(SignatureError::Mismatch)
+    );
+}
+
+#[test]
+fn signature_wrong_secret() {
+    assert_eq!(
+        verify_signature(b"not the secret", SIGNATURE, b"Hello, World!"),
+        Err(SignatureError::Mismatch)
+    );
+}
+
+#[test]
+fn signature_truncated() {
+    assert_eq!(
+        verify_signature(SECRET, &SIGNATURE[..21], b"Hello, World!"),
+        Err(SignatureError::Mismatch)
+    );
+}
+
+#[test]
+fn signature_sha1() {
+    let err = verify_signature(
+        SECRET,
+        "sha1=01dc10d0c83e72ed246219cdd91669667fe2ca59",
+        b"Hello, World!",
+    )
+    .unwrap_err();
+    assert_eq!(err.status(), Status::BadRequest);
+}
+
+#[test]
+fn content_type_json() {
+    assert_eq!(check_content_type(Some(&ContentType::JSON)), Ok(()));
+    let with_charset: ContentType = "application/json; charset=utf-8".parse().unwrap();
+    assert_eq!(check_content_type(Some(&with_charset)), Ok(()));
+}
+
+#[test]
+fn content_type_wrong() {
+    let err = check_content_type(Some(&ContentType::Form)).unwrap_err();
+    assert_eq!(err.status(), Status::UnsupportedMediaType);
+    assert_eq!(
+        err.to_string(),
+        "expected application/json, not application/x-www-form-urlencoded"
+    );
+    assert_eq!(
+        check_content_type(None).unwrap_err().status(),
+        Status::UnsupportedMediaType
+    );
+}
+
+#[test]
+fn body_oversized() {
+    assert_eq!(
+        read_body(&b"Hello, World!"[..], 13).unwrap(),
+        b"Hello, World!"
+    );
+    match read_body(&b"Hello, World!"[..], 12) {
+        Err(ReadError::Payload(err)) => {
+            assert_eq!(err, PayloadError::TooLarge(12));
+            assert_eq!(err.status(), Status::PayloadTooLarge);
+        }
+        other => panic!("expected the body to be too large, got {:?}", other),
+    }
+}
+
+/// Counts the deliveries which made it to the route, as the webhook would handle them.
+#[cfg(test)]
+#[post("/github-hook", data = "<_payload>")]
+fn counting_hook(_payload: SignedPayload, calls: State<std::sync::atomic::AtomicUsize>) {
+    calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+}
+
+#[cfg(test)]
+fn deliver(content_type: ContentType, body: &str) -> (Status, usize) {
+    use rocket::http::Header;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    let rocket = rocket::ignite()
+        .manage(PayloadLimit(16))
+        .manage(AtomicUsize::new(0))
+        .mount("/", routes![counting_hook]);
+    let client = rocket::local::Client::new(rocket).unwrap();
+    let status = client
+        .post("/github-hook")
+        .header(content_type)
+        .header(Header::new("X-Hub-Signature-256", SIGNATURE))
+        .body(body)
+        .dispatch()
+        .status();
+    let calls = client.rocket().state::<AtomicUsize>().unwrap();
+    (status, calls.load(Ordering::SeqCst))
+}
+
+#[test]
+fn webhook_rejects_oversized() {
+    assert_eq!(
+        deliver(ContentType::JSON, "Hello, World! Hello, World!"),
+        (Status::PayloadTooLarge, 0)
+    );
+}
+
+#[test]
+fn webhook_rejects_wrong_content_type() {
+    assert_eq!(
+        deliver(ContentType::Form, "Hello, World!"),
+        (Status::UnsupportedMediaType, 0)
+    );
+}
@@ -0,0 +1,222 @@
+//! Running jobs periodically, like checking on inactive assignees.
+//!
+//! Jobs are registered with a `Scheduler`, along with how often they should run; each runs as
+//! soon as the scheduler starts, and then once per interval. `ThreadScheduler` runs them on a
+//! thread of its own. In tests, `ManualScheduler` runs them instead, with a clock which only
+//! moves when it's told to, so that periodic jobs can be tested without sleeping.
+//!
+//! A job which fails (or panics) is logged, and runs again at its next time as usual.
+
+use crate::shutdown::Shutdown;
+use failure::Error;
+use std::panic::{self, AssertUnwindSafe};
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub trait Scheduler {
+    /// Runs `job` every `interval`, under `name` in the log.
+    fn register<F>(&mut self, name: &'static str, interval: Duration, job: F)
+    where
+        F: FnMut() -> Result<(), Error> + Send + 'static;
+}
+
+struct Job {
+    name: &'static str,
+    interval: Duration,
+    next: Instant,
+    run: Box<dyn FnMut() -> Result<(), Error> + Send>,
+}
+
+impl Job {
+    fn run(&mut self) {
+        match panic::catch_unwind(AssertUnwindSafe(|| (self.run)())) {
+            Ok(Ok(())) => log::debug!("periodic job {} finished", self.name),
+            Ok(Err(err)) => log::error!("periodic job {} failed: {:?}", self.name, err),
+            // The panic itself has already been reported by the panic hook.
+            Err(_) => log::error!("periodic job {} panicked", self.name),
+        }
+    }
+}
+
+/// The registered jobs, and when each should run next.
+#[derive(Default)]
+struct Jobs {
+    jobs: Vec<Job>,
+}
+
+impl Jobs {
+    fn add<F>(&mut self, name: &'static str, interval: Duration, job: F, now: Instant)
+    where
+        F: FnMut() -> Result<(), Error> + Send + 'static,
+    {
+        assert!(
+            interval > Duration::from_secs(0),
+            "job {} has no interval",
+            name
+        );
+        self.jobs.push(Job {
+            name,
+            interval,
+            next: now,
+            run: Box::new(job),
+        });
+    }
+
+    /// When the next job is due, if there are any.
+    fn next_due(&self) -> Option<Instant> {
+        self.jobs.iter().map(|job| job.next).min()
+    }
+
+    /// Runs the jobs which are due at `now`, in the order they were registered, and schedules
+    /// their next runs; `clock` tells the time afterwards.
+    ///
+    /// A job which should already have run again by then (because this run took longer than its
+    /// interval, say) waits for a whole interval instead of running twice in a row.
+    fn run_due(&mut self, now: Instant, clock: impl Fn() -> Instant) {
+        for job in &mut self.jobs {
+            if job.next > now {
+                continue;
+            }
+            job.run();
+            job.next += job.interval;
+            let after = clock();
+            if job.next < after {
+                job.next = after + job.interval;
+            }
+        }
+    }
+}
+
+/// Runs the jobs on a thread, with the real clock.
+pub struct ThreadScheduler {
+    jobs: Jobs,
+}
+
+impl ThreadScheduler {
+    pub fn new() -> ThreadScheduler {
+        ThreadScheduler {
+            jobs: Jobs::default(),
+        }
+    }
+
+    /// Starts running the jobs, until we shut down; shutting down waits for a running job to
+    /// finish, like it does for handlers.
+    pub fn spawn(mut self, shutdown: Shutdown) -> thread::JoinHandle<()> {
+        thread::spawn(move || loop {
+            let due = match self.jobs.next_due() {
+                Some(due) => due,
+                None => return,
+            };
+            let now = Instant::now();
+            if due > now {
+                thread::sleep(due - now);
+            }
+            let _task = match shutdown.start() {
+                Some(task) => task,
+                None => return,
+            };
+            self.jobs.run_due(Instant::now(), Instant::now);
+        })
+    }
+}
+
+impl Scheduler for ThreadScheduler {
+    fn register<F>(&mut self, name: &'static str, interval: Duration, job: F)
+    where
+        F: FnMut() -> Result<(), Error> + Send + 'static,
+    {
+        self.jobs.add(name, interval, job, Instant::now());
+    }
+}
+
+/// Runs the jobs as its clock is moved forward by `advance`, for tests.
+#[cfg(test)]
+pub struct ManualScheduler {
+    jobs: Jobs,
+    now: Instant,
+}
+
+#[cfg(test)]
+impl ManualScheduler {
+    pub fn new() -> ManualScheduler {
+        ManualScheduler {
+            jobs: Jobs::default(),
+            now: Instant::now(),
+        }
+    }
+
+    /// Moves the clock forward by `by`, running each job whenever it's due along the way (which
+    /// includes right away, for jobs which haven't run yet).
+    pub fn advance(&mut self, by: Duration) {
+        let until = self.now + by;
+        while let Some(due) = self.jobs.next_due() {
+            if due > until {
+                break;
+            }
+            self.now = due;
+            let now = self.now;
+            self.jobs.run_due(now, || now);
+        }
+        self.now = until;
+    }
+}
+
+#[cfg(test)]
+impl Scheduler for ManualScheduler {
+    fn register<F>(&mut self, name: &'static str, interval: Duration, job: F)
+    where
+        F: FnMut() -> Result<(), Error> + Send + 'static,
+    {
+        self.jobs.add(name, interval, job, self.now);
+    }
+}
+
+#[cfg(test)]
+type Runs = std::sync::Arc<std::sync::Mutex<Vec<&'static str>>>;
+
+/// A job which adds `name` to `runs` whenever it runs.
+#[cfg(test)]
+fn record(runs: &Runs, name: &'static str) -> impl FnMut() -> Result<(), Error> + Send {
+    let runs = runs.clone();
+    move || {
+        runs.lock().unwrap().push(name);
+        Ok(())
+    }
+}
+
+#[test]
+fn jobs_run_every_interval() {
+    let runs = Runs::default();
+    let mut scheduler = ManualScheduler::new();
+    scheduler.register("hourly", Duration::from_secs(3600), record(&runs, "hourly"));
+    scheduler.register(
+        "daily",
+        Duration::from_secs(24 * 3600),
+        record(&runs, "daily"),
+    );
+    scheduler.advance(Duration::from_secs(0));
+    assert_eq!(*runs.lock().unwrap(), ["hourly", "daily"]);
+    scheduler.advance(Duration::from_secs(3599));
+    assert_eq!(runs.lock().unwrap().len(), 2);
+    scheduler.advance(Duration::from_secs(1));
+    assert_eq!(*runs.lock().unwrap(), ["hourly", "daily", "hourly"]);
+    scheduler.advance(Duration::from_secs(23 * 3600));
+    let runs = runs.lock().unwrap();
+    assert_eq!(runs.iter().filter(|&&name| name == "hourly").count(), 25);
+    assert_eq!(runs.iter().filter(|&&name| name == "daily").count(), 2);
+}
+
+#[test]
+fn failing_jobs_keep_running() {
+    let runs = Runs::default();
+    let mut scheduler = ManualScheduler::new();
+    let mut failures = 0;
+    scheduler.register("failing", Duration::from_secs(60), move || {
+        failures += 1;
+        failure::bail!("failure {}", failures)
+    });
+    scheduler.register("panicking", Duration::from_secs(60), || panic!("oops"));
+    scheduler.register("working", Duration::from_secs(60), record(&runs, "working"));
+    scheduler.advance(Duration::from_secs(120));
+    assert_eq!(*runs.lock().unwrap(), ["working"; 3]);
+}
@@ -0,0 +1,146 @@
+//! Graceful shutdown.
+//!
+//! When we're asked to stop (on SIGTERM, e.g. when deploying a new version), we stop taking on
+//! new work and wait for the handlers which are already running to finish, so that they don't
+//! leave things half-done (say, an issue assigned but its body not updated). Handlers still
+//! running once the timeout is up are abandoned.
+
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long to wait for running handlers, unless configured otherwise.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Clone)]
+pub struct Shutdown {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    state: Mutex<State>,
+    /// Notified whenever a task finishes.
+    finished: Condvar,
+}
+
+struct State {
+    stopping: bool,
+    in_flight: usize,
+}
+
+/// Work which shutting down waits for, until it's dropped.
+pub struct Task {
+    inner: Arc<Inner>,
+}
+
+impl Drop for Task {
+    fn drop(&mut self) {
+        self.inner.state.lock().unwrap().in_flight -= 1;
+        self.inner.finished.notify_all();
+    }
+}
+
+/// What happened to the tasks running when shutting down.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Drained {
+    /// The tasks which finished in time.
+    pub finished: usize,
+    /// The tasks still running when we gave up waiting.
+    pub abandoned: usize,
+}
+
+impl Shutdown {
+    pub fn new() -> Shutdown {
+        Shutdown {
+            inner: Arc::new(Inner {
+                state: Mutex::new(State {
+                    stopping: false,
+                    in_flight: 0,
+                }),
+                finished: Condvar::new(),
+            }),
+        }
+    }
+
+    /// Starts a task, which shutting down will wait for; `None` if we're shutting down, in which
+    /// case the work should be refused.
+    pub fn start(&self) -> Option<Task> {
+        let mut state = self.inner.state.lock().unwrap();
+        if state.stopping {
+            return None;
+        }
+        state.in_flight += 1;
+        Some(Task {
+            inner: self.inner.clone(),
+        })
+    }
+
+    /// Stops new tasks from starting, and waits up to `timeout` for the running ones to finish.
+    pub fn drain(&self, timeout: Duration) -> Drained {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.inner.state.lock().unwrap();
+        state.stopping = true;
+        let running = state.in_flight;
+        while state.in_flight > 0 {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            state = self
+                .inner
+                .finished
+                .wait_timeout(state, deadline - now)
+                .unwrap()
+                .0;
+        }
+        Drained {
+            finished: running - state.in_flight,
+            abandoned: state.in_flight,
+        }
+    }
+}
+
+#[test]
+fn drain_idle() {
+    let shutdown = Shutdown::new();
+    drop(shutdown.start());
+    assert_eq!(
+        shutdown.drain(Duration::from_secs(1)),
+        Drained {
+            finished: 0,
+            abandoned: 0
+        }
+    );
+    assert!(shutdown.start().is_none());
+}
+
+#[test]
+fn drain_waits_for_tasks() {
+    let shutdown = Shutdown::new();
+    let task = shutdown.start().unwrap();
+    let handle = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(50));
+        drop(task);
+    });
+    assert_eq!(
+        shutdown.drain(Duration::from_secs(10)),
+        Drained {
+            finished: 1,
+            abandoned: 0
+        }
+    );
+    handle.join().unwrap();
+}
+
+#[test]
+fn drain_times_out() {
+    let shutdown = Shutdown::new();
+    let _task = shutdown.start().unwrap();
+    let _other = shutdown.start().unwrap();
+    assert_eq!(
+        shutdown.drain(Duration::from_millis(10)),
+        Drained {
+            finished: 0,
+            abandoned: 2
+        }
+    );
+}
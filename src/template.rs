@@ -0,0 +1,245 @@
+//! Templates for the comments handlers post, which repositories may override.
+//!
+//! Each message has a name and a built-in default, and can be replaced by an entry of the same
+//! name in the `[templates]` section of `triagebot.toml`. Templates refer to the values the
+//! handler fills in as `{name}`; `{{` and `}}` stand for literal braces.
+//!
+//! Templates are checked along with the rest of the configuration, so that a misspelt template
+//! or placeholder name is reported rather than posted.
+
+use std::collections::HashMap;
+
+/// A message which can be customized.
+pub(crate) struct Template {
+    pub(crate) name: &'static str,
+    /// The placeholders the handler fills in.
+    pub(crate) vars: &'static [&'static str],
+    pub(crate) default: &'static str,
+}
+
+/// Every message which can be customized.
+pub(crate) static TEMPLATES: &[Template] = &[
+    Template {
+        name: "assigned",
+        vars: &["user", "issue", "comment_url"],
+        default: "This issue has been assigned to @{user} via [this comment]({comment_url}).",
+    },
+    Template {
+        name: "locked",
+        vars: &["user", "reason"],
+        default: "@{user} locked this conversation ({reason}), so only collaborators can \
+                  comment on it now.",
+    },
+    Template {
+        name: "minimize",
+        vars: &["user", "reporter", "marker"],
+        default: "@{reporter}, could you help us reproduce this? We need the smallest program \
+                  which still triggers the problem, without any dependencies: start from the \
+                  code which crashes, and remove everything you can while it still does. \
+                  When you have it, please post it in a comment with `{marker}` in it.",
+    },
+    Template {
+        name: "permission-denied",
+        vars: &["user", "reason"],
+        default: "Sorry @{user}, I can't do that: {reason}",
+    },
+    Template {
+        name: "ping",
+        vars: &["group", "issue", "comment_url", "user", "mentions"],
+        default: "Hey `{group}` group! This issue could use your attention \
+                  ([requested]({comment_url}) by @{user}).\n\ncc {mentions}",
+    },
+    Template {
+        name: "renamed",
+        vars: &["old", "new"],
+        default: "Renamed from \"{old}\" to \"{new}\".",
+    },
+    Template {
+        name: "review-picked",
+        vars: &["user"],
+        default: "@{user} has been picked to review this from the rotation.",
+    },
+    Template {
+        name: "subscribers",
+        vars: &["user", "change", "mentions", "bot"],
+        default: "@{user} {change}.\n\ncc {mentions} (you're subscribed to this issue; \
+                  `@{bot} unsubscribe` to stop being notified)",
+    },
+    Template {
+        name: "transferred",
+        vars: &["source", "user"],
+        default: "Moved here from {source} at the request of @{user}.",
+    },
+];
+
+#[derive(Debug, PartialEq)]
+enum Piece<'a> {
+    Text(&'a str),
+    Var(&'a str),
+}
+
+/// Splits `template` into literal text and placeholders.
+fn pieces(template: &str) -> Result<Vec<Piece>, String> {
+    let mut pieces = Vec::new();
+    let mut rest = template;
+    while let Some(i) = rest.find(|c| c == '{' || c == '}') {
+        if i > 0 {
+            pieces.push(Piece::Text(&rest[..i]));
+        }
+        let brace = &rest[i..=i];
+        rest = &rest[i + 1..];
+        if rest.starts_with(brace) {
+            pieces.push(Piece::Text(brace));
+            rest = &rest[1..];
+        } else if brace == "}" {
+            return Err("`}` must be written as `}}`".to_owned());
+        } else {
+            let end = rest
+                .find('}')
+                .ok_or_else(|| "a placeholder is missing its closing `}`".to_owned())?;
+            pieces.push(Piece::Var(rest[..end].trim()));
+            rest = &rest[end + 1..];
+        }
+    }
+    if !rest.is_empty() {
+        pieces.push(Piece::Text(rest));
+    }
+    Ok(pieces)
+}
+
+/// Checks the overridden templates in `templates`, keyed by name, returning a message for each
+/// problem found.
+pub(crate) fn validate(templates: &HashMap<String, String>) -> Vec<String> {
+    let mut errors = Vec::new();
+    let mut names = templates.keys().collect::<Vec<_>>();
+    names.sort();
+    for name in names {
+        let template = match TEMPLATES.iter().find(|t| t.name == name) {
+            Some(template) => template,
+            None => {
+                errors.push(format!("there is no template called `{}`", name));
+                continue;
+            }
+        };
+        match pieces(&templates[name]) {
+            Ok(pieces) => {
+                for piece in pieces {
+                    if let Piece::Var(var) = piece {
+                        if !template.vars.contains(&var) {
+                            errors.push(format!(
+                                "template `{}` has no placeholder `{{{}}}`; it has {}",
+                                name,
+                                var,
+                                template
+                                    .vars
+                                    .iter()
+                                    .map(|v| format!("`{{{}}}`", v))
+                                    .collect::<Vec<_>>()
+                                    .join(", ")
+                            ));
+                        }
+                    }
+                }
+            }
+            Err(err) => errors.push(format!("template `{}` is malformed: {}", name, err)),
+        }
+    }
+    errors
+}
+
+/// Renders the message called `name`, using the repository's template from `templates` if it
+/// has one, with the placeholders replaced by `vars`.
+///
+/// Templates are validated when the configuration is loaded, and the defaults are tested, so
+/// a placeholder without a value is a bug; it's left as it is.
+pub(crate) fn render_template(
+    templates: &HashMap<String, String>,
+    name: &str,
+    vars: &[(&str, &str)],
+) -> String {
+    let template = match templates.get(name) {
+        Some(template) => template.as_str(),
+        None => {
+            TEMPLATES
+                .iter()
+                .find(|t| t.name == name)
+                .unwrap_or_else(|| panic!("unknown template {}", name))
+                .default
+        }
+    };
+    let pieces = match pieces(template) {
+        Ok(pieces) => pieces,
+        Err(_) => return template.to_owned(),
+    };
+    let mut text = String::new();
+    for piece in pieces {
+        match piece {
+            Piece::Text(s) => text.push_str(s),
+            Piece::Var(var) => match vars.iter().find(|(name, _)| *name == var) {
+                Some((_, value)) => text.push_str(value),
+                None => {
+                    log::error!("no value for `{{{}}}` in template {}", var, name);
+                    text.push_str(&format!("{{{}}}", var));
+                }
+            },
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+fn templates(entries: &[(&str, &str)]) -> HashMap<String, String> {
+    entries
+        .iter()
+        .map(|(name, template)| (name.to_string(), template.to_string()))
+        .collect()
+}
+
+#[test]
+fn defaults_valid() {
+    for template in TEMPLATES {
+        let overridden = templates(&[(template.name, template.default)]);
+        assert_eq!(
+            validate(&overridden),
+            Vec::<String>::new(),
+            "{}",
+            template.name
+        );
+    }
+}
+
+#[test]
+fn render_default() {
+    assert_eq!(
+        render_template(&templates(&[]), "review-picked", &[("user", "alice")]),
+        "@alice has been picked to review this from the rotation."
+    );
+}
+
+#[test]
+fn render_override() {
+    let templates = templates(&[("renamed", "Titel: {{{old}}} -> {{{ new }}}")]);
+    assert_eq!(
+        render_template(&templates, "renamed", &[("old", "a"), ("new", "b")]),
+        "Titel: {a} -> {b}"
+    );
+}
+
+#[test]
+fn validate_reports_typos() {
+    let templates = templates(&[
+        ("asigned", "{user}"),
+        ("renamed", "from {old} to {nwe}"),
+        ("review-picked", "{user"),
+        ("ping", "}"),
+    ]);
+    assert_eq!(
+        validate(&templates),
+        vec![
+            "there is no template called `asigned`",
+            "template `ping` is malformed: `}` must be written as `}}`",
+            "template `renamed` has no placeholder `{nwe}`; it has `{old}`, `{new}`",
+            "template `review-picked` is malformed: a placeholder is missing its closing `}`",
+        ]
+    );
+}